@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|bytes: &[u8]| {
+    // Decoding either fails cleanly or round-trips byte for byte.
+    if let Ok((text, encoding)) = nomoji::encoding::decode(bytes.to_vec()) {
+        let encoded = encoding.encode(&text).expect("decoded text re-encodes");
+        assert_eq!(encoded, bytes);
+    }
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|text: &str| {
+    let (cleaned, removed) = nomoji::remove_emojis(text);
+    assert_eq!(cleaned.chars().count() + removed, text.chars().count());
+    assert_eq!(nomoji::count_emojis(&cleaned), 0);
+
+    // Span-based removal, used whenever categories are filtered, must agree
+    // with the character-based fast path.
+    let spans = nomoji::emoji_spans(text);
+    assert_eq!(nomoji::remove_spans(text, &spans), (cleaned, removed));
+});
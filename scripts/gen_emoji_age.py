@@ -0,0 +1,64 @@
+#!/usr/bin/env python3
+"""Generate src/emoji_age.rs from the Age property in Perl's Unicode::UCD.
+
+Usage: python3 scripts/gen_emoji_age.py > src/emoji_age.rs
+
+Covers the characters with Emoji or Extended_Pictographic, and records the
+Unicode version each was first assigned in. Unassigned pictographic code
+points have no age and are left out. The Unicode version of the data follows
+the Perl interpreter used to run the script.
+"""
+
+import subprocess
+
+SCRIPT = r"""
+    use Unicode::UCD qw(charprop);
+    print Unicode::UCD::UnicodeVersion(), "\n";
+    for my $c (0..0x10FFFF) {
+        next if $c >= 0xD800 && $c <= 0xDFFF;
+        my $s = chr($c);
+        next unless $s =~ /\p{Emoji}/ || $s =~ /\p{Extended_Pictographic}/;
+        my $age = charprop($c, "Age");
+        next if $age eq "Unassigned";
+        printf "%X %s\n", $c, $age;
+    }
+"""
+
+
+def ranges(rows):
+    first = last = age = None
+    for code, code_age in rows:
+        if last is not None and code == last + 1 and code_age == age:
+            last = code
+            continue
+        if first is not None:
+            yield first, last, age
+        first = last = code
+        age = code_age
+    if first is not None:
+        yield first, last, age
+
+
+def main():
+    lines = subprocess.run(
+        ["perl", "-e", SCRIPT], check=True, capture_output=True, text=True
+    ).stdout.splitlines()
+    version, rows = lines[0], []
+    for line in lines[1:]:
+        code, age = line.split()
+        major, minor = age.lstrip("V").split("_")
+        rows.append((int(code, 16), (int(major), int(minor))))
+
+    print("// Generated by scripts/gen_emoji_age.py from Unicode %s." % version)
+    print("// Do not edit by hand.")
+    print()
+    print("/// The Unicode version each emoji character was introduced in: (first,")
+    print("/// last, (major, minor)), sorted.")
+    print("pub const EMOJI_AGE: &[(char, char, (u8, u8))] = &[")
+    for first, last, (major, minor) in ranges(rows):
+        print("    ('\\u{%X}', '\\u{%X}', (%d, %d))," % (first, last, major, minor))
+    print("];")
+
+
+if __name__ == "__main__":
+    main()
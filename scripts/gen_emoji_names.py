@@ -0,0 +1,61 @@
+#!/usr/bin/env python3
+"""Generate src/emoji_names.rs from Python's unicodedata module.
+
+Usage: python3 scripts/gen_emoji_names.py > src/emoji_names.rs
+
+Covers every assigned character `is_emoji` matches except the components
+(variation selectors, skin tone modifiers, keycap mark, ZWJ) and regional
+indicators, which are named from their sequence instead. The Unicode version
+follows the Python interpreter used to run the script.
+"""
+
+import unicodedata
+
+# Keep in step with is_emoji in src/main.rs.
+RANGES = [
+    (0x1F300, 0x1F5FF),
+    (0x1F900, 0x1F9FF),
+    (0x1F600, 0x1F64F),
+    (0x1F680, 0x1F6FF),
+    (0x2600, 0x26FF),
+    (0x2700, 0x27BF),
+    (0x1F100, 0x1F1FF),
+    (0x1F200, 0x1F2FF),
+    (0x1F780, 0x1F7FF),
+    (0x1FA00, 0x1FAFF),
+]
+SINGLES = [
+    0x231A, 0x231B, 0x23E9, 0x23EA, 0x23EB, 0x23EC, 0x23F0, 0x23F3, 0x25FD,
+    0x25FE, 0x00A9, 0x00AE, 0x2122, 0x2B50, 0x2B55, 0x3030, 0x303D,
+]
+COMPONENTS = set(range(0xFE00, 0xFE10)) | set(range(0x1F3FB, 0x1F400)) | {0x20E3, 0x200D}
+REGIONAL_INDICATORS = set(range(0x1F1E6, 0x1F200))
+
+
+def code_points():
+    points = set(SINGLES)
+    for first, last in RANGES:
+        points.update(range(first, last + 1))
+    return sorted(points - COMPONENTS - REGIONAL_INDICATORS)
+
+
+def shortcode(name):
+    return name.lower().replace(" ", "_").replace("-", "_")
+
+
+def main():
+    print("// Generated by scripts/gen_emoji_names.py from Unicode %s." % unicodedata.unidata_version)
+    print("// Do not edit by hand.")
+    print()
+    print("/// Lower-case Unicode character names with words joined by `_`, sorted by")
+    print("/// character.")
+    print("pub const EMOJI_NAMES: &[(char, &str)] = &[")
+    for cp in code_points():
+        name = unicodedata.name(chr(cp), None)
+        if name:
+            print("    ('\\u{%X}', \"%s\")," % (cp, shortcode(name)))
+    print("];")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,178 @@
+#!/usr/bin/env python3
+"""Generate src/emoji-test.txt, the corpus `nomoji selftest` checks against.
+
+Usage:
+    python3 scripts/gen_emoji_test.py emoji-test.txt > src/emoji-test.txt
+    python3 scripts/gen_emoji_test.py > src/emoji-test.txt
+
+Given the official file from https://unicode.org/Public/emoji/latest/, the
+fully-qualified lines are kept and everything else is dropped. Without it,
+an equivalent corpus is derived from the emoji properties in Perl's
+Unicode::UCD, names from Python's unicodedata, region codes from the tz
+database's iso3166.tab, and the ZWJ sequences listed below. Either way the
+output uses the emoji-test.txt line format, so the two are interchangeable.
+"""
+
+import subprocess
+import sys
+import unicodedata
+
+TONES = [0x1F3FB, 0x1F3FC, 0x1F3FD, 0x1F3FE, 0x1F3FF]
+ZWJ, VS16, KEYCAP = 0x200D, 0xFE0F, 0x20E3
+FEMALE, MALE = [0x2640, VS16], [0x2642, VS16]
+
+# Region codes with flags that ISO 3166-1 does not assign.
+EXTRA_REGIONS = ["AC", "CP", "DG", "EA", "EU", "IC", "TA", "UN"]
+
+# England, Scotland, Wales.
+SUBDIVISIONS = ["gbeng", "gbsct", "gbwls"]
+
+# Objects joined to person, man and woman for professions.
+PROFESSIONS = [
+    [0x2695, VS16], [0x1F393], [0x1F3EB], [0x2696, VS16], [0x1F33E],
+    [0x1F373], [0x1F527], [0x1F3ED], [0x1F4BC], [0x1F52C], [0x1F4BB],
+    [0x1F3A4], [0x1F3A8], [0x2708, VS16], [0x1F680], [0x1F692],
+    [0x1F9AF], [0x1F9B0], [0x1F9B1], [0x1F9B3], [0x1F9B2],
+]
+
+# Roles and gestures with man and woman forms.
+GENDERED = [
+    0x1F46E, 0x1F482, 0x1F477, 0x1F473, 0x1F471, 0x1F64D, 0x1F64E, 0x1F645,
+    0x1F646, 0x1F481, 0x1F64B, 0x1F647, 0x1F926, 0x1F937, 0x1F486, 0x1F487,
+    0x1F6B6, 0x1F3C3, 0x1F6A3, 0x1F3CA, 0x1F6B4, 0x1F6B5, 0x1F938, 0x1F93D,
+    0x1F93E, 0x1F939, 0x1F9D8, 0x1F9D6, 0x1F9D7, 0x1F9D9, 0x1F9DA, 0x1F9DB,
+    0x1F9DC, 0x1F9DD, 0x1F9B8, 0x1F9B9, 0x1F9CD, 0x1F9CE, 0x1F9CF,
+]
+
+OTHER_ZWJ = [
+    [0x1F3F3, VS16, ZWJ, 0x1F308],
+    [0x1F3F3, VS16, ZWJ, 0x26A7, VS16],
+    [0x1F3F4, ZWJ, 0x2620, VS16],
+    [0x1F415, ZWJ, 0x1F9BA],
+    [0x1F408, ZWJ, 0x2B1B],
+    [0x1F43B, ZWJ, 0x2744, VS16],
+    [0x1F426, ZWJ, 0x2B1B],
+    [0x1F62E, ZWJ, 0x1F4A8],
+    [0x1F635, ZWJ, 0x1F4AB],
+    [0x1F636, ZWJ, 0x1F32B, VS16],
+    [0x2764, VS16, ZWJ, 0x1F525],
+    [0x2764, VS16, ZWJ, 0x1FA79],
+    [0x1F441, VS16, ZWJ, 0x1F5E8, VS16],
+    [0x1F9D1, ZWJ, 0x1F91D, ZWJ, 0x1F9D1],
+    [0x1F469, ZWJ, 0x2764, VS16, ZWJ, 0x1F468],
+    [0x1F468, ZWJ, 0x2764, VS16, ZWJ, 0x1F468],
+    [0x1F469, ZWJ, 0x2764, VS16, ZWJ, 0x1F48B, ZWJ, 0x1F468],
+    [0x1F468, ZWJ, 0x1F469, ZWJ, 0x1F466],
+    [0x1F468, ZWJ, 0x1F469, ZWJ, 0x1F467, ZWJ, 0x1F466],
+    [0x1F469, ZWJ, 0x1F469, ZWJ, 0x1F467],
+    [0x1F468, ZWJ, 0x1F466, ZWJ, 0x1F466],
+    [0x1F469, ZWJ, 0x1F467],
+    [0x1F9D1, ZWJ, 0x1F384],
+    [0x1F46F, ZWJ, 0x2640, VS16],
+    [0x1F93C, ZWJ, 0x2642, VS16],
+    [0x1F575, VS16, ZWJ, 0x2640, VS16],
+    [0x1F3CC, VS16, ZWJ, 0x2642, VS16],
+    [0x26F9, VS16, ZWJ, 0x2640, VS16],
+]
+
+
+def name(code):
+    try:
+        return unicodedata.name(chr(code)).lower()
+    except ValueError:
+        return "u+%04x" % code
+
+
+def line(codes, description):
+    text = "".join(chr(c) for c in codes)
+    points = " ".join("%04X" % c for c in codes)
+    return "%s ; fully-qualified # %s %s" % (points, text, description)
+
+
+def from_official(path):
+    with open(path, encoding="utf-8") as f:
+        for row in f:
+            if "; fully-qualified" in row:
+                yield row.rstrip("\n")
+
+
+def properties():
+    """Code points with Emoji, Emoji_Presentation and Emoji_Modifier_Base."""
+    script = r"""
+        for my $c (0..0x10FFFF) {
+            next if $c >= 0xD800 && $c <= 0xDFFF;
+            my $s = chr($c);
+            next unless $s =~ /\p{Emoji}/;
+            printf "%X %d %d %d\n", $c, $s =~ /\p{Emoji_Presentation}/ ? 1 : 0,
+                $s =~ /\p{Emoji_Modifier_Base}/ ? 1 : 0,
+                $s =~ /\p{Emoji_Component}/ ? 1 : 0;
+        }
+    """
+    output = subprocess.run(
+        ["perl", "-e", script], check=True, capture_output=True, text=True
+    ).stdout
+    for row in output.splitlines():
+        code, presentation, modifier_base, component = row.split()
+        yield int(code, 16), presentation == "1", modifier_base == "1", component == "1"
+
+
+def regions():
+    with open("/usr/share/zoneinfo/iso3166.tab", encoding="utf-8") as f:
+        codes = [row.split("\t")[0] for row in f if not row.startswith("#")]
+    return sorted(set(codes + EXTRA_REGIONS))
+
+
+def derived():
+    bases = []
+    for code, presentation, modifier_base, component in properties():
+        if component:
+            continue
+        codes = [code] if presentation else [code, VS16]
+        yield line(codes, name(code))
+        if modifier_base:
+            bases.append(code)
+
+    for key in "#*0123456789":
+        yield line([ord(key), VS16, KEYCAP], "keycap: %s" % key)
+
+    for base in bases:
+        for tone in TONES:
+            yield line([base, tone], "%s: %s" % (name(base), name(tone)))
+
+    for region in regions():
+        codes = [0x1F1E6 + ord(c) - ord("A") for c in region]
+        yield line(codes, "flag: %s" % region)
+
+    for subdivision in SUBDIVISIONS:
+        codes = [0x1F3F4] + [0xE0000 + ord(c) for c in subdivision] + [0xE007F]
+        yield line(codes, "flag: %s" % subdivision)
+
+    for person in [0x1F9D1, 0x1F468, 0x1F469]:
+        for tone in [None] + TONES:
+            prefix = [person] + ([tone] if tone else [])
+            for thing in PROFESSIONS:
+                codes = prefix + [ZWJ] + thing
+                yield line(codes, " ".join(name(c) for c in codes if c not in (ZWJ, VS16)))
+
+    for base in GENDERED:
+        for tone in [None] + TONES:
+            prefix = [base] + ([tone] if tone else [])
+            for gender in (FEMALE, MALE):
+                codes = prefix + [ZWJ] + gender
+                yield line(codes, " ".join(name(c) for c in codes if c not in (ZWJ, VS16)))
+
+    for codes in OTHER_ZWJ:
+        yield line(codes, " ".join(name(c) for c in codes if c not in (ZWJ, VS16)))
+
+
+def main():
+    rows = from_official(sys.argv[1]) if len(sys.argv) > 1 else derived()
+    print("# Fully-qualified emoji sequences for `nomoji selftest`.")
+    print("# Generated by scripts/gen_emoji_test.py; do not edit.")
+    print("# Unicode %s" % unicodedata.unidata_version)
+    for row in rows:
+        print(row)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,86 @@
+#!/usr/bin/env python3
+"""Generate src/normalize_tables.rs from Python's unicodedata module.
+
+Usage: python3 scripts/gen_normalization_tables.py > src/normalize_tables.rs
+
+The Unicode version follows the Python interpreter used to run the script.
+"""
+
+import unicodedata
+
+MAX = 0x110000
+
+
+def is_hangul_syllable(cp):
+    return 0xAC00 <= cp <= 0xD7A3
+
+
+def chars():
+    for cp in range(MAX):
+        if 0xD800 <= cp <= 0xDFFF:
+            continue
+        yield cp, chr(cp)
+
+
+def rust_char(cp):
+    return "'\\u{%X}'" % cp
+
+
+def main():
+    decompositions = []
+    compositions = []
+    combining = []
+
+    for cp, c in chars():
+        ccc = unicodedata.combining(c)
+        if ccc:
+            combining.append((cp, ccc))
+
+        if is_hangul_syllable(cp):
+            continue
+
+        nfd = unicodedata.normalize("NFD", c)
+        if nfd != c:
+            decompositions.append((cp, nfd))
+
+        # Primary composites: a canonical pair that NFC recombines into c.
+        decomposition = unicodedata.decomposition(c)
+        if decomposition and not decomposition.startswith("<"):
+            parts = [int(p, 16) for p in decomposition.split()]
+            if len(parts) == 2 and unicodedata.normalize("NFC", c) == c:
+                compositions.append((parts[0], parts[1], cp))
+
+    # Collapse consecutive code points that share a combining class.
+    ranges = []
+    for cp, ccc in combining:
+        if ranges and ranges[-1][1] == cp - 1 and ranges[-1][2] == ccc:
+            ranges[-1][1] = cp
+        else:
+            ranges.append([cp, cp, ccc])
+
+    compositions.sort()
+
+    print("// Generated by scripts/gen_normalization_tables.py from Unicode %s." % unicodedata.unidata_version)
+    print("// Do not edit by hand.")
+    print()
+    print("/// Canonical combining class ranges: (first, last, class).")
+    print("pub const COMBINING_CLASS: &[(char, char, u8)] = &[")
+    for first, last, ccc in ranges:
+        print("    (%s, %s, %d)," % (rust_char(first), rust_char(last), ccc))
+    print("];")
+    print()
+    print("/// Full canonical decompositions (NFD of each character), Hangul excluded.")
+    print("pub const DECOMPOSITION: &[(char, &str)] = &[")
+    for cp, nfd in decompositions:
+        print("    (%s, \"%s\")," % (rust_char(cp), "".join("\\u{%X}" % ord(x) for x in nfd)))
+    print("];")
+    print()
+    print("/// Primary composites: (first, second, composite), sorted by pair.")
+    print("pub const COMPOSITION: &[(char, char, char)] = &[")
+    for first, second, cp in compositions:
+        print("    (%s, %s, %s)," % (rust_char(first), rust_char(second), rust_char(cp)))
+    print("];")
+
+
+if __name__ == "__main__":
+    main()
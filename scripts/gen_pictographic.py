@@ -0,0 +1,54 @@
+#!/usr/bin/env python3
+"""Generate src/pictographic.rs from Perl's Unicode::UCD.
+
+Usage: python3 scripts/gen_pictographic.py > src/pictographic.rs
+
+Lists the characters with Extended_Pictographic, which includes text-default
+symbols and code points reserved for future emoji. The Unicode version
+follows the Perl interpreter used to run the script.
+"""
+
+import subprocess
+
+SCRIPT = r"""
+    use Unicode::UCD;
+    print Unicode::UCD::UnicodeVersion(), "\n";
+    for my $c (0..0x10FFFF) {
+        next if $c >= 0xD800 && $c <= 0xDFFF;
+        my $s = chr($c);
+        next unless $s =~ /\p{Extended_Pictographic}/;
+        printf "%X\n", $c;
+    }
+"""
+
+
+def ranges(codes):
+    first = last = None
+    for code in codes:
+        if last is not None and code == last + 1:
+            last = code
+            continue
+        if first is not None:
+            yield first, last
+        first = last = code
+    if first is not None:
+        yield first, last
+
+
+def main():
+    output = subprocess.run(
+        ["perl", "-e", SCRIPT], check=True, capture_output=True, text=True
+    ).stdout.split()
+    version, codes = output[0], [int(code, 16) for code in output[1:]]
+    print("// Generated by scripts/gen_pictographic.py from Unicode %s." % version)
+    print("// Do not edit by hand.")
+    print()
+    print("/// Ranges of Extended_Pictographic characters: (first, last), sorted.")
+    print("pub const EXTENDED_PICTOGRAPHIC: &[(char, char)] = &[")
+    for first, last in ranges(codes):
+        print("    ('\\u{%X}', '\\u{%X}')," % (first, last))
+    print("];")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,57 @@
+#!/usr/bin/env python3
+"""Generate src/text_default.rs from the emoji properties in Perl's Unicode::UCD.
+
+Usage: python3 scripts/gen_text_default.py > src/text_default.rs
+
+Lists the characters with Emoji but not Emoji_Presentation, leaving out the
+components (keycap bases, which are digits, `#` and `*`, among them). UTS #51
+renders these as text unless VS16 follows. The Unicode version follows the
+Perl interpreter used to run the script.
+"""
+
+import subprocess
+
+SCRIPT = r"""
+    use Unicode::UCD;
+    print Unicode::UCD::UnicodeVersion(), "\n";
+    for my $c (0..0x10FFFF) {
+        next if $c >= 0xD800 && $c <= 0xDFFF;
+        my $s = chr($c);
+        next unless $s =~ /\p{Emoji}/;
+        next if $s =~ /\p{Emoji_Presentation}/ || $s =~ /\p{Emoji_Component}/;
+        printf "%X\n", $c;
+    }
+"""
+
+
+def ranges(codes):
+    first = last = None
+    for code in codes:
+        if last is not None and code == last + 1:
+            last = code
+            continue
+        if first is not None:
+            yield first, last
+        first = last = code
+    if first is not None:
+        yield first, last
+
+
+def main():
+    output = subprocess.run(
+        ["perl", "-e", SCRIPT], check=True, capture_output=True, text=True
+    ).stdout.split()
+    version, codes = output[0], [int(code, 16) for code in output[1:]]
+    print("// Generated by scripts/gen_text_default.py from Unicode %s." % version)
+    print("// Do not edit by hand.")
+    print()
+    print("/// Ranges of emoji characters with text presentation by default: (first,")
+    print("/// last), sorted.")
+    print("pub const TEXT_DEFAULT: &[(char, char)] = &[")
+    for first, last in ranges(codes):
+        print("    ('\\u{%X}', '\\u{%X}')," % (first, last))
+    print("];")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,35 @@
+//! The Unicode version an emoji first appeared in, for `--list`.
+//!
+//! A sequence is as old as its newest character. Sequences were often
+//! standardized later than the characters they are made of, so a result is
+//! the earliest version that could show one.
+
+use crate::emoji_age::EMOJI_AGE;
+
+/// The Unicode version, as (major, minor), that introduced the newest
+/// character of `sequence`, if any of them is an emoji character.
+pub fn age(sequence: &str) -> Option<(u8, u8)> {
+    sequence.chars().filter_map(char_age).max()
+}
+
+fn char_age(c: char) -> Option<(u8, u8)> {
+    let i = EMOJI_AGE.partition_point(|&(_, last, _)| last < c);
+    EMOJI_AGE
+        .get(i)
+        .filter(|&&(first, _, _)| first <= c)
+        .map(|&(_, _, age)| age)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_age() {
+        assert_eq!(age("\u{263A}\u{FE0F}"), Some((1, 1)));
+        assert_eq!(age("🚀"), Some((6, 0)));
+        assert_eq!(age("👍🏽"), Some((8, 0)));
+        assert_eq!(age("🫠"), Some((14, 0)));
+        assert_eq!(age("abc"), None);
+    }
+}
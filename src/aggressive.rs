@@ -0,0 +1,48 @@
+//! The wider net `--aggressive` casts: every Extended_Pictographic
+//! character, whatever its default presentation, plus the Miscellaneous
+//! Technical and Miscellaneous Symbols and Arrows blocks and every emoji
+//! component, on top of what `is_emoji` already matches.
+
+use crate::{is_emoji, is_emoji_component, is_regional_indicator};
+
+use crate::pictographic::EXTENDED_PICTOGRAPHIC;
+
+/// Whether `--aggressive` removes `c`.
+pub fn matches(c: char) -> bool {
+    is_emoji(c)
+        || is_emoji_component(c)
+        || is_regional_indicator(c)
+        || matches!(c as u32, 0x2300..=0x23FF | 0x2B00..=0x2BFF)
+        || is_pictographic(c)
+}
+
+fn is_pictographic(c: char) -> bool {
+    let i = EXTENDED_PICTOGRAPHIC.partition_point(|&(_, last)| last < c);
+    EXTENDED_PICTOGRAPHIC
+        .get(i)
+        .is_some_and(|&(first, _)| first <= c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        // ‼ ℹ ↔ ⌨ ▶ ⬛ and a reserved pictographic code point.
+        for c in [
+            '\u{203C}',
+            '\u{2139}',
+            '\u{2194}',
+            '\u{2328}',
+            '\u{25B6}',
+            '\u{2B1B}',
+            '\u{1FAFF}',
+        ] {
+            assert!(matches(c), "{:04X}", c as u32);
+        }
+        for c in ['a', '1', '#', '\u{2190}', '\u{25A0}', '\u{E9}'] {
+            assert!(!matches(c), "{:04X}", c as u32);
+        }
+    }
+}
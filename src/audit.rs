@@ -0,0 +1,255 @@
+//! `--audit` sidecars: a JSON record of every change a run made to a file,
+//! precise enough to check and reverse it.
+//!
+//! ```json
+//! {"schema_version":1,"files":[{"file":"notes.md",
+//!   "before_sha256":"...","after_sha256":"...",
+//!   "changes":[{"offset":8,"removed":"🚀","inserted":""}]}]}
+//! ```
+//!
+//! Offsets are byte offsets into the original text, in order. The hashes
+//! are SHA-256 of the text as UTF-8 before and after cleaning, and let
+//! `nomoji restore` check that it is undoing exactly the recorded change.
+
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+use nomoji::replace_spans;
+
+use crate::json::{self, Value};
+use crate::sha256;
+
+/// The version of the sidecar structure this nomoji writes.
+pub const SCHEMA_VERSION: usize = 1;
+
+/// One replaced sequence; `inserted` is empty when it was just removed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    pub offset: usize,
+    pub removed: String,
+    pub inserted: String,
+}
+
+/// Everything a run changed in one file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub file: String,
+    pub before_sha256: String,
+    pub after_sha256: String,
+    pub changes: Vec<Change>,
+}
+
+impl Entry {
+    /// Records replacing each of `spans` in `original` by `replacement`,
+    /// failing unless that is exactly how `original` became `cleaned`.
+    pub fn new(
+        file: &str,
+        original: &str,
+        cleaned: &str,
+        spans: &[Range<usize>],
+        replacement: impl Fn(&str) -> String,
+    ) -> Result<Entry, String> {
+        let changes: Vec<Change> = spans
+            .iter()
+            .map(|span| {
+                let removed = &original[span.clone()];
+                Change {
+                    offset: span.start,
+                    removed: removed.to_string(),
+                    inserted: replacement(removed),
+                }
+            })
+            .collect();
+
+        let (replayed, _) = replace_spans(original, spans, |removed| replacement(removed));
+        if replayed != cleaned {
+            return Err("Cannot audit this change: it is more than replacing emojis".to_string());
+        }
+
+        Ok(Entry {
+            file: file.to_string(),
+            before_sha256: sha256::hex(original.as_bytes()),
+            after_sha256: sha256::hex(cleaned.as_bytes()),
+            changes,
+        })
+    }
+
+    /// The original text, rebuilt by putting each removed sequence back into
+    /// `cleaned`. Fails unless `cleaned` is exactly the text the run wrote.
+    pub fn restore(&self, cleaned: &str) -> Result<String, String> {
+        if sha256::hex(cleaned.as_bytes()) != self.after_sha256 {
+            return Err("changed since the audited run".to_string());
+        }
+
+        let mut original = String::with_capacity(cleaned.len());
+        let mut last = 0;
+        for change in &self.changes {
+            // Where the change landed in `cleaned`: its original offset,
+            // shifted by how much the earlier changes shrank or grew the text.
+            let at = (change.offset + last)
+                .checked_sub(original.len())
+                .filter(|&at| at >= last)
+                .ok_or("audit changes overlap or are out of order")?;
+            let end = at + change.inserted.len();
+            if cleaned.get(at..end) != Some(change.inserted.as_str()) {
+                return Err(format!("no recorded change at offset {}", change.offset));
+            }
+            original.push_str(&cleaned[last..at]);
+            original.push_str(&change.removed);
+            last = end;
+        }
+        original.push_str(&cleaned[last..]);
+
+        if sha256::hex(original.as_bytes()) != self.before_sha256 {
+            return Err("restored text does not match the recorded original".to_string());
+        }
+        Ok(original)
+    }
+}
+
+/// Reads the entries of the sidecar at `path`.
+pub fn load(path: &Path) -> Result<Vec<Entry>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn parse(text: &str) -> Result<Vec<Entry>, String> {
+    let sidecar = json::parse(text)?;
+    match sidecar.get("schema_version").and_then(Value::as_i64) {
+        Some(1) => {}
+        Some(version) => return Err(format!("unsupported audit schema version {}", version)),
+        None => return Err("not a nomoji audit file (no \"schema_version\")".to_string()),
+    }
+    let files = sidecar
+        .get("files")
+        .and_then(Value::as_array)
+        .ok_or("audit file without a \"files\" array")?;
+
+    files.iter().map(entry).collect()
+}
+
+fn entry(value: &Value) -> Result<Entry, String> {
+    let string = |value: &Value, key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| format!("audit entry without \"{}\"", key))
+    };
+    let changes = value
+        .get("changes")
+        .and_then(Value::as_array)
+        .ok_or("audit entry without \"changes\"")?
+        .iter()
+        .map(|change| {
+            Ok(Change {
+                offset: change
+                    .get("offset")
+                    .and_then(Value::as_i64)
+                    .and_then(|n| usize::try_from(n).ok())
+                    .ok_or("audit change without a valid \"offset\"")?,
+                removed: string(change, "removed")?,
+                inserted: string(change, "inserted")?,
+            })
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok(Entry {
+        file: string(value, "file")?,
+        before_sha256: string(value, "before_sha256")?,
+        after_sha256: string(value, "after_sha256")?,
+        changes,
+    })
+}
+
+pub fn to_json<'a>(entries: impl IntoIterator<Item = &'a Entry>) -> Value {
+    let files: Vec<Value> = entries
+        .into_iter()
+        .map(|entry| {
+            let changes: Vec<Value> = entry
+                .changes
+                .iter()
+                .map(|change| {
+                    Value::object()
+                        .with("offset", change.offset)
+                        .with("removed", change.removed.as_str())
+                        .with("inserted", change.inserted.as_str())
+                })
+                .collect();
+            Value::object()
+                .with("file", entry.file.as_str())
+                .with("before_sha256", entry.before_sha256.as_str())
+                .with("after_sha256", entry.after_sha256.as_str())
+                .with("changes", changes)
+        })
+        .collect();
+
+    Value::object()
+        .with("schema_version", SCHEMA_VERSION)
+        .with("files", files)
+}
+
+pub fn write<'a>(path: &Path, entries: impl IntoIterator<Item = &'a Entry>) -> io::Result<()> {
+    fs::write(path, to_json(entries).to_string() + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_records_changes() {
+        let original = "Ship 🚀 it ✅";
+        let spans = [5..9, 13..16];
+        let entry = Entry::new("a.md", original, "Ship  it ", &spans, |_| String::new()).unwrap();
+
+        assert_eq!(entry.changes[1].offset, 13);
+        assert_eq!(entry.changes[1].removed, "✅");
+        assert_eq!(entry.before_sha256, sha256::hex(original.as_bytes()));
+        assert_eq!(
+            to_json([&entry]).to_string(),
+            format!(
+                r#"{{"schema_version":1,"files":[{{"file":"a.md","before_sha256":"{}","after_sha256":"{}","changes":[{{"offset":5,"removed":"🚀","inserted":""}},{{"offset":13,"removed":"✅","inserted":""}}]}}]}}"#,
+                entry.before_sha256, entry.after_sha256
+            )
+        );
+    }
+
+    #[test]
+    fn test_restore_round_trip() {
+        let original = "Ship 🚀 it ✅ today";
+        let cleaned = "Ship &#x1F680; it  today";
+        let entry = Entry::new("a.md", original, cleaned, &[5..9, 13..16], |span| {
+            if span == "🚀" {
+                "&#x1F680;".to_string()
+            } else {
+                String::new()
+            }
+        })
+        .unwrap();
+
+        let loaded = parse(&to_json([&entry]).to_string()).unwrap();
+        assert_eq!(loaded, std::slice::from_ref(&entry));
+        assert_eq!(entry.restore(cleaned).unwrap(), original);
+        assert_eq!(
+            entry.restore("Ship &#x1F680; it  today!").unwrap_err(),
+            "changed since the audited run"
+        );
+        assert!(parse(r#"{"schema_version":2,"files":[]}"#).is_err());
+        assert!(parse(r#"{"files":[]}"#).is_err());
+    }
+
+    #[test]
+    fn test_entry_rejects_other_edits() {
+        // Normalization or a filter command changed more than the spans.
+        let original = "Cafe\u{301} 🚀 ✅";
+        assert!(
+            Entry::new("a.md", original, "Café  ", &[7..11, 12..15], |_| {
+                String::new()
+            })
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! Cleaning many texts at once on every core, for services that sanitize
+//! records in bulk.
+//!
+//! The batch is a slice split over scoped std threads. Integration with
+//! rayon, taking an `IntoParallelIterator` behind a feature, is not done.
+//!
+//! ```
+//! use nomoji::par_remove;
+//!
+//! let records = vec!["Ship 🚀".to_string(), "plain".to_string(), "✅ done".to_string()];
+//! let cleaned = par_remove(&records);
+//! assert_eq!(cleaned[0], ("Ship ".to_string(), 1));
+//! assert_eq!(cleaned[1], ("plain".to_string(), 0));
+//! assert_eq!(cleaned[2], (" done".to_string(), 1));
+//! ```
+
+use std::thread;
+
+use crate::{RemoveOptions, remove_emojis, remove_emojis_with};
+
+/// Below this many texts a batch is cleaned on the calling thread, where
+/// starting threads would cost more than it saves.
+const MIN_PARALLEL: usize = 64;
+
+/// `remove_emojis` applied to each of `texts`, spread over the available
+/// cores. Results are in the order of `texts`.
+pub fn par_remove<S: AsRef<str> + Sync>(texts: &[S]) -> Vec<(String, usize)> {
+    par_map(texts, remove_emojis)
+}
+
+/// `remove_emojis_with` applied to each of `texts`, spread over the
+/// available cores. Results are in the order of `texts`.
+pub fn par_remove_with<S: AsRef<str> + Sync>(
+    texts: &[S],
+    options: &RemoveOptions,
+) -> Vec<(String, usize)> {
+    par_map(texts, |text| remove_emojis_with(text, options))
+}
+
+/// Runs `clean` over `texts` in one contiguous chunk per core, so each
+/// thread's results can be appended in order without sorting.
+fn par_map<S, F>(texts: &[S], clean: F) -> Vec<(String, usize)>
+where
+    S: AsRef<str> + Sync,
+    F: Fn(&str) -> (String, usize) + Sync,
+{
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    if threads == 1 || texts.len() < MIN_PARALLEL {
+        return texts.iter().map(|text| clean(text.as_ref())).collect();
+    }
+
+    let chunk = texts.len().div_ceil(threads);
+    thread::scope(|scope| {
+        let workers: Vec<_> = texts
+            .chunks(chunk)
+            .map(|chunk| {
+                let clean = &clean;
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|text| clean(text.as_ref()))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(texts.len());
+        for worker in workers {
+            match worker.join() {
+                Ok(cleaned) => results.extend(cleaned),
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        }
+        results
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::category::Category;
+
+    #[test]
+    fn test_par_remove_keeps_order() {
+        let texts: Vec<String> = (0..1000)
+            .map(|i| match i % 3 {
+                0 => format!("{} 🚀", i),
+                1 => format!("{} 👍🏽 ✅", i),
+                _ => i.to_string(),
+            })
+            .collect();
+        let expected: Vec<(String, usize)> = texts.iter().map(|t| remove_emojis(t)).collect();
+        assert_eq!(par_remove(&texts), expected);
+        assert_eq!(par_remove(&texts[..5]), expected[..5]);
+        assert!(par_remove::<&str>(&[]).is_empty());
+
+        let options = RemoveOptions::new().skip(Category::CheckMarks);
+        assert_eq!(
+            par_remove_with(&["a ✅ 🚀"], &options),
+            [("a ✅ ".to_string(), 1)]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Named groups of emoji sequences for `--only`, `--skip` and the
+//! configuration file's `keep` list.
+//!
+//! A sequence belongs to a category by its first character, except `flags`
+//! and `keycaps`, which look at the whole sequence. Categories may overlap:
+//! ✅ is both a check mark and a dingbat.
+
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum Category {
+    /// ✅ ☑️ ✔️ ❌ ❎ ✖️ ⚠️ and similar status marks
+    CheckMarks,
+    /// ⚠️ ⛔ 🚫 ❗ ❓ 🚨 and other warning signs
+    Warnings,
+    /// ➡️ ⬅️ ↩️ 🔄 🔙 and other arrows
+    Arrows,
+    /// ❤️ 💔 💛 🧡 and other hearts
+    Hearts,
+    /// 👍 👋 👏 🙏 ✌️ and other hand gestures
+    Hands,
+    /// Country flags (pairs of regional indicators)
+    Flags,
+    /// Keycap sequences such as #️⃣ and 1️⃣
+    Keycaps,
+    /// © ® ™
+    Legal,
+    /// The Emoticons block (U+1F600-U+1F64F)
+    Emoticons,
+    /// Miscellaneous Symbols and Pictographs (U+1F300-U+1F5FF)
+    Pictographs,
+    /// Transport and Map Symbols (U+1F680-U+1F6FF)
+    Transport,
+    /// Miscellaneous Symbols (U+2600-U+26FF)
+    Symbols,
+    /// Dingbats (U+2700-U+27BF)
+    Dingbats,
+    /// Geometric Shapes Extended (U+1F780-U+1F7FF) and emoji squares and
+    /// circles elsewhere
+    Geometric,
+    /// Supplemental Symbols and Pictographs and Extended-A/B
+    /// (U+1F900-U+1F9FF, U+1FA00-U+1FAFF)
+    Supplemental,
+}
+
+impl Category {
+    pub const ALL: [Category; 15] = [
+        Category::CheckMarks,
+        Category::Warnings,
+        Category::Arrows,
+        Category::Hearts,
+        Category::Hands,
+        Category::Flags,
+        Category::Keycaps,
+        Category::Legal,
+        Category::Emoticons,
+        Category::Pictographs,
+        Category::Transport,
+        Category::Symbols,
+        Category::Dingbats,
+        Category::Geometric,
+        Category::Supplemental,
+    ];
+
+    /// The category's command-line name, e.g. `check-marks`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::CheckMarks => "check-marks",
+            Category::Warnings => "warnings",
+            Category::Arrows => "arrows",
+            Category::Hearts => "hearts",
+            Category::Hands => "hands",
+            Category::Flags => "flags",
+            Category::Keycaps => "keycaps",
+            Category::Legal => "legal",
+            Category::Emoticons => "emoticons",
+            Category::Pictographs => "pictographs",
+            Category::Transport => "transport",
+            Category::Symbols => "symbols",
+            Category::Dingbats => "dingbats",
+            Category::Geometric => "geometric",
+            Category::Supplemental => "supplemental",
+        }
+    }
+
+    /// Looks up a category by its command-line name, as used in
+    /// configuration files.
+    pub fn from_name(name: &str) -> Result<Category, String> {
+        Category::ALL
+            .into_iter()
+            .find(|c| c.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Category::ALL.iter().map(|c| c.name()).collect();
+                format!(
+                    "unknown category '{}' (expected one of: {})",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+
+    /// Whether `sequence`, one span from `emoji_spans`, belongs to this
+    /// category.
+    pub fn matches(self, sequence: &str) -> bool {
+        match self {
+            Category::Flags => crate::is_flag(sequence),
+            Category::Keycaps => sequence.contains('\u{20E3}'),
+            _ => sequence
+                .chars()
+                .next()
+                .is_some_and(|base| self.contains_base(base as u32)),
+        }
+    }
+
+    fn contains_base(self, code: u32) -> bool {
+        match self {
+            Category::CheckMarks => matches!(
+                code,
+                0x2705 | 0x2611 | 0x2612 | 0x2713..=0x2718 | 0x274C | 0x274E | 0x26A0 | 0x1F5F8
+            ),
+            Category::Warnings => matches!(
+                code,
+                0x26A0 | 0x26D4 | 0x1F6AB | 0x1F6A8 | 0x2753..=0x2755 | 0x2757 | 0x203C | 0x2049
+            ),
+            Category::Arrows => matches!(
+                code,
+                0x2194..=0x2199
+                    | 0x21A9..=0x21AA
+                    | 0x2934..=0x2935
+                    | 0x2B05..=0x2B07
+                    | 0x2794..=0x27AF
+                    | 0x27B1..=0x27BE
+                    | 0x1F500..=0x1F504
+                    | 0x1F519..=0x1F51D
+            ),
+            Category::Hearts => matches!(
+                code,
+                0x2763..=0x2764
+                    | 0x1F493..=0x1F49F
+                    | 0x1F5A4
+                    | 0x1F90D..=0x1F90E
+                    | 0x1F9E1
+                    | 0x1FA75..=0x1FA77
+            ),
+            Category::Hands => matches!(
+                code,
+                0x261D
+                    | 0x270A..=0x270D
+                    | 0x1F446..=0x1F450
+                    | 0x1F590
+                    | 0x1F595..=0x1F596
+                    | 0x1F64C
+                    | 0x1F64F
+                    | 0x1F90C
+                    | 0x1F90F
+                    | 0x1F918..=0x1F91F
+                    | 0x1F932
+                    | 0x1FAF0..=0x1FAF8
+            ),
+            Category::Legal => matches!(code, 0x00A9 | 0x00AE | 0x2122),
+            Category::Emoticons => (0x1F600..=0x1F64F).contains(&code),
+            Category::Pictographs => (0x1F300..=0x1F5FF).contains(&code),
+            Category::Transport => (0x1F680..=0x1F6FF).contains(&code),
+            Category::Symbols => (0x2600..=0x26FF).contains(&code),
+            Category::Dingbats => (0x2700..=0x27BF).contains(&code),
+            Category::Geometric => matches!(
+                code,
+                0x1F780..=0x1F7FF | 0x25FD..=0x25FE | 0x2B1B..=0x2B1C | 0x2B50 | 0x2B55
+            ),
+            Category::Supplemental => (0x1F900..=0x1FAFF).contains(&code),
+            Category::Flags | Category::Keycaps => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        assert!(Category::CheckMarks.matches("✅"));
+        assert!(Category::CheckMarks.matches("⚠\u{FE0F}"));
+        assert!(Category::Dingbats.matches("✅"));
+        assert!(!Category::CheckMarks.matches("🚀"));
+        assert!(Category::Arrows.matches("➡"));
+        assert!(Category::Hearts.matches("❤\u{FE0F}"));
+        assert!(Category::Hands.matches("👍🏽"));
+        assert!(Category::Flags.matches("🇫🇷"));
+        assert!(!Category::Flags.matches("🇫"));
+        assert!(Category::Keycaps.matches("\u{FE0F}\u{20E3}"));
+        assert!(Category::Transport.matches("🚀"));
+        assert!(!Category::Legal.matches(""));
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Category::from_name("check-marks"), Ok(Category::CheckMarks));
+        assert_eq!(Category::CheckMarks.name(), "check-marks");
+        let error = Category::from_name("smileys").unwrap_err();
+        assert!(error.contains("unknown category 'smileys'"));
+        assert!(error.contains("arrows"));
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn test_names_match_the_command_line() {
+        let variants = Category::value_variants();
+        assert_eq!(variants, Category::ALL);
+        for category in variants {
+            let value = category.to_possible_value().unwrap();
+            assert_eq!(value.get_name(), category.name());
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Checkstyle XML reports (`--format checkstyle`), which CI plugins such as
+//! Jenkins Warnings NG and reviewdog read.
+
+use std::io::{self, Write};
+
+use crate::ProcessResult;
+use crate::selftest::codepoints;
+
+pub fn print(results: &[&ProcessResult], out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(out, "<checkstyle version=\"8.0\">")?;
+    for result in results {
+        let mut errors = Vec::new();
+        for finding in &result.findings {
+            errors.push(format!(
+                "line=\"{}\" column=\"{}\" severity=\"warning\" message=\"{}\" source=\"nomoji.emoji\"",
+                finding.line,
+                finding.column,
+                escape(&format!(
+                    "Emoji {} ({})",
+                    finding.emoji,
+                    codepoints(&finding.emoji)
+                ))
+            ));
+        }
+        if let Some(ref error) = result.error {
+            errors.push(format!(
+                "severity=\"error\" message=\"{}\" source=\"nomoji.failed\"",
+                escape(error)
+            ));
+        }
+        if let Some(ref reason) = result.skipped {
+            errors.push(format!(
+                "severity=\"info\" message=\"{}\" source=\"nomoji.skipped\"",
+                escape(&format!("Skipped, {}", reason))
+            ));
+        }
+        if result.name_emojis > 0 {
+            errors.push(format!(
+                "severity=\"warning\" message=\"{} emojis in the file name\" source=\"nomoji.file-name\"",
+                result.name_emojis
+            ));
+        }
+
+        if errors.is_empty() {
+            writeln!(out, "  <file name=\"{}\"/>", escape(&result.file))?;
+            continue;
+        }
+        writeln!(out, "  <file name=\"{}\">", escape(&result.file))?;
+        for error in errors {
+            writeln!(out, "    <error {}/>", error)?;
+        }
+        writeln!(out, "  </file>")?;
+    }
+    writeln!(out, "</checkstyle>")
+}
+
+/// `value` escaped for a double-quoted XML attribute. Characters XML 1.0
+/// doesn't allow at all become U+FFFD.
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' | '\n' | '\r' => out.push_str(&format!("&#{};", c as u32)),
+            '\u{0}'..='\u{1F}' | '\u{FFFE}' | '\u{FFFF}' => out.push('\u{FFFD}'),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("<a href=\"x\">&</a>\n"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;&#10;"
+        );
+        assert_eq!(escape("bell\u{7} 🚀"), "bell\u{FFFD} 🚀");
+    }
+}
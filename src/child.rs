@@ -0,0 +1,261 @@
+//! `nomoji run`: a command whose stdout and stderr are cleaned on their way
+//! to the terminal or the CI log.
+//!
+//! Output is relayed as soon as the command writes it, prompts without a
+//! newline included, through a `StreamCleaner`, so a sequence split between
+//! two writes is still removed as a whole.
+
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use nomoji::stream::StreamCleaner;
+
+/// Runs `command` with its stdout and stderr cleaned into `out` and `err`,
+/// under pseudo-terminals if `pty` is set. Returns the command's exit code,
+/// or 128 plus the signal that killed it.
+pub fn run(
+    command: &[String],
+    pty: bool,
+    out: &mut (dyn Write + Send),
+    err: &mut (dyn Write + Send),
+) -> io::Result<i32> {
+    let mut child = Command::new(&command[0]);
+    child.args(&command[1..]);
+
+    let (stdout, stderr): (Box<dyn Read + Send>, Box<dyn Read + Send>) = if pty {
+        let (out_master, out_slave) = pty::open()?;
+        let (err_master, err_slave) = pty::open()?;
+        child.stdout(out_slave).stderr(err_slave);
+        (
+            Box::new(pty::Master(out_master)),
+            Box::new(pty::Master(err_master)),
+        )
+    } else {
+        child.stdout(Stdio::piped()).stderr(Stdio::piped());
+        let mut spawned = child.spawn()?;
+        let stdout = spawned.stdout.take().expect("stdout is piped");
+        let stderr = spawned.stderr.take().expect("stderr is piped");
+        return finish(spawned, Box::new(stdout), Box::new(stderr), out, err);
+    };
+    let spawned = child.spawn()?;
+    // `child` still holds the slave ends; the masters only see end of file
+    // once every copy is closed.
+    drop(child);
+    finish(spawned, stdout, stderr, out, err)
+}
+
+fn finish(
+    mut spawned: std::process::Child,
+    stdout: Box<dyn Read + Send>,
+    stderr: Box<dyn Read + Send>,
+    out: &mut (dyn Write + Send),
+    err: &mut (dyn Write + Send),
+) -> io::Result<i32> {
+    signals::forward(spawned.id());
+    thread::scope(|scope| {
+        scope.spawn(|| relay(stdout, out));
+        scope.spawn(|| relay(stderr, err));
+    });
+    let status = spawned.wait()?;
+    Ok(exit_code(status))
+}
+
+/// Copies `reader` to `writer`, cleaned. A writer that stops accepting
+/// output, such as a closed pipe, just ends the copy.
+fn relay(mut reader: impl Read, writer: &mut dyn Write) {
+    let mut stripper = StreamCleaner::default();
+    let mut buffer = [0; 8192];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        let cleaned = stripper.feed(&buffer[..read]);
+        if writer
+            .write_all(&cleaned)
+            .and_then(|_| writer.flush())
+            .is_err()
+        {
+            return;
+        }
+    }
+    let _ = writer.write_all(&stripper.finish());
+    let _ = writer.flush();
+}
+
+#[cfg(unix)]
+fn exit_code(status: std::process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    match (status.code(), status.signal()) {
+        (Some(code), _) => code,
+        (None, Some(signal)) => 128 + signal,
+        (None, None) => 1,
+    }
+}
+
+#[cfg(not(unix))]
+fn exit_code(status: std::process::ExitStatus) -> i32 {
+    status.code().unwrap_or(1)
+}
+
+#[cfg(unix)]
+mod pty {
+    use std::ffi::CStr;
+    use std::fs::{File, OpenOptions};
+    use std::io::{self, Read};
+    use std::os::fd::{AsRawFd, FromRawFd};
+    use std::os::unix::fs::OpenOptionsExt;
+
+    /// A new pseudo-terminal as its master and slave ends. The slave copies
+    /// the size of the terminal nomoji runs in, if any, and doesn't turn
+    /// newlines into CRLF, so the output reads the same as through a pipe.
+    pub fn open() -> io::Result<(File, File)> {
+        // SAFETY: each call is checked, and the master descriptor is owned
+        // by the File as soon as it exists.
+        let master = unsafe {
+            let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let master = File::from_raw_fd(fd);
+            if libc::grantpt(fd) != 0 || libc::unlockpt(fd) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            master
+        };
+        // SAFETY: ptsname returns a static buffer, copied before anything
+        // else can call it; nomoji opens terminals from one thread only.
+        let name = unsafe {
+            let name = libc::ptsname(master.as_raw_fd());
+            if name.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            CStr::from_ptr(name).to_string_lossy().into_owned()
+        };
+        let slave = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(name)?;
+
+        // SAFETY: termios and winsize are plain structs the calls fill in.
+        unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(slave.as_raw_fd(), &mut termios) == 0 {
+                termios.c_oflag &= !libc::ONLCR;
+                libc::tcsetattr(slave.as_raw_fd(), libc::TCSANOW, &termios);
+            }
+            let mut size: libc::winsize = std::mem::zeroed();
+            if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) == 0 {
+                libc::ioctl(slave.as_raw_fd(), libc::TIOCSWINSZ, &size);
+            }
+        }
+        Ok((master, slave))
+    }
+
+    /// The master end of a pseudo-terminal. Once the command and everything
+    /// it started have closed the slave, Linux fails reads with EIO rather
+    /// than returning end of file; that is reported as end of file here.
+    pub struct Master(pub File);
+
+    impl Read for Master {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.read(buf) {
+                Err(e) if e.raw_os_error() == Some(libc::EIO) => Ok(0),
+                result => result,
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod pty {
+    use std::fs::File;
+    use std::io;
+
+    pub fn open() -> io::Result<(File, File)> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "--pty needs a Unix system",
+        ))
+    }
+
+    pub struct Master(pub File);
+
+    impl io::Read for Master {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+}
+
+#[cfg(unix)]
+mod signals {
+    use std::sync::atomic::{AtomicI32, Ordering};
+
+    static CHILD: AtomicI32 = AtomicI32::new(0);
+
+    /// Passes SIGTERM and SIGHUP on to the command, so a cancelled CI job
+    /// stops it too, and keeps nomoji alive to relay what it writes while it
+    /// exits. SIGINT from the terminal already reaches the command, being in
+    /// the same process group, so nomoji only has to survive it.
+    pub fn forward(pid: u32) {
+        extern "C" fn handle(signal: libc::c_int) {
+            let pid = CHILD.load(Ordering::SeqCst);
+            if signal != libc::SIGINT && pid > 0 {
+                // SAFETY: kill is async-signal-safe.
+                unsafe {
+                    libc::kill(pid, signal);
+                }
+            }
+        }
+
+        CHILD.store(pid as i32, Ordering::SeqCst);
+        let handler = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+            // SAFETY: the handler only touches an atomic and calls kill.
+            unsafe {
+                libc::signal(signal, handler);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod signals {
+    pub fn forward(_pid: u32) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run() {
+        let command = |script: &str| {
+            ["sh", "-c", script]
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+        };
+        let script = "echo 'out 🚀'; echo 'err ✅' >&2; [ -t 1 ] && echo tty; exit 3";
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let code = run(&command(script), false, &mut out, &mut err).unwrap();
+        assert_eq!(code, 3);
+        assert_eq!(String::from_utf8(out).unwrap(), "out \n");
+        assert_eq!(String::from_utf8(err).unwrap(), "err \n");
+
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let code = run(&command(script), true, &mut out, &mut err).unwrap();
+        assert_eq!(code, 3);
+        assert_eq!(String::from_utf8(out).unwrap(), "out \ntty\n");
+        assert_eq!(String::from_utf8(err).unwrap(), "err \n");
+
+        let missing = run(&command("")[2..], false, &mut Vec::new(), &mut Vec::new());
+        assert!(missing.is_err());
+    }
+}
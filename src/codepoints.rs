@@ -0,0 +1,82 @@
+//! Code point ranges for `--exclude-range`.
+
+use std::ops::RangeInclusive;
+
+/// Parses `U+2600..U+26FF`, or a single `U+2705`. The `U+` prefix is
+/// optional and the digits are hexadecimal.
+pub fn parse_range(value: &str) -> Result<RangeInclusive<char>, String> {
+    let (first, last) = value.split_once("..").unwrap_or((value, value));
+    let (first, last) = (parse_code_point(first)?, parse_code_point(last)?);
+    if first > last {
+        return Err(format!(
+            "invalid range '{}': it ends before it starts",
+            value
+        ));
+    }
+    Ok(first..=last)
+}
+
+fn parse_code_point(value: &str) -> Result<char, String> {
+    let value = value.trim();
+    let digits = value
+        .strip_prefix("U+")
+        .or_else(|| value.strip_prefix("u+"))
+        .unwrap_or(value);
+    u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| format!("invalid code point '{}': expected U+XXXX", value))
+}
+
+/// `ranges` sorted, with overlapping and adjacent ones joined.
+pub fn merge(mut ranges: Vec<RangeInclusive<char>>) -> Vec<RangeInclusive<char>> {
+    ranges.sort_by_key(|range| *range.start());
+    let mut merged: Vec<RangeInclusive<char>> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if (*last.end() as u32).saturating_add(1) >= *range.start() as u32 => {
+                if range.end() > last.end() {
+                    *last = *last.start()..=*range.end();
+                }
+            }
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Whether `c` is in one of `ranges`, which must come from `merge`.
+pub fn contains(ranges: &[RangeInclusive<char>], c: char) -> bool {
+    let i = ranges.partition_point(|range| *range.end() < c);
+    ranges.get(i).is_some_and(|range| *range.start() <= c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("U+2600..U+26FF"), Ok('\u{2600}'..='\u{26FF}'));
+        assert_eq!(parse_range("2705"), Ok('\u{2705}'..='\u{2705}'));
+        assert_eq!(parse_range("u+1f600..1F64F"), Ok('\u{1F600}'..='\u{1F64F}'));
+        assert!(parse_range("U+26FF..U+2600").is_err());
+        assert!(parse_range("U+D800").is_err());
+        assert!(parse_range("rocket").is_err());
+    }
+
+    #[test]
+    fn test_merge_and_contains() {
+        let ranges = merge(vec![
+            '\u{2700}'..='\u{27BF}',
+            '\u{2600}'..='\u{2650}',
+            '\u{2640}'..='\u{26FF}',
+            '\u{1F680}'..='\u{1F680}',
+        ]);
+        assert_eq!(ranges, ['\u{2600}'..='\u{27BF}', '\u{1F680}'..='\u{1F680}']);
+        assert!(contains(&ranges, '\u{2705}'));
+        assert!(contains(&ranges, '\u{1F680}'));
+        assert!(!contains(&ranges, '\u{1F681}'));
+        assert!(!contains(&ranges, 'a'));
+    }
+}
@@ -0,0 +1,114 @@
+//! `--color` and `NO_COLOR`: whether reports are highlighted with ANSI
+//! escape sequences.
+//!
+//! With the default `auto`, color is used only when the output is a
+//! terminal, `NO_COLOR` is unset or empty (see <https://no-color.org>) and
+//! `TERM` isn't `dumb`. `always` and `never` override all of that.
+
+use std::env;
+use std::ffi::OsString;
+use std::fmt::Display;
+
+use clap::ValueEnum;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum When {
+    /// Color output to a terminal unless NO_COLOR is set
+    #[default]
+    Auto,
+    /// Always color, even when redirected
+    Always,
+    /// Never color
+    Never,
+}
+
+/// How to highlight output: a no-op unless color is on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Palette {
+    enabled: bool,
+}
+
+impl Palette {
+    /// The palette for output to a terminal or not, as `when` and the
+    /// environment decide.
+    pub fn new(when: When, is_terminal: bool) -> Self {
+        Self::from_env(
+            when,
+            is_terminal,
+            env::var_os("NO_COLOR"),
+            env::var_os("TERM"),
+        )
+    }
+
+    fn from_env(
+        when: When,
+        is_terminal: bool,
+        no_color: Option<OsString>,
+        term: Option<OsString>,
+    ) -> Self {
+        let enabled = match when {
+            When::Always => true,
+            When::Never => false,
+            When::Auto => {
+                is_terminal
+                    && no_color.is_none_or(|value| value.is_empty())
+                    && term.is_none_or(|term| term != "dumb")
+            }
+        };
+        Palette { enabled }
+    }
+
+    pub fn bold(self, text: impl Display) -> String {
+        self.paint("1", text)
+    }
+
+    pub fn red(self, text: impl Display) -> String {
+        self.paint("31", text)
+    }
+
+    pub fn green(self, text: impl Display) -> String {
+        self.paint("32", text)
+    }
+
+    pub fn yellow(self, text: impl Display) -> String {
+        self.paint("33", text)
+    }
+
+    pub fn magenta(self, text: impl Display) -> String {
+        self.paint("35", text)
+    }
+
+    fn paint(self, code: &str, text: impl Display) -> String {
+        if self.enabled {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_env() {
+        let palette = |when, tty, no_color: Option<&str>, term: Option<&str>| {
+            Palette::from_env(when, tty, no_color.map(Into::into), term.map(Into::into)).enabled
+        };
+        assert!(palette(When::Auto, true, None, Some("xterm")));
+        assert!(palette(When::Auto, true, Some(""), None));
+        assert!(!palette(When::Auto, false, None, None));
+        assert!(!palette(When::Auto, true, Some("1"), None));
+        assert!(!palette(When::Auto, true, None, Some("dumb")));
+        assert!(palette(When::Always, false, Some("1"), Some("dumb")));
+        assert!(!palette(When::Never, true, None, None));
+    }
+
+    #[test]
+    fn test_paint() {
+        let on = Palette::from_env(When::Always, false, None, None);
+        assert_eq!(on.red("ERROR"), "\x1b[31mERROR\x1b[0m");
+        assert_eq!(Palette::default().red("ERROR"), "ERROR");
+    }
+}
@@ -0,0 +1,575 @@
+//! Project configuration read from `.nomoji.toml`.
+//!
+//! The file in the current directory, or the one named by `--config`, is
+//! the top-level configuration. Files in directories below it apply to the
+//! files beneath them, nearer ones first, so a subproject can carry its own
+//! policy.
+//!
+//! ```toml
+//! # Keep CI status marks in docs; remove every other emoji.
+//! keep = ["check-marks", "arrows"]
+//!
+//! # Clean each file the way its type calls for, as `--auto` does.
+//! auto = true
+//!
+//! # The first rule whose glob matches a file decides how it is cleaned.
+//! [[rules]]
+//! glob = "*.py"
+//! mode = "comments"
+//!
+//! # Chosen with `--profile docs`.
+//! [profiles.docs]
+//! skip-urls = true
+//! skip-inline-code = true
+//!
+//! # Commit types for `--gitmoji-convert`, by emoji or :code:.
+//! [gitmoji]
+//! "🦀" = "refactor"
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::glob::glob_match;
+use crate::mode::Mode;
+use crate::toml::{self, Table, Value};
+use nomoji::category::Category;
+
+/// Configuration file looked up in the current directory.
+pub const FILE_NAME: &str = ".nomoji.toml";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    /// Categories left in place unless named by `--only`.
+    pub keep: Vec<Category>,
+    /// Per-file-type behaviour, tried in order.
+    pub rules: Vec<Rule>,
+    /// Named bundles of settings, by name.
+    pub profiles: BTreeMap<String, Profile>,
+    /// Whether `--recursive` edits in place without `--write`.
+    pub write_recursive: bool,
+    /// Whether configurations in directories above are ignored.
+    pub root: bool,
+    /// Whether `--auto` is on unless `--no-auto` is given.
+    pub auto: bool,
+    /// Gitmoji and the commit types they convert to, tried before
+    /// `gitmoji::DEFAULT`.
+    pub gitmoji: Vec<(String, String)>,
+}
+
+/// A configuration file below the current directory, applied to the files
+/// beneath it before the configurations farther up.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Local {
+    /// Replaces the `keep` list of the configurations farther up.
+    pub keep: Option<Vec<Category>>,
+    /// Tried before the rules farther up. Globs match paths relative to the
+    /// directory holding the file.
+    pub rules: Vec<Rule>,
+    /// Whether the configurations farther up, the top-level one included,
+    /// are ignored.
+    pub root: bool,
+}
+
+/// Settings chosen together with `--profile NAME`. Flags given on the
+/// command line win over the profile's.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Profile {
+    /// Replaces the top-level `keep` list.
+    pub keep: Option<Vec<Category>>,
+    /// Replaces the top-level rules.
+    pub rules: Option<Vec<Rule>>,
+    /// Used unless `--only` is given.
+    pub only: Vec<Category>,
+    /// Used unless `--skip` is given.
+    pub skip: Vec<Category>,
+    pub conservative: bool,
+    pub aggressive: bool,
+    pub keep_flags: bool,
+    pub escapes: bool,
+    pub skip_urls: bool,
+    pub skip_inline_code: bool,
+}
+
+/// How files matching one glob are cleaned.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rule {
+    pub glob: String,
+    pub mode: Mode,
+    /// Replaces the top-level `keep` list for matching files.
+    pub keep: Option<Vec<Category>>,
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Config, String> {
+        Config::from_table(toml::parse(text)?)
+    }
+
+    fn from_table(table: Table) -> Result<Config, String> {
+        let mut config = Config::default();
+
+        for (key, value) in table {
+            match key.as_str() {
+                "keep" => config.keep = categories(&key, value)?,
+                "rules" => config.rules = rules(value)?,
+                "profiles" => config.profiles = profiles(value)?,
+                "gitmoji" => config.gitmoji = gitmoji(value)?,
+                "write-recursive" | "root" | "auto" => {
+                    let switch = match key.as_str() {
+                        "root" => &mut config.root,
+                        "auto" => &mut config.auto,
+                        _ => &mut config.write_recursive,
+                    };
+                    match value {
+                        Value::Boolean(on) => *switch = on,
+                        other => {
+                            return Err(format!(
+                                "'{}' must be a boolean, not {}",
+                                key,
+                                other.type_name()
+                            ));
+                        }
+                    }
+                }
+                _ => return Err(format!("unknown key '{}'", key)),
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// The first rule whose glob matches `path`.
+    pub fn rule_for(&self, path: &str) -> Option<&Rule> {
+        self.rules.iter().find(|rule| glob_match(&rule.glob, path))
+    }
+
+    /// The profile called `name`.
+    pub fn profile(&self, name: &str) -> Result<&Profile, String> {
+        self.profiles.get(name).ok_or_else(|| {
+            let names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            match names.is_empty() {
+                true => format!("unknown profile '{}': no profiles are defined", name),
+                false => format!("unknown profile '{}' (defined: {})", name, names.join(", ")),
+            }
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Config::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+impl Local {
+    /// Profiles and `write-recursive` are accepted, so a subproject's file
+    /// also works as the top-level one, but only apply there.
+    pub fn parse(text: &str) -> Result<Local, String> {
+        let table = toml::parse(text)?;
+        let has_keep = table.contains_key("keep");
+        let config = Config::from_table(table)?;
+        Ok(Local {
+            keep: has_keep.then_some(config.keep),
+            rules: config.rules,
+            root: config.root,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Local, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Local::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// The first rule whose glob matches `path`, relative to the directory
+    /// of this file.
+    pub fn rule_for(&self, path: &str) -> Option<&Rule> {
+        self.rules.iter().find(|rule| glob_match(&rule.glob, path))
+    }
+}
+
+/// The configuration file in the current directory, if there is one.
+pub fn discover() -> Option<PathBuf> {
+    let path = PathBuf::from(FILE_NAME);
+    path.is_file().then_some(path)
+}
+
+/// `file` relative to the current directory, without `.` components, or
+/// `None` if it isn't below it.
+pub fn relative(file: &str) -> Option<PathBuf> {
+    let path = Path::new(file);
+    let cwd;
+    let path = match path.is_absolute() {
+        true => {
+            cwd = std::env::current_dir().ok()?;
+            path.strip_prefix(&cwd).ok()?
+        }
+        false => path,
+    };
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(relative)
+}
+
+/// The directories that may hold a configuration for the file at
+/// `relative`, from its own up to the one below the current directory.
+pub fn local_dirs(relative: &Path) -> impl Iterator<Item = &Path> {
+    relative
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| !dir.as_os_str().is_empty())
+}
+
+fn gitmoji(value: Value) -> Result<Vec<(String, String)>, String> {
+    let Value::Table(table) = value else {
+        return Err(format!(
+            "'gitmoji' must be a table of commit types ([gitmoji]), not {}",
+            value.type_name()
+        ));
+    };
+    table
+        .into_iter()
+        .map(|(key, value)| match value {
+            Value::String(kind) => crate::gitmoji::check_type(&kind)
+                .map(|()| (key.clone(), kind))
+                .map_err(|e| format!("gitmoji.{}: {}", key, e)),
+            other => Err(format!(
+                "gitmoji.{} must be a string, not {}",
+                key,
+                other.type_name()
+            )),
+        })
+        .collect()
+}
+
+fn rules(value: Value) -> Result<Vec<Rule>, String> {
+    let Value::Array(items) = value else {
+        return Err("'rules' must be an array of tables ([[rules]])".to_string());
+    };
+
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| match item {
+            Value::Table(table) => rule(table).map_err(|e| format!("rules[{}]: {}", i, e)),
+            other => Err(format!(
+                "rules[{}] must be a table, not {}",
+                i,
+                other.type_name()
+            )),
+        })
+        .collect()
+}
+
+fn rule(table: Table) -> Result<Rule, String> {
+    let mut rule = Rule::default();
+    let mut has_glob = false;
+
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("glob", Value::String(glob)) => {
+                rule.glob = glob;
+                has_glob = true;
+            }
+            ("mode", Value::String(mode)) => rule.mode = Mode::from_name(&mode)?,
+            ("keep", value) => rule.keep = Some(categories(&key, value)?),
+            ("glob" | "mode", value) => {
+                return Err(format!(
+                    "'{}' must be a string, not {}",
+                    key,
+                    value.type_name()
+                ));
+            }
+            _ => return Err(format!("unknown key '{}'", key)),
+        }
+    }
+
+    if !has_glob {
+        return Err("missing 'glob'".to_string());
+    }
+    Ok(rule)
+}
+
+fn profiles(value: Value) -> Result<BTreeMap<String, Profile>, String> {
+    let Value::Table(table) = value else {
+        return Err(format!(
+            "'profiles' must be a table of profiles ([profiles.NAME]), not {}",
+            value.type_name()
+        ));
+    };
+
+    table
+        .into_iter()
+        .map(|(name, value)| match value {
+            Value::Table(table) => profile(table)
+                .map(|profile| (name.clone(), profile))
+                .map_err(|e| format!("profiles.{}: {}", name, e)),
+            other => Err(format!(
+                "profiles.{} must be a table, not {}",
+                name,
+                other.type_name()
+            )),
+        })
+        .collect()
+}
+
+fn profile(table: Table) -> Result<Profile, String> {
+    let mut profile = Profile::default();
+
+    for (key, value) in table {
+        let switch = match key.as_str() {
+            "keep" => {
+                profile.keep = Some(categories(&key, value)?);
+                continue;
+            }
+            "rules" => {
+                profile.rules = Some(rules(value)?);
+                continue;
+            }
+            "only" => {
+                profile.only = categories(&key, value)?;
+                continue;
+            }
+            "skip" => {
+                profile.skip = categories(&key, value)?;
+                continue;
+            }
+            "conservative" => &mut profile.conservative,
+            "aggressive" => &mut profile.aggressive,
+            "keep-flags" => &mut profile.keep_flags,
+            "escapes" => &mut profile.escapes,
+            "skip-urls" => &mut profile.skip_urls,
+            "skip-inline-code" => &mut profile.skip_inline_code,
+            _ => return Err(format!("unknown key '{}'", key)),
+        };
+        match value {
+            Value::Boolean(on) => *switch = on,
+            other => {
+                return Err(format!(
+                    "'{}' must be a boolean, not {}",
+                    key,
+                    other.type_name()
+                ));
+            }
+        }
+    }
+
+    if profile.conservative && profile.aggressive {
+        return Err("'conservative' and 'aggressive' can't both be set".to_string());
+    }
+    Ok(profile)
+}
+
+/// Reads an array of category names stored under `key`.
+pub fn categories(key: &str, value: Value) -> Result<Vec<Category>, String> {
+    let Value::Array(items) = value else {
+        return Err(format!(
+            "'{}' must be an array of category names, not {}",
+            key,
+            value.type_name()
+        ));
+    };
+
+    items
+        .into_iter()
+        .map(|item| match item {
+            Value::String(name) => Category::from_name(&name),
+            other => Err(format!(
+                "'{}' entries must be strings, not {}",
+                key,
+                other.type_name()
+            )),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keep() {
+        let config = Config::parse("keep = [\"check-marks\", \"arrows\"]\n").unwrap();
+        assert_eq!(config.keep, [Category::CheckMarks, Category::Arrows]);
+        assert_eq!(Config::parse("# empty\n").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let config = Config::parse(
+            r#"
+[[rules]]
+glob = "*.md"
+mode = "markdown"
+
+[[rules]]
+glob = "docs/**"
+keep = ["arrows"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.rule_for("guide/intro.md").unwrap().mode,
+            Mode::Markdown
+        );
+        let docs = config.rule_for("docs/notes.txt").unwrap();
+        assert_eq!(docs.mode, Mode::All);
+        assert_eq!(docs.keep, Some(vec![Category::Arrows]));
+        assert!(config.rule_for("main.rs").is_none());
+
+        assert_eq!(
+            Config::parse("[[rules]]\nmode = \"comments\"").unwrap_err(),
+            "rules[0]: missing 'glob'"
+        );
+        assert!(
+            Config::parse("[[rules]]\nglob = \"*\"\nmode = \"prose\"")
+                .unwrap_err()
+                .contains("unknown mode 'prose'")
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(
+            Config::parse("keep = \"arrows\"")
+                .unwrap_err()
+                .contains("must be an array")
+        );
+        assert!(
+            Config::parse("keep = [\"smileys\"]")
+                .unwrap_err()
+                .contains("unknown category 'smileys'")
+        );
+        assert_eq!(Config::parse("kep = []").unwrap_err(), "unknown key 'kep'");
+        assert!(
+            Config::parse("write-recursive = true")
+                .unwrap()
+                .write_recursive
+        );
+        assert!(
+            Config::parse("auto = \"yes\"")
+                .unwrap_err()
+                .contains("'auto' must be a boolean")
+        );
+        assert!(Config::parse("auto = true").unwrap().auto);
+        assert!(
+            Config::parse("write-recursive = 1")
+                .unwrap_err()
+                .contains("must be a boolean")
+        );
+    }
+
+    #[test]
+    fn test_parse_local() {
+        let local = Local::parse(
+            "root = true\nkeep = []\n[[rules]]\nglob = \"docs/*.md\"\nmode = \"markdown\"\n",
+        )
+        .unwrap();
+        assert!(local.root);
+        assert_eq!(local.keep, Some(vec![]));
+        assert!(local.rule_for("docs/intro.md").is_some());
+        assert_eq!(Local::parse("").unwrap(), Local::default());
+        assert!(
+            Local::parse("root = \"yes\"")
+                .unwrap_err()
+                .contains("must be a boolean")
+        );
+    }
+
+    #[test]
+    fn test_local_dirs() {
+        let dirs = |file: &str| -> Vec<String> {
+            let relative = relative(file).unwrap();
+            local_dirs(&relative)
+                .map(|dir| dir.to_string_lossy().replace('\\', "/"))
+                .collect()
+        };
+        assert_eq!(
+            dirs("./services/api/src/main.rs"),
+            ["services/api/src", "services/api", "services"]
+        );
+        assert!(dirs("README.md").is_empty());
+        assert!(relative("../other/README.md").is_none());
+
+        let cwd = std::env::current_dir().unwrap();
+        let absolute = cwd.join("docs").join("guide.md");
+        assert_eq!(dirs(absolute.to_str().unwrap()), ["docs"]);
+    }
+
+    #[test]
+    fn test_parse_gitmoji() {
+        let config =
+            Config::parse("[gitmoji]\n\"🦀\" = \"refactor(rust)\"\n\":bug:\" = \"fix\"\n").unwrap();
+        assert_eq!(config.gitmoji.len(), 2);
+        assert!(
+            config
+                .gitmoji
+                .contains(&("🦀".to_string(), "refactor(rust)".to_string()))
+        );
+        assert!(
+            Config::parse("[gitmoji]\n\"🦀\" = \"fix: \"")
+                .unwrap_err()
+                .contains("gitmoji.🦀: invalid commit type")
+        );
+        assert!(Config::parse("gitmoji = 1").is_err());
+    }
+
+    #[test]
+    fn test_parse_profiles() {
+        let config = Config::parse(
+            r#"
+keep = ["check-marks"]
+
+[profiles.docs]
+keep = ["arrows"]
+skip-urls = true
+
+[profiles.strict]
+aggressive = true
+only = ["flags", "dingbats"]
+
+[[profiles.code.rules]]
+glob = "*.rs"
+mode = "comments"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.keep, [Category::CheckMarks]);
+        let docs = config.profile("docs").unwrap();
+        assert_eq!(docs.keep, Some(vec![Category::Arrows]));
+        assert!(docs.skip_urls && !docs.skip_inline_code);
+        let strict = config.profile("strict").unwrap();
+        assert!(strict.aggressive);
+        assert_eq!(strict.only, [Category::Flags, Category::Dingbats]);
+        assert_eq!(strict.rules, None);
+        let code = config.profile("code").unwrap();
+        assert_eq!(code.rules.as_ref().unwrap()[0].mode, Mode::Comments);
+        assert_eq!(
+            config.profile("prose").unwrap_err(),
+            "unknown profile 'prose' (defined: code, docs, strict)"
+        );
+
+        assert_eq!(
+            Config::parse("[profiles.docs]\nskip-urls = \"yes\"").unwrap_err(),
+            "profiles.docs: 'skip-urls' must be a boolean, not string"
+        );
+        assert!(
+            Config::parse("[profiles.x]\nconservative = true\naggressive = true")
+                .unwrap_err()
+                .contains("can't both be set")
+        );
+        assert!(
+            Config::default()
+                .profile("docs")
+                .unwrap_err()
+                .contains("no profiles are defined")
+        );
+    }
+}
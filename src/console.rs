@@ -0,0 +1,53 @@
+//! Console setup so UTF-8 output displays correctly.
+//!
+//! A Windows console decodes the bytes written to it with its output code
+//! page, a legacy one such as 437 or 1252 unless the user changed it, so
+//! cleaned text piped through other console programs comes out mangled. The
+//! code page is switched to UTF-8 for the run and restored on exit. Other
+//! platforms need nothing.
+
+#[cfg(windows)]
+pub fn init() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    const CP_UTF8: u32 = 65001;
+    static ORIGINAL: AtomicU32 = AtomicU32::new(0);
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetConsoleOutputCP() -> u32;
+        fn SetConsoleOutputCP(code_page: u32) -> i32;
+    }
+
+    unsafe extern "C" {
+        fn atexit(callback: extern "C" fn()) -> i32;
+    }
+
+    // Runs on normal return and on std::process::exit alike.
+    extern "C" fn restore() {
+        let original = ORIGINAL.load(Ordering::SeqCst);
+        if original != 0 {
+            // SAFETY: takes no pointers; failure leaves the code page as is.
+            unsafe {
+                SetConsoleOutputCP(original);
+            }
+        }
+    }
+
+    // SAFETY: these calls take no pointers. GetConsoleOutputCP returns 0
+    // when the process has no console, e.g. when output is redirected from a
+    // service, and then nothing is changed.
+    unsafe {
+        let original = GetConsoleOutputCP();
+        if original == 0 || original == CP_UTF8 {
+            return;
+        }
+        if SetConsoleOutputCP(CP_UTF8) != 0 {
+            ORIGINAL.store(original, Ordering::SeqCst);
+            atexit(restore);
+        }
+    }
+}
+
+#[cfg(not(windows))]
+pub fn init() {}
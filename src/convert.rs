@@ -0,0 +1,87 @@
+//! Rewriting emoji into a plain ASCII form instead of removing them, for
+//! pipelines that need ASCII-only files but still want the emoji shown.
+
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+
+/// How a matched sequence is rewritten.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Conversion {
+    /// HTML numeric character references, `&#x1F680;`.
+    Entities,
+    /// String literal escapes in a programming language.
+    Escape(Language),
+}
+
+/// Languages `--to-escape` writes escapes for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum Language {
+    /// `\u{1F680}`
+    Rust,
+    /// `\uD83D\uDE80`, as UTF-16 surrogate pairs; also valid JSON
+    Js,
+    /// `\U0001F680`
+    Python,
+}
+
+impl Conversion {
+    pub fn apply(self, sequence: &str) -> String {
+        match self {
+            Conversion::Entities => entities(sequence),
+            Conversion::Escape(language) => escape(sequence, language),
+        }
+    }
+}
+
+/// One `&#xHEX;` reference per code point, so joiners, selectors and skin
+/// tones survive and the sequence renders as before.
+pub fn entities(sequence: &str) -> String {
+    sequence
+        .chars()
+        .map(|c| format!("&#x{:X};", c as u32))
+        .collect()
+}
+
+/// The escape form of every code point in `sequence`, which decodes back
+/// to the same string inside a literal of `language`.
+pub fn escape(sequence: &str, language: Language) -> String {
+    let mut escaped = String::new();
+    for c in sequence.chars() {
+        match language {
+            Language::Rust => escaped.push_str(&format!("\\u{{{:X}}}", c as u32)),
+            Language::Js => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    escaped.push_str(&format!("\\u{:04X}", unit));
+                }
+            }
+            Language::Python if (c as u32) > 0xFFFF => {
+                escaped.push_str(&format!("\\U{:08X}", c as u32))
+            }
+            Language::Python => escaped.push_str(&format!("\\u{:04X}", c as u32)),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entities() {
+        assert_eq!(entities("🚀"), "&#x1F680;");
+        assert_eq!(entities("👍🏽"), "&#x1F44D;&#x1F3FD;");
+        assert_eq!(entities("❤\u{FE0F}"), "&#x2764;&#xFE0F;");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("🚀", Language::Rust), "\\u{1F680}");
+        assert_eq!(escape("🚀", Language::Js), "\\uD83D\\uDE80");
+        assert_eq!(escape("🚀", Language::Python), "\\U0001F680");
+        assert_eq!(escape("❤\u{FE0F}", Language::Js), "\\u2764\\uFE0F");
+        assert_eq!(escape("❤\u{FE0F}", Language::Python), "\\u2764\\uFE0F");
+        assert_eq!(escape("❤\u{FE0F}", Language::Rust), "\\u{2764}\\u{FE0F}");
+    }
+}
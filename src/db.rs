@@ -0,0 +1,220 @@
+//! `nomoji db clean`: cleaning text columns of an SQLite database in place.
+//!
+//! The database is driven through the `sqlite3` command-line shell, so no
+//! SQLite library is linked in. Values are read with `-json`, and every
+//! change is written by one script inside a single transaction that
+//! `-bail` abandons at the first error. Each update also requires the old
+//! value to be unchanged, so rows edited since they were read are left
+//! alone.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::json::{self, Value};
+
+/// One text value to rewrite.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Update {
+    pub rowid: i64,
+    pub column: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// `name` as an SQL identifier.
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// `text` as an SQL string literal.
+pub fn quote_literal(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// The text values of `column` in `table`, by rowid. Values of other types
+/// are not returned, and neither is text with NUL characters, which can't
+/// be written back as a literal.
+pub fn read(
+    sqlite3: &str,
+    database: &Path,
+    table: &str,
+    column: &str,
+) -> Result<Vec<(i64, String)>, String> {
+    let column = quote_ident(column);
+    let query = format!(
+        "SELECT rowid AS id, {column} AS value FROM {} WHERE typeof({column}) = 'text';",
+        quote_ident(table)
+    );
+    let output = run(sqlite3, &["-bail", "-json"], database, Some(&query), None)?;
+    // The shell prints nothing at all for an empty result.
+    if output.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let rows = json::parse(&output)?;
+    let rows = rows.as_array().ok_or("unexpected sqlite3 output")?;
+    rows.iter()
+        .map(|row| {
+            let id = row.get("id").and_then(Value::as_i64);
+            let value = row.get("value").and_then(Value::as_str);
+            id.zip(value)
+                .map(|(id, value)| (id, value.to_string()))
+                .ok_or_else(|| "unexpected sqlite3 output".to_string())
+        })
+        .filter(|row| !matches!(row, Ok((_, value)) if value.contains('\0')))
+        .collect()
+}
+
+/// The script applying `updates` to `table` in one transaction.
+pub fn script(table: &str, updates: &[Update]) -> String {
+    let table = quote_ident(table);
+    let mut script = String::from("BEGIN IMMEDIATE;\n");
+    for update in updates {
+        let column = quote_ident(&update.column);
+        script.push_str(&format!(
+            "UPDATE {} SET {} = {} WHERE rowid = {} AND {} = {};\n",
+            table,
+            column,
+            quote_literal(&update.new),
+            update.rowid,
+            column,
+            quote_literal(&update.old)
+        ));
+    }
+    script.push_str("COMMIT;\n");
+    script
+}
+
+/// Applies `updates` to `table`; nothing is changed if any statement fails.
+pub fn apply(
+    sqlite3: &str,
+    database: &Path,
+    table: &str,
+    updates: &[Update],
+) -> Result<(), String> {
+    run(
+        sqlite3,
+        &["-bail"],
+        database,
+        None,
+        Some(&script(table, updates)),
+    )
+    .map(drop)
+}
+
+/// Runs the shell on `database` with `sql` as its argument or `stdin` as its
+/// input, returning what it printed.
+fn run(
+    sqlite3: &str,
+    flags: &[&str],
+    database: &Path,
+    sql: Option<&str>,
+    stdin: Option<&str>,
+) -> Result<String, String> {
+    let mut command = Command::new(sqlite3);
+    command.args(flags).arg(database_arg(database)).args(sql);
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run {}: {}", sqlite3, e))?;
+
+    if let Some(mut input) = child.stdin.take() {
+        input
+            .write_all(stdin.unwrap_or_default().as_bytes())
+            .map_err(|e| format!("failed to write to {}: {}", sqlite3, e))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("failed to run {}: {}", sqlite3, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} failed: {}", sqlite3, stderr.trim()));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("{} output is not UTF-8", sqlite3))
+}
+
+/// `database` as an argument the shell can't take for an option: a relative
+/// path such as `-init` is given as `./-init`.
+fn database_arg(database: &Path) -> PathBuf {
+    match database.is_relative() {
+        true => Path::new(".").join(database),
+        false => database.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_quotes_everything() {
+        let updates = [Update {
+            rowid: 7,
+            column: "bo\"dy".to_string(),
+            old: "it's 🚀".to_string(),
+            new: "it's ".to_string(),
+        }];
+        assert_eq!(
+            script("my table", &updates),
+            "BEGIN IMMEDIATE;\n\
+             UPDATE \"my table\" SET \"bo\"\"dy\" = 'it''s ' WHERE rowid = 7 AND \"bo\"\"dy\" = 'it''s 🚀';\n\
+             COMMIT;\n"
+        );
+    }
+
+    #[test]
+    fn test_database_arg_is_never_an_option() {
+        assert_eq!(database_arg(Path::new("-init")), Path::new("./-init"));
+        assert_eq!(
+            database_arg(Path::new("data/app.db")),
+            Path::new("./data/app.db")
+        );
+        let absolute = std::env::temp_dir().join("-app.db");
+        assert_eq!(database_arg(&absolute), absolute);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_and_apply_through_the_shell() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // A stand-in for sqlite3 that answers queries with canned rows and
+        // records scripts it is fed.
+        let dir = tempfile::tempdir().unwrap();
+        let fake = dir.path().join("sqlite3");
+        let fed = dir.path().join("fed.sql");
+        std::fs::write(
+            &fake,
+            format!(
+                "#!/bin/sh\n\
+                 if [ $# -eq 4 ]; then printf '%s\\n' '[{{\"id\":1,\"value\":\"Hi \\ud83d\\ude80\"}},{{\"id\":2,\"value\":\"a\\u0000b\"}}]'; \
+                 else cat > '{}'; fi\n",
+                fed.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let fake = fake.to_str().unwrap();
+
+        let rows = read(fake, Path::new("app.db"), "comments", "body").unwrap();
+        assert_eq!(rows, [(1, "Hi 🚀".to_string())]);
+
+        let updates = [Update {
+            rowid: 1,
+            column: "body".to_string(),
+            old: "Hi 🚀".to_string(),
+            new: "Hi ".to_string(),
+        }];
+        apply(fake, Path::new("app.db"), "comments", &updates).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&fed).unwrap(),
+            script("comments", &updates)
+        );
+
+        assert!(read("/nonexistent/sqlite3", Path::new("app.db"), "t", "c").is_err());
+    }
+}
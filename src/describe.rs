@@ -0,0 +1,120 @@
+//! Replacing emoji with a parenthesized description, for `--describe`, so
+//! plain-text readers and screen readers still learn what was there.
+//!
+//! Common emoji have short names in each `--lang`, after the CLDR
+//! annotations. Anything else is described by its Unicode character name,
+//! which is English whatever the language.
+
+use clap::ValueEnum;
+
+use crate::selftest;
+
+/// Languages `--lang` describes emoji in.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Lang {
+    #[default]
+    En,
+    Fr,
+    De,
+    Es,
+}
+
+/// Short names by sequence, without VS16, in English, French, German and
+/// Spanish.
+#[rustfmt::skip]
+const NAMES: &[(&str, [&str; 4])] = &[
+    ("😀", ["grinning face", "visage rieur", "grinsendes Gesicht", "cara sonriendo"]),
+    ("😃", ["grinning face with big eyes", "visage souriant avec de grands yeux", "grinsendes Gesicht mit großen Augen", "cara sonriendo con ojos grandes"]),
+    ("😂", ["face with tears of joy", "visage riant aux larmes", "Gesicht mit Freudentränen", "cara llorando de risa"]),
+    ("😅", ["grinning face with sweat", "visage souriant avec une goutte de sueur", "grinsendes Gesicht mit Schweißtropfen", "cara sonriendo con sudor frío"]),
+    ("😉", ["winking face", "visage qui fait un clin d’œil", "zwinkerndes Gesicht", "cara guiñando el ojo"]),
+    ("😊", ["smiling face with smiling eyes", "visage souriant avec yeux rieurs", "lächelndes Gesicht mit lachenden Augen", "cara feliz con ojos sonrientes"]),
+    ("😍", ["smiling face with heart-eyes", "visage souriant avec yeux en forme de cœur", "lächelndes Gesicht mit herzförmigen Augen", "cara sonriendo con ojos de corazón"]),
+    ("😎", ["smiling face with sunglasses", "visage avec lunettes de soleil", "lächelndes Gesicht mit Sonnenbrille", "cara sonriendo con gafas de sol"]),
+    ("😢", ["crying face", "visage qui pleure", "weinendes Gesicht", "cara llorando"]),
+    ("😭", ["loudly crying face", "visage qui pleure à chaudes larmes", "heulendes Gesicht", "cara llorando fuerte"]),
+    ("🙂", ["slightly smiling face", "visage avec un léger sourire", "leicht lächelndes Gesicht", "cara sonriendo ligeramente"]),
+    ("🤔", ["thinking face", "visage en pleine réflexion", "nachdenkendes Gesicht", "cara pensativa"]),
+    ("🤖", ["robot", "robot", "Roboter", "robot"]),
+    ("👀", ["eyes", "yeux", "Augen", "ojos"]),
+    ("👋", ["waving hand", "main qui fait coucou", "winkende Hand", "mano saludando"]),
+    ("👍", ["thumbs up", "pouce vers le haut", "Daumen hoch", "pulgar hacia arriba"]),
+    ("👎", ["thumbs down", "pouce vers le bas", "Daumen runter", "pulgar hacia abajo"]),
+    ("👏", ["clapping hands", "applaudissements", "klatschende Hände", "manos aplaudiendo"]),
+    ("🙏", ["folded hands", "mains en prière", "gefaltete Hände", "manos en oración"]),
+    ("💪", ["flexed biceps", "biceps contracté", "angespannter Bizeps", "bíceps flexionado"]),
+    ("❤", ["red heart", "cœur rouge", "rotes Herz", "corazón rojo"]),
+    ("💔", ["broken heart", "cœur brisé", "gebrochenes Herz", "corazón roto"]),
+    ("💯", ["hundred points", "cent points", "100 Punkte", "cien puntos"]),
+    ("🔥", ["fire", "feu", "Feuer", "fuego"]),
+    ("✨", ["sparkles", "étincelles", "funkelnde Sterne", "chispas"]),
+    ("⭐", ["star", "étoile", "Stern", "estrella"]),
+    ("☀", ["sun", "soleil", "Sonne", "sol"]),
+    ("🌍", ["globe showing Europe-Africa", "globe tourné sur l’Afrique et l’Europe", "Globus mit Europa und Afrika", "globo terráqueo mostrando Europa y África"]),
+    ("🎉", ["party popper", "cotillons", "Konfettibombe", "cañón de confeti"]),
+    ("🎂", ["birthday cake", "gâteau d’anniversaire", "Geburtstagskuchen", "tarta de cumpleaños"]),
+    ("☕", ["hot beverage", "boisson chaude", "Heißgetränk", "bebida caliente"]),
+    ("🍕", ["pizza", "pizza", "Pizza", "pizza"]),
+    ("🚀", ["rocket", "fusée", "Rakete", "cohete"]),
+    ("🐛", ["bug", "chenille", "Raupe", "insecto"]),
+    ("🐞", ["lady beetle", "coccinelle", "Marienkäfer", "mariquita"]),
+    ("💡", ["light bulb", "ampoule", "Glühbirne", "bombilla"]),
+    ("🔔", ["bell", "cloche", "Glocke", "campana"]),
+    ("🔒", ["locked", "cadenas fermé", "Schloss", "candado"]),
+    ("🔧", ["wrench", "clé à molette", "Schraubenschlüssel", "llave inglesa"]),
+    ("📦", ["package", "colis", "Paket", "paquete"]),
+    ("📌", ["pushpin", "punaise", "Reißzwecke", "chincheta"]),
+    ("📝", ["memo", "mémo", "Papier und Bleistift", "nota"]),
+    ("✅", ["check mark button", "bouton coché", "Häkchen", "botón de marca de verificación"]),
+    ("❌", ["cross mark", "croix", "Kreuzzeichen", "marca de cruz"]),
+    ("⚠", ["warning", "avertissement", "Warnung", "advertencia"]),
+];
+
+/// `sequence` as `(description)` in `lang`.
+pub fn describe(sequence: &str, lang: Lang) -> String {
+    format!("({})", name(sequence, lang))
+}
+
+/// The name of `sequence` in `lang`. Skin tones are left out, so `👍🏽` is
+/// a thumbs up.
+fn name(sequence: &str, lang: Lang) -> String {
+    let base: String = sequence
+        .chars()
+        .filter(|c| *c != '\u{FE0F}' && !('\u{1F3FB}'..='\u{1F3FF}').contains(c))
+        .collect();
+    short_name(&base, lang)
+        .map(str::to_string)
+        .or_else(|| selftest::name(&base))
+        .or_else(|| selftest::name(sequence))
+        .unwrap_or_else(|| "emoji".to_string())
+}
+
+fn short_name(sequence: &str, lang: Lang) -> Option<&'static str> {
+    NAMES
+        .iter()
+        .find(|(emoji, _)| *emoji == sequence)
+        .map(|(_, names)| names[lang as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(describe("😀", Lang::En), "(grinning face)");
+        assert_eq!(describe("🚀", Lang::Fr), "(fusée)");
+        assert_eq!(describe("❤\u{FE0F}", Lang::De), "(rotes Herz)");
+        assert_eq!(describe("👍🏽", Lang::Es), "(pulgar hacia arriba)");
+        // Without a short name, the Unicode name stands in.
+        assert_eq!(describe("🦀", Lang::Fr), "(crab)");
+    }
+
+    #[test]
+    fn test_every_emoji_has_all_languages() {
+        for (emoji, names) in NAMES {
+            assert!(!emoji.contains('\u{FE0F}'), "{}", emoji);
+            assert!(names.iter().all(|name| !name.is_empty()), "{}", emoji);
+        }
+    }
+}
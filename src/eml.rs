@@ -0,0 +1,502 @@
+//! RFC 822/MIME messages for `--eml`.
+//!
+//! Text parts are decoded from quoted-printable or base64, cleaned, and
+//! re-encoded the same way; encoded-word headers (`=?UTF-8?B?...?=`) get the
+//! same treatment. Multipart structure, attachments, non-UTF-8 text and
+//! anything unchanged by cleaning are copied byte for byte.
+
+/// Cleans one piece of decoded text, returning the result and how many
+/// characters were removed.
+pub type Clean<'a> = dyn Fn(&str) -> (String, usize) + 'a;
+
+/// Cleans `message`, returning the rewritten message and the number of
+/// characters removed across all headers and text parts.
+pub fn clean(message: &[u8], clean: &Clean) -> (Vec<u8>, usize) {
+    let mut count = 0;
+    let cleaned = clean_entity(message, clean, &mut count);
+    (cleaned, count)
+}
+
+fn clean_entity(entity: &[u8], clean: &Clean, count: &mut usize) -> Vec<u8> {
+    let (head, body) = split_head(entity);
+    let fields = fields(head);
+
+    let mut out = Vec::with_capacity(entity.len());
+    for field in &fields {
+        out.extend(clean_field(field, clean, count));
+    }
+
+    let content_type = header(&fields, "content-type").unwrap_or_default();
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let attachment = header(&fields, "content-disposition")
+        .is_some_and(|d| d.trim().to_ascii_lowercase().starts_with("attachment"));
+
+    if media_type.starts_with("multipart/") {
+        match param(&content_type, "boundary") {
+            Some(boundary) => out.extend(clean_multipart(body, &boundary, clean, count)),
+            None => out.extend_from_slice(body),
+        }
+    } else if media_type == "message/rfc822" {
+        out.extend(clean_entity(body, clean, count));
+    } else if (media_type.is_empty() || media_type.starts_with("text/")) && !attachment {
+        let charset = param(&content_type, "charset").unwrap_or_default();
+        let encoding = header(&fields, "content-transfer-encoding")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        out.extend(clean_text(body, &charset, &encoding, clean, count));
+    } else {
+        out.extend_from_slice(body);
+    }
+    out
+}
+
+/// Splits an entity after the blank line ending its header section.
+fn split_head(entity: &[u8]) -> (&[u8], &[u8]) {
+    let mut start = 0;
+    for line in entity.split_inclusive(|&b| b == b'\n') {
+        start += line.len();
+        if line == b"\n" || line == b"\r\n" {
+            return entity.split_at(start);
+        }
+    }
+    (entity, &[])
+}
+
+/// Header fields with their continuation lines, plus the blank separator
+/// line as a final field of its own.
+fn fields(head: &[u8]) -> Vec<&[u8]> {
+    let mut fields: Vec<&[u8]> = Vec::new();
+    let mut start = 0;
+    let mut end = 0;
+    for line in head.split_inclusive(|&b| b == b'\n') {
+        let continues = matches!(line.first(), Some(b' ' | b'\t'));
+        if !continues && end > start {
+            fields.push(&head[start..end]);
+            start = end;
+        }
+        end += line.len();
+    }
+    if end > start {
+        fields.push(&head[start..end]);
+    }
+    fields
+}
+
+/// The unfolded value of the first field called `name`.
+fn header(fields: &[&[u8]], name: &str) -> Option<String> {
+    fields.iter().find_map(|field| {
+        let field = String::from_utf8_lossy(field);
+        let (key, value) = field.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.replace(['\r', '\n'], ""))
+    })
+}
+
+/// A parameter such as `boundary` or `charset` from a header value.
+fn param(value: &str, name: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|part| {
+        let (key, value) = part.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+fn clean_field(field: &[u8], clean: &Clean, count: &mut usize) -> Vec<u8> {
+    let Ok(text) = std::str::from_utf8(field) else {
+        return field.to_vec();
+    };
+    let mut found = 0;
+    let decoded = replace_encoded_words(text, clean, &mut found);
+    // Raw UTF-8 headers (RFC 6532) may hold emojis outside encoded words.
+    let (cleaned, raw) = clean(&decoded);
+    if found + raw == 0 {
+        return field.to_vec();
+    }
+    *count += found + raw;
+    cleaned.into_bytes()
+}
+
+/// Cleans the UTF-8 and ASCII encoded words in `text`, re-encoding each
+/// changed one with its original encoding.
+fn replace_encoded_words(text: &str, clean: &Clean, count: &mut usize) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("=?") {
+        out.push_str(&rest[..start]);
+        let word = &rest[start..];
+        match encoded_word(word) {
+            Some((len, charset, encoding, decoded)) => {
+                let (cleaned, n) = clean(&decoded);
+                if n == 0 {
+                    out.push_str(&word[..len]);
+                } else {
+                    *count += n;
+                    if !cleaned.is_empty() {
+                        out.push_str(&format!("=?{}?{}?", charset, encoding));
+                        match encoding {
+                            'B' | 'b' => out.push_str(&base64_encode(cleaned.as_bytes())),
+                            _ => out.push_str(&q_encode(cleaned.as_bytes())),
+                        }
+                        out.push_str("?=");
+                    }
+                }
+                rest = &word[len..];
+            }
+            None => {
+                out.push_str("=?");
+                rest = &word[2..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Parses `=?charset?encoding?text?=` at the start of `word`, returning its
+/// length, charset, encoding letter and decoded text.
+fn encoded_word(word: &str) -> Option<(usize, &str, char, String)> {
+    let inner = word.strip_prefix("=?")?;
+    let (charset, inner) = inner.split_once('?')?;
+    let (encoding, inner) = inner.split_once('?')?;
+    let end = inner.find("?=")?;
+    let text = &inner[..end];
+    if text.contains(char::is_whitespace) || !is_utf8_charset(charset) {
+        return None;
+    }
+
+    let encoding = match encoding {
+        "B" | "b" | "Q" | "q" => encoding.chars().next()?,
+        _ => return None,
+    };
+    let bytes = match encoding {
+        'B' | 'b' => base64_decode(text.as_bytes())?,
+        _ => q_decode(text.as_bytes()),
+    };
+    let decoded = String::from_utf8(bytes).ok()?;
+    let len = 2 + charset.len() + 3 + text.len() + 2;
+    Some((len, charset, encoding, decoded))
+}
+
+/// Charsets whose text can be read as UTF-8; an RFC 2231 language suffix
+/// (`utf-8*en`) is allowed.
+fn is_utf8_charset(charset: &str) -> bool {
+    let charset = charset.split('*').next().unwrap_or_default();
+    charset.is_empty()
+        || ["utf-8", "utf8", "us-ascii"]
+            .iter()
+            .any(|c| charset.eq_ignore_ascii_case(c))
+}
+
+fn clean_multipart(body: &[u8], boundary: &str, clean: &Clean, count: &mut usize) -> Vec<u8> {
+    let delimiter = format!("--{}", boundary);
+    let mut out = Vec::with_capacity(body.len());
+    // The preamble before the first delimiter is kept as is.
+    let mut part: Option<Vec<u8>> = None;
+    let mut closed = false;
+
+    for line in body.split_inclusive(|&b| b == b'\n') {
+        let trimmed = line.trim_ascii_end();
+        let is_delimiter = !closed && trimmed.starts_with(delimiter.as_bytes()) && {
+            let tail = &trimmed[delimiter.len()..];
+            tail.is_empty() || tail == b"--"
+        };
+        if !is_delimiter {
+            match part.as_mut() {
+                Some(part) if !closed => part.extend_from_slice(line),
+                _ => out.extend_from_slice(line),
+            }
+            continue;
+        }
+        if let Some(part) = part.take() {
+            out.extend(clean_entity(&part, clean, count));
+        }
+        out.extend_from_slice(line);
+        closed = trimmed.ends_with(b"--") && trimmed.len() == delimiter.len() + 2;
+        if !closed {
+            part = Some(Vec::new());
+        }
+    }
+    // A missing close delimiter still leaves the last part cleaned.
+    if let Some(part) = part {
+        out.extend(clean_entity(&part, clean, count));
+    }
+    out
+}
+
+fn clean_text(
+    body: &[u8],
+    charset: &str,
+    encoding: &str,
+    clean: &Clean,
+    count: &mut usize,
+) -> Vec<u8> {
+    if !is_utf8_charset(charset) {
+        return body.to_vec();
+    }
+    let decoded = match encoding {
+        "quoted-printable" => qp_decode(body),
+        "base64" => match base64_decode(body) {
+            Some(decoded) => decoded,
+            None => return body.to_vec(),
+        },
+        _ => body.to_vec(),
+    };
+    let Ok(text) = String::from_utf8(decoded) else {
+        return body.to_vec();
+    };
+    let (cleaned, n) = clean(&text);
+    if n == 0 {
+        return body.to_vec();
+    }
+    *count += n;
+
+    let newline: &[u8] = if body.windows(2).any(|w| w == b"\r\n") {
+        b"\r\n"
+    } else {
+        b"\n"
+    };
+    match encoding {
+        "quoted-printable" => qp_encode(cleaned.as_bytes(), newline),
+        "base64" => {
+            let mut out = Vec::new();
+            let encoded = base64_encode(cleaned.as_bytes());
+            for line in encoded.as_bytes().chunks(76) {
+                out.extend_from_slice(line);
+                out.extend_from_slice(newline);
+            }
+            out
+        }
+        _ => cleaned.into_bytes(),
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decodes base64, ignoring line breaks and other whitespace.
+fn base64_decode(text: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let mut n = 0u32;
+    let mut bits = 0;
+    for &b in text {
+        let value = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            b if b.is_ascii_whitespace() => continue,
+            _ => return None,
+        };
+        n = n << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((n >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|d| d as u8)
+}
+
+fn qp_decode(body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len());
+    let mut i = 0;
+    while i < body.len() {
+        if body[i] == b'=' {
+            let rest = &body[i + 1..];
+            if rest.starts_with(b"\r\n") {
+                i += 3;
+                continue;
+            }
+            if rest.starts_with(b"\n") {
+                i += 2;
+                continue;
+            }
+            if let (Some(hi), Some(lo)) = (
+                rest.first().copied().and_then(hex_value),
+                rest.get(1).copied().and_then(hex_value),
+            ) {
+                out.push(hi << 4 | lo);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(body[i]);
+        i += 1;
+    }
+    out
+}
+
+/// Quoted-printable with lines of at most 76 characters, keeping the text's
+/// own line breaks as `newline`.
+fn qp_encode(text: &[u8], newline: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    let lines: Vec<&[u8]> = text.split(|&b| b == b'\n').collect();
+
+    for (n, line) in lines.iter().enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let mut width = 0;
+        for (i, &b) in line.iter().enumerate() {
+            let last = i + 1 == line.len();
+            let literal = matches!(b, b'!'..=b'<' | b'>'..=b'~') || (b == b' ' && !last);
+            let encoded: Vec<u8> = if literal {
+                vec![b]
+            } else {
+                format!("={:02X}", b).into_bytes()
+            };
+            if width + encoded.len() > 75 {
+                out.push(b'=');
+                out.extend_from_slice(newline);
+                width = 0;
+            }
+            width += encoded.len();
+            out.extend(encoded);
+        }
+        if n + 1 < lines.len() {
+            out.extend_from_slice(newline);
+        }
+    }
+    out
+}
+
+fn q_decode(text: &[u8]) -> Vec<u8> {
+    let spaced: Vec<u8> = text
+        .iter()
+        .map(|&b| if b == b'_' { b' ' } else { b })
+        .collect();
+    qp_decode(&spaced)
+}
+
+fn q_encode(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| match b {
+            b' ' => "_".to_string(),
+            b if b.is_ascii_alphanumeric() || b"!*+-/".contains(&b) => (b as char).to_string(),
+            b => format!("={:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip(text: &str) -> (String, usize) {
+        crate::remove_emojis(text)
+    }
+
+    #[test]
+    fn test_base64_and_qp_round_trip() {
+        for text in ["", "a", "ab", "abc", "Grüße 🚀\n", "x = 1\ttab \n"] {
+            let bytes = text.as_bytes();
+            assert_eq!(
+                base64_decode(base64_encode(bytes).as_bytes()).unwrap(),
+                bytes
+            );
+            assert_eq!(qp_decode(&qp_encode(bytes, b"\n")), bytes);
+            assert_eq!(q_decode(q_encode(bytes).as_bytes()), bytes);
+        }
+        let long = "é".repeat(100);
+        let encoded = qp_encode(long.as_bytes(), b"\r\n");
+        assert!(encoded.split(|&b| b == b'\n').all(|line| line.len() <= 77));
+        assert_eq!(qp_decode(&encoded), long.as_bytes());
+    }
+
+    #[test]
+    fn test_clean_headers() {
+        let message = "Subject: =?UTF-8?B?TGF1bmNoIPCfmoA=?= today\r\n\
+                       From: =?utf-8?q?Ren=C3=A9_=F0=9F=8E=89?= <rene@example.com>\r\n\
+                       To: plain@example.com\r\n\
+                       \r\n\
+                       Body 🚀\r\n";
+        let (cleaned, count) = clean(message.as_bytes(), &strip);
+        assert_eq!(count, 3);
+        assert_eq!(
+            String::from_utf8(cleaned).unwrap(),
+            "Subject: =?UTF-8?B?TGF1bmNoIA==?= today\r\n\
+             From: =?utf-8?q?Ren=C3=A9_?= <rene@example.com>\r\n\
+             To: plain@example.com\r\n\
+             \r\n\
+             Body \r\n"
+        );
+    }
+
+    #[test]
+    fn test_clean_multipart() {
+        let message = "Content-Type: multipart/mixed; boundary=\"b1\"\n\
+                       \n\
+                       preamble 🚀\n\
+                       --b1\n\
+                       Content-Type: text/plain; charset=utf-8\n\
+                       Content-Transfer-Encoding: quoted-printable\n\
+                       \n\
+                       Shipped =F0=9F=9A=80 caf=C3=A9\n\
+                       --b1\n\
+                       Content-Type: text/html; charset=UTF-8\n\
+                       Content-Transfer-Encoding: base64\n\
+                       \n\
+                       PHA+8J+OiTwvcD4=\n\
+                       --b1\n\
+                       Content-Type: text/plain\n\
+                       Content-Disposition: attachment; filename=notes.txt\n\
+                       \n\
+                       keep 🚀\n\
+                       --b1\n\
+                       Content-Type: text/plain; charset=iso-8859-1\n\
+                       \n\
+                       🚀\n\
+                       --b1--\n\
+                       epilogue 🚀\n";
+        let (cleaned, count) = clean(message.as_bytes(), &strip);
+        assert_eq!(count, 2);
+        let cleaned = String::from_utf8(cleaned).unwrap();
+        assert!(
+            cleaned.contains("\nShipped  caf=C3=A9\n--b1\n"),
+            "{}",
+            cleaned
+        );
+        assert!(cleaned.contains("\nPHA+PC9wPg==\n--b1\n"), "{}", cleaned);
+        assert!(cleaned.contains("preamble 🚀\n"));
+        assert!(cleaned.contains("keep 🚀\n"));
+        assert!(cleaned.contains("iso-8859-1\n\n🚀\n"));
+        assert!(cleaned.ends_with("--b1--\nepilogue 🚀\n"));
+    }
+
+    #[test]
+    fn test_unchanged_message_is_identical() {
+        let message = b"Subject: =?UTF-8?Q?caf=C3=A9?=\n\nno emoji =E2\n";
+        assert_eq!(clean(message, &strip), (message.to_vec(), 0));
+    }
+}
@@ -0,0 +1,260 @@
+// Generated by scripts/gen_emoji_age.py from Unicode 14.0.0.
+// Do not edit by hand.
+
+/// The Unicode version each emoji character was introduced in: (first,
+/// last, (major, minor)), sorted.
+pub const EMOJI_AGE: &[(char, char, (u8, u8))] = &[
+    ('\u{23}', '\u{23}', (1, 1)),
+    ('\u{2A}', '\u{2A}', (1, 1)),
+    ('\u{30}', '\u{39}', (1, 1)),
+    ('\u{A9}', '\u{A9}', (1, 1)),
+    ('\u{AE}', '\u{AE}', (1, 1)),
+    ('\u{203C}', '\u{203C}', (1, 1)),
+    ('\u{2049}', '\u{2049}', (3, 0)),
+    ('\u{2122}', '\u{2122}', (1, 1)),
+    ('\u{2139}', '\u{2139}', (3, 0)),
+    ('\u{2194}', '\u{2199}', (1, 1)),
+    ('\u{21A9}', '\u{21AA}', (1, 1)),
+    ('\u{231A}', '\u{231B}', (1, 1)),
+    ('\u{2328}', '\u{2328}', (1, 1)),
+    ('\u{2388}', '\u{2388}', (3, 0)),
+    ('\u{23CF}', '\u{23CF}', (4, 0)),
+    ('\u{23E9}', '\u{23F3}', (6, 0)),
+    ('\u{23F8}', '\u{23FA}', (7, 0)),
+    ('\u{24C2}', '\u{24C2}', (1, 1)),
+    ('\u{25AA}', '\u{25AB}', (1, 1)),
+    ('\u{25B6}', '\u{25B6}', (1, 1)),
+    ('\u{25C0}', '\u{25C0}', (1, 1)),
+    ('\u{25FB}', '\u{25FE}', (3, 2)),
+    ('\u{2600}', '\u{2605}', (1, 1)),
+    ('\u{2607}', '\u{2612}', (1, 1)),
+    ('\u{2614}', '\u{2615}', (4, 0)),
+    ('\u{2616}', '\u{2617}', (3, 2)),
+    ('\u{2618}', '\u{2618}', (4, 1)),
+    ('\u{2619}', '\u{2619}', (3, 0)),
+    ('\u{261A}', '\u{266F}', (1, 1)),
+    ('\u{2670}', '\u{2671}', (3, 0)),
+    ('\u{2672}', '\u{267D}', (3, 2)),
+    ('\u{267E}', '\u{267F}', (4, 1)),
+    ('\u{2680}', '\u{2685}', (3, 2)),
+    ('\u{2690}', '\u{2691}', (4, 0)),
+    ('\u{2692}', '\u{269C}', (4, 1)),
+    ('\u{269D}', '\u{269D}', (5, 1)),
+    ('\u{269E}', '\u{269F}', (5, 2)),
+    ('\u{26A0}', '\u{26A1}', (4, 0)),
+    ('\u{26A2}', '\u{26B1}', (4, 1)),
+    ('\u{26B2}', '\u{26B2}', (5, 0)),
+    ('\u{26B3}', '\u{26BC}', (5, 1)),
+    ('\u{26BD}', '\u{26BF}', (5, 2)),
+    ('\u{26C0}', '\u{26C3}', (5, 1)),
+    ('\u{26C4}', '\u{26CD}', (5, 2)),
+    ('\u{26CE}', '\u{26CE}', (6, 0)),
+    ('\u{26CF}', '\u{26E1}', (5, 2)),
+    ('\u{26E2}', '\u{26E2}', (6, 0)),
+    ('\u{26E3}', '\u{26E3}', (5, 2)),
+    ('\u{26E4}', '\u{26E7}', (6, 0)),
+    ('\u{26E8}', '\u{26FF}', (5, 2)),
+    ('\u{2700}', '\u{2700}', (7, 0)),
+    ('\u{2701}', '\u{2704}', (1, 1)),
+    ('\u{2705}', '\u{2705}', (6, 0)),
+    ('\u{2708}', '\u{2709}', (1, 1)),
+    ('\u{270A}', '\u{270B}', (6, 0)),
+    ('\u{270C}', '\u{2712}', (1, 1)),
+    ('\u{2714}', '\u{2714}', (1, 1)),
+    ('\u{2716}', '\u{2716}', (1, 1)),
+    ('\u{271D}', '\u{271D}', (1, 1)),
+    ('\u{2721}', '\u{2721}', (1, 1)),
+    ('\u{2728}', '\u{2728}', (6, 0)),
+    ('\u{2733}', '\u{2734}', (1, 1)),
+    ('\u{2744}', '\u{2744}', (1, 1)),
+    ('\u{2747}', '\u{2747}', (1, 1)),
+    ('\u{274C}', '\u{274C}', (6, 0)),
+    ('\u{274E}', '\u{274E}', (6, 0)),
+    ('\u{2753}', '\u{2755}', (6, 0)),
+    ('\u{2757}', '\u{2757}', (5, 2)),
+    ('\u{2763}', '\u{2767}', (1, 1)),
+    ('\u{2795}', '\u{2797}', (6, 0)),
+    ('\u{27A1}', '\u{27A1}', (1, 1)),
+    ('\u{27B0}', '\u{27B0}', (6, 0)),
+    ('\u{27BF}', '\u{27BF}', (6, 0)),
+    ('\u{2934}', '\u{2935}', (3, 2)),
+    ('\u{2B05}', '\u{2B07}', (4, 0)),
+    ('\u{2B1B}', '\u{2B1C}', (5, 1)),
+    ('\u{2B50}', '\u{2B50}', (5, 1)),
+    ('\u{2B55}', '\u{2B55}', (5, 2)),
+    ('\u{3030}', '\u{3030}', (1, 1)),
+    ('\u{303D}', '\u{303D}', (3, 2)),
+    ('\u{3297}', '\u{3297}', (1, 1)),
+    ('\u{3299}', '\u{3299}', (1, 1)),
+    ('\u{1F000}', '\u{1F02B}', (5, 1)),
+    ('\u{1F030}', '\u{1F093}', (5, 1)),
+    ('\u{1F0A0}', '\u{1F0AE}', (6, 0)),
+    ('\u{1F0B1}', '\u{1F0BE}', (6, 0)),
+    ('\u{1F0BF}', '\u{1F0BF}', (7, 0)),
+    ('\u{1F0C1}', '\u{1F0CF}', (6, 0)),
+    ('\u{1F0D1}', '\u{1F0DF}', (6, 0)),
+    ('\u{1F0E0}', '\u{1F0F5}', (7, 0)),
+    ('\u{1F10D}', '\u{1F10F}', (13, 0)),
+    ('\u{1F12F}', '\u{1F12F}', (11, 0)),
+    ('\u{1F16C}', '\u{1F16C}', (12, 0)),
+    ('\u{1F16D}', '\u{1F16F}', (13, 0)),
+    ('\u{1F170}', '\u{1F171}', (6, 0)),
+    ('\u{1F17E}', '\u{1F17E}', (6, 0)),
+    ('\u{1F17F}', '\u{1F17F}', (5, 2)),
+    ('\u{1F18E}', '\u{1F18E}', (6, 0)),
+    ('\u{1F191}', '\u{1F19A}', (6, 0)),
+    ('\u{1F1AD}', '\u{1F1AD}', (13, 0)),
+    ('\u{1F1E6}', '\u{1F1FF}', (6, 0)),
+    ('\u{1F201}', '\u{1F202}', (6, 0)),
+    ('\u{1F21A}', '\u{1F21A}', (5, 2)),
+    ('\u{1F22F}', '\u{1F22F}', (5, 2)),
+    ('\u{1F232}', '\u{1F23A}', (6, 0)),
+    ('\u{1F250}', '\u{1F251}', (6, 0)),
+    ('\u{1F260}', '\u{1F265}', (10, 0)),
+    ('\u{1F300}', '\u{1F320}', (6, 0)),
+    ('\u{1F321}', '\u{1F32C}', (7, 0)),
+    ('\u{1F32D}', '\u{1F32F}', (8, 0)),
+    ('\u{1F330}', '\u{1F335}', (6, 0)),
+    ('\u{1F336}', '\u{1F336}', (7, 0)),
+    ('\u{1F337}', '\u{1F37C}', (6, 0)),
+    ('\u{1F37D}', '\u{1F37D}', (7, 0)),
+    ('\u{1F37E}', '\u{1F37F}', (8, 0)),
+    ('\u{1F380}', '\u{1F393}', (6, 0)),
+    ('\u{1F394}', '\u{1F39F}', (7, 0)),
+    ('\u{1F3A0}', '\u{1F3C4}', (6, 0)),
+    ('\u{1F3C5}', '\u{1F3C5}', (7, 0)),
+    ('\u{1F3C6}', '\u{1F3CA}', (6, 0)),
+    ('\u{1F3CB}', '\u{1F3CE}', (7, 0)),
+    ('\u{1F3CF}', '\u{1F3D3}', (8, 0)),
+    ('\u{1F3D4}', '\u{1F3DF}', (7, 0)),
+    ('\u{1F3E0}', '\u{1F3F0}', (6, 0)),
+    ('\u{1F3F1}', '\u{1F3F7}', (7, 0)),
+    ('\u{1F3F8}', '\u{1F3FF}', (8, 0)),
+    ('\u{1F400}', '\u{1F43E}', (6, 0)),
+    ('\u{1F43F}', '\u{1F43F}', (7, 0)),
+    ('\u{1F440}', '\u{1F440}', (6, 0)),
+    ('\u{1F441}', '\u{1F441}', (7, 0)),
+    ('\u{1F442}', '\u{1F4F7}', (6, 0)),
+    ('\u{1F4F8}', '\u{1F4F8}', (7, 0)),
+    ('\u{1F4F9}', '\u{1F4FC}', (6, 0)),
+    ('\u{1F4FD}', '\u{1F4FE}', (7, 0)),
+    ('\u{1F4FF}', '\u{1F4FF}', (8, 0)),
+    ('\u{1F500}', '\u{1F53D}', (6, 0)),
+    ('\u{1F546}', '\u{1F54A}', (7, 0)),
+    ('\u{1F54B}', '\u{1F54F}', (8, 0)),
+    ('\u{1F550}', '\u{1F567}', (6, 0)),
+    ('\u{1F568}', '\u{1F579}', (7, 0)),
+    ('\u{1F57A}', '\u{1F57A}', (9, 0)),
+    ('\u{1F57B}', '\u{1F5A3}', (7, 0)),
+    ('\u{1F5A4}', '\u{1F5A4}', (9, 0)),
+    ('\u{1F5A5}', '\u{1F5FA}', (7, 0)),
+    ('\u{1F5FB}', '\u{1F5FF}', (6, 0)),
+    ('\u{1F600}', '\u{1F600}', (6, 1)),
+    ('\u{1F601}', '\u{1F610}', (6, 0)),
+    ('\u{1F611}', '\u{1F611}', (6, 1)),
+    ('\u{1F612}', '\u{1F614}', (6, 0)),
+    ('\u{1F615}', '\u{1F615}', (6, 1)),
+    ('\u{1F616}', '\u{1F616}', (6, 0)),
+    ('\u{1F617}', '\u{1F617}', (6, 1)),
+    ('\u{1F618}', '\u{1F618}', (6, 0)),
+    ('\u{1F619}', '\u{1F619}', (6, 1)),
+    ('\u{1F61A}', '\u{1F61A}', (6, 0)),
+    ('\u{1F61B}', '\u{1F61B}', (6, 1)),
+    ('\u{1F61C}', '\u{1F61E}', (6, 0)),
+    ('\u{1F61F}', '\u{1F61F}', (6, 1)),
+    ('\u{1F620}', '\u{1F625}', (6, 0)),
+    ('\u{1F626}', '\u{1F627}', (6, 1)),
+    ('\u{1F628}', '\u{1F62B}', (6, 0)),
+    ('\u{1F62C}', '\u{1F62C}', (6, 1)),
+    ('\u{1F62D}', '\u{1F62D}', (6, 0)),
+    ('\u{1F62E}', '\u{1F62F}', (6, 1)),
+    ('\u{1F630}', '\u{1F633}', (6, 0)),
+    ('\u{1F634}', '\u{1F634}', (6, 1)),
+    ('\u{1F635}', '\u{1F640}', (6, 0)),
+    ('\u{1F641}', '\u{1F642}', (7, 0)),
+    ('\u{1F643}', '\u{1F644}', (8, 0)),
+    ('\u{1F645}', '\u{1F64F}', (6, 0)),
+    ('\u{1F680}', '\u{1F6C5}', (6, 0)),
+    ('\u{1F6C6}', '\u{1F6CF}', (7, 0)),
+    ('\u{1F6D0}', '\u{1F6D0}', (8, 0)),
+    ('\u{1F6D1}', '\u{1F6D2}', (9, 0)),
+    ('\u{1F6D3}', '\u{1F6D4}', (10, 0)),
+    ('\u{1F6D5}', '\u{1F6D5}', (12, 0)),
+    ('\u{1F6D6}', '\u{1F6D7}', (13, 0)),
+    ('\u{1F6DD}', '\u{1F6DF}', (14, 0)),
+    ('\u{1F6E0}', '\u{1F6EC}', (7, 0)),
+    ('\u{1F6F0}', '\u{1F6F3}', (7, 0)),
+    ('\u{1F6F4}', '\u{1F6F6}', (9, 0)),
+    ('\u{1F6F7}', '\u{1F6F8}', (10, 0)),
+    ('\u{1F6F9}', '\u{1F6F9}', (11, 0)),
+    ('\u{1F6FA}', '\u{1F6FA}', (12, 0)),
+    ('\u{1F6FB}', '\u{1F6FC}', (13, 0)),
+    ('\u{1F7D5}', '\u{1F7D8}', (11, 0)),
+    ('\u{1F7E0}', '\u{1F7EB}', (12, 0)),
+    ('\u{1F7F0}', '\u{1F7F0}', (14, 0)),
+    ('\u{1F8B0}', '\u{1F8B1}', (13, 0)),
+    ('\u{1F90C}', '\u{1F90C}', (13, 0)),
+    ('\u{1F90D}', '\u{1F90F}', (12, 0)),
+    ('\u{1F910}', '\u{1F918}', (8, 0)),
+    ('\u{1F919}', '\u{1F91E}', (9, 0)),
+    ('\u{1F91F}', '\u{1F91F}', (10, 0)),
+    ('\u{1F920}', '\u{1F927}', (9, 0)),
+    ('\u{1F928}', '\u{1F92F}', (10, 0)),
+    ('\u{1F930}', '\u{1F930}', (9, 0)),
+    ('\u{1F931}', '\u{1F932}', (10, 0)),
+    ('\u{1F933}', '\u{1F93A}', (9, 0)),
+    ('\u{1F93C}', '\u{1F93E}', (9, 0)),
+    ('\u{1F93F}', '\u{1F93F}', (12, 0)),
+    ('\u{1F940}', '\u{1F945}', (9, 0)),
+    ('\u{1F947}', '\u{1F94B}', (9, 0)),
+    ('\u{1F94C}', '\u{1F94C}', (10, 0)),
+    ('\u{1F94D}', '\u{1F94F}', (11, 0)),
+    ('\u{1F950}', '\u{1F95E}', (9, 0)),
+    ('\u{1F95F}', '\u{1F96B}', (10, 0)),
+    ('\u{1F96C}', '\u{1F970}', (11, 0)),
+    ('\u{1F971}', '\u{1F971}', (12, 0)),
+    ('\u{1F972}', '\u{1F972}', (13, 0)),
+    ('\u{1F973}', '\u{1F976}', (11, 0)),
+    ('\u{1F977}', '\u{1F978}', (13, 0)),
+    ('\u{1F979}', '\u{1F979}', (14, 0)),
+    ('\u{1F97A}', '\u{1F97A}', (11, 0)),
+    ('\u{1F97B}', '\u{1F97B}', (12, 0)),
+    ('\u{1F97C}', '\u{1F97F}', (11, 0)),
+    ('\u{1F980}', '\u{1F984}', (8, 0)),
+    ('\u{1F985}', '\u{1F991}', (9, 0)),
+    ('\u{1F992}', '\u{1F997}', (10, 0)),
+    ('\u{1F998}', '\u{1F9A2}', (11, 0)),
+    ('\u{1F9A3}', '\u{1F9A4}', (13, 0)),
+    ('\u{1F9A5}', '\u{1F9AA}', (12, 0)),
+    ('\u{1F9AB}', '\u{1F9AD}', (13, 0)),
+    ('\u{1F9AE}', '\u{1F9AF}', (12, 0)),
+    ('\u{1F9B0}', '\u{1F9B9}', (11, 0)),
+    ('\u{1F9BA}', '\u{1F9BF}', (12, 0)),
+    ('\u{1F9C0}', '\u{1F9C0}', (8, 0)),
+    ('\u{1F9C1}', '\u{1F9C2}', (11, 0)),
+    ('\u{1F9C3}', '\u{1F9CA}', (12, 0)),
+    ('\u{1F9CB}', '\u{1F9CB}', (13, 0)),
+    ('\u{1F9CC}', '\u{1F9CC}', (14, 0)),
+    ('\u{1F9CD}', '\u{1F9CF}', (12, 0)),
+    ('\u{1F9D0}', '\u{1F9E6}', (10, 0)),
+    ('\u{1F9E7}', '\u{1F9FF}', (11, 0)),
+    ('\u{1FA00}', '\u{1FA53}', (12, 0)),
+    ('\u{1FA60}', '\u{1FA6D}', (11, 0)),
+    ('\u{1FA70}', '\u{1FA73}', (12, 0)),
+    ('\u{1FA74}', '\u{1FA74}', (13, 0)),
+    ('\u{1FA78}', '\u{1FA7A}', (12, 0)),
+    ('\u{1FA7B}', '\u{1FA7C}', (14, 0)),
+    ('\u{1FA80}', '\u{1FA82}', (12, 0)),
+    ('\u{1FA83}', '\u{1FA86}', (13, 0)),
+    ('\u{1FA90}', '\u{1FA95}', (12, 0)),
+    ('\u{1FA96}', '\u{1FAA8}', (13, 0)),
+    ('\u{1FAA9}', '\u{1FAAC}', (14, 0)),
+    ('\u{1FAB0}', '\u{1FAB6}', (13, 0)),
+    ('\u{1FAB7}', '\u{1FABA}', (14, 0)),
+    ('\u{1FAC0}', '\u{1FAC2}', (13, 0)),
+    ('\u{1FAC3}', '\u{1FAC5}', (14, 0)),
+    ('\u{1FAD0}', '\u{1FAD6}', (13, 0)),
+    ('\u{1FAD7}', '\u{1FAD9}', (14, 0)),
+    ('\u{1FAE0}', '\u{1FAE7}', (14, 0)),
+    ('\u{1FAF0}', '\u{1FAF6}', (14, 0)),
+];
@@ -0,0 +1,231 @@
+//! Text encodings for reading input and writing cleaned output.
+
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use std::io;
+
+/// Encodings accepted by `--output-encoding`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum Encoding {
+    /// UTF-8 without a byte order mark
+    #[default]
+    #[cfg_attr(feature = "cli", value(name = "utf-8", alias = "utf8"))]
+    Utf8,
+    /// UTF-16 little endian with a byte order mark
+    #[cfg_attr(feature = "cli", value(name = "utf-16le", alias = "utf16le"))]
+    Utf16Le,
+    /// UTF-16 big endian with a byte order mark
+    #[cfg_attr(feature = "cli", value(name = "utf-16be", alias = "utf16be"))]
+    Utf16Be,
+    /// ISO-8859-1; characters above U+00FF are an error
+    #[cfg_attr(feature = "cli", value(name = "latin1", alias = "iso-8859-1"))]
+    Latin1,
+    /// Windows-1252; characters outside the code page are an error
+    #[cfg_attr(feature = "cli", value(name = "windows-1252", alias = "cp1252"))]
+    Windows1252,
+    /// 7-bit ASCII; any other character is an error
+    #[cfg_attr(feature = "cli", value(name = "ascii", alias = "us-ascii"))]
+    Ascii,
+}
+
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Latin1 => "latin1",
+            Encoding::Windows1252 => "windows-1252",
+            Encoding::Ascii => "ascii",
+        }
+    }
+
+    /// Encodes `text`, failing on the first character the target cannot
+    /// represent rather than substituting it.
+    pub fn encode(self, text: &str) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            Encoding::Utf16Le => Ok(encode_utf16(text, u16::to_le_bytes)),
+            Encoding::Utf16Be => Ok(encode_utf16(text, u16::to_be_bytes)),
+            Encoding::Latin1 => self.encode_single_byte(text, |c| u8::try_from(c).ok()),
+            Encoding::Windows1252 => self.encode_single_byte(text, windows_1252_byte),
+            Encoding::Ascii => {
+                self.encode_single_byte(text, |c| u8::try_from(c).ok().filter(u8::is_ascii))
+            }
+        }
+    }
+
+    fn encode_single_byte(
+        self,
+        text: &str,
+        map: impl Fn(char) -> Option<u8>,
+    ) -> io::Result<Vec<u8>> {
+        text.chars()
+            .map(|c| {
+                map(c).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("U+{:04X} cannot be encoded as {}", c as u32, self.name()),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+fn encode_utf16(text: &str, to_bytes: fn(u16) -> [u8; 2]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(2 + text.len() * 2);
+    bytes.extend(to_bytes(0xFEFF));
+    for unit in text.encode_utf16() {
+        bytes.extend(to_bytes(unit));
+    }
+    bytes
+}
+
+/// Characters Windows-1252 maps into 0x80-0x9F; the rest of the page matches
+/// Latin-1. Unassigned positions are `None`.
+const WINDOWS_1252_HIGH: [Option<char>; 32] = [
+    Some('\u{20AC}'),
+    None,
+    Some('\u{201A}'),
+    Some('\u{0192}'),
+    Some('\u{201E}'),
+    Some('\u{2026}'),
+    Some('\u{2020}'),
+    Some('\u{2021}'),
+    Some('\u{02C6}'),
+    Some('\u{2030}'),
+    Some('\u{0160}'),
+    Some('\u{2039}'),
+    Some('\u{0152}'),
+    None,
+    Some('\u{017D}'),
+    None,
+    None,
+    Some('\u{2018}'),
+    Some('\u{2019}'),
+    Some('\u{201C}'),
+    Some('\u{201D}'),
+    Some('\u{2022}'),
+    Some('\u{2013}'),
+    Some('\u{2014}'),
+    Some('\u{02DC}'),
+    Some('\u{2122}'),
+    Some('\u{0161}'),
+    Some('\u{203A}'),
+    Some('\u{0153}'),
+    None,
+    Some('\u{017E}'),
+    Some('\u{0178}'),
+];
+
+fn windows_1252_byte(c: char) -> Option<u8> {
+    match c as u32 {
+        0x00..=0x7F | 0xA0..=0xFF => Some(c as u8),
+        _ => WINDOWS_1252_HIGH
+            .iter()
+            .position(|&mapped| mapped == Some(c))
+            .map(|i| 0x80 + i as u8),
+    }
+}
+
+/// Decodes input bytes as text and reports the encoding found. UTF-16 is
+/// recognised by its byte order mark (which is consumed); everything else must
+/// be valid UTF-8.
+pub fn decode(bytes: Vec<u8>) -> io::Result<(String, Encoding)> {
+    let utf16 = match bytes.as_slice() {
+        [0xFF, 0xFE, rest @ ..] => Some((rest, Encoding::Utf16Le)),
+        [0xFE, 0xFF, rest @ ..] => Some((rest, Encoding::Utf16Be)),
+        _ => None,
+    };
+
+    match utf16 {
+        Some((rest, encoding)) => {
+            let from_bytes = if encoding == Encoding::Utf16Le {
+                u16::from_le_bytes
+            } else {
+                u16::from_be_bytes
+            };
+            if rest.len() % 2 != 0 {
+                return Err(invalid_data("UTF-16 input has an odd number of bytes"));
+            }
+            let units = rest
+                .chunks_exact(2)
+                .map(|pair| from_bytes([pair[0], pair[1]]));
+            char::decode_utf16(units)
+                .collect::<Result<String, _>>()
+                .map(|text| (text, encoding))
+                .map_err(|e| invalid_data(&format!("invalid UTF-16: {}", e)))
+        }
+        None => String::from_utf8(bytes)
+            .map(|text| (text, Encoding::Utf8))
+            .map_err(|e| invalid_data(&e.to_string())),
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf16_round_trip() {
+        let text = "Café 日本 😀";
+        for encoding in [Encoding::Utf16Le, Encoding::Utf16Be] {
+            let bytes = encoding.encode(text).unwrap();
+            assert_eq!(decode(bytes).unwrap(), (text.to_string(), encoding));
+        }
+        assert_eq!(
+            Encoding::Utf16Le.encode("A").unwrap(),
+            [0xFF, 0xFE, 0x41, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_single_byte_encodings() {
+        assert_eq!(Encoding::Latin1.encode("café").unwrap(), b"caf\xE9");
+        assert_eq!(
+            Encoding::Windows1252.encode("€ “quoted” café").unwrap(),
+            b"\x80 \x93quoted\x94 caf\xE9"
+        );
+        assert_eq!(Encoding::Ascii.encode("plain").unwrap(), b"plain");
+
+        let error = Encoding::Latin1.encode("日本").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("U+65E5 cannot be encoded as latin1")
+        );
+        assert!(Encoding::Ascii.encode("é").is_err());
+        assert!(Encoding::Windows1252.encode("\u{81}").is_err());
+    }
+
+    #[test]
+    fn test_decode_rejects_invalid_input() {
+        assert_eq!(
+            decode(b"plain".to_vec()).unwrap(),
+            ("plain".to_string(), Encoding::Utf8)
+        );
+        assert!(decode(vec![0xFF, 0xFE, 0x41]).is_err());
+        assert!(decode(vec![0xC3, 0x28]).is_err());
+        // Unpaired surrogate.
+        assert!(decode(vec![0xFF, 0xFE, 0x00, 0xD8]).is_err());
+    }
+
+    #[test]
+    fn test_decode_round_trips() {
+        for bytes in [
+            vec![],
+            vec![0xFF, 0xFE],
+            vec![0xEF, 0xBB, 0xBF, b'a'],
+            vec![0xFE, 0xFF, 0xD8, 0x3D, 0xDE, 0x80],
+        ] {
+            let (text, encoding) = decode(bytes.clone()).unwrap();
+            assert_eq!(encoding.encode(&text).unwrap(), bytes);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Emoji written as escape sequences, for `--escapes`: `\u{1F680}` (Rust,
+//! JavaScript), `\uD83D\uDE80` (JavaScript, JSON, Java) and `\U0001F680`
+//! (Python, C). The file itself is plain ASCII, so ordinary detection never
+//! sees them.
+
+use std::ops::Range;
+
+use crate::emoji_spans;
+
+/// Byte ranges of runs of escapes in `text` that decode to emoji sequences.
+/// A run mixing other characters with emoji yields only the emoji part, and
+/// an escaped backslash (`\\u2705`) is not an escape.
+pub fn spans(text: &str) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let mut spans = Vec::new();
+    // The current run: each decoded character with its source range.
+    let mut run: Vec<(char, Range<usize>)> = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            i += 1;
+            continue;
+        }
+        match escape_at(text, i) {
+            Some((c, end)) => {
+                if run.last().is_some_and(|(_, r)| r.end != i) {
+                    flush(&mut run, &mut spans);
+                }
+                run.push((c, i..end));
+                i = end;
+            }
+            None => {
+                i += if bytes.get(i + 1) == Some(&b'\\') {
+                    2
+                } else {
+                    1
+                }
+            }
+        }
+    }
+    flush(&mut run, &mut spans);
+
+    spans
+}
+
+/// Decodes `text` if it consists only of escapes.
+pub fn decode(text: &str) -> Option<String> {
+    let mut decoded = String::new();
+    let mut i = 0;
+    while i < text.len() {
+        let (c, end) = escape_at(text, i)?;
+        decoded.push(c);
+        i = end;
+    }
+    (!decoded.is_empty()).then_some(decoded)
+}
+
+fn flush(run: &mut Vec<(char, Range<usize>)>, spans: &mut Vec<Range<usize>>) {
+    let decoded: String = run.iter().map(|&(c, _)| c).collect();
+    let mut offset = 0;
+    let offsets: Vec<usize> = run
+        .iter()
+        .map(|&(c, _)| {
+            let start = offset;
+            offset += c.len_utf8();
+            start
+        })
+        .collect();
+
+    for span in emoji_spans(&decoded) {
+        let first = offsets.partition_point(|&o| o < span.start);
+        let last = offsets.partition_point(|&o| o < span.end) - 1;
+        spans.push(run[first].1.start..run[last].1.end);
+    }
+    run.clear();
+}
+
+/// The character escaped at byte `i` of `text` and the end of its escape,
+/// joining a UTF-16 surrogate pair into one character.
+fn escape_at(text: &str, i: usize) -> Option<(char, usize)> {
+    let rest = text.get(i..)?;
+    if let Some(hex) = rest.strip_prefix("\\u{") {
+        let close = hex.find('}')?;
+        let c = hex_char(&hex[..close], 1..=6)?;
+        return Some((c, i + 3 + close + 1));
+    }
+    if let Some(hex) = rest.strip_prefix("\\U") {
+        return Some((hex_char(hex.get(..8)?, 8..=8)?, i + 10));
+    }
+
+    let unit = utf16_unit(rest)?;
+    if let Some(c) = char::from_u32(unit) {
+        return Some((c, i + 6));
+    }
+    let low = utf16_unit(rest.get(6..)?)?;
+    if (0xD800..0xDC00).contains(&unit) && (0xDC00..0xE000).contains(&low) {
+        let c = char::from_u32(0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00))?;
+        return Some((c, i + 12));
+    }
+    None
+}
+
+/// The code unit of a `\uXXXX` escape at the start of `text`.
+fn utf16_unit(text: &str) -> Option<u32> {
+    let hex = text.strip_prefix("\\u")?.get(..4)?;
+    hex.bytes()
+        .all(|b| b.is_ascii_hexdigit())
+        .then(|| u32::from_str_radix(hex, 16).ok())?
+}
+
+fn hex_char(hex: &str, digits: std::ops::RangeInclusive<usize>) -> Option<char> {
+    if !digits.contains(&hex.len()) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(text: &str) -> Vec<&str> {
+        spans(text).into_iter().map(|s| &text[s]).collect()
+    }
+
+    #[test]
+    fn test_spans() {
+        assert_eq!(
+            found(r#"let s = "\u{1F680} \uD83D\uDE80 \U0001F680";"#),
+            [r"\u{1F680}", r"\uD83D\uDE80", r"\U0001F680"]
+        );
+        // Whole sequences, and only the emoji part of a run.
+        assert_eq!(
+            found(r"\u00E9\uD83D\uDC4D\uD83C\uDFFD!"),
+            [r"\uD83D\uDC4D\uD83C\uDFFD"]
+        );
+        assert!(found(r#""café \\u2705 \uD83D \u{110000} \u27""#).is_empty());
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(r"\u2764\uFE0F").as_deref(), Some("❤\u{FE0F}"));
+        assert_eq!(decode(r"\U0001F680").as_deref(), Some("🚀"));
+        assert_eq!(decode("🚀"), None);
+        assert_eq!(decode(r"\u2705x"), None);
+    }
+}
@@ -0,0 +1,352 @@
+//! File types for `--auto`, recognised by extension or, failing that, by
+//! content.
+//!
+//! Markdown, JSON, notebooks and XML are cleaned the way their own modes
+//! clean them. YAML, HTML and CSV have their editable regions found here:
+//! values and comments but not keys, text but not markup, and every record
+//! but the header.
+
+use std::ops::Range;
+use std::path::Path;
+
+use crate::{ipynb, xml};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileType {
+    Markdown,
+    Json,
+    Notebook,
+    Yaml,
+    Html,
+    Xml,
+    Csv,
+    /// Anything else, cleaned everywhere.
+    Plain,
+}
+
+impl FileType {
+    pub fn for_path(path: &str) -> Option<FileType> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "md" | "markdown" | "mdx" => Some(FileType::Markdown),
+            "json" | "jsonc" | "geojson" | "webmanifest" => Some(FileType::Json),
+            "ipynb" => Some(FileType::Notebook),
+            "yaml" | "yml" => Some(FileType::Yaml),
+            "html" | "htm" | "xhtml" => Some(FileType::Html),
+            "xml" | "svg" | "xliff" | "xlf" | "resx" | "plist" => Some(FileType::Xml),
+            "csv" => Some(FileType::Csv),
+            "txt" | "log" => Some(FileType::Plain),
+            _ => None,
+        }
+    }
+
+    /// The type `text` looks like, for files whose name doesn't tell.
+    pub fn sniff(text: &str) -> FileType {
+        let start = text.trim_start_matches('\u{FEFF}').trim_start();
+        if looks_json(start) {
+            return match ipynb::regions(text, false) {
+                Ok(_) => FileType::Notebook,
+                Err(_) => FileType::Json,
+            };
+        }
+        if start.starts_with('<') {
+            let head = start[..start.len().min(1024)].to_ascii_lowercase();
+            return match head.contains("<!doctype html") || head.contains("<html") {
+                true => FileType::Html,
+                false if xml::regions(text, &xml::Scope::default()).is_ok() => FileType::Xml,
+                false => FileType::Plain,
+            };
+        }
+        let lines: Vec<&str> = text.lines().take(50).collect();
+        if looks_yaml(&lines) {
+            FileType::Yaml
+        } else if looks_markdown(&lines) {
+            FileType::Markdown
+        } else if looks_csv(&lines) {
+            FileType::Csv
+        } else {
+            FileType::Plain
+        }
+    }
+}
+
+/// An object, or an array of values rather than a TOML or INI `[section]`.
+fn looks_json(text: &str) -> bool {
+    let end = text.trim_end();
+    match text.strip_prefix('[') {
+        Some(items) => {
+            items
+                .trim_start()
+                .starts_with(|c: char| matches!(c, '{' | '[' | '"' | ']' | '-' | '0'..='9'))
+                && end.ends_with(']')
+        }
+        None => text.starts_with('{') && end.ends_with('}'),
+    }
+}
+
+/// Every line a document marker, comment, list item or `key:` line, and at
+/// least one mapping key. Prose fails on its first ordinary line, and keys
+/// with spaces, legal as they are, are too much like prose to count.
+fn looks_yaml(lines: &[&str]) -> bool {
+    let mut keys = 0;
+    for line in lines {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed == "---" {
+            continue;
+        }
+        if line.starts_with([' ', '\t']) || trimmed.starts_with("- ") || trimmed == "-" {
+            continue;
+        }
+        match key_end(trimmed) {
+            Some(end) if trimmed.starts_with(['"', '\'']) || !trimmed[..end].contains(' ') => {
+                keys += 1
+            }
+            _ => return false,
+        }
+    }
+    keys > 0
+}
+
+/// A heading, a code fence, a link, or front matter before text that isn't
+/// YAML.
+fn looks_markdown(lines: &[&str]) -> bool {
+    let front_matter = lines.first() == Some(&"---") && lines[1..].contains(&"---");
+    front_matter
+        || lines.iter().any(|line| {
+            let hashes = line.bytes().take_while(|&b| b == b'#').count();
+            ((1..=6).contains(&hashes) && line[hashes..].starts_with(' '))
+                || line.starts_with("```")
+                || line.contains("](")
+        })
+}
+
+/// At least two lines, all with the same number of commas outside quotes.
+fn looks_csv(lines: &[&str]) -> bool {
+    let commas = |line: &str| {
+        let mut quoted = false;
+        line.chars()
+            .filter(|&c| {
+                quoted ^= c == '"';
+                c == ',' && !quoted
+            })
+            .count()
+    };
+    let lines: Vec<&str> = lines
+        .iter()
+        .copied()
+        .filter(|l| !l.is_empty())
+        .take(5)
+        .collect();
+    lines.len() >= 2
+        && commas(lines[0]) > 0
+        && lines.iter().all(|line| commas(line) == commas(lines[0]))
+}
+
+/// Byte ranges of `text` that cleaning may change, for the types without a
+/// mode of their own, or `None` for the rest.
+pub fn regions(text: &str, file_type: FileType) -> Option<Vec<Range<usize>>> {
+    match file_type {
+        FileType::Yaml => Some(yaml(text)),
+        FileType::Html => Some(html(text)),
+        FileType::Csv => Some(csv(text)),
+        _ => None,
+    }
+}
+
+/// The offset just past the `:` ending a mapping key at the start of
+/// `line`, if it has one, plain or quoted.
+fn key_end(line: &str) -> Option<usize> {
+    let colon = match line.chars().next()? {
+        quote @ ('"' | '\'') => 1 + line[1..].find(quote)? + 1,
+        _ => line.find(':')?,
+    };
+    let key = &line[..colon];
+    let plain = !key.is_empty()
+        && !key.starts_with('#')
+        && !key.contains(" #")
+        && !key.contains(['{', '[']);
+    let quoted = key.starts_with(['"', '\'']);
+    let after = &line[colon..];
+    let ends = after == ":" || after.starts_with(": ") || after.starts_with(":\t");
+    ((plain || quoted) && ends).then_some(colon + 1)
+}
+
+/// Everything after each mapping key, comments and block scalars included.
+fn yaml(text: &str) -> Vec<Range<usize>> {
+    let mut regions = Vec::new();
+    // The indentation of the key whose block scalar is being read.
+    let mut block: Option<usize> = None;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let content = line.trim_end_matches(['\n', '\r']);
+        let indent = content.len() - content.trim_start_matches(' ').len();
+
+        if let Some(key_indent) = block {
+            if content.trim().is_empty() || indent > key_indent {
+                regions.push(start..start + content.len());
+                continue;
+            }
+            block = None;
+        }
+
+        let mut value = indent;
+        while content[value..].starts_with("- ") {
+            value += 2;
+            value += content[value..].len() - content[value..].trim_start_matches(' ').len();
+        }
+        if let Some(end) = key_end(&content[value..]) {
+            value += end;
+        }
+        if content[value..].trim_start().starts_with(['|', '>']) {
+            block = Some(indent);
+        }
+        if content.trim() != "---" && value < content.len() {
+            regions.push(start + value..start + content.len());
+        }
+    }
+
+    regions
+}
+
+/// Text between tags, outside comments and `<script>` and `<style>`.
+fn html(text: &str) -> Vec<Range<usize>> {
+    let mut regions = Vec::new();
+    let lower = text.to_ascii_lowercase();
+    let mut i = 0;
+
+    while i < text.len() {
+        let rest = &text[i..];
+        if !rest.starts_with('<') {
+            let end = rest.find('<').map_or(text.len(), |n| i + n);
+            regions.push(i..end);
+            i = end;
+        } else if rest.starts_with("<!--") {
+            i = rest.find("-->").map_or(text.len(), |n| i + n + 3);
+        } else {
+            let start = i;
+            i = tag_end(text, start);
+            let tag = &lower[start + 1..i];
+            let raw = ["script", "style"].into_iter().find(|name| {
+                tag.strip_prefix(name)
+                    .is_some_and(|after| after.starts_with(['>', '/', ' ', '\t', '\n']))
+            });
+            if let Some(name) = raw.filter(|_| !tag.ends_with("/>")) {
+                let close = format!("</{}", name);
+                i = lower[i..].find(&close).map_or(text.len(), |n| i + n);
+            }
+        }
+    }
+
+    regions
+}
+
+/// The offset after the tag at `start`, skipping `>` inside quotes.
+fn tag_end(text: &str, start: usize) -> usize {
+    let mut quote = None;
+    for (i, c) in text[start..].char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return start + i + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// Every record after the first, which names the columns.
+fn csv(text: &str) -> Vec<Range<usize>> {
+    let mut quoted = false;
+    let header_end = text
+        .char_indices()
+        .find(|&(_, c)| {
+            quoted ^= c == '"';
+            c == '\n' && !quoted
+        })
+        .map(|(i, _)| i + 1);
+    header_end
+        .filter(|&end| end < text.len())
+        .map(|end| end..text.len())
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(text: &'static str, file_type: FileType) -> Vec<&'static str> {
+        regions(text, file_type)
+            .unwrap()
+            .into_iter()
+            .map(|region| text[region].trim())
+            .filter(|text| !text.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn test_sniff() {
+        let cases = [
+            ("{\"title\": \"🚀\", \"n\": 1.5}\n", FileType::Json),
+            ("[{\"a\": 1}]", FileType::Json),
+            ("{\"cells\": [], \"nbformat\": 4}", FileType::Notebook),
+            ("<!DOCTYPE html>\n<p>Hi 🚀<br></p>", FileType::Html),
+            ("<?xml version=\"1.0\"?>\n<a>🚀</a>", FileType::Xml),
+            ("---\nname: ship 🚀\nsteps:\n  - run: x\n", FileType::Yaml),
+            ("# Launch 🚀\n\nSome prose.\n", FileType::Markdown),
+            (
+                "---\ntitle: x\n---\nSome prose, with: a colon.\n",
+                FileType::Markdown,
+            ),
+            ("id,name\n1,🚀\n2,\"a, b\"\n", FileType::Csv),
+            ("[section]\nkey = 1\n", FileType::Plain),
+            ("Launch 🚀\n", FileType::Plain),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(FileType::sniff(text), expected, "{}", text);
+        }
+        assert_eq!(
+            FileType::for_path("docs/README.MD"),
+            Some(FileType::Markdown)
+        );
+        assert_eq!(FileType::for_path("Makefile"), None);
+    }
+
+    #[test]
+    fn test_yaml() {
+        let text = "# Ship it 🚀\nname: Deploy 🚀\n\"key 🔑\": yes ✅\nsteps:\n  - run: echo 🎉\n  - plain item 📦\nnotes: |\n  line one 📝\n  key: not a key 🙃\nafter 🔥: x\n";
+        assert_eq!(
+            texts(text, FileType::Yaml),
+            [
+                "# Ship it 🚀",
+                "Deploy 🚀",
+                "yes ✅",
+                "echo 🎉",
+                "plain item 📦",
+                "|",
+                "line one 📝",
+                "key: not a key 🙃",
+                "x"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_html_and_csv() {
+        let page = "<p title=\"🚀\">Go 🚀</p><!-- ✅ --><script>alert('🎉')</script><STYLE>a{content:'📦'}</STYLE>Done ✅";
+        assert_eq!(texts(page, FileType::Html), ["Go 🚀", "Done ✅"]);
+
+        let table = "\"name 🚀\",\"note\nline 🎉\"\nship,🚀\n";
+        assert_eq!(texts(table, FileType::Csv), ["ship,🚀"]);
+        assert!(texts("only,header 🚀\n", FileType::Csv).is_empty());
+        assert_eq!(regions("🚀", FileType::Plain), None);
+    }
+}
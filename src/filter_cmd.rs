@@ -0,0 +1,112 @@
+//! External decision command for `--filter-cmd`.
+//!
+//! Each occurrence is written as one JSON object to the command's stdin. The
+//! first line of its stdout decides what happens:
+//!
+//! - `remove` (or no output) removes the occurrence,
+//! - `keep` leaves it in place,
+//! - `replace TEXT` substitutes `TEXT`.
+//!
+//! A non-zero exit status or any other output is an error, and the file is
+//! left unchanged.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::json;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    Keep,
+    Remove,
+    Replace(String),
+}
+
+/// Runs `command` through the shell for one occurrence.
+pub fn decide(command: &str, occurrence: &json::Value) -> Result<Action, String> {
+    let mut child = shell(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run filter command: {}", e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that decides without reading its input closes the pipe
+        // early; that is not an error.
+        let _ = writeln!(stdin, "{}", occurrence);
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("failed to run filter command: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("filter command failed: {}", output.status));
+    }
+
+    let stdout = String::from_utf8(output.stdout)
+        .map_err(|_| "filter command output is not UTF-8".to_string())?;
+    parse_action(stdout.lines().next().unwrap_or_default())
+}
+
+pub fn parse_action(line: &str) -> Result<Action, String> {
+    let line = line.trim_end_matches('\r');
+    match line.trim() {
+        "" | "remove" => Ok(Action::Remove),
+        "keep" => Ok(Action::Keep),
+        _ => match line.strip_prefix("replace ") {
+            Some(text) => Ok(Action::Replace(text.to_string())),
+            None => Err(format!(
+                "unexpected filter command output '{}' (expected keep, remove or replace TEXT)",
+                line
+            )),
+        },
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_action() {
+        assert_eq!(parse_action(""), Ok(Action::Remove));
+        assert_eq!(parse_action("remove"), Ok(Action::Remove));
+        assert_eq!(parse_action("keep\r"), Ok(Action::Keep));
+        assert_eq!(
+            parse_action("replace [rocket] "),
+            Ok(Action::Replace("[rocket] ".to_string()))
+        );
+        assert!(parse_action("delete").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_decide() {
+        let occurrence = json::Value::object().with("emoji", "✅");
+        let keep_checks = r#"grep -q '"emoji":"✅"' && echo keep || echo remove"#;
+        assert_eq!(decide(keep_checks, &occurrence), Ok(Action::Keep));
+        assert_eq!(
+            decide("cat >/dev/null; echo replace x", &occurrence),
+            Ok(Action::Replace("x".to_string()))
+        );
+        assert!(
+            decide("exit 3", &occurrence)
+                .unwrap_err()
+                .contains("failed")
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Locating emoji without changing the text, for highlighters and editor
+//! integrations.
+//!
+//! Spans are byte ranges into the UTF-8 text. Editor protocols count in
+//! UTF-16 code units instead, so `find_emoji_positions` also gives each
+//! sequence's UTF-16 range and its start and end as LSP positions.
+
+use std::ops::Range;
+
+use crate::{Role, emoji_spans};
+
+/// One emoji sequence in a text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EmojiSpan<'a> {
+    /// The whole sequence, e.g. `👍🏽` or `🇫🇷`.
+    pub sequence: &'a str,
+    /// Where `sequence` is in the input, in bytes.
+    pub span: Range<usize>,
+}
+
+impl EmojiSpan<'_> {
+    /// The code points of the sequence, as `U+1F44D U+1F3FD` lists them.
+    pub fn codepoints(&self) -> Vec<u32> {
+        self.sequence.chars().map(|c| c as u32).collect()
+    }
+
+    /// Whether the sequence is a whole emoji, or which stray component.
+    pub fn role(&self) -> Role {
+        Role::of(self.sequence)
+    }
+}
+
+/// A position as the Language Server Protocol counts it: a zero-based
+/// line, and UTF-16 code units from the start of that line. Lines end at
+/// `\n`, `\r\n` or a lone `\r`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+/// An `EmojiSpan` with its location in the other units editors use.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EmojiPosition<'a> {
+    pub emoji: EmojiSpan<'a>,
+    /// Where the sequence is, in UTF-16 code units from the start of the
+    /// input, as JavaScript indexes strings.
+    pub utf16: Range<usize>,
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Every emoji sequence in `input`, in order: the same sequences
+/// `remove_emojis` strips.
+pub fn find_emoji(input: &str) -> Vec<EmojiSpan<'_>> {
+    emoji_spans(input)
+        .into_iter()
+        .map(|span| EmojiSpan {
+            sequence: &input[span.clone()],
+            span,
+        })
+        .collect()
+}
+
+/// Like `find_emoji`, with UTF-16 offsets and LSP positions worked out in
+/// the same pass over `input`.
+pub fn find_emoji_positions(input: &str) -> Vec<EmojiPosition<'_>> {
+    let mut cursor = Cursor::default();
+    find_emoji(input)
+        .into_iter()
+        .map(|emoji| {
+            cursor.advance(input, emoji.span.start);
+            let (start_utf16, start) = (cursor.utf16, cursor.position());
+            cursor.advance(input, emoji.span.end);
+            EmojiPosition {
+                emoji,
+                utf16: start_utf16..cursor.utf16,
+                start,
+                end: cursor.position(),
+            }
+        })
+        .collect()
+}
+
+/// A place in the input, in every unit at once.
+#[derive(Default)]
+struct Cursor {
+    byte: usize,
+    utf16: usize,
+    line: usize,
+    /// The UTF-16 offset where the current line starts.
+    line_start: usize,
+}
+
+impl Cursor {
+    /// Moves forward to the byte offset `to`, a character boundary.
+    fn advance(&mut self, input: &str, to: usize) {
+        for c in input[self.byte..to].chars() {
+            self.byte += c.len_utf8();
+            self.utf16 += c.len_utf16();
+            let ends_line =
+                c == '\n' || (c == '\r' && input.as_bytes().get(self.byte) != Some(&b'\n'));
+            if ends_line {
+                self.line += 1;
+                self.line_start = self.utf16;
+            }
+        }
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            character: self.utf16 - self.line_start,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_emoji() {
+        let text = "Hi 👋🏽, ship 🚀!";
+        let found = find_emoji(text);
+        assert_eq!(
+            found,
+            [
+                EmojiSpan {
+                    sequence: "👋🏽",
+                    span: 3..11,
+                },
+                EmojiSpan {
+                    sequence: "🚀",
+                    span: 18..22,
+                },
+            ]
+        );
+        assert_eq!(&text[found[1].span.clone()], "🚀");
+        assert_eq!(found[0].codepoints(), [0x1F44B, 0x1F3FD]);
+        assert!(find_emoji("plain text").is_empty());
+    }
+
+    #[test]
+    fn test_find_emoji_positions() {
+        // 🚀 is two UTF-16 units, é is one; each line ending is different.
+        let text = "é🚀\r\nx\ry 👍🏽\n\n✅";
+        let found = find_emoji_positions(text);
+        let located: Vec<_> = found
+            .iter()
+            .map(|p| (p.emoji.sequence, p.utf16.clone(), p.start, p.end))
+            .collect();
+        let at = |line, character| Position { line, character };
+        assert_eq!(
+            located,
+            [
+                ("🚀", 1..3, at(0, 1), at(0, 3)),
+                ("👍🏽", 9..13, at(2, 2), at(2, 6)),
+                ("✅", 15..16, at(4, 0), at(4, 1)),
+            ]
+        );
+        let utf16: Vec<u16> = text.encode_utf16().collect();
+        assert_eq!(
+            String::from_utf16(&utf16[found[1].utf16.clone()]).unwrap(),
+            "👍🏽"
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! The Markdown summary `--github-summary` appends to the file GitHub
+//! Actions names in `$GITHUB_STEP_SUMMARY`, shown on the workflow run page.
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::ProcessResult;
+
+/// The variable GitHub Actions sets to the summary file of the current step.
+pub const SUMMARY_VAR: &str = "GITHUB_STEP_SUMMARY";
+
+/// How many files the worst offenders table lists.
+const WORST: usize = 10;
+
+/// A Markdown summary of `results`. `verb` says what happened to the emojis
+/// found, e.g. `removed` or `found` for a dry run.
+pub fn summary(results: &[ProcessResult], verb: &str) -> String {
+    let emojis: usize = results.iter().map(|r| r.emojis_found).sum();
+    let with_emojis = results.iter().filter(|r| r.emojis_found > 0).count();
+    let failed: Vec<&ProcessResult> = results.iter().filter(|r| !r.success).collect();
+
+    let mut out = String::from("## nomoji\n\n");
+    out.push_str(&format!(
+        "| Files | With emojis | Failed | Emojis {} |\n|---:|---:|---:|---:|\n",
+        verb
+    ));
+    out.push_str(&format!(
+        "| {} | {} | {} | {} |\n",
+        results.len(),
+        with_emojis,
+        failed.len(),
+        emojis
+    ));
+
+    let mut worst: Vec<&ProcessResult> = results.iter().filter(|r| r.emojis_found > 0).collect();
+    worst.sort_by_key(|r| std::cmp::Reverse(r.emojis_found));
+    worst.truncate(WORST);
+    if !worst.is_empty() {
+        out.push_str("\n### Worst offenders\n\n| File | Emojis |\n|---|---:|\n");
+        for result in worst {
+            out.push_str(&format!(
+                "| {} | {} |\n",
+                cell(&result.file),
+                result.emojis_found
+            ));
+        }
+    }
+
+    if !failed.is_empty() {
+        out.push_str("\n### Failed\n\n| File | Error |\n|---|---|\n");
+        for result in failed {
+            let error = result.error.as_deref().unwrap_or("failed");
+            out.push_str(&format!("| {} | {} |\n", cell(&result.file), text(error)));
+        }
+    }
+    out
+}
+
+/// Appends `summary` to the file at `path`, creating it if needed.
+pub fn append(path: &Path, summary: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(summary.as_bytes())?;
+    // Summaries from several steps or runs share the file.
+    file.write_all(b"\n")
+}
+
+/// A file name as a table cell, in a code span unless it holds a backtick.
+fn cell(file: &str) -> String {
+    match file.contains('`') {
+        true => text(file),
+        false => format!("`{}`", file.replace('|', "\\|")),
+    }
+}
+
+/// `s` as plain text in a table cell.
+fn text(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '|' | '\\' | '`' | '*' | '_' | '<' | '>' | '[' | ']' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' | '\r' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let results = vec![
+            ProcessResult::succeeded("README.md", 3),
+            ProcessResult::succeeded("a|b.md", 7),
+            ProcessResult::succeeded("plain.txt", 0),
+            ProcessResult::failed("gone.md", 0, "No such file <os error 2>".to_string()),
+        ];
+        assert_eq!(
+            summary(&results, "removed"),
+            "## nomoji\n\n\
+             | Files | With emojis | Failed | Emojis removed |\n|---:|---:|---:|---:|\n\
+             | 4 | 2 | 1 | 10 |\n\
+             \n### Worst offenders\n\n| File | Emojis |\n|---|---:|\n\
+             | `a\\|b.md` | 7 |\n\
+             | `README.md` | 3 |\n\
+             \n### Failed\n\n| File | Error |\n|---|---|\n\
+             | `gone.md` | No such file \\<os error 2\\> |\n"
+        );
+
+        let clean = summary(&[ProcessResult::succeeded("plain.txt", 0)], "found");
+        assert!(clean.contains("| 1 | 0 | 0 | 0 |"));
+        assert!(!clean.contains("Worst offenders"));
+    }
+
+    #[test]
+    fn test_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("summary.md");
+        append(&path, "first\n").unwrap();
+        append(&path, "second\n").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "first\n\nsecond\n\n"
+        );
+    }
+}
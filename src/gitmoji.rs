@@ -0,0 +1,231 @@
+//! Gitmoji to Conventional Commits, for `--gitmoji-convert`.
+//!
+//! A gitmoji at the start of a commit subject, as an emoji or as its
+//! `:code:`, becomes the matching type prefix (`🐛 Fix crash` becomes
+//! `fix: Fix crash`), so the meaning survives when the emoji is stripped.
+//! A subject that already has a prefix only loses the emoji.
+//!
+//! `--keep-gitmoji` uses the same notion of a leading gitmoji to leave it
+//! in place while the rest of a commit message is cleaned.
+
+/// Gitmoji, their codes and the commit type each stands for, after the
+/// gitmoji guide and the common commitlint mappings.
+#[rustfmt::skip]
+pub const DEFAULT: &[(&str, &str, &str)] = &[
+    ("✨", ":sparkles:", "feat"),
+    ("🐛", ":bug:", "fix"),
+    ("🚑", ":ambulance:", "fix"),
+    ("🩹", ":adhesive_bandage:", "fix"),
+    ("🔒", ":lock:", "fix"),
+    ("📝", ":memo:", "docs"),
+    ("✏", ":pencil2:", "docs"),
+    ("💡", ":bulb:", "docs"),
+    ("🎨", ":art:", "style"),
+    ("💄", ":lipstick:", "style"),
+    ("🚨", ":rotating_light:", "style"),
+    ("♻", ":recycle:", "refactor"),
+    ("🏗", ":building_construction:", "refactor"),
+    ("⚰", ":coffin:", "refactor"),
+    ("⚡", ":zap:", "perf"),
+    ("✅", ":white_check_mark:", "test"),
+    ("🧪", ":test_tube:", "test"),
+    ("📦", ":package:", "build"),
+    ("⬆", ":arrow_up:", "build"),
+    ("⬇", ":arrow_down:", "build"),
+    ("📌", ":pushpin:", "build"),
+    ("➕", ":heavy_plus_sign:", "build"),
+    ("➖", ":heavy_minus_sign:", "build"),
+    ("👷", ":construction_worker:", "ci"),
+    ("💚", ":green_heart:", "ci"),
+    ("🔧", ":wrench:", "chore"),
+    ("🔨", ":hammer:", "chore"),
+    ("🔥", ":fire:", "chore"),
+    ("🗑", ":wastebasket:", "chore"),
+    ("🔖", ":bookmark:", "chore"),
+    ("🎉", ":tada:", "chore"),
+    ("🚀", ":rocket:", "chore"),
+    ("🔀", ":twisted_rightwards_arrows:", "chore"),
+    ("⏪", ":rewind:", "revert"),
+    ("💥", ":boom:", "feat!"),
+];
+
+/// The type for the gitmoji `key`, an emoji sequence or a `:code:`:
+/// from `custom`, the configuration file's `[gitmoji]` entries, or else
+/// from `DEFAULT`.
+fn lookup<'a>(custom: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    let key = bare(key);
+    custom
+        .iter()
+        .find(|(gitmoji, _)| bare(gitmoji) == key)
+        .map(|(_, kind)| kind.as_str())
+        .or_else(|| {
+            DEFAULT
+                .iter()
+                .find(|(emoji, code, _)| *emoji == key || *code == key)
+                .map(|(_, _, kind)| *kind)
+        })
+}
+
+/// A gitmoji as written, without variation selectors: `🚑️` and `🚑` are
+/// the same gitmoji.
+fn bare(gitmoji: &str) -> String {
+    gitmoji.replace('\u{FE0F}', "")
+}
+
+/// Checks a commit type from the configuration file, such as `feat`,
+/// `fix(ui)` or `feat!`.
+pub fn check_type(kind: &str) -> Result<(), String> {
+    let name_end = kind
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+        .unwrap_or(kind.len());
+    let mut rest = &kind[name_end..];
+    if let Some(scope) = rest.strip_prefix('(') {
+        rest = match scope.split_once(')') {
+            Some((scope, after)) if !scope.is_empty() => after,
+            _ => rest,
+        };
+    }
+    match name_end > 0 && matches!(rest, "" | "!") {
+        true => Ok(()),
+        false => Err(format!(
+            "invalid commit type '{}': use a type like feat, fix(ui) or feat!",
+            kind
+        )),
+    }
+}
+
+/// `message` with the gitmoji leading its subject line replaced by the
+/// type it stands for, or `None` if the subject doesn't start with one in
+/// `custom` or the defaults.
+pub fn convert(message: &str, custom: &[(String, String)]) -> Option<String> {
+    let indent = message.len() - message.trim_start_matches([' ', '\t']).len();
+    let subject = &message[indent..];
+    let (gitmoji, kind) = leading(subject, custom)?;
+    let rest = subject[gitmoji..].trim_start_matches([' ', '\t']);
+    let converted = match has_prefix(rest) {
+        true => rest.to_string(),
+        false => format!("{}: {}", kind, rest),
+    };
+    Some(format!("{}{}", &message[..indent], converted))
+}
+
+/// The length of the leading part of `message` that `--keep-gitmoji`
+/// keeps: indentation and the one gitmoji or emoji starting its subject
+/// line, or 0 if there is none.
+pub fn kept_len(message: &str, custom: &[(String, String)]) -> usize {
+    let indent = message.len() - message.trim_start_matches([' ', '\t']).len();
+    let subject = &message[indent..];
+    let end = match leading(subject, custom) {
+        Some((end, _)) if !subject.starts_with(':') => end,
+        _ => match nomoji::emoji_spans(subject).first() {
+            Some(span) if span.start == 0 => span.end,
+            _ => return 0,
+        },
+    };
+    indent + end
+}
+
+/// The length of the gitmoji `subject` starts with, and its type.
+fn leading<'a>(subject: &str, custom: &'a [(String, String)]) -> Option<(usize, &'a str)> {
+    if let Some(code) = subject.strip_prefix(':') {
+        let end = code.find(':')? + 2;
+        return lookup(custom, &subject[..end]).map(|kind| (end, kind));
+    }
+    // Some gitmoji, like ⬆, are text symbols nomoji only removes with
+    // --aggressive, so fall back to the first character and its selector.
+    let end = match nomoji::emoji_spans(subject).first() {
+        Some(span) if span.start == 0 => span.end,
+        _ => {
+            let first = subject.chars().next()?.len_utf8();
+            match subject[first..].starts_with('\u{FE0F}') {
+                true => first + '\u{FE0F}'.len_utf8(),
+                false => first,
+            }
+        }
+    };
+    lookup(custom, &subject[..end]).map(|kind| (end, kind))
+}
+
+/// Whether `subject` starts with a Conventional Commits prefix, such as
+/// `feat: ` or `fix(parser)!: `.
+fn has_prefix(subject: &str) -> bool {
+    subject
+        .split_once(": ")
+        .is_some_and(|(prefix, _)| check_type(prefix).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        let table = [];
+        assert_eq!(
+            convert("🐛 Fix crash on empty input\n\nDetails 🔥\n", &table).as_deref(),
+            Some("fix: Fix crash on empty input\n\nDetails 🔥\n")
+        );
+        assert_eq!(
+            convert("✨Add sampling", &table).as_deref(),
+            Some("feat: Add sampling")
+        );
+        assert_eq!(
+            convert(":memo: Document --xml", &table).as_deref(),
+            Some("docs: Document --xml")
+        );
+        // With or without its variation selector.
+        assert_eq!(
+            convert("🚑\u{FE0F} Patch", &table).as_deref(),
+            Some("fix: Patch")
+        );
+        assert_eq!(
+            convert("💥 feat(api)!: Drop v1", &table).as_deref(),
+            Some("feat(api)!: Drop v1")
+        );
+        assert_eq!(convert("Fix 🐛 crash", &table), None);
+        assert_eq!(convert("🦀 Port to Rust", &table), None);
+        assert_eq!(convert(":nope: x", &table), None);
+
+        let table = [
+            ("🦀".to_string(), "refactor(rust)".to_string()),
+            ("🐛".to_string(), "bugfix".to_string()),
+        ];
+        assert_eq!(
+            convert("🦀 Port to Rust", &table).as_deref(),
+            Some("refactor(rust): Port to Rust")
+        );
+        assert_eq!(convert("🐛 x", &table).as_deref(), Some("bugfix: x"));
+    }
+
+    #[test]
+    fn test_kept_len() {
+        assert_eq!(kept_len("🐛 Fix 🔥", &[]), "🐛".len());
+        assert_eq!(kept_len("  ⬆\u{FE0F} Bump", &[]), "  ⬆\u{FE0F}".len());
+        // Any emoji leading the subject, listed or not, but only one.
+        assert_eq!(kept_len("🦀🦀 Port", &[]), "🦀".len());
+        assert_eq!(kept_len(":bug: Fix", &[]), 0);
+        assert_eq!(kept_len("Fix 🐛", &[]), 0);
+        assert_eq!(kept_len("", &[]), 0);
+    }
+
+    #[test]
+    fn test_every_default_is_one_emoji() {
+        for (emoji, code, kind) in DEFAULT {
+            let subject = format!("{}\u{FE0F} x", emoji);
+            let expected = format!("{}: x", kind);
+            assert_eq!(convert(&subject, &[]), Some(expected.clone()), "{}", emoji);
+            assert_eq!(convert(&format!("{} x", code), &[]), Some(expected));
+            assert!(check_type(kind).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_check_type() {
+        for good in ["feat", "fix(ui)", "feat!", "fix(a-b)!", "build-deps"] {
+            assert!(check_type(good).is_ok(), "{}", good);
+        }
+        for bad in ["", "fix:", "fix()", "fix (ui)", "(ui)", "feat!!"] {
+            assert!(check_type(bad).is_err(), "{}", bad);
+        }
+    }
+}
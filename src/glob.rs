@@ -0,0 +1,75 @@
+//! Minimal glob matching for path filters.
+//!
+//! Supported syntax: `*` matches any run of characters within one path
+//! component, `**` matches across components, and `?` matches a single
+//! character. As in `.gitignore`, a pattern without a `/` is matched against
+//! the file name in any directory.
+
+/// Returns `true` if `path` (using `/` separators) matches `pattern`.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let path = path.strip_prefix("./").unwrap_or(path);
+
+    if pattern.contains('/') {
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+        matches(pattern.as_bytes(), path.as_bytes())
+    } else {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        matches(pattern.as_bytes(), name.as_bytes())
+    }
+}
+
+fn matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            // `**/` may also match zero directories.
+            let rest_after_slash = rest.strip_prefix(b"/").unwrap_or(rest);
+            (0..=text.len()).any(|i| matches(rest, &text[i..]))
+                || (rest.len() != rest_after_slash.len() && matches(rest_after_slash, text))
+        }
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != b'/')
+            .any(|i| matches(rest, &text[i..])),
+        [b'?', rest @ ..] => match text {
+            [] | [b'/', ..] => false,
+            _ => matches(rest, &text[utf8_len(text[0])..]),
+        },
+        [c, rest @ ..] => text.first() == Some(c) && matches(rest, &text[1..]),
+    }
+}
+
+/// Length of the UTF-8 sequence introduced by `lead`, so `?` consumes a
+/// whole character.
+fn utf8_len(lead: u8) -> usize {
+    match lead {
+        0xF0..=0xFF => 4,
+        0xE0..=0xEF => 3,
+        0xC0..=0xDF => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basename_patterns() {
+        assert!(glob_match("*.md", "README.md"));
+        assert!(glob_match("*.md", "docs/guide/intro.md"));
+        assert!(!glob_match("*.md", "src/main.rs"));
+        assert!(glob_match("fil?.txt", "a/file.txt"));
+        assert!(glob_match("caf?.txt", "café.txt"));
+    }
+
+    #[test]
+    fn test_path_patterns() {
+        assert!(glob_match("docs/*.md", "docs/intro.md"));
+        assert!(!glob_match("docs/*.md", "docs/guide/intro.md"));
+        assert!(glob_match("docs/**/*.md", "docs/intro.md"));
+        assert!(glob_match("docs/**/*.md", "docs/guide/deep/intro.md"));
+        assert!(glob_match("docs/**", "docs/a/b.txt"));
+        assert!(glob_match("/src/*.rs", "./src/main.rs"));
+        assert!(!glob_match("src/*.rs", "other/src/main.rs"));
+    }
+}
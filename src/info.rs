@@ -0,0 +1,218 @@
+//! `nomoji info`: everything nomoji knows about one sequence, for settling
+//! why it was or wasn't removed.
+
+use nomoji::Role;
+use nomoji::category::Category;
+
+use crate::age::age;
+use crate::emoji_names::EMOJI_NAMES;
+use crate::selftest::{self, CORPUS};
+
+/// How `emoji-test.txt` would classify a sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Qualification {
+    FullyQualified,
+    /// The first character has the variation selector it needs, but a later
+    /// one is missing its own.
+    MinimallyQualified,
+    Unqualified,
+    /// A character used to build emoji rather than one itself, or a
+    /// sequence of them.
+    Component,
+    /// Not an emoji sequence Unicode lists.
+    Unlisted,
+}
+
+impl Qualification {
+    pub fn name(self) -> &'static str {
+        match self {
+            Qualification::FullyQualified => "fully-qualified",
+            Qualification::MinimallyQualified => "minimally-qualified",
+            Qualification::Unqualified => "unqualified",
+            Qualification::Component => "component",
+            Qualification::Unlisted => "not a listed emoji sequence",
+        }
+    }
+}
+
+/// What the Unicode data says about a sequence.
+#[derive(Debug, PartialEq)]
+pub struct Info {
+    /// The name of the sequence as a whole, e.g. `thumbs up: medium skin tone`.
+    pub name: Option<String>,
+    /// Each character with its Unicode name, if it has one nomoji knows.
+    pub characters: Vec<(char, Option<String>)>,
+    pub categories: Vec<&'static str>,
+    pub qualification: Qualification,
+    pub shortcode: Option<String>,
+    pub version: Option<(u8, u8)>,
+    pub role: Role,
+}
+
+pub fn of(sequence: &str) -> Info {
+    Info {
+        name: selftest::name(sequence),
+        characters: sequence.chars().map(|c| (c, char_name(c))).collect(),
+        categories: Category::ALL
+            .into_iter()
+            .filter(|c| c.matches(sequence))
+            .map(Category::name)
+            .collect(),
+        qualification: qualification(sequence),
+        shortcode: crate::mode::shortcode(sequence),
+        version: age(sequence),
+        role: Role::of(sequence),
+    }
+}
+
+/// The sequence an `info` argument names: the text itself, or characters
+/// written as `U+XXXX` and separated by spaces, for those a shell can't
+/// easily pass.
+pub fn parse(arg: &str) -> String {
+    let points: Option<String> = arg
+        .split_whitespace()
+        .map(|point| {
+            let digits = point.strip_prefix("U+").or(point.strip_prefix("u+"))?;
+            u32::from_str_radix(digits, 16)
+                .ok()
+                .and_then(char::from_u32)
+        })
+        .collect();
+    match points {
+        Some(sequence) if !sequence.is_empty() => sequence,
+        _ => arg.to_string(),
+    }
+}
+
+/// Compares `sequence` with the fully-qualified form it would have in the
+/// corpus, which differs at most in variation selectors.
+fn qualification(sequence: &str) -> Qualification {
+    let bare = |s: &str| s.replace('\u{FE0F}', "");
+    let target = bare(sequence);
+    let full = selftest::sequences(CORPUS)
+        .map(|(candidate, _)| candidate)
+        .find(|candidate| bare(candidate) == target);
+    match full {
+        Some(full) if full == sequence => Qualification::FullyQualified,
+        Some(full) => {
+            let second = |s: &str| s.chars().nth(1);
+            match second(&full) != Some('\u{FE0F}') || second(sequence) == Some('\u{FE0F}') {
+                true => Qualification::MinimallyQualified,
+                false => Qualification::Unqualified,
+            }
+        }
+        None if !sequence.is_empty() && sequence.chars().all(is_component) => {
+            Qualification::Component
+        }
+        None => Qualification::Unlisted,
+    }
+}
+
+/// The characters `emoji-test.txt` lists as components.
+fn is_component(c: char) -> bool {
+    matches!(c, '#' | '*' | '0'..='9')
+        || nomoji::is_emoji_component(c)
+        || nomoji::is_regional_indicator(c)
+        || ('\u{1F9B0}'..='\u{1F9B3}').contains(&c)
+}
+
+/// The Unicode name of `c`, in capitals as the standard writes it.
+pub fn char_name(c: char) -> Option<String> {
+    const DIGITS: [&str; 10] = [
+        "ZERO", "ONE", "TWO", "THREE", "FOUR", "FIVE", "SIX", "SEVEN", "EIGHT", "NINE",
+    ];
+    let name = match c as u32 {
+        0x23 => "NUMBER SIGN".to_string(),
+        0x2A => "ASTERISK".to_string(),
+        0x30..=0x39 => format!("DIGIT {}", DIGITS[c as usize - 0x30]),
+        0x200D => "ZERO WIDTH JOINER".to_string(),
+        0x20E3 => "COMBINING ENCLOSING KEYCAP".to_string(),
+        0xFE0E => "VARIATION SELECTOR-15".to_string(),
+        0xFE0F => "VARIATION SELECTOR-16".to_string(),
+        0x1F1E6..=0x1F1FF => format!(
+            "REGIONAL INDICATOR SYMBOL LETTER {}",
+            char::from(b'A' + (c as u32 - 0x1F1E6) as u8)
+        ),
+        0x1F3FB => "EMOJI MODIFIER FITZPATRICK TYPE-1-2".to_string(),
+        0x1F3FC..=0x1F3FF => format!("EMOJI MODIFIER FITZPATRICK TYPE-{}", c as u32 - 0x1F3FC + 3),
+        0xE007F => "CANCEL TAG".to_string(),
+        0xE0020..=0xE007E => {
+            let ascii = char::from((c as u32 - 0xE0000) as u8);
+            let name = match ascii {
+                'a'..='z' => format!("LATIN SMALL LETTER {}", ascii.to_ascii_uppercase()),
+                'A'..='Z' => format!("LATIN CAPITAL LETTER {}", ascii),
+                '0'..='9' => format!("DIGIT {}", DIGITS[ascii as usize - 0x30]),
+                ' ' => "SPACE".to_string(),
+                _ => format!("U+{:04X}", ascii as u32),
+            };
+            format!("TAG {}", name)
+        }
+        _ => {
+            let i = EMOJI_NAMES.binary_search_by_key(&c, |&(e, _)| e).ok()?;
+            EMOJI_NAMES[i].1.replace('_', " ").to_uppercase()
+        }
+    };
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_info() {
+        let info = of("👩\u{200D}🚀");
+        assert_eq!(info.name.as_deref(), Some("woman rocket"));
+        assert_eq!(
+            info.characters,
+            [
+                ('👩', Some("WOMAN".to_string())),
+                ('\u{200D}', Some("ZERO WIDTH JOINER".to_string())),
+                ('🚀', Some("ROCKET".to_string())),
+            ]
+        );
+        assert_eq!(info.categories, ["pictographs"]);
+        assert_eq!(info.qualification, Qualification::FullyQualified);
+        assert_eq!(info.shortcode.as_deref(), Some(":woman_rocket:"));
+        assert_eq!(info.role, Role::Pictograph);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("U+1F44D u+1F3FD"), "👍🏽");
+        assert_eq!(parse("🚀"), "🚀");
+        assert_eq!(parse("U+1F44D and"), "U+1F44D and");
+    }
+
+    #[test]
+    fn test_qualification() {
+        assert_eq!(qualification("©\u{FE0F}"), Qualification::FullyQualified);
+        assert_eq!(qualification("©"), Qualification::Unqualified);
+        // Only the variation selector after the speech bubble is missing.
+        assert_eq!(
+            qualification("👁\u{FE0F}\u{200D}🗨"),
+            Qualification::MinimallyQualified
+        );
+        assert_eq!(qualification("👁\u{200D}🗨"), Qualification::Unqualified);
+        assert_eq!(qualification("\u{1F3FD}"), Qualification::Component);
+        assert_eq!(qualification("a"), Qualification::Unlisted);
+    }
+
+    #[test]
+    fn test_char_name() {
+        assert_eq!(char_name('7').as_deref(), Some("DIGIT SEVEN"));
+        assert_eq!(
+            char_name('\u{1F1EB}').as_deref(),
+            Some("REGIONAL INDICATOR SYMBOL LETTER F")
+        );
+        assert_eq!(
+            char_name('\u{1F3FD}').as_deref(),
+            Some("EMOJI MODIFIER FITZPATRICK TYPE-4")
+        );
+        assert_eq!(
+            char_name('\u{E0067}').as_deref(),
+            Some("TAG LATIN SMALL LETTER G")
+        );
+        assert_eq!(char_name('a'), None);
+    }
+}
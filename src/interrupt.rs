@@ -0,0 +1,66 @@
+//! Graceful handling of SIGINT and SIGTERM while files are processed.
+//!
+//! The first signal only records itself, so the file being written is
+//! finished and no later file is started. A second signal terminates
+//! immediately with the default action.
+
+use std::sync::atomic::{AtomicI32, Ordering};
+
+static SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+#[cfg(unix)]
+pub fn install() {
+    extern "C" fn handle(signal: libc::c_int) {
+        if SIGNAL.swap(signal, Ordering::SeqCst) != 0 {
+            // SAFETY: signal and raise are async-signal-safe.
+            unsafe {
+                libc::signal(signal, libc::SIG_DFL);
+                libc::raise(signal);
+            }
+            return;
+        }
+
+        let message = b"\nnomoji: interrupted, finishing the current file (repeat to abort)\n";
+        // SAFETY: write is async-signal-safe and the buffer outlives the call.
+        unsafe {
+            libc::write(2, message.as_ptr().cast(), message.len());
+        }
+    }
+
+    let handler = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: the handler only touches an atomic and async-signal-safe
+        // functions.
+        unsafe {
+            libc::signal(signal, handler);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install() {}
+
+/// The signal that interrupted the run, if any.
+pub fn requested() -> Option<i32> {
+    match SIGNAL.load(Ordering::SeqCst) {
+        0 => None,
+        signal => Some(signal),
+    }
+}
+
+/// Shell convention for a process stopped by `signal`: 130 for SIGINT, 143
+/// for SIGTERM.
+pub fn exit_code(signal: i32) -> i32 {
+    128 + signal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code() {
+        assert_eq!(exit_code(2), 130);
+        assert_eq!(exit_code(15), 143);
+    }
+}
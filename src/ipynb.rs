@@ -0,0 +1,319 @@
+//! Jupyter notebooks for `--ipynb`.
+//!
+//! Only the text of markdown cells and of cell outputs may change, and the
+//! source of code cells with `--ipynb-code`. Those are string literals in the
+//! notebook JSON; cleaning edits them in place, so keys, metadata, outputs
+//! that aren't text and the file's own formatting are kept byte for byte.
+
+use std::ops::Range;
+
+use crate::json;
+
+/// The output formats cleaned in `display_data` and `execute_result`
+/// outputs. HTML, images and the like are left alone.
+const TEXT_OUTPUTS: &[&str] = &["text/plain", "text/markdown"];
+
+/// A JSON value with the byte range of every string literal in it.
+enum Node {
+    Object(Vec<(String, Node)>),
+    Array(Vec<Node>),
+    /// A string literal: its range, quotes included.
+    String(Range<usize>),
+    /// A number, `true`, `false` or `null`.
+    Scalar,
+}
+
+impl Node {
+    fn get(&self, key: &str) -> Option<&Node> {
+        match self {
+            Node::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn items(&self) -> &[Node] {
+        match self {
+            Node::Array(items) => items,
+            _ => &[],
+        }
+    }
+}
+
+/// Byte ranges of `notebook` that cleaning may change: the inside of each
+/// string literal holding markdown or output text, and code cell source if
+/// `code` is set. Fails if `notebook` isn't an nbformat 4 notebook.
+pub fn regions(notebook: &str, code: bool) -> Result<Vec<Range<usize>>, String> {
+    let mut parser = Parser {
+        text: notebook,
+        bytes: notebook.as_bytes(),
+        pos: 0,
+    };
+    let root = parser
+        .value()
+        .and_then(|root| {
+            parser.skip_whitespace();
+            match parser.pos < parser.bytes.len() {
+                true => Err("trailing characters".to_string()),
+                false => Ok(root),
+            }
+        })
+        .map_err(|e| format!("Not a Jupyter notebook: {} at byte {}", e, parser.pos))?;
+
+    let cells = match (root.get("cells"), root.get("worksheets")) {
+        (Some(cells @ Node::Array(_)), _) => cells.items(),
+        (None, Some(_)) => return Err("Only nbformat 4 notebooks are supported".to_string()),
+        _ => return Err("Not a Jupyter notebook: no cells".to_string()),
+    };
+
+    let mut regions = Vec::new();
+    for cell in cells {
+        let cell_type = cell.get("cell_type").and_then(|t| parser.decode(t));
+        let wanted = match cell_type.as_deref() {
+            Some("markdown") => true,
+            Some("code") => code,
+            _ => false,
+        };
+        if wanted && let Some(source) = cell.get("source") {
+            parser.text_regions(source, &mut regions);
+        }
+        for output in cell.get("outputs").map_or(&[][..], Node::items) {
+            if let Some(text) = output.get("text") {
+                parser.text_regions(text, &mut regions);
+            }
+            for format in TEXT_OUTPUTS {
+                if let Some(text) = output.get("data").and_then(|data| data.get(format)) {
+                    parser.text_regions(text, &mut regions);
+                }
+            }
+        }
+    }
+    regions.sort_by_key(|region| region.start);
+    Ok(regions)
+}
+
+/// `replacement` as it must be written inside a JSON string literal.
+pub fn escape(replacement: &str) -> String {
+    let quoted = json::Value::from(replacement).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    /// Adds the inside of `text`, a multiline string in nbformat: one
+    /// string or a list of lines.
+    fn text_regions(&self, text: &Node, regions: &mut Vec<Range<usize>>) {
+        let literals = match text {
+            Node::String(_) => std::slice::from_ref(text),
+            _ => text.items(),
+        };
+        for literal in literals {
+            if let Node::String(range) = literal {
+                regions.push(range.start + 1..range.end - 1);
+            }
+        }
+    }
+
+    /// The value of a string literal.
+    fn decode(&self, node: &Node) -> Option<String> {
+        match node {
+            Node::String(range) => match json::parse(&self.text[range.clone()]) {
+                Ok(value) => value.as_str().map(str::to_string),
+                Err(_) => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.bytes.get(self.pos), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.bytes.get(self.pos) == Some(&byte) {
+            true => {
+                self.pos += 1;
+                Ok(())
+            }
+            false => Err(format!("expected '{}'", byte as char)),
+        }
+    }
+
+    fn value(&mut self) -> Result<Node, String> {
+        self.skip_whitespace();
+        match self.bytes.get(self.pos) {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(Node::String),
+            Some(b't' | b'f' | b'n' | b'-' | b'0'..=b'9') => self.scalar(),
+            Some(_) => Err("unexpected character".to_string()),
+            None => Err("unexpected end of input".to_string()),
+        }
+    }
+
+    fn object(&mut self) -> Result<Node, String> {
+        self.expect(b'{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) == Some(&b'}') {
+            self.pos += 1;
+            return Ok(Node::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            let key = self
+                .decode(&Node::String(key))
+                .ok_or_else(|| "invalid string".to_string())?;
+            self.expect(b':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Node::Object(fields));
+                }
+                _ => return Err("expected ',' or '}'".to_string()),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Node, String> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) == Some(&b']') {
+            self.pos += 1;
+            return Ok(Node::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Node::Array(items));
+                }
+                _ => return Err("expected ',' or ']'".to_string()),
+            }
+        }
+    }
+
+    /// Skips a literal or number, fractions and exponents included, since
+    /// notebook metadata has them.
+    fn scalar(&mut self) -> Result<Node, String> {
+        let start = self.pos;
+        while matches!(
+            self.bytes.get(self.pos),
+            Some(b'a'..=b'z' | b'0'..=b'9' | b'-' | b'+' | b'.' | b'E')
+        ) {
+            self.pos += 1;
+        }
+        match &self.text[start..self.pos] {
+            "true" | "false" | "null" => Ok(Node::Scalar),
+            number if number.parse::<f64>().is_ok() => Ok(Node::Scalar),
+            _ => Err("invalid literal".to_string()),
+        }
+    }
+
+    /// The range of the string literal at the current position.
+    fn string(&mut self) -> Result<Range<usize>, String> {
+        if self.bytes.get(self.pos) != Some(&b'"') {
+            return Err("expected a string".to_string());
+        }
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.bytes.get(self.pos) {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(start..self.pos);
+                }
+                Some(b'\\') => self.pos += 2,
+                Some(b) if *b < 0x20 => return Err("control character in string".to_string()),
+                Some(_) => self.pos += 1,
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTEBOOK: &str = r##"{
+ "cells": [
+  {
+   "cell_type": "markdown",
+   "metadata": {"tags": ["🚀"]},
+   "source": ["# Launch 🚀\n", "Done ✅"]
+  },
+  {
+   "cell_type": "code",
+   "execution_count": 1,
+   "metadata": {"scrolled": 0.5},
+   "outputs": [
+    {"name": "stdout", "output_type": "stream", "text": ["ok ✅\n"]},
+    {"data": {"image/png": "🚀", "text/plain": "'🎉'"}, "output_type": "execute_result"}
+   ],
+   "source": "print('🎉')"
+  }
+ ],
+ "metadata": {"title": "🚀"},
+ "nbformat": 4,
+ "nbformat_minor": 5
+}"##;
+
+    fn texts(code: bool) -> Vec<&'static str> {
+        regions(NOTEBOOK, code)
+            .unwrap()
+            .into_iter()
+            .map(|region| &NOTEBOOK[region])
+            .collect()
+    }
+
+    #[test]
+    fn test_regions() {
+        assert_eq!(
+            texts(false),
+            ["# Launch 🚀\\n", "Done ✅", "ok ✅\\n", "'🎉'"]
+        );
+        assert_eq!(
+            texts(true),
+            [
+                "# Launch 🚀\\n",
+                "Done ✅",
+                "ok ✅\\n",
+                "'🎉'",
+                "print('🎉')"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_regions_rejects_other_json() {
+        assert!(regions(r#"{"cells": 3}"#, false).is_err());
+        assert!(
+            regions(r#"{"worksheets": []}"#, false)
+                .unwrap_err()
+                .contains("nbformat 4")
+        );
+        assert!(regions("[1, 2", false).is_err());
+        assert_eq!(regions(r#"{"cells": []}"#, false), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape(r#"\u{1F680} "x""#), r#"\\u{1F680} \"x\""#);
+    }
+}
@@ -0,0 +1,61 @@
+//! JSON Lines reports (`--format jsonl`): an object per occurrence, one per
+//! file and the totals last, each on its own line with `schema_version` and
+//! a `type` to tell them apart, so a reader can handle the stream line by
+//! line instead of parsing one large document.
+
+use std::io::{self, Write};
+
+use nomoji::Role;
+use nomoji::schema::{Occurrence, SCHEMA_VERSION};
+
+use crate::json::Value;
+use crate::{ProcessResult, mode, report};
+
+/// Writes the stream for `listed`, in that order, and the totals over
+/// `results`.
+pub fn print(
+    results: &[ProcessResult],
+    listed: &[&ProcessResult],
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let report = report::to_report(results, listed.iter().copied());
+    for (result, file) in listed.iter().zip(&report.files) {
+        for finding in &result.findings {
+            let occurrence = Occurrence {
+                file: result.file.clone(),
+                line: finding.line,
+                column: finding.column,
+                emoji: finding.emoji.clone(),
+                shortcode: mode::shortcode(&finding.emoji),
+                role: Role::of(&finding.emoji),
+            };
+            writeln!(out, "{}", encode(occurrence))?;
+        }
+        writeln!(out, "{}", report::with_file(header("file"), file))?;
+    }
+    writeln!(out, "{}", report::with_totals(header("summary"), &report))
+}
+
+/// The fields every line starts with.
+fn header(kind: &str) -> Value {
+    Value::object()
+        .with("schema_version", SCHEMA_VERSION as usize)
+        .with("type", kind)
+}
+
+/// `occurrence` as one line of the stream, with fields in schema order.
+fn encode(occurrence: Occurrence) -> Value {
+    let codepoints: Vec<String> = occurrence
+        .emoji
+        .chars()
+        .map(|c| format!("U+{:04X}", c as u32))
+        .collect();
+    header("occurrence")
+        .with("file", occurrence.file)
+        .with("line", occurrence.line)
+        .with("column", occurrence.column)
+        .with("emoji", occurrence.emoji)
+        .with("codepoints", codepoints)
+        .with("shortcode", occurrence.shortcode)
+        .with("role", occurrence.role.name())
+}
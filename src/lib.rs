@@ -0,0 +1,332 @@
+//! Emoji detection and removal, shared by the `nomoji` binary and the fuzz
+//! targets in `fuzz/`, and the types of its JSON reports.
+//!
+//! `remove_emojis` strips every emoji; `remove_emojis_with` takes
+//! `RemoveOptions` for the choices the command line offers, and `visit`
+//! leaves the decision for each sequence to a callback. `find_emoji` only
+//! locates them, and `Role` tells a whole emoji from debris of one.
+//! `par_remove` cleans a batch of texts on every core, and
+//! `stream::StreamCleaner` a stream that arrives in chunks.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+pub mod aggressive;
+mod batch;
+pub mod category;
+pub mod convert;
+pub mod encoding;
+mod find;
+mod options;
+mod pictographic;
+pub mod presentation;
+mod role;
+pub mod schema;
+pub mod stream;
+mod text_default;
+mod visit;
+
+pub use batch::{par_remove, par_remove_with};
+pub use find::{EmojiPosition, EmojiSpan, Position, find_emoji, find_emoji_positions};
+pub use options::{CountMode, RemoveOptions, Replacement};
+pub use role::Role;
+pub use visit::{Action, visit};
+
+pub fn is_emoji(c: char) -> bool {
+    // Emoji ranges based on Unicode standard
+    let code = c as u32;
+
+    // Miscellaneous Symbols and Pictographs
+    (0x1F300..=0x1F5FF).contains(&code)
+        // Supplemental Symbols and Pictographs
+        || (0x1F900..=0x1F9FF).contains(&code)
+        // Emoticons
+        || (0x1F600..=0x1F64F).contains(&code)
+        // Transport and Map Symbols
+        || (0x1F680..=0x1F6FF).contains(&code)
+        // Miscellaneous Symbols
+        || (0x2600..=0x26FF).contains(&code)
+        // Dingbats
+        || (0x2700..=0x27BF).contains(&code)
+        // Enclosed Alphanumeric Supplement
+        || (0x1F100..=0x1F1FF).contains(&code)
+        // Enclosed Ideographic Supplement
+        || (0x1F200..=0x1F2FF).contains(&code)
+        // Geometric Shapes Extended
+        || (0x1F780..=0x1F7FF).contains(&code)
+        // Symbols and Pictographs Extended-A
+        || (0x1FA00..=0x1FA6F).contains(&code)
+        // Symbols and Pictographs Extended-B
+        || (0x1FA70..=0x1FAFF).contains(&code)
+        // Flags (regional indicators)
+        || (0x1F1E6..=0x1F1FF).contains(&code)
+        // Keycap sequences
+        || code == 0x20E3
+        // Zero Width Joiner for emoji sequences
+        || code == 0x200D
+        // Variation Selectors
+        || (0xFE00..=0xFE0F).contains(&code)
+        // Emoji modifier fitzpatrick
+        || (0x1F3FB..=0x1F3FF).contains(&code)
+        // Tag characters, as in subdivision flags
+        || (0xE0020..=0xE007F).contains(&code)
+        // Some additional symbols commonly used as emoji
+        || matches!(code, 0x231A..=0x231B | 0x23E9..=0x23EC | 0x23F0 | 0x23F3 
+            | 0x25FD..=0x25FE | 0x2614..=0x2615 | 0x2648..=0x2653 | 0x267F 
+            | 0x2693 | 0x26A1 | 0x26AA..=0x26AB | 0x26BD..=0x26BE | 0x26C4..=0x26C5 
+            | 0x26CE | 0x26D4 | 0x26EA | 0x26F2..=0x26F3 | 0x26F5 | 0x26FA 
+            | 0x26FD | 0x2705 | 0x2728 | 0x274C | 0x274E | 0x2753..=0x2755 
+            | 0x2795..=0x2797 | 0x27B0 | 0x27BF | 0x2B50 | 0x2B55 | 0x00A9 | 0x00AE 
+            | 0x2122 | 0x3030 | 0x303D)
+}
+
+/// Characters that attach to a preceding emoji rather than standing alone:
+/// variation selectors, skin tone modifiers, the keycap mark, tag
+/// characters and ZWJ. They are invisible on their own, so a stray one is
+/// removed as a sequence of its own.
+pub fn is_emoji_component(c: char) -> bool {
+    matches!(c as u32, 0xFE00..=0xFE0F | 0x1F3FB..=0x1F3FF | 0x20E3 | 0x200D | 0xE0020..=0xE007F)
+}
+
+pub fn is_variation_selector(c: char) -> bool {
+    ('\u{FE00}'..='\u{FE0F}').contains(&c)
+}
+
+pub fn is_regional_indicator(c: char) -> bool {
+    (0x1F1E6..=0x1F1FF).contains(&(c as u32))
+}
+
+/// Byte ranges of the emoji sequences in `input`. A sequence is a base emoji
+/// plus any attached components and ZWJ-joined emoji, or a pair of regional
+/// indicators forming a flag. Every character `remove_emojis` strips falls in
+/// exactly one range.
+pub fn emoji_spans(input: &str) -> Vec<Range<usize>> {
+    emoji_spans_by(input, is_emoji)
+}
+
+/// Like `emoji_spans`, with `is_emoji` widened or narrowed to `matches`.
+/// Sequences are put together the same way, from the characters it
+/// accepts.
+pub fn emoji_spans_by(input: &str, matches: impl Fn(char) -> bool) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if !matches(c) {
+            continue;
+        }
+
+        let mut end = start + c.len_utf8();
+        let mut after_zwj = c == '\u{200D}';
+        let mut open_flag = is_regional_indicator(c);
+
+        while let Some(&(i, next)) = chars.peek() {
+            let joins = matches(next)
+                && (after_zwj
+                    || is_emoji_component(next)
+                    || (open_flag && is_regional_indicator(next)));
+            if !joins {
+                break;
+            }
+
+            open_flag = !open_flag && is_regional_indicator(next) && !after_zwj;
+            after_zwj = next == '\u{200D}';
+            end = i + next.len_utf8();
+            chars.next();
+        }
+
+        spans.push(start..end);
+    }
+
+    spans
+}
+
+pub fn remove_emojis(input: &str) -> (String, usize) {
+    let (text, count) = remove_emojis_cow(input);
+    (text.into_owned(), count)
+}
+
+/// Like `remove_emojis`, but borrows `input` instead of copying it when
+/// there is nothing to remove.
+pub fn remove_emojis_cow(input: &str) -> (Cow<'_, str>, usize) {
+    match input.find(is_emoji) {
+        None => (Cow::Borrowed(input), 0),
+        Some(first) => {
+            let (rest, count) = remove_chars(&input[first..], is_emoji);
+            (Cow::Owned(input[..first].to_string() + &rest), count)
+        }
+    }
+}
+
+/// Like `remove_emojis`, with the categories, allowlist, replacement and
+/// matching `options` choose.
+pub fn remove_emojis_with(input: &str, options: &RemoveOptions) -> (String, usize) {
+    let mut spans = if options.aggressive {
+        emoji_spans_by(input, aggressive::matches)
+    } else {
+        emoji_spans(input)
+    };
+    spans.retain(|span| !options.keeps(&input[span.clone()]));
+    let (text, characters) = replace_spans(input, &spans, |sequence| options.replace(sequence));
+    match options.count {
+        CountMode::Characters => (text, characters),
+        CountMode::Sequences => (text, spans.len()),
+    }
+}
+
+/// Removes the given byte ranges, which must be in order and not overlap,
+/// returning the remaining text and the number of characters removed.
+pub fn remove_spans(input: &str, spans: &[Range<usize>]) -> (String, usize) {
+    replace_spans(input, spans, |_| String::new())
+}
+
+/// Like `remove_spans`, but substitutes `replacement` of each removed
+/// sequence.
+pub fn replace_spans(
+    input: &str,
+    spans: &[Range<usize>],
+    mut replacement: impl FnMut(&str) -> String,
+) -> (String, usize) {
+    let mut result = String::with_capacity(input.len());
+    let mut count = 0;
+    let mut last = 0;
+
+    for span in spans {
+        result.push_str(&input[last..span.start]);
+        result.push_str(&replacement(&input[span.clone()]));
+        count += input[span.clone()].chars().count();
+        last = span.end;
+    }
+    result.push_str(&input[last..]);
+
+    (result, count)
+}
+
+/// A country flag: exactly two regional indicators.
+pub fn is_flag(sequence: &str) -> bool {
+    let mut chars = sequence.chars();
+    matches!(
+        (chars.next(), chars.next(), chars.next()),
+        (Some(a), Some(b), None) if is_regional_indicator(a) && is_regional_indicator(b)
+    )
+}
+
+pub fn remove_chars(input: &str, remove: impl Fn(char) -> bool) -> (String, usize) {
+    let mut result = String::with_capacity(input.len());
+    let mut count = 0;
+
+    for c in input.chars() {
+        if remove(c) {
+            count += 1;
+        } else {
+            result.push(c);
+        }
+    }
+
+    (result, count)
+}
+
+pub fn count_emojis(input: &str) -> usize {
+    input.chars().filter(|&c| is_emoji(c)).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The properties the fuzz targets check, on known tricky inputs.
+    #[test]
+    fn test_removal_paths_agree() {
+        for text in [
+            "",
+            "\u{200D}\u{200D}a\u{FE0F}",
+            "🇫🇷🇫",
+            "1\u{FE0F}\u{20E3}#\u{20E3}",
+            "👍🏽\u{200D}\u{200D}🚀é",
+            "🏴\u{E0067}\u{E0062}\u{E007F}",
+            "\u{1F3FB}x\u{1F3FF}",
+            "e\u{301}🚀\u{301}\u{20DD}x\u{301}",
+        ] {
+            let (cleaned, removed) = remove_emojis(text);
+            assert_eq!(cleaned.chars().count() + removed, text.chars().count());
+            assert_eq!(count_emojis(&cleaned), 0);
+            assert_eq!(remove_emojis_cow(text).0, cleaned);
+            assert_eq!(
+                remove_spans(text, &emoji_spans(text)),
+                (cleaned, removed),
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_remove_emojis_with() {
+        let text = "Ship 🚀 ✅ 👍🏽 ©";
+        assert_eq!(
+            remove_emojis_with(text, &RemoveOptions::new()),
+            remove_emojis(text)
+        );
+
+        let options = RemoveOptions::new()
+            .only(category::Category::Transport)
+            .only(category::Category::Hands)
+            .allow("👍🏽")
+            .replacement(Replacement::Entities);
+        assert_eq!(
+            remove_emojis_with(text, &options),
+            ("Ship &#x1F680; ✅ 👍🏽 ©".to_string(), 1)
+        );
+
+        let options = RemoveOptions::new()
+            .skip(category::Category::CheckMarks)
+            .conservative(true)
+            .count(CountMode::Sequences);
+        assert_eq!(
+            remove_emojis_with(text, &options),
+            ("Ship  ✅  ©".to_string(), 2)
+        );
+
+        // ‼ is pictographic but not caught by `is_emoji`.
+        let options = RemoveOptions::new().aggressive(true);
+        assert_eq!(remove_emojis_with("a‼", &options).0, "a");
+        assert_eq!(remove_emojis_with("a‼", &RemoveOptions::new()).0, "a‼");
+    }
+
+    #[test]
+    fn test_components_leave_no_residue() {
+        // England's flag, then a stray tag, ZWJ and skin tone.
+        let text = "a🏴\u{E0067}\u{E0062}\u{E0065}\u{E006E}\u{E0067}\u{E007F}b\u{E0041}c\u{200D}d\u{1F3FD}";
+        assert_eq!(remove_emojis(text), ("abcd".to_string(), 10));
+        assert_eq!(
+            emoji_spans(text)
+                .iter()
+                .map(|s| text[s.clone()].chars().count())
+                .collect::<Vec<_>>(),
+            [7, 1, 1, 1]
+        );
+    }
+
+    #[test]
+    fn test_orphaned_components_go_and_other_marks_stay() {
+        // A keycap mark and variation selector left by a key that isn't an
+        // emoji are removed. An enclosing circle and accents are not emoji
+        // components, so they stay even next to a removed emoji.
+        let text = "Done \u{2705}\u{20DD} 1\u{FE0F}\u{20E3} cafe\u{301}\u{1F680}\u{301}!";
+        assert_eq!(
+            remove_emojis(text),
+            ("Done \u{20DD} 1 cafe\u{301}\u{301}!".to_string(), 4)
+        );
+        assert_eq!(count_emojis(text), 4);
+    }
+
+    #[test]
+    fn test_remove_emojis_cow_borrows_clean_input() {
+        let text = "plain text, née 日本";
+        assert!(matches!(remove_emojis_cow(text), (Cow::Borrowed(t), 0) if t == text));
+
+        let (cleaned, count) = remove_emojis_cow("née 🚀!");
+        assert!(matches!(cleaned, Cow::Owned(_)));
+        assert_eq!((cleaned.as_ref(), count), ("née !", 1));
+    }
+}
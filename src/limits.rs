@@ -0,0 +1,170 @@
+//! Limits that stop a run early: `--max-files`, `--max-total-bytes` and
+//! `--timeout`.
+//!
+//! They are checked between files, like an interrupt, so the file in
+//! progress is always finished and the report covers every file touched.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Exit code for a run stopped by a limit.
+pub const EXIT_CODE: i32 = 3;
+
+/// The limit a run was stopped by.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Limit {
+    Files(u64),
+    TotalBytes(u64),
+    Timeout(Duration),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Files(n) => write!(f, "--max-files {} reached", n),
+            Limit::TotalBytes(n) => write!(f, "--max-total-bytes {} reached", n),
+            Limit::Timeout(d) => write!(f, "--timeout {}s reached", d.as_secs_f64()),
+        }
+    }
+}
+
+/// The limits of one run, counting from when it was created.
+pub struct Limits {
+    max_files: Option<u64>,
+    max_total_bytes: Option<u64>,
+    timeout: Option<Duration>,
+    started: Instant,
+}
+
+impl Limits {
+    pub fn new(
+        max_files: Option<u64>,
+        max_total_bytes: Option<u64>,
+        timeout: Option<Duration>,
+    ) -> Self {
+        Limits {
+            max_files,
+            max_total_bytes,
+            timeout,
+            started: Instant::now(),
+        }
+    }
+
+    /// The limit that forbids starting another file after `files` files of
+    /// `bytes` bytes in total, if any.
+    pub fn reached(&self, files: u64, bytes: u64) -> Option<Limit> {
+        if let Some(max) = self.max_files
+            && files >= max
+        {
+            return Some(Limit::Files(max));
+        }
+        if let Some(max) = self.max_total_bytes
+            && bytes >= max
+        {
+            return Some(Limit::TotalBytes(max));
+        }
+        self.timeout
+            .filter(|timeout| self.started.elapsed() >= *timeout)
+            .map(Limit::Timeout)
+    }
+}
+
+/// Parses `--max-total-bytes` values such as `4096`, `512K`, `10MB` or `1G`
+/// (binary multiples).
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let digits = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(digits);
+
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}': expected a number", value))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        other => return Err(format!("invalid size unit '{}': use K, M or G", other)),
+    };
+    match number.checked_mul(multiplier) {
+        Some(0) => Err("size must be greater than zero".to_string()),
+        Some(size) => Ok(size),
+        None => Err(format!("size '{}' is too large", value)),
+    }
+}
+
+/// Parses `--timeout` values such as `90`, `90s`, `15m`, `2h` or `500ms`;
+/// a bare number is seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let digits = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(digits);
+
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid timeout '{}': expected a number", value))?;
+    if number == 0 {
+        return Err("timeout must be greater than zero".to_string());
+    }
+    let seconds = match unit.trim() {
+        "ms" => return Ok(Duration::from_millis(number)),
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        other => {
+            return Err(format!(
+                "invalid timeout unit '{}': use ms, s, m or h",
+                other
+            ));
+        }
+    };
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("timeout '{}' is too large", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("512K"), Ok(512 << 10));
+        assert_eq!(parse_size("10 MB"), Ok(10 << 20));
+        assert_eq!(parse_size("1g"), Ok(1 << 30));
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("10T").is_err());
+        assert!(parse_size("lots").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("15m"), Ok(Duration::from_secs(900)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("1d").is_err());
+    }
+
+    #[test]
+    fn test_reached() {
+        let limits = Limits::new(Some(2), Some(100), None);
+        assert_eq!(limits.reached(1, 99), None);
+        assert_eq!(limits.reached(2, 0), Some(Limit::Files(2)));
+        assert_eq!(limits.reached(1, 100), Some(Limit::TotalBytes(100)));
+
+        let limits = Limits::new(None, None, Some(Duration::ZERO));
+        assert_eq!(limits.reached(0, 0), Some(Limit::Timeout(Duration::ZERO)));
+        assert_eq!(
+            Limits::new(None, None, None).reached(u64::MAX, u64::MAX),
+            None
+        );
+    }
+}
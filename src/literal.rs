@@ -0,0 +1,62 @@
+//! Cleaned text as a string literal, for `--escape-output`, so it can be
+//! pasted into a script or generated config without another quoting step.
+
+use clap::ValueEnum;
+
+/// The languages `--escape-output` quotes for.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// A JSON string; non-ASCII text is kept as UTF-8
+    Json,
+    /// A POSIX shell word in single quotes
+    Shell,
+    /// A C string literal of the UTF-8 bytes, with octal escapes
+    C,
+}
+
+/// `text` as one literal of `target`.
+pub fn quote(text: &str, target: Target) -> String {
+    match target {
+        Target::Json => crate::json::Value::String(text.to_string()).to_string(),
+        Target::Shell => format!("'{}'", text.replace('\'', r"'\''")),
+        Target::C => c_string(text),
+    }
+}
+
+fn c_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for &byte in text.as_bytes() {
+        match byte {
+            b'"' => quoted.push_str("\\\""),
+            b'\\' => quoted.push_str("\\\\"),
+            b'\n' => quoted.push_str("\\n"),
+            b'\r' => quoted.push_str("\\r"),
+            b'\t' => quoted.push_str("\\t"),
+            // `?` would otherwise start a trigraph.
+            b'?' => quoted.push_str("\\?"),
+            0x20..=0x7E => quoted.push(byte as char),
+            // Octal escapes stop after three digits; `\x` would swallow any
+            // hex digits that follow.
+            _ => quoted.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        let text = "it's \"done\"\n\tnaïve?";
+        assert_eq!(quote(text, Target::Json), r#""it's \"done\"\n\tnaïve?""#);
+        assert_eq!(quote(text, Target::Shell), "'it'\\''s \"done\"\n\tnaïve?'");
+        assert_eq!(
+            quote(text, Target::C),
+            r#""it's \"done\"\n\tna\303\257ve\?""#
+        );
+        assert_eq!(quote("", Target::Shell), "''");
+    }
+}
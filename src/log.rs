@@ -0,0 +1,102 @@
+//! Where `--rpc` sessions send their operational messages (`--log`).
+
+use clap::ValueEnum;
+
+/// Targets accepted by `--log`.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Target {
+    /// Standard error
+    #[default]
+    Stderr,
+    /// The local syslog daemon
+    Syslog,
+    /// The systemd journal (Linux only)
+    Journald,
+}
+
+/// Logs an error to `target`, falling back to stderr when the system
+/// logger is unavailable.
+pub fn error(target: Target, message: &str) {
+    let delivered = match target {
+        Target::Stderr => false,
+        Target::Syslog => syslog(message),
+        Target::Journald => journald(message),
+    };
+    if !delivered {
+        eprintln!("nomoji: {}", message);
+    }
+}
+
+/// syslog(3) priority for errors.
+const PRIORITY_ERR: u8 = 3;
+
+#[cfg(unix)]
+fn syslog(message: &str) -> bool {
+    use std::ffi::CString;
+    use std::sync::Once;
+
+    static OPEN: Once = Once::new();
+    let Ok(message) = CString::new(message.replace('\0', " ")) else {
+        return false;
+    };
+    // SAFETY: the identifier is a static C string that outlives every
+    // syslog call, and the message goes through a "%s" format so it is
+    // never interpreted as one.
+    unsafe {
+        OPEN.call_once(|| libc::openlog(c"nomoji".as_ptr(), libc::LOG_PID, libc::LOG_USER));
+        libc::syslog(
+            libc::LOG_ERR | libc::LOG_USER,
+            c"%s".as_ptr(),
+            message.as_ptr(),
+        );
+    }
+    true
+}
+
+#[cfg(not(unix))]
+fn syslog(_message: &str) -> bool {
+    false
+}
+
+#[cfg(target_os = "linux")]
+fn journald(message: &str) -> bool {
+    use std::os::unix::net::UnixDatagram;
+
+    UnixDatagram::unbound()
+        .and_then(|socket| {
+            socket.send_to(
+                journal_entry(PRIORITY_ERR, message).as_bytes(),
+                "/run/systemd/journal/socket",
+            )
+        })
+        .is_ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn journald(_message: &str) -> bool {
+    false
+}
+
+/// A journal entry in the native protocol's plain `KEY=value` form, which
+/// cannot carry newlines, so they are folded into spaces.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn journal_entry(priority: u8, message: &str) -> String {
+    format!(
+        "MESSAGE={}\nPRIORITY={}\nSYSLOG_IDENTIFIER=nomoji\n",
+        message.replace('\n', " "),
+        priority
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_entry() {
+        assert_eq!(
+            journal_entry(PRIORITY_ERR, "bad\nrequest"),
+            "MESSAGE=bad request\nPRIORITY=3\nSYSLOG_IDENTIFIER=nomoji\n"
+        );
+    }
+}
@@ -1,485 +1,5165 @@
-use clap::Parser;
+use clap::builder::BoolishValueParser;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fs;
-use std::io::{self, Read, Write};
-use std::path::Path;
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
+use std::ops::{Range, RangeInclusive};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Instant, SystemTime};
 
-#[derive(Parser, Debug)]
+mod age;
+mod audit;
+mod checkstyle;
+mod child;
+mod codepoints;
+mod color;
+mod config;
+mod console;
+mod db;
+mod describe;
+#[rustfmt::skip]
+mod emoji_age;
+mod eml;
+mod emoji_names;
+mod escapes;
+mod filetype;
+mod filter_cmd;
+mod github;
+mod gitmoji;
+mod glob;
+mod info;
+mod interrupt;
+mod ipynb;
+mod json;
+mod jsonl;
+mod limits;
+mod literal;
+mod log;
+mod manifest;
+mod mbox;
+mod metrics;
+mod mode;
+mod normalize;
+#[rustfmt::skip]
+mod normalize_tables;
+mod parquet;
+#[cfg(feature = "wasm")]
+mod plugin;
+mod pre_receive;
+mod protect;
+mod rdjson;
+mod regex;
+mod report;
+mod rpc;
+mod sample;
+mod self_update;
+mod selftest;
+mod sha256;
+mod staged;
+mod staging;
+mod stats;
+mod tar;
+mod teamcity;
+mod throttle;
+mod timing;
+mod toml;
+mod trash;
+mod walk;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod xml;
+
+use color::Palette;
+use config::{Config, Local, Profile, Rule};
+use filetype::FileType;
+use filter_cmd::Action;
+use limits::{Limit, Limits};
+use mode::{Mode, Syntax};
+use nomoji::category::Category;
+use nomoji::convert::{Conversion, Language};
+use nomoji::encoding::{self, Encoding};
+use nomoji::schema::Estimate;
+use nomoji::{aggressive, presentation};
+use nomoji::{
+    count_emojis, emoji_spans, emoji_spans_by, is_emoji, is_emoji_component, is_flag,
+    is_regional_indicator, is_variation_selector, remove_emojis, remove_emojis_cow, replace_spans,
+};
+use normalize::Form;
+use pre_receive::RawChange;
+use regex::Regex;
+use report::{Destination, Sort};
+use sample::Sample;
+use throttle::{Rate, Throttle};
+use timing::Timings;
+
+#[derive(Parser, Debug, Default)]
 #[command(name = "nomoji")]
 #[command(about = "Remove emoji characters from text files")]
 #[command(version)]
 #[command(arg_required_else_help = true)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Input file(s) to process (use - for stdin)
     files: Vec<String>,
 
+    /// Also process the files listed in FILE, one per line, or in a .toml or
+    /// .json manifest with per-file overrides (use - for stdin)
+    #[arg(long, value_name = "FILE")]
+    files_from: Option<PathBuf>,
+
     /// Create backup files with .bak extension
     #[arg(short, long)]
     backup: bool,
 
+    /// Store backups in a mirrored tree under DIR instead of next to each file
+    #[arg(long, value_name = "DIR")]
+    backup_dir: Option<PathBuf>,
+
     /// Edit files in place
-    #[arg(short, long)]
+    #[arg(short, long, visible_alias = "write")]
     inplace: bool,
 
-    /// Count emojis without removing (dry run)
-    #[arg(long)]
-    dry_run: bool,
-}
+    /// Process the files under each directory given (default: the current
+    /// directory); only reports unless --write or another way of writing
+    /// files is given
+    #[arg(short, long)]
+    recursive: bool,
+
+    /// Move originals to the system trash before writing the cleaned version
+    #[arg(long)]
+    trash: bool,
+
+    /// Run as a pre-commit hook: edit the files given in place, print a line
+    /// per file changed, and fail if any was changed or couldn't be
+    #[arg(
+        long,
+        conflicts_with_all = ["dry_run", "suffix", "escape_output", "format", "report_to", "split_documents", "rpc"]
+    )]
+    pre_commit: bool,
+
+    /// Run as a commit-msg hook: clean the message file git passes in place,
+    /// printing a line if it changed
+    #[arg(
+        long,
+        requires = "files",
+        conflicts_with_all = ["dry_run", "suffix", "escape_output", "format", "report_to", "split_documents", "rpc", "pre_commit", "staged", "recursive", "eml", "mbox", "ipynb", "xml"]
+    )]
+    commit_msg: bool,
+
+    /// With --commit-msg, leave the one gitmoji leading the subject line
+    /// in place
+    #[arg(long, requires = "commit_msg", conflicts_with_all = ["gitmoji_convert", "decider"])]
+    keep_gitmoji: bool,
+
+    /// Check what is staged in the git index instead of the working tree,
+    /// only FILES if given, failing if it finds emojis; with --inplace or
+    /// --pre-commit, stage the cleaned content
+    #[arg(
+        long,
+        conflicts_with_all = ["files_from", "recursive", "split_documents", "rpc", "suffix", "backup", "backup_dir", "trash", "eml", "mbox"]
+    )]
+    staged: bool,
+
+    /// Write each cleaned file next to its input with SUFFIX appended, e.g.
+    /// `--suffix .clean`, leaving the input untouched
+    #[arg(
+        long,
+        value_name = "SUFFIX",
+        value_parser = clap::builder::NonEmptyStringValueParser::new(),
+        conflicts_with_all = ["inplace", "backup", "backup_dir", "trash"]
+    )]
+    suffix: Option<String>,
+
+    /// Give a hard-linked file a new inode of its own when rewriting it,
+    /// leaving its other links with the original content [default: write
+    /// through the shared inode]
+    #[arg(long)]
+    break_links: bool,
+
+    /// Stage rewritten files and backups in DIR instead of next to each file
+    #[arg(long, env = "NOMOJI_TEMP_DIR", value_name = "DIR")]
+    temp_dir: Option<PathBuf>,
+
+    /// Count emojis without removing (dry run)
+    #[arg(long, env = "NOMOJI_DRY_RUN", value_parser = BoolishValueParser::new())]
+    dry_run: bool,
+
+    /// Re-read written files and confirm they match the cleaned output
+    #[arg(long, env = "NOMOJI_VERIFY", value_parser = BoolishValueParser::new())]
+    verify: bool,
+
+    /// Overwrite files even if they changed on disk after being read
+    #[arg(long)]
+    force_write: bool,
+
+    /// Write a JSON record of every change to FILE: offsets, removed
+    /// sequences and before/after hashes of each modified file
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["dry_run", "decider", "normalize", "output_encoding", "eml", "mbox"]
+    )]
+    audit: Option<PathBuf>,
+
+    /// Report format
+    #[arg(long, env = "NOMOJI_FORMAT", value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// List only the N files with the most emojis in the text and JSON
+    /// reports, worst first
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    top_files: Option<u64>,
+
+    /// Leave files without emojis out of the per-file part of the text and
+    /// JSON reports; failures are still listed
+    #[arg(long)]
+    only_matches: bool,
+
+    /// Report how long the run took and its throughput; give twice (-vv) to
+    /// break the time down into reading, cleaning and writing each file
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Also total the report per directory, down to DEPTH levels (default 1)
+    #[arg(
+        long,
+        value_name = "DEPTH",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    group_by_dir: Option<u64>,
+
+    /// Order per-file lines by name, count or errors, optionally with :asc
+    /// or :desc (default: input order)
+    #[arg(long, value_name = "KEY")]
+    sort: Option<Sort>,
+
+    /// With --dry-run, list each distinct emoji found with its count,
+    /// codepoints and name, most frequent first
+    #[arg(long, requires = "dry_run")]
+    list: bool,
+
+    /// With --dry-run, read only the first SIZE bytes of each file, e.g.
+    /// 64K, for a quick sample of a huge corpus
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = limits::parse_size,
+        requires = "dry_run",
+        conflicts_with_all = ["staged", "eml", "mbox"]
+    )]
+    head_bytes: Option<u64>,
+
+    /// Read only a random sample of the files, e.g. 5% or 200, and estimate
+    /// the totals for all of them; counts only
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = sample::parse,
+        conflicts_with_all = ["inplace", "backup", "backup_dir", "trash", "suffix", "pre_commit", "staged", "audit", "tar_filter"]
+    )]
+    sample: Option<Sample>,
+
+    /// Seed for --sample, to draw the same files again
+    #[arg(long, value_name = "N", requires = "sample")]
+    sample_seed: Option<u64>,
+
+    /// Limit processing speed, e.g. 4MB/s or 20files/s
+    #[arg(long, env = "NOMOJI_THROTTLE", value_name = "RATE", value_parser = throttle::parse_rate)]
+    throttle: Option<Rate>,
+
+    /// Run with the lowest CPU and I/O priority the platform allows
+    #[arg(long)]
+    nice: bool,
+
+    /// Stop after processing N files
+    #[arg(
+        long,
+        env = "NOMOJI_MAX_FILES",
+        value_name = "N",
+        value_parser = clap::value_parser!(u64).range(1..),
+    )]
+    max_files: Option<u64>,
+
+    /// Stop once the files processed add up to SIZE bytes, e.g. 500M
+    #[arg(
+        long,
+        env = "NOMOJI_MAX_TOTAL_BYTES",
+        value_name = "SIZE",
+        value_parser = limits::parse_size,
+    )]
+    max_total_bytes: Option<u64>,
+
+    /// Stop starting new files after DURATION, e.g. 90s, 15m or 2h
+    #[arg(
+        long,
+        env = "NOMOJI_TIMEOUT",
+        value_name = "DURATION",
+        value_parser = limits::parse_duration,
+    )]
+    timeout: Option<std::time::Duration>,
+
+    /// Stop at the first file that can't be read or written, leaving the
+    /// rest untouched
+    #[arg(
+        long,
+        env = "NOMOJI_FAIL_FAST",
+        value_parser = BoolishValueParser::new(),
+    )]
+    fail_fast: bool,
+
+    /// Encoding for cleaned output [default: same as the input]
+    #[arg(
+        long,
+        env = "NOMOJI_OUTPUT_ENCODING",
+        value_enum,
+        value_name = "ENCODING"
+    )]
+    output_encoding: Option<Encoding>,
+
+    /// Print cleaned text as a JSON, shell or C string literal, one per
+    /// input
+    #[arg(
+        long,
+        value_enum,
+        value_name = "TARGET",
+        conflicts_with_all = ["inplace", "backup", "backup_dir", "trash", "suffix", "eml", "mbox"]
+    )]
+    escape_output: Option<literal::Target>,
+
+    /// Remove only variation selectors (U+FE00-U+FE0F), leaving emojis and
+    /// every other character in place
+    #[arg(long)]
+    strip_selectors: bool,
+
+    /// Leave symbols shown as text by default (☀ ✈ ♥ © and bare keycaps)
+    /// alone unless VS16 follows them
+    #[arg(
+        long,
+        env = "NOMOJI_CONSERVATIVE",
+        value_parser = BoolishValueParser::new(),
+        conflicts_with = "strip_selectors",
+    )]
+    conservative: bool,
+
+    /// Also remove text-default symbols, other pictographs and the
+    /// Miscellaneous Technical and Symbols and Arrows blocks, whatever their
+    /// presentation
+    #[arg(
+        long,
+        env = "NOMOJI_AGGRESSIVE",
+        value_parser = BoolishValueParser::new(),
+        conflicts_with_all = ["strip_selectors", "conservative"],
+    )]
+    aggressive: bool,
+
+    /// Never remove characters in RANGE, e.g. U+2600..U+26FF or U+2705
+    /// (repeatable)
+    #[arg(
+        long = "exclude-range",
+        value_name = "RANGE",
+        value_parser = codepoints::parse_range,
+        conflicts_with = "strip_selectors"
+    )]
+    exclude_ranges: Vec<RangeInclusive<char>>,
+
+    /// Keep country flags (regional indicator pairs) and remove every other
+    /// emoji; shorthand for --skip flags
+    #[arg(
+        long,
+        env = "NOMOJI_KEEP_FLAGS",
+        value_parser = BoolishValueParser::new(),
+        conflicts_with = "strip_selectors",
+    )]
+    keep_flags: bool,
+
+    /// Remove only emojis in these categories (comma-separated); overrides
+    /// the config file's keep list for the categories named
+    #[arg(
+        long,
+        env = "NOMOJI_ONLY",
+        value_enum,
+        value_delimiter = ',',
+        value_name = "CATEGORIES",
+        conflicts_with = "strip_selectors"
+    )]
+    only: Vec<Category>,
+
+    /// Never remove emojis in these categories (comma-separated); takes
+    /// precedence over --only
+    #[arg(
+        long,
+        env = "NOMOJI_SKIP",
+        value_enum,
+        value_delimiter = ',',
+        value_name = "CATEGORIES",
+        conflicts_with = "strip_selectors"
+    )]
+    skip: Vec<Category>,
+
+    /// Ask CMD what to do with each occurrence: it reads one JSON object on
+    /// stdin and prints keep, remove or replace TEXT
+    #[arg(long, value_name = "CMD", group = "decider")]
+    filter_cmd: Option<String>,
+
+    /// Ask the WebAssembly module FILE what to do with each occurrence, in
+    /// process: it answers what --filter-cmd would be asked
+    #[cfg(feature = "wasm")]
+    #[arg(long, value_name = "FILE", value_parser = plugin::load, group = "decider")]
+    plugin: Option<std::sync::Arc<wasm::Module>>,
+
+    /// Treat inputs as RFC 822/MIME messages: clean encoded headers and
+    /// decoded text parts, leaving attachments and structure intact
+    #[arg(long, conflicts_with_all = ["decider", "output_encoding", "normalize"])]
+    eml: bool,
+
+    /// Treat inputs as mbox archives and clean each message as --eml does
+    #[arg(
+        long,
+        conflicts_with_all = ["eml", "decider", "output_encoding", "normalize"]
+    )]
+    mbox: bool,
+
+    /// Clean each file the way its type calls for, by extension or content:
+    /// Markdown prose, JSON string values, YAML values and comments, HTML and
+    /// XML text, notebook cells, and CSV records below the header
+    #[arg(
+        long,
+        conflicts_with_all = ["eml", "mbox", "ipynb", "xml", "decider", "gitmoji_convert", "commit_msg"]
+    )]
+    auto: bool,
+
+    /// Clean every file the same way even if the configuration file sets
+    /// auto = true
+    #[arg(long, overrides_with = "auto")]
+    no_auto: bool,
+
+    /// Treat inputs as Jupyter notebooks: clean markdown cells and output
+    /// text only, keeping the rest of the notebook byte for byte
+    #[arg(
+        long,
+        conflicts_with_all = ["eml", "mbox", "decider", "normalize", "split_documents"]
+    )]
+    ipynb: bool,
+
+    /// With --ipynb, clean the source of code cells too
+    #[arg(long, requires = "ipynb")]
+    ipynb_code: bool,
+
+    /// Treat inputs as commit messages: turn a leading gitmoji into its
+    /// Conventional Commits type (🐛 becomes fix:) before removing emojis
+    #[arg(
+        long,
+        conflicts_with_all = ["eml", "mbox", "ipynb", "decider"]
+    )]
+    gitmoji_convert: bool,
+
+    /// Treat inputs as XML: clean text nodes only, never tags, attributes,
+    /// comments or CDATA unless selected
+    #[arg(
+        long,
+        conflicts_with_all = ["eml", "mbox", "ipynb", "gitmoji_convert", "decider", "normalize", "split_documents"]
+    )]
+    xml: bool,
+
+    /// With --xml, clean only the text of elements matching SELECTOR, or
+    /// the attribute it ends in, e.g. //string or //item/@title (repeatable)
+    #[arg(long, value_name = "SELECTOR", requires = "xml")]
+    xpath: Vec<xml::Selector>,
+
+    /// With --xml, clean the content of CDATA sections as text
+    #[arg(long, requires = "xml")]
+    xml_cdata: bool,
+
+    /// Leave emojis inside URLs untouched
+    #[arg(long, env = "NOMOJI_SKIP_URLS", value_parser = BoolishValueParser::new())]
+    skip_urls: bool,
+
+    /// Leave emojis inside `backticked` code untouched
+    #[arg(long, env = "NOMOJI_SKIP_INLINE_CODE", value_parser = BoolishValueParser::new())]
+    skip_inline_code: bool,
+
+    /// Leave regions matching REGEX untouched, e.g. 'BEGIN-RAW.*?END-RAW'
+    /// (repeatable; `.` also matches newlines)
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new)]
+    skip_pattern: Vec<Regex>,
+
+    /// Also find emojis written as escape sequences (\u{1F680},
+    /// \uD83D\uDE80, \U0001F680) in source code and JSON
+    #[arg(
+        long,
+        env = "NOMOJI_ESCAPES",
+        value_parser = BoolishValueParser::new(),
+        conflicts_with = "strip_selectors",
+    )]
+    escapes: bool,
+
+    /// Rewrite emojis as HTML numeric character references (`&#x1F680;`)
+    /// instead of removing them
+    #[arg(
+        long,
+        env = "NOMOJI_TO_ENTITIES",
+        value_parser = BoolishValueParser::new(),
+        conflicts_with_all = ["strip_selectors", "decider"],
+    )]
+    to_entities: bool,
+
+    /// Rewrite emojis as string escapes for LANGUAGE (rust, js, python)
+    /// instead of removing them
+    #[arg(
+        long,
+        env = "NOMOJI_TO_ESCAPE",
+        value_enum,
+        value_name = "LANGUAGE",
+        conflicts_with_all = ["strip_selectors", "decider", "to_entities"]
+    )]
+    to_escape: Option<Language>,
+
+    /// Replace emojis with a description in parentheses, e.g. "(rocket)"
+    #[arg(
+        long,
+        env = "NOMOJI_DESCRIBE",
+        value_parser = BoolishValueParser::new(),
+        conflicts_with_all = ["strip_selectors", "decider", "to_entities", "to_escape"],
+    )]
+    describe: bool,
+
+    /// Language for --describe: en, fr, de or es
+    #[arg(
+        long,
+        env = "NOMOJI_LANG",
+        value_enum,
+        value_name = "LANG",
+        default_value = "en",
+        requires = "describe"
+    )]
+    lang: describe::Lang,
+
+    /// Read settings from FILE [default: ./.nomoji.toml if present]
+    #[arg(long, env = "NOMOJI_CONFIG", value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Ignore configuration files
+    #[arg(
+        long,
+        env = "NOMOJI_NO_CONFIG",
+        value_parser = BoolishValueParser::new(),
+        conflicts_with = "config",
+    )]
+    no_config: bool,
+
+    /// Apply the settings of the profile NAME from the configuration file
+    #[arg(
+        long,
+        env = "NOMOJI_PROFILE",
+        value_name = "NAME",
+        conflicts_with = "no_config"
+    )]
+    profile: Option<String>,
+
+    /// Unicode normalization applied to the text left after removal
+    #[arg(
+        long,
+        env = "NOMOJI_NORMALIZE",
+        value_enum,
+        value_name = "FORM",
+        default_value_t = Form::None,
+    )]
+    normalize: Form,
+
+    /// Skip files another process holds a lock on instead of waiting
+    #[arg(long, env = "NOMOJI_SKIP_LOCKED", value_parser = BoolishValueParser::new())]
+    skip_locked: bool,
+
+    /// Name to report for input read from stdin
+    #[arg(long, visible_alias = "label", value_name = "NAME")]
+    stdin_filename: Option<String>,
+
+    /// Treat stdin as several documents separated by SEP (default NUL, `\0`),
+    /// each cleaned and reported on its own
+    #[arg(
+        long,
+        value_name = "SEP",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "\\0",
+        value_parser = parse_separator
+    )]
+    // Spelled out so clap takes the bytes as one value, not a list.
+    split_documents: Option<::std::vec::Vec<u8>>,
+
+    /// Serve JSON-RPC requests (clean, count, spans), one per line on stdin,
+    /// for editor plugins
+    #[arg(long, conflicts_with = "files")]
+    rpc: bool,
+
+    /// Read a tar archive on stdin and write it to stdout with its text
+    /// members cleaned, without unpacking it
+    #[arg(
+        long,
+        conflicts_with_all = ["files", "files_from", "recursive", "inplace", "backup", "backup_dir", "trash", "suffix", "pre_commit", "staged", "split_documents", "rpc", "dry_run", "eml", "mbox"]
+    )]
+    tar_filter: bool,
+
+    /// Serve Prometheus metrics for the --rpc session at
+    /// http://ADDR/metrics
+    #[arg(long, value_name = "ADDR", requires = "rpc")]
+    metrics_addr: Option<String>,
+
+    /// Where the --rpc session logs operational errors
+    #[arg(long, value_enum, value_name = "TARGET", default_value_t = log::Target::Stderr, requires = "rpc")]
+    log: log::Target,
+
+    /// Don't print `==> file <==` headers when several files are written to
+    /// stdout
+    #[arg(long)]
+    no_filename: bool,
+
+    /// Where to write the report: stdout, stderr, fd:N or a file path
+    /// (default: stderr for the text report, stdout otherwise)
+    #[arg(long, env = "NOMOJI_REPORT_TO", value_name = "DEST")]
+    report_to: Option<Destination>,
+
+    /// Under GitHub Actions, also append a Markdown summary of the run to
+    /// $GITHUB_STEP_SUMMARY
+    #[arg(
+        long,
+        env = "NOMOJI_GITHUB_SUMMARY",
+        value_parser = BoolishValueParser::new()
+    )]
+    github_summary: bool,
+
+    /// When to color reports: auto (default; only on a terminal, and not
+    /// if NO_COLOR is set), always or never
+    #[arg(
+        long,
+        env = "NOMOJI_COLOR",
+        value_enum,
+        value_name = "WHEN",
+        default_value_t = color::When::Auto,
+    )]
+    color: color::When,
+
+    /// Settings loaded from the configuration file.
+    #[arg(skip)]
+    settings: Config,
+
+    /// Configuration files below the current directory by directory, or
+    /// `None` for a directory without one.
+    #[arg(skip)]
+    local_configs: HashMap<PathBuf, Option<Local>>,
+
+    /// Entries read from `--files-from`, with their overrides.
+    #[arg(skip)]
+    manifest: Vec<manifest::Entry>,
+
+    /// Highlighting for the report, once its destination is known.
+    #[arg(skip)]
+    palette: color::Palette,
+
+    /// How many files `--sample` drew `files` from.
+    #[arg(skip)]
+    population: Option<usize>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Format {
+    /// Human-readable summary on stderr
+    #[default]
+    Text,
+    /// One `file:line:col:text` line per occurrence on stdout, for editor
+    /// quickfix lists
+    Vimgrep,
+    /// A JSON summary on stdout, suitable for saving and `report-diff`
+    Json,
+    /// JSON Lines on stdout: an object per occurrence, one per file and
+    /// the totals last
+    Jsonl,
+    /// TeamCity service messages on stdout: an inspection per occurrence
+    /// and the total as a build statistic
+    Teamcity,
+    /// Checkstyle XML on stdout, an error element per occurrence, for CI
+    /// plugins
+    Checkstyle,
+    /// Reviewdog Diagnostic Format JSON on stdout, with the cleaned line as
+    /// a suggested fix
+    Rdjson,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check that cleaning is idempotent: a second pass must change nothing
+    CheckIdempotent {
+        /// Input file(s) to check (use - for stdin)
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+
+    /// Act as a git filter driver: read one blob on stdin, write it to stdout
+    Filter {
+        /// Strip emojis from the blob (use as filter.<driver>.clean)
+        #[arg(long, conflicts_with = "smudge", required_unless_present = "smudge")]
+        clean: bool,
+
+        /// Pass the blob through unchanged (use as filter.<driver>.smudge)
+        #[arg(long)]
+        smudge: bool,
+    },
+
+    /// Run as a git pre-receive hook, rejecting pushes that add emojis
+    PreReceive {
+        /// Only check paths matching GLOB (repeatable; defaults to the
+        /// nomoji.path git config values, or every path)
+        #[arg(long = "path", value_name = "GLOB")]
+        paths: Vec<String>,
+    },
+
+    /// Run a command, stripping emojis from its stdout and stderr as it
+    /// writes them, and exit with its exit code
+    Run {
+        /// Give the command pseudo-terminals for stdout and stderr, so it
+        /// keeps the colors and progress output it shows a terminal (Unix only)
+        #[arg(long)]
+        pty: bool,
+
+        /// The command and its arguments
+        #[arg(
+            required = true,
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            value_name = "COMMAND"
+        )]
+        command: Vec<String>,
+    },
+
+    /// Check the detector against the embedded emoji-test.txt corpus and
+    /// list sequences it does not fully remove
+    Selftest,
+
+    /// Export every emoji occurrence in FILES as a row (file, offset, emoji,
+    /// category, Unicode version, role) of a CSV or Parquet file
+    Stats {
+        /// Write the rows as FORMAT, csv or parquet, to PATH (- for stdout)
+        #[arg(long, num_args = 2, value_names = ["FORMAT", "PATH"], required = true)]
+        export: Vec<String>,
+
+        /// Read the files beneath directories given in FILES
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// Input file(s) to read
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+
+    /// Show what nomoji knows about an emoji sequence and whether the
+    /// current options would remove it
+    Info {
+        /// The sequence, as text or as code points like "U+1F469 U+200D U+1F680"
+        #[arg(required = true)]
+        sequences: Vec<String>,
+
+        /// Apply the config rules that match FILE
+        #[arg(long, value_name = "FILE")]
+        file: Option<String>,
+    },
+
+    /// Compare two saved JSON reports and fail if any file got worse
+    ReportDiff {
+        /// Baseline report (from --format json)
+        old: PathBuf,
+        /// Report to check against the baseline
+        new: PathBuf,
+    },
+
+    /// Undo a cleaning run by putting back the sequences an --audit sidecar
+    /// recorded, after checking each file is unchanged since
+    Restore {
+        /// Sidecar written by --audit
+        #[arg(long, value_name = "FILE")]
+        audit: PathBuf,
+    },
+
+    /// Replace this binary with the latest GitHub release, after checking
+    /// its SHA-256 checksum
+    SelfUpdate {
+        /// Only report whether a newer release is available
+        #[arg(long)]
+        check: bool,
+
+        /// The curl command used for downloads
+        #[arg(long, value_name = "PATH", default_value = "curl")]
+        curl: String,
+    },
+
+    /// Work on a database instead of files
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DbCommand {
+    /// Strip emojis from text columns of an SQLite database, in place and in
+    /// a single transaction
+    Clean {
+        /// SQLite database file
+        database: PathBuf,
+
+        /// Table to clean
+        #[arg(long)]
+        table: String,
+
+        /// Column to clean (repeatable)
+        #[arg(long = "column", value_name = "COLUMN", required = true)]
+        columns: Vec<String>,
+
+        /// Report what would change without writing to the database
+        #[arg(long)]
+        dry_run: bool,
+
+        /// The sqlite3 command-line shell to drive
+        #[arg(long, value_name = "PATH", default_value = "sqlite3")]
+        sqlite3: String,
+    },
+}
+
+impl Args {
+    /// Loads the configuration file named by `--config`, or the one in the
+    /// current directory, into `settings`, then applies `--profile`.
+    fn load_config(&mut self) -> Result<(), String> {
+        if self.no_config {
+            return Ok(());
+        }
+        let Some(path) = self.config.clone().or_else(config::discover) else {
+            return match self.profile {
+                Some(ref name) => Err(format!(
+                    "--profile {}: no configuration file (use --config or add {})",
+                    name,
+                    config::FILE_NAME
+                )),
+                None => Ok(()),
+            };
+        };
+        self.settings = Config::load(&path)?;
+        if let Some(ref name) = self.profile {
+            let profile = self
+                .settings
+                .profile(name)
+                .map_err(|e| format!("{}: {}", path.display(), e))?
+                .clone();
+            self.apply_profile(profile);
+        }
+        Ok(())
+    }
+
+    /// Fills in what `profile` sets and the command line doesn't.
+    fn apply_profile(&mut self, profile: Profile) {
+        if let Some(keep) = profile.keep {
+            self.settings.keep = keep;
+        }
+        if let Some(rules) = profile.rules {
+            self.settings.rules = rules;
+        }
+        if self.only.is_empty() {
+            self.only = profile.only;
+        }
+        if self.skip.is_empty() {
+            self.skip = profile.skip;
+        }
+        // A profile's switch that clashes with a flag on the command line is
+        // dropped rather than reported, as the flag is the more specific.
+        self.conservative |= profile.conservative && !self.aggressive && !self.strip_selectors;
+        self.aggressive |= profile.aggressive && !self.conservative && !self.strip_selectors;
+        self.keep_flags |= profile.keep_flags;
+        self.escapes |= profile.escapes;
+        self.skip_urls |= profile.skip_urls;
+        self.skip_inline_code |= profile.skip_inline_code;
+    }
+
+    /// With `--recursive`, replaces each directory in `files` with the
+    /// files beneath it. A recursive run that wasn't asked to write files
+    /// only reports, unless the config file's `write-recursive` says to edit
+    /// in place. Returns whether it was made report-only.
+    fn expand_recursive(&mut self) -> io::Result<bool> {
+        if !self.recursive {
+            return Ok(false);
+        }
+        if self.files.is_empty() && self.files_from.is_none() {
+            self.files.push(".".to_string());
+        }
+        let mut files = Vec::new();
+        for file in std::mem::take(&mut self.files) {
+            match Path::new(&file).is_dir() {
+                true => files.extend(walk::files(Path::new(&file))?),
+                false => files.push(file),
+            }
+        }
+        self.files = files;
+
+        if self.writes_files() || self.suffix.is_some() || self.dry_run {
+            return Ok(false);
+        }
+        match self.settings.write_recursive {
+            true => self.inplace = true,
+            false => self.dry_run = true,
+        }
+        Ok(self.dry_run)
+    }
+
+    /// Appends the files listed by `--files-from` to `files`.
+    fn load_manifest(&mut self) -> Result<(), String> {
+        if let Some(ref path) = self.files_from {
+            self.manifest = manifest::load(path)?;
+            self.files
+                .extend(self.manifest.iter().map(|entry| entry.path.clone()));
+        }
+        Ok(())
+    }
+
+    /// Reads the configuration files in the directories between each of
+    /// `files` and the current directory, so a broken one is reported
+    /// before any file is touched.
+    fn load_local_configs(&mut self, files: &[String]) -> Result<(), String> {
+        if self.no_config {
+            return Ok(());
+        }
+        for file in files {
+            let Some(relative) = config::relative(file) else {
+                continue;
+            };
+            for dir in config::local_dirs(&relative) {
+                // Its parents were read along with it.
+                if self.local_configs.contains_key(dir) {
+                    break;
+                }
+                let path = dir.join(config::FILE_NAME);
+                let local = match path.is_file() {
+                    true => Some(Local::load(&path)?),
+                    false => None,
+                };
+                self.local_configs.insert(dir.to_path_buf(), local);
+            }
+        }
+        Ok(())
+    }
+
+    /// The config rule and `keep` list for `file`. Configurations below the
+    /// current directory are tried nearest first, then the top-level one,
+    /// unless one of them is marked `root`.
+    fn policy(&self, file: &str) -> (Option<&Rule>, &[Category]) {
+        let mut rule = None;
+        let mut keep = None;
+        if let Some(relative) = config::relative(file) {
+            for dir in config::local_dirs(&relative) {
+                let Some(Some(local)) = self.local_configs.get(dir) else {
+                    continue;
+                };
+                let inner = relative.strip_prefix(dir).unwrap_or(&relative);
+                rule = rule.or_else(|| local.rule_for(&inner.to_string_lossy().replace('\\', "/")));
+                keep = keep.or(local.keep.as_deref());
+                if local.root {
+                    return (rule, keep.unwrap_or_default());
+                }
+            }
+        }
+        (
+            rule.or_else(|| self.settings.rule_for(file)),
+            keep.unwrap_or(&self.settings.keep),
+        )
+    }
+
+    /// The manifest entry for `file`; the first one wins if it is listed
+    /// twice.
+    fn manifest_entry(&self, file: &str) -> Option<&manifest::Entry> {
+        self.manifest.iter().find(|entry| entry.path == file)
+    }
+
+    /// The cleaner for `file`, which selects the matching config rule.
+    fn cleaner(&self, file: &str) -> Cleaner {
+        Cleaner::from_args(self, file)
+    }
+
+    /// Whether `--auto` applies, given on the command line or by the
+    /// configuration file. A format chosen on the command line wins over
+    /// the file's.
+    fn auto(&self) -> bool {
+        let explicit = self.eml
+            || self.mbox
+            || self.ipynb
+            || self.xml
+            || self.gitmoji_convert
+            || self.commit_msg
+            || self.decides();
+        (self.auto || (self.settings.auto && !self.no_auto)) && !explicit
+    }
+
+    /// Whether `--filter-cmd` or `--plugin` decides each occurrence.
+    fn decides(&self) -> bool {
+        #[cfg(feature = "wasm")]
+        if self.plugin.is_some() {
+            return true;
+        }
+        self.filter_cmd.is_some()
+    }
+
+    /// What `--xml` may change, if it was given.
+    fn xml_scope(&self) -> Option<xml::Scope> {
+        self.xml.then(|| xml::Scope {
+            selectors: self.xpath.clone(),
+            cdata: self.xml_cdata,
+        })
+    }
+
+    /// Whether the report needs the position of every occurrence.
+    fn wants_findings(&self) -> bool {
+        matches!(
+            self.format,
+            Format::Vimgrep
+                | Format::Jsonl
+                | Format::Teamcity
+                | Format::Checkstyle
+                | Format::Rdjson
+        )
+    }
+
+    /// Where the report goes.
+    fn report_destination(&self) -> Destination {
+        self.report_to.clone().unwrap_or(match self.format {
+            Format::Text => Destination::Stderr,
+            Format::Vimgrep
+            | Format::Json
+            | Format::Jsonl
+            | Format::Teamcity
+            | Format::Checkstyle
+            | Format::Rdjson => Destination::Stdout,
+        })
+    }
+
+    /// Whether cleaned text goes to stdout. A report sent to stdout owns it,
+    /// so there the cleaned text is only written back to files.
+    fn streams_cleaned(&self) -> bool {
+        !self.report_destination().is_stdout()
+    }
+
+    /// Whether cleaned output replaces the input files rather than going to
+    /// stdout.
+    fn writes_files(&self) -> bool {
+        self.inplace
+            || self.backup
+            || self.backup_dir.is_some()
+            || self.trash
+            || self.pre_commit
+            || self.commit_msg
+    }
+
+    /// Whether a file of plain ASCII can be passed over without decoding or
+    /// cleaning it: it holds no emoji, and nothing needs to be written for
+    /// it. Escapes and MIME encodings can spell emojis in ASCII,
+    /// `--gitmoji-convert` rewrites `:codes:`, and a different output
+    /// encoding means rewriting it anyway.
+    fn prefilters(&self) -> bool {
+        (self.dry_run || self.writes_files())
+            && !self.escapes
+            && !self.eml
+            && !self.mbox
+            && !self.gitmoji_convert
+            && self.output_encoding.is_none_or(|e| e == Encoding::Utf8)
+    }
+
+    /// How `--to-entities` or `--to-escape` rewrites emojis, if either was
+    /// given.
+    fn conversion(&self) -> Option<Conversion> {
+        match self.to_escape {
+            Some(language) => Some(Conversion::Escape(language)),
+            None => self.to_entities.then_some(Conversion::Entities),
+        }
+    }
+
+    /// Where `--suffix` writes the cleaned copy of `file`.
+    fn suffixed(&self, file: &str) -> Option<String> {
+        self.suffix
+            .as_ref()
+            .map(|suffix| format!("{}{}", file, suffix))
+    }
+
+    /// The file cleaned output of `file` is written to, or `None` for
+    /// stdout.
+    fn written_path(&self, file: &str) -> Option<String> {
+        self.suffixed(file)
+            .or_else(|| self.writes_files().then(|| file.to_string()))
+    }
+
+    /// With `--sample`, the totals for every input estimated from `results`.
+    fn estimate(&self, results: &[ProcessResult]) -> Option<Estimate> {
+        let counts: Vec<usize> = results.iter().map(|r| r.emojis_found).collect();
+        self.population
+            .map(|population| sample::estimate(&counts, population))
+    }
+
+    /// The before and after hashes for the report, hashing the text as
+    /// UTF-8 the way `--audit` does so the two can be matched up. Only JSON
+    /// reports carry them, so other runs skip the work.
+    fn checksums(&self, original: &str, cleaned: &str) -> Option<(String, String)> {
+        let json = matches!(self.format, Format::Json | Format::Jsonl);
+        (json && original != cleaned).then(|| {
+            (
+                sha256::hex(original.as_bytes()),
+                sha256::hex(cleaned.as_bytes()),
+            )
+        })
+    }
+
+    /// Cleaned text as it goes to stdout: as given, or as a literal on a
+    /// line of its own with `--escape-output`.
+    fn printed<'a>(&self, cleaned: &'a str) -> Cow<'a, str> {
+        match self.escape_output {
+            Some(target) => Cow::Owned(literal::quote(cleaned, target) + "\n"),
+            None => Cow::Borrowed(cleaned),
+        }
+    }
+}
+
+/// Decides what a run removes, built once from the command line and
+/// configuration file.
+#[derive(Clone, Debug, Default)]
+struct Cleaner {
+    /// Remove variation selectors only, wherever they appear.
+    selectors_only: bool,
+    /// Categories to remove exclusively; empty means every category.
+    only: Vec<Category>,
+    /// Categories always kept: `--skip`, plus flags for `--keep-flags`.
+    skip: Vec<Category>,
+    /// Categories kept by the configuration file unless named in `only`.
+    keep: Vec<Category>,
+    /// Which parts of the file may change, from the matching config rule.
+    mode: Mode,
+    /// Comment and string syntax for `Mode::Comments`.
+    syntax: Syntax,
+    /// Rewrite matches this way instead of removing them.
+    conversion: Option<Conversion>,
+    /// Replace matches with a description in this language.
+    describe: Option<describe::Lang>,
+    /// Also match emojis written as escape sequences.
+    escapes: bool,
+    /// Leave emojis inside URLs alone.
+    skip_urls: bool,
+    /// Leave emojis inside backticked code alone.
+    skip_inline_code: bool,
+    /// Leave emojis inside matches of these alone.
+    skip_patterns: Vec<Regex>,
+    /// Leave sequences with text presentation alone.
+    conservative: bool,
+    /// Match `aggressive::matches` instead of `is_emoji`.
+    aggressive: bool,
+    /// Characters never matched, merged by `codepoints::merge`.
+    excluded: Vec<RangeInclusive<char>>,
+    /// Only change the text of notebook cells, as `ipynb::regions` finds it.
+    ipynb: bool,
+    /// Include the source of code cells in `ipynb`.
+    ipynb_code: bool,
+    /// Only change the parts of an XML document this allows.
+    xml: Option<xml::Scope>,
+    /// Pick the rest of the settings for each text by its type; see
+    /// `resolve`.
+    auto: bool,
+    /// With `auto`, the type the file name tells; once resolved, the type
+    /// cleaned as, limiting changes to what `filetype::regions` finds.
+    file_type: Option<FileType>,
+}
+
+impl Cleaner {
+    fn from_args(args: &Args, file: &str) -> Self {
+        // A manifest entry overrides both the command line and the config.
+        let entry = args.manifest_entry(file);
+        let mut skip = entry
+            .and_then(|e| e.skip.clone())
+            .unwrap_or_else(|| args.skip.clone());
+        if args.keep_flags {
+            skip.push(Category::Flags);
+        }
+        let (rule, keep) = args.policy(file);
+        let mode = entry
+            .and_then(|e| e.mode)
+            .or(rule.map(|r| r.mode))
+            .unwrap_or_default();
+        // A mode chosen for the file wins over guessing.
+        let auto = args.auto() && mode == Mode::All;
+        Cleaner {
+            selectors_only: args.strip_selectors,
+            only: entry
+                .and_then(|e| e.only.clone())
+                .unwrap_or_else(|| args.only.clone()),
+            skip,
+            keep: entry
+                .and_then(|e| e.keep.clone())
+                .or_else(|| rule.and_then(|r| r.keep.clone()))
+                .unwrap_or_else(|| keep.to_vec()),
+            mode,
+            syntax: Syntax::for_path(file),
+            conversion: args.conversion(),
+            describe: args.describe.then_some(args.lang),
+            escapes: args.escapes,
+            skip_urls: args.skip_urls,
+            skip_inline_code: args.skip_inline_code,
+            skip_patterns: args.skip_pattern.clone(),
+            conservative: args.conservative,
+            aggressive: args.aggressive,
+            excluded: codepoints::merge(args.exclude_ranges.clone()),
+            ipynb: args.ipynb,
+            ipynb_code: args.ipynb_code,
+            xml: args.xml_scope(),
+            auto,
+            file_type: auto.then(|| FileType::for_path(file)).flatten(),
+        }
+    }
+
+    /// With `auto`, this cleaner set up for the type of `text`, from the
+    /// file name or else from the text itself; otherwise this cleaner.
+    fn resolve(&self, text: &str) -> Cow<'_, Cleaner> {
+        if !self.auto {
+            return Cow::Borrowed(self);
+        }
+        let file_type = self.file_type.unwrap_or_else(|| FileType::sniff(text));
+        let mut resolved = Cleaner {
+            auto: false,
+            file_type: Some(file_type),
+            ..self.clone()
+        };
+        match file_type {
+            FileType::Markdown => resolved.mode = Mode::Markdown,
+            FileType::Json => resolved.mode = Mode::Strings,
+            FileType::Notebook => resolved.ipynb = true,
+            FileType::Xml => resolved.xml = Some(xml::Scope::default()),
+            FileType::Yaml | FileType::Html | FileType::Csv | FileType::Plain => {}
+        }
+        Cow::Owned(resolved)
+    }
+
+    /// Whether `sequence`, one span from `emoji_spans`, stays in the output.
+    /// `--skip` and `--conservative` win over everything; with `--only`,
+    /// sequences outside the listed categories stay; the config `keep` list
+    /// applies to the rest, except for categories `--only` names explicitly.
+    fn keeps(&self, sequence: &str) -> bool {
+        let in_any = |categories: &[Category]| categories.iter().any(|c| c.matches(sequence));
+
+        if in_any(&self.skip) || (self.conservative && presentation::is_text(sequence)) {
+            return true;
+        }
+        if !self.only.is_empty() && !in_any(&self.only) {
+            return true;
+        }
+        self.keep
+            .iter()
+            .any(|c| !self.only.contains(c) && c.matches(sequence))
+    }
+
+    /// Whether each line can be cleaned on its own, without the lines around
+    /// it, so input can be processed as it arrives.
+    fn is_line_local(&self) -> bool {
+        // Backticked code and pattern matches may span lines.
+        self.mode == Mode::All
+            && !self.auto
+            && !self.skip_inline_code
+            && self.skip_patterns.is_empty()
+    }
+
+    /// Byte ranges of `text` that `--skip-urls`, `--skip-inline-code` and
+    /// `--skip-pattern` protect.
+    fn protected(&self, text: &str) -> Vec<Range<usize>> {
+        let mut regions = Vec::new();
+        if self.skip_urls {
+            regions.extend(protect::urls(text));
+        }
+        if self.skip_inline_code {
+            regions.extend(protect::inline_code(text));
+        }
+        for pattern in &self.skip_patterns {
+            regions.extend(pattern.find_all(text));
+        }
+        protect::merge(regions)
+    }
+
+    fn filters_categories(&self) -> bool {
+        !(self.only.is_empty() && self.skip.is_empty() && self.keep.is_empty())
+    }
+
+    /// Byte ranges of each occurrence this cleaner removes: whole sequences
+    /// for emojis, single characters for selectors.
+    fn spans(&self, text: &str) -> Vec<Range<usize>> {
+        if self.auto {
+            return self.resolve(text).spans(text);
+        }
+        let mut spans = if self.selectors_only {
+            text.char_indices()
+                .filter(|&(_, c)| is_variation_selector(c))
+                .map(|(i, c)| i..i + c.len_utf8())
+                .collect()
+        } else {
+            let mut spans = if self.aggressive || !self.excluded.is_empty() {
+                let base = if self.aggressive {
+                    aggressive::matches
+                } else {
+                    is_emoji
+                };
+                let excluded = |c| codepoints::contains(&self.excluded, c);
+                let mut spans = emoji_spans_by(text, |c| base(c) && !excluded(c));
+                // Selectors and modifiers stay with the excluded character
+                // they belong to.
+                spans.retain(|span| {
+                    let mut after = text[span.clone()].chars();
+                    let mut before = text[..span.start].chars();
+                    !(after.next().is_some_and(is_emoji_component)
+                        && before.next_back().is_some_and(excluded))
+                });
+                spans
+            } else {
+                emoji_spans(text)
+            };
+            if self.escapes {
+                spans.extend(escapes::spans(text));
+                spans.sort_by_key(|span| span.start);
+            }
+            spans.retain(|span| !self.keeps(&self.sequence(&text[span.clone()])));
+            spans
+        };
+
+        if let Some(regions) = mode::editable_regions(text, self.mode, self.syntax) {
+            spans.retain(|span| {
+                regions
+                    .iter()
+                    .any(|r| r.start <= span.start && span.end <= r.end)
+            });
+        }
+        let regions = match (self.ipynb, &self.xml) {
+            (true, _) => Some(ipynb::regions(text, self.ipynb_code)),
+            (false, Some(scope)) => Some(xml::regions(text, scope)),
+            (false, None) => self
+                .file_type
+                .and_then(|file_type| filetype::regions(text, file_type))
+                .map(Ok),
+        };
+        if let Some(regions) = regions {
+            let regions = regions.unwrap_or_default();
+            spans.retain(|span| {
+                regions
+                    .iter()
+                    .any(|r| r.start <= span.start && span.end <= r.end)
+            });
+        }
+        let protected = self.protected(text);
+        if !protected.is_empty() {
+            spans.retain(|span| !protect::overlaps(&protected, span));
+        }
+        spans
+    }
+
+    /// The emoji sequence one span of `spans` stands for: the span itself,
+    /// or what it decodes to if it is a run of escapes.
+    fn sequence<'a>(&self, span: &'a str) -> Cow<'a, str> {
+        match self.escapes.then(|| escapes::decode(span)).flatten() {
+            Some(decoded) => Cow::Owned(decoded),
+            None => Cow::Borrowed(span),
+        }
+    }
+
+    /// Removes this cleaner's occurrences from `text`, borrowing it when
+    /// nothing matches.
+    fn remove<'a>(&self, text: &'a str) -> (Cow<'a, str>, usize) {
+        if self.auto {
+            return self.resolve(text).remove(text);
+        }
+        let plain = self.mode == Mode::All
+            && self.conversion.is_none()
+            && self.describe.is_none()
+            && !self.escapes
+            && !self.skip_urls
+            && !self.skip_inline_code
+            && self.skip_patterns.is_empty()
+            && !self.conservative
+            && !self.aggressive
+            && self.excluded.is_empty()
+            && !self.ipynb
+            && self.xml.is_none()
+            && self.file_type.is_none_or(|t| t == FileType::Plain);
+        if plain && !self.selectors_only && !self.filters_categories() {
+            return remove_emojis_cow(text);
+        }
+
+        let spans = self.spans(text);
+        if spans.is_empty() {
+            return (Cow::Borrowed(text), 0);
+        }
+        let (cleaned, count) = replace_spans(text, &spans, |span| self.replacement(span));
+        // An escape is many characters standing for one.
+        let count = if self.escapes {
+            self.count_spans(text, spans)
+        } else {
+            count
+        };
+        (Cow::Owned(cleaned), count)
+    }
+
+    /// What `remove` puts in place of one span of `spans`.
+    fn replacement(&self, span: &str) -> String {
+        let replacement = self.substitute(span);
+        match (self.ipynb, &self.xml) {
+            // It lands inside a JSON string.
+            (true, _) => ipynb::escape(&replacement),
+            (false, Some(_)) => xml::escape(&replacement),
+            (false, None) if self.file_type == Some(FileType::Html) => xml::escape(&replacement),
+            (false, None) => replacement,
+        }
+    }
+
+    fn substitute(&self, span: &str) -> String {
+        if let Some(lang) = self.describe {
+            return describe::describe(&self.sequence(span), lang);
+        }
+        match self.conversion {
+            Some(conversion) => conversion.apply(&self.sequence(span)),
+            None if self.mode == Mode::Markdown => {
+                mode::shortcode(&self.sequence(span)).unwrap_or_default()
+            }
+            None => String::new(),
+        }
+    }
+
+    /// Counts the characters `remove` would strip from `text`.
+    fn count(&self, text: &str) -> usize {
+        self.count_spans(text, self.spans(text))
+    }
+
+    fn count_spans(&self, text: &str, spans: Vec<Range<usize>>) -> usize {
+        spans
+            .into_iter()
+            .map(|span| self.sequence(&text[span]).chars().count())
+            .sum()
+    }
+
+    fn describe(&self) -> &'static str {
+        if self.selectors_only {
+            "variation selectors"
+        } else {
+            "emojis"
+        }
+    }
+}
+
+/// Name used for stdin in machine-readable output.
+const STDIN_NAME: &str = "<stdin>";
+
+#[derive(Debug, Default)]
+struct ProcessResult {
+    file: String,
+    emojis_found: usize,
+    success: bool,
+    error: Option<String>,
+    findings: Vec<Finding>,
+    bytes_read: usize,
+    /// How many bytes smaller the cleaned content is than the original.
+    bytes_saved: usize,
+    /// Why the file was deliberately left alone, if it was.
+    skipped: Option<String>,
+    /// Emojis in the path itself, which cleaning never changes.
+    name_emojis: usize,
+    /// Emojis in each message of an mbox archive.
+    messages: Vec<usize>,
+    /// What was changed in the file, for `--audit`.
+    audit: Option<audit::Entry>,
+    /// SHA-256 of the text before and after cleaning, for a file the run
+    /// rewrote and a JSON report to show.
+    sha256: Option<(String, String)>,
+    /// Passed over as plain ASCII without being cleaned.
+    fast_skipped: bool,
+    /// Every occurrence found, for `--list`.
+    sequences: Vec<String>,
+    /// Time spent reading, cleaning and writing the file, for `-v`.
+    timings: Timings,
+}
+
+impl ProcessResult {
+    /// Whether cleaning changed the file.
+    fn changed(&self) -> bool {
+        self.success && self.skipped.is_none() && self.emojis_found > 0
+    }
+
+    fn skipped(file: &str, reason: &str) -> Self {
+        ProcessResult {
+            skipped: Some(reason.to_string()),
+            ..ProcessResult::succeeded(file, 0)
+        }
+    }
+
+    fn succeeded(file: &str, emojis_found: usize) -> Self {
+        ProcessResult {
+            file: file.to_string(),
+            emojis_found,
+            success: true,
+            ..Default::default()
+        }
+    }
+
+    fn failed(file: &str, emojis_found: usize, error: String) -> Self {
+        ProcessResult {
+            file: file.to_string(),
+            emojis_found,
+            success: false,
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+}
+
+/// One emoji occurrence, located for editor-style output.
+#[derive(Debug, PartialEq)]
+struct Finding {
+    /// 1-based line number.
+    line: usize,
+    /// 1-based byte column within the line.
+    column: usize,
+    /// The line containing the occurrence, without its line terminator.
+    line_text: String,
+    /// The sequence found.
+    emoji: String,
+    /// What cleaning puts in its place; see `report_findings`.
+    replacement: String,
+}
+
+/// Locates each span in `content` by line and column. `spans` must be in
+/// order.
+fn find_occurrences(content: &str, spans: Vec<Range<usize>>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut spans = spans.into_iter().peekable();
+    let mut line_start = 0;
+
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let line_end = line_start + line.len();
+        while let Some(span) = spans.next_if(|span| span.start < line_end) {
+            findings.push(Finding {
+                line: index + 1,
+                column: span.start - line_start + 1,
+                line_text: line.trim_end_matches(['\n', '\r']).to_string(),
+                emoji: content[span].to_string(),
+                replacement: String::new(),
+            });
+        }
+        line_start = line_end;
+    }
+
+    findings
+}
+
+/// The occurrences `cleaner` finds in `content`, each with its replacement,
+/// for reports that list them.
+fn report_findings(content: &str, cleaner: &Cleaner) -> Vec<Finding> {
+    let cleaner = cleaner.resolve(content);
+    let spans = cleaner.spans(content);
+    let replacements: Vec<String> = spans
+        .iter()
+        .map(|span| cleaner.replacement(&content[span.clone()]))
+        .collect();
+    find_occurrences(content, spans)
+        .into_iter()
+        .zip(replacements)
+        .map(|(finding, replacement)| Finding {
+            replacement,
+            ..finding
+        })
+        .collect()
+}
+
+/// The text of each span in `content`, for `--list`.
+fn sequences(content: &str, spans: Vec<Range<usize>>) -> Vec<String> {
+    spans
+        .into_iter()
+        .map(|span| content[span].to_string())
+        .collect()
+}
+
+/// Each distinct sequence in `results` with its number of occurrences,
+/// most frequent first and ties in codepoint order.
+fn tally(results: &[ProcessResult]) -> Vec<(&str, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for sequence in results.iter().flat_map(|r| &r.sequences) {
+        *counts.entry(sequence).or_default() += 1;
+    }
+    let mut tally: Vec<_> = counts.into_iter().collect();
+    tally.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    tally
+}
+
+/// Writes the `--list` table for `results`.
+fn print_list(results: &[ProcessResult], paint: Palette, out: &mut dyn Write) -> io::Result<()> {
+    let tally = tally(results);
+    if tally.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\n{}", paint.bold("Distinct emojis:"))?;
+    for (sequence, count) in tally {
+        let age = age::age(sequence)
+            .map(|(major, minor)| format!("  (Unicode {}.{})", major, minor))
+            .unwrap_or_default();
+        writeln!(
+            out,
+            "  {}  {}  {}  {}{}",
+            sequence,
+            count,
+            selftest::codepoints(sequence),
+            selftest::name(sequence).unwrap_or_else(|| "unknown".to_string()),
+            age
+        )?;
+    }
+    Ok(())
+}
+
+/// Output of the cleaning pipeline for one input. `text` borrows the input
+/// when nothing changed.
+struct Cleaned<'a> {
+    text: Cow<'a, str>,
+    emojis: usize,
+    /// Bytes taken up by the removed emojis.
+    bytes_saved: usize,
+}
+
+/// Removes emojis and then applies the post-processing options to what is
+/// left. Only a `--filter-cmd` failure, or with `--ipynb` or `--xml` input
+/// that can't be parsed, is an error.
+fn clean<'a>(
+    content: &'a str,
+    file: &str,
+    cleaner: &Cleaner,
+    args: &Args,
+) -> Result<Cleaned<'a>, String> {
+    if args.ipynb {
+        ipynb::regions(content, args.ipynb_code)?;
+    }
+    if let Some(scope) = args.xml_scope() {
+        xml::regions(content, &scope)?;
+    }
+    let converted = match args.gitmoji_convert {
+        true => gitmoji::convert(content, &args.settings.gitmoji),
+        false => None,
+    };
+    let kept = match args.keep_gitmoji {
+        true => gitmoji::kept_len(content, &args.settings.gitmoji),
+        false => 0,
+    };
+    let decided = match &args.filter_cmd {
+        Some(command) => Some(filter_with(file, content, cleaner, |occurrence| {
+            filter_cmd::decide(command, occurrence)
+        })?),
+        None => None,
+    };
+    #[cfg(feature = "wasm")]
+    let decided = match (decided, &args.plugin) {
+        (None, Some(module)) => {
+            // Instantiated on the first occurrence, so files without one
+            // cost nothing.
+            let mut plugin = None;
+            Some(filter_with(file, content, cleaner, |occurrence| {
+                let plugin = match plugin {
+                    Some(ref mut plugin) => plugin,
+                    None => plugin.insert(plugin::Plugin::new(module)?),
+                };
+                plugin.decide(occurrence)
+            })?)
+        }
+        (decided, _) => decided,
+    };
+    let (text, emojis) = match (decided, converted) {
+        (Some((text, emojis)), _) => (Cow::Owned(text), emojis),
+        // The converted gitmoji counts as one removed.
+        (None, Some(converted)) => {
+            let (text, emojis) = cleaner.remove(&converted);
+            (Cow::Owned(text.into_owned()), emojis + 1)
+        }
+        (None, None) if kept > 0 => {
+            let (text, emojis) = cleaner.remove(&content[kept..]);
+            (Cow::Owned(format!("{}{}", &content[..kept], text)), emojis)
+        }
+        (None, None) => cleaner.remove(content),
+    };
+    // Shortcode replacement can grow the text.
+    let bytes_saved = content.len().saturating_sub(text.len());
+
+    let text = match args.normalize {
+        Form::None => text,
+        form => Cow::Owned(normalize::normalize(&text, form)),
+    };
+
+    Ok(Cleaned {
+        text,
+        emojis,
+        bytes_saved,
+    })
+}
+
+/// Lets `decide` settle the fate of every occurrence `cleaner` would
+/// remove, given the JSON object `--filter-cmd` reads. Returns the resulting
+/// text and the number of characters removed or replaced.
+fn filter_with(
+    file: &str,
+    content: &str,
+    cleaner: &Cleaner,
+    mut decide: impl FnMut(&json::Value) -> Result<Action, String>,
+) -> Result<(String, usize), String> {
+    let spans = cleaner.spans(content);
+    let findings = find_occurrences(content, spans.clone());
+    let mut changed = Vec::new();
+    let mut replacements = Vec::new();
+
+    for (span, finding) in spans.into_iter().zip(findings) {
+        let sequence = &content[span.clone()];
+        let codepoints: Vec<String> = sequence
+            .chars()
+            .map(|c| format!("U+{:04X}", c as u32))
+            .collect();
+        let occurrence = json::Value::object()
+            .with("file", file)
+            .with("line", finding.line)
+            .with("column", finding.column)
+            .with("emoji", sequence)
+            .with("codepoints", codepoints)
+            .with("shortcode", mode::shortcode(sequence))
+            .with("role", nomoji::Role::of(sequence).name())
+            .with("line_text", finding.line_text);
+
+        match decide(&occurrence)? {
+            Action::Keep => continue,
+            Action::Remove => replacements.push(String::new()),
+            Action::Replace(text) => replacements.push(text),
+        }
+        changed.push(span);
+    }
+
+    let mut replacements = replacements.into_iter();
+    Ok(replace_spans(content, &changed, |_| {
+        replacements.next().unwrap_or_default()
+    }))
+}
+
+fn read_input<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    read_decoded(path).map(|(content, _)| content)
+}
+
+/// Reads a file as text along with the encoding it was stored in.
+fn read_decoded<P: AsRef<Path>>(path: P) -> io::Result<(String, Encoding)> {
+    encoding::decode(fs::read(path)?)
+}
+
+/// Rewrites the file at `path` by renaming a finished copy over it. A
+/// symlink is resolved first, so the file it points at is edited and the
+/// link stays in place.
+fn write_output<P: AsRef<Path>, C: AsRef<[u8]>>(
+    path: P,
+    content: C,
+    temp_dir: Option<&Path>,
+    break_links: bool,
+) -> io::Result<()> {
+    let target = fs::canonicalize(path)?;
+    let original = fs::metadata(&target)?;
+    // Renaming a new copy into place would leave the file's other names
+    // with the old content.
+    if !break_links && staging::links(&original) > 1 {
+        return staging::rewrite(&target, content.as_ref());
+    }
+    staging::replace(&target, content.as_ref(), &original, temp_dir)
+}
+
+/// Maps `file` onto a relative path that can be joined under a backup
+/// directory. Relative paths are mirrored as given; anything that would climb
+/// out of the tree (absolute paths, `..`) is mirrored by its canonical path
+/// with the root stripped.
+fn mirrored_path(file: &Path) -> io::Result<PathBuf> {
+    let escapes = file
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+    let source = if escapes {
+        fs::canonicalize(file)?
+    } else {
+        file.to_path_buf()
+    };
+
+    Ok(source
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part),
+            _ => None,
+        })
+        .collect())
+}
+
+fn backup_path(file: &str, backup_dir: Option<&Path>) -> io::Result<PathBuf> {
+    match backup_dir {
+        Some(dir) => Ok(dir.join(mirrored_path(Path::new(file))?)),
+        None => Ok(PathBuf::from(format!("{}.bak", file))),
+    }
+}
+
+fn create_backup(
+    file: &str,
+    backup_dir: Option<&Path>,
+    temp_dir: Option<&Path>,
+) -> io::Result<PathBuf> {
+    let path = backup_path(file, backup_dir)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    staging::back_up(Path::new(file), &path, temp_dir)?;
+    Ok(path)
+}
+
+fn write_cleaned(file: &str, cleaned: &str, encoding: Encoding, args: &Args) -> Result<(), String> {
+    let to_stdout = args.written_path(file).is_none();
+    if to_stdout && !args.streams_cleaned() {
+        return Ok(());
+    }
+
+    let cleaned = if to_stdout {
+        encoding.encode(&(header(file, args) + &args.printed(cleaned)))
+    } else {
+        encoding.encode(cleaned)
+    }
+    .map_err(|e| format!("Failed to encode output: {}", e))?;
+    write_bytes(file, &cleaned, args)
+}
+
+/// Sends the cleaned bytes of `file` to its `--suffix` copy, stdout, or
+/// back to the file itself, making any backup first.
+fn write_bytes(file: &str, cleaned: &[u8], args: &Args) -> Result<(), String> {
+    if let Some(copy) = args.suffixed(file) {
+        return fs::metadata(file)
+            .and_then(|original| {
+                staging::replace(
+                    Path::new(&copy),
+                    cleaned,
+                    &original,
+                    args.temp_dir.as_deref(),
+                )
+            })
+            .map_err(|e| format!("Failed to write file: {}", e));
+    }
+
+    if !args.writes_files() {
+        // Output to stdout
+        return io::stdout()
+            .write_all(cleaned)
+            .map_err(|e| format!("Failed to write to stdout: {}", e));
+    }
+
+    if args.backup || args.backup_dir.is_some() {
+        create_backup(file, args.backup_dir.as_deref(), args.temp_dir.as_deref())
+            .map_err(|e| format!("Failed to create backup: {}", e))?;
+    }
+
+    if args.trash {
+        // The original leaves with its inode, so carry its permissions over
+        // to the file that replaces it.
+        let original =
+            fs::metadata(file).map_err(|e| format!("Failed to move file to trash: {}", e))?;
+        trash::move_to_trash(file).map_err(|e| format!("Failed to move file to trash: {}", e))?;
+        return staging::write_new(Path::new(file), cleaned, &original)
+            .map_err(|e| format!("Failed to write file: {}", e));
+    }
+
+    write_output(file, cleaned, args.temp_dir.as_deref(), args.break_links)
+        .map_err(|e| format!("Failed to write file: {}", e))
+}
+
+/// The `==> file <==` line that precedes each file's cleaned text on stdout
+/// when several files are given, as `head` and `tail` print. Headers after
+/// the first file's are preceded by a blank line.
+fn header(file: &str, args: &Args) -> String {
+    if args.no_filename || args.files.len() < 2 {
+        return String::new();
+    }
+    let gap = if args.files.first().is_some_and(|first| first == file) {
+        ""
+    } else {
+        "\n"
+    };
+    format!("{}==> {} <==\n", gap, file)
+}
+
+fn process_file(file: &str, args: &Args) -> ProcessResult {
+    if is_fifo(file) {
+        return process_fifo(file, args);
+    }
+
+    // Held until the file has been rewritten.
+    let _lock = if args.writes_files() && !args.dry_run {
+        match lock_for_edit(file, args.skip_locked) {
+            Ok(Some(lock)) => Some(lock),
+            Ok(None) => return ProcessResult::skipped(file, "locked by another process"),
+            Err(e) => return ProcessResult::failed(file, 0, format!("Failed to lock file: {}", e)),
+        }
+    } else {
+        None
+    };
+
+    // Taken before reading, so a change that lands mid-read is caught too.
+    let stamp = match Stamp::of(file) {
+        Ok(stamp) => stamp,
+        Err(e) => return ProcessResult::failed(file, 0, format!("Failed to read file: {}", e)),
+    };
+    if args.eml || args.mbox {
+        return process_eml(file, stamp, args);
+    }
+    let started = Instant::now();
+    let read = match args.head_bytes {
+        Some(limit) => read_head(file, limit),
+        None => fs::read(file),
+    };
+    let bytes = match read {
+        Ok(bytes) => bytes,
+        Err(e) => return ProcessResult::failed(file, 0, format!("Failed to read file: {}", e)),
+    };
+    if args.prefilters() && bytes.is_ascii() {
+        return ProcessResult {
+            bytes_read: bytes.len(),
+            fast_skipped: true,
+            timings: Timings {
+                read: started.elapsed(),
+                ..Timings::default()
+            },
+            ..ProcessResult::succeeded(file, 0)
+        };
+    }
+    let (content, input_encoding) = match encoding::decode(bytes) {
+        Ok(decoded) => decoded,
+        Err(e) => return ProcessResult::failed(file, 0, format!("Failed to read file: {}", e)),
+    };
+    let read = started.elapsed();
+    let result = process_content(file, &content, input_encoding, Some(stamp), args);
+    ProcessResult {
+        timings: Timings {
+            read,
+            ..result.timings
+        },
+        ..result
+    }
+}
+
+/// The first `limit` bytes of `file` for `--head-bytes`, less a character
+/// the limit cut in two, so the head still decodes.
+fn read_head(file: &str, limit: u64) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    fs::File::open(file)?.take(limit).read_to_end(&mut bytes)?;
+    trim_partial_char(&mut bytes);
+    Ok(bytes)
+}
+
+fn trim_partial_char(bytes: &mut Vec<u8>) {
+    match bytes.as_slice() {
+        [0xFF, 0xFE, ..] | [0xFE, 0xFF, ..] => {
+            bytes.truncate(bytes.len() & !1);
+            // A high surrogate whose pair was cut off.
+            if let [.., a, b] = bytes[2..] {
+                let unit = match bytes[0] {
+                    0xFF => u16::from_le_bytes([a, b]),
+                    _ => u16::from_be_bytes([a, b]),
+                };
+                if (0xD800..0xDC00).contains(&unit) {
+                    bytes.truncate(bytes.len() - 2);
+                }
+            }
+        }
+        _ => {
+            if let Err(e) = std::str::from_utf8(bytes)
+                && e.error_len().is_none()
+            {
+                bytes.truncate(e.valid_up_to());
+            }
+        }
+    }
+}
+
+/// Cleans `file` as a MIME message or mbox archive. The message is handled
+/// as bytes, since parts in other charsets or encodings are copied through
+/// untouched.
+fn process_eml(file: &str, stamp: Stamp, args: &Args) -> ProcessResult {
+    let started = Instant::now();
+    let message = match fs::read(file) {
+        Ok(message) => message,
+        Err(e) => return ProcessResult::failed(file, 0, format!("Failed to read file: {}", e)),
+    };
+    let read = started.elapsed();
+    let started = Instant::now();
+    let cleaner = args.cleaner(file);
+    let (cleaned, counts) = clean_message(&message, &cleaner, args);
+    let mut result = ProcessResult {
+        bytes_read: message.len(),
+        bytes_saved: message.len().saturating_sub(cleaned.len()),
+        messages: if args.mbox {
+            counts.clone()
+        } else {
+            Vec::new()
+        },
+        timings: Timings {
+            read,
+            clean: started.elapsed(),
+            ..Timings::default()
+        },
+        ..ProcessResult::succeeded(file, counts.iter().sum())
+    };
+
+    let to_stdout = args.written_path(file).is_none();
+    let unchanged = !to_stdout && cleaned == message;
+    if args.dry_run || unchanged || (to_stdout && !args.streams_cleaned()) {
+        return result;
+    }
+
+    let output = if to_stdout {
+        [header(file, args).into_bytes(), cleaned].concat()
+    } else {
+        cleaned
+    };
+    let started = Instant::now();
+    let written = ensure_unmodified(file, Some(stamp), args)
+        .and_then(|()| write_bytes(file, &output, args))
+        .and_then(|()| match args.written_path(file) {
+            Some(target) if args.verify => verify_bytes(&target, &output),
+            _ => Ok(()),
+        });
+    result.timings.write = started.elapsed();
+    match written {
+        Ok(()) => result,
+        Err(e) => ProcessResult {
+            success: false,
+            error: Some(e),
+            ..result
+        },
+    }
+}
+
+/// Cleans a message, or every message of an archive with `--mbox`, and
+/// returns the counts per message.
+fn clean_message(message: &[u8], cleaner: &Cleaner, args: &Args) -> (Vec<u8>, Vec<usize>) {
+    let clean = |text: &str| {
+        let (cleaned, count) = cleaner.remove(text);
+        (cleaned.into_owned(), count)
+    };
+    if args.mbox {
+        mbox::clean(message, &clean)
+    } else {
+        let (cleaned, count) = eml::clean(message, &clean);
+        (cleaned, vec![count])
+    }
+}
+
+/// Fails if `file` no longer matches the `stamp` taken when it was read,
+/// unless `--force-write` is given or the cleaned text doesn't replace it.
+fn ensure_unmodified(file: &str, stamp: Option<Stamp>, args: &Args) -> Result<(), String> {
+    if args.writes_files()
+        && !args.force_write
+        && let Some(stamp) = stamp
+        && Stamp::of(file).ok() != Some(stamp)
+    {
+        return Err(
+            "File changed since it was read; not overwriting (use --force-write)".to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// A file's size and modification time, recorded when it is read so an
+/// in-place edit can tell whether something else wrote to it since.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Stamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Stamp {
+    fn of(file: &str) -> io::Result<Self> {
+        let metadata = fs::metadata(file)?;
+        Ok(Stamp {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+/// Takes an exclusive advisory lock on `file` for an in-place edit, waiting
+/// for other holders unless `skip_locked` is set, in which case `Ok(None)`
+/// means the file is busy. Filesystems without lock support are edited
+/// unlocked.
+fn lock_for_edit(file: &str, skip_locked: bool) -> io::Result<Option<fs::File>> {
+    let handle = fs::File::open(file)?;
+    let locked = if skip_locked {
+        match handle.try_lock() {
+            Ok(()) => Ok(()),
+            Err(fs::TryLockError::WouldBlock) => return Ok(None),
+            Err(fs::TryLockError::Error(e)) => Err(e),
+        }
+    } else {
+        handle.lock()
+    };
+
+    match locked {
+        Err(e) if e.kind() != io::ErrorKind::Unsupported => Err(e),
+        _ => Ok(Some(handle)),
+    }
+}
+
+/// Cleans `content` read from `file` and writes the result. With a `stamp`
+/// from read time, an in-place write is refused if the file has changed
+/// since, unless `--force-write` is given.
+fn process_content(
+    file: &str,
+    content: &str,
+    input_encoding: Encoding,
+    stamp: Option<Stamp>,
+    args: &Args,
+) -> ProcessResult {
+    let output_encoding = args.output_encoding.unwrap_or(input_encoding);
+    let cleaner = args.cleaner(file);
+
+    let started = Instant::now();
+    let Cleaned {
+        text: cleaned,
+        emojis: emoji_count,
+        bytes_saved,
+    } = match clean(content, file, &cleaner, args) {
+        Ok(cleaned) => cleaned,
+        Err(e) => return ProcessResult::failed(file, 0, e),
+    };
+    let findings = if args.wants_findings() {
+        report_findings(content, &cleaner)
+    } else {
+        Vec::new()
+    };
+    let found = if args.list {
+        sequences(content, cleaner.spans(content))
+    } else {
+        Vec::new()
+    };
+    let mut timings = Timings {
+        clean: started.elapsed(),
+        ..Timings::default()
+    };
+
+    // Rewriting a file that would come out byte-for-byte the same only
+    // dirties its mtime and, with --backup or --trash, leaves a pointless
+    // copy behind.
+    let unchanged = args.writes_files() && cleaned == content && output_encoding == input_encoding;
+    if args.dry_run || unchanged {
+        return ProcessResult {
+            findings,
+            bytes_read: content.len(),
+            bytes_saved,
+            sequences: found,
+            timings,
+            ..ProcessResult::succeeded(file, emoji_count)
+        };
+    }
+
+    let started = Instant::now();
+    if let Err(e) = ensure_unmodified(file, stamp, args) {
+        return ProcessResult::failed(file, emoji_count, e);
+    }
+
+    let audit = match args.written_path(file) {
+        Some(target) if args.audit.is_some() && cleaned != content => {
+            let cleaner = cleaner.resolve(content);
+            let spans = cleaner.spans(content);
+            match audit::Entry::new(&target, content, &cleaned, &spans, |span| {
+                cleaner.replacement(span)
+            }) {
+                Ok(entry) => Some(entry),
+                Err(e) => return ProcessResult::failed(file, emoji_count, e),
+            }
+        }
+        _ => None,
+    };
+
+    let written = write_cleaned(file, &cleaned, output_encoding, args).and_then(|()| {
+        match args.written_path(file) {
+            Some(target) if args.verify => {
+                // Occurrences the filter command or plugin kept are expected
+                // to remain.
+                let leftovers = (!args.decides()).then_some(&cleaner);
+                let kept = match args.keep_gitmoji {
+                    true => gitmoji::kept_len(&cleaned, &args.settings.gitmoji),
+                    false => 0,
+                };
+                verify_written(&target, &cleaned, output_encoding, leftovers, kept)
+            }
+            _ => Ok(()),
+        }
+    });
+    timings.write = started.elapsed();
+
+    let result = match written {
+        Ok(()) => ProcessResult {
+            audit,
+            sha256: args
+                .written_path(file)
+                .and_then(|_| args.checksums(content, &cleaned)),
+            ..ProcessResult::succeeded(file, emoji_count)
+        },
+        Err(e) => ProcessResult::failed(file, emoji_count, e),
+    };
+    ProcessResult {
+        findings,
+        bytes_read: content.len(),
+        bytes_saved,
+        timings,
+        ..result
+    }
+}
+
+/// Cleans the regular files staged in the git index rather than the copies
+/// in the working tree, as listed by `staged::changes`. With edits enabled the
+/// cleaned content is staged in place of the original, and a working tree
+/// file that still matches what was staged is rewritten to match; one with
+/// unstaged changes is left alone so that work isn't lost.
+fn process_staged(
+    args: &Args,
+    changes: &[RawChange],
+    stop: impl Fn() -> bool,
+) -> Vec<ProcessResult> {
+    let dir = Path::new(".");
+    let mut results = Vec::new();
+    for change in changes {
+        if stop() {
+            break;
+        }
+        let result = process_staged_file(dir, change, args);
+        let failed = !result.success;
+        results.push(result);
+        if failed && args.fail_fast {
+            break;
+        }
+    }
+    results
+}
+
+fn process_staged_file(dir: &Path, change: &RawChange, args: &Args) -> ProcessResult {
+    let file = change.path.as_str();
+    let started = Instant::now();
+    let staged = match staged::blob(dir, &change.new_sha) {
+        Ok(blob) => blob,
+        Err(e) => {
+            return ProcessResult::failed(file, 0, format!("Failed to read staged file: {}", e));
+        }
+    };
+    let Ok((content, input_encoding)) = encoding::decode(staged.clone()) else {
+        return ProcessResult::skipped(file, "not text");
+    };
+    let read = started.elapsed();
+
+    let started = Instant::now();
+    let cleaner = args.cleaner(file);
+    let cleaned = match clean(&content, file, &cleaner, args) {
+        Ok(cleaned) => cleaned,
+        Err(e) => return ProcessResult::failed(file, 0, e),
+    };
+    let mut result = ProcessResult {
+        bytes_read: content.len(),
+        bytes_saved: cleaned.bytes_saved,
+        timings: Timings {
+            read,
+            clean: started.elapsed(),
+            ..Timings::default()
+        },
+        ..ProcessResult::succeeded(file, cleaned.emojis)
+    };
+    if args.wants_findings() {
+        result.findings = report_findings(&content, &cleaner);
+    }
+    if args.list {
+        result.sequences = sequences(&content, cleaner.spans(&content));
+    }
+    if args.dry_run || cleaned.text == content {
+        return result;
+    }
+
+    let started = Instant::now();
+    let output_encoding = args.output_encoding.unwrap_or(input_encoding);
+    let written = output_encoding
+        .encode(&cleaned.text)
+        .map_err(|e| format!("Failed to encode output: {}", e))
+        .and_then(|bytes| {
+            staged::stage(dir, change, &bytes)
+                .map_err(|e| format!("Failed to stage file: {}", e))?;
+            match fs::read(file) {
+                Ok(current) if current == staged => {
+                    write_output(file, &bytes, args.temp_dir.as_deref(), args.break_links)
+                        .map_err(|e| format!("Failed to write file: {}", e))
+                }
+                _ => Ok(()),
+            }
+        });
+    result.timings.write = started.elapsed();
+    match written {
+        Ok(()) => result.sha256 = args.checksums(&content, &cleaned.text),
+        Err(e) => {
+            result.success = false;
+            result.error = Some(e);
+        }
+    }
+    result
+}
+
+/// What identifies a file whatever path reaches it: the device and inode on
+/// Unix, the canonical path elsewhere.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum FileId {
+    #[cfg(unix)]
+    Inode(u64, u64),
+    #[cfg(not(unix))]
+    Path(PathBuf),
+}
+
+impl FileId {
+    /// The identity of `file`, following symlinks, or `None` if it can't be
+    /// read, which reading it will then report.
+    #[cfg(unix)]
+    fn of(file: &str) -> Option<FileId> {
+        use std::os::unix::fs::MetadataExt;
+        let metadata = fs::metadata(file).ok()?;
+        Some(FileId::Inode(metadata.dev(), metadata.ino()))
+    }
+
+    #[cfg(not(unix))]
+    fn of(file: &str) -> Option<FileId> {
+        fs::canonicalize(file).ok().map(FileId::Path)
+    }
+}
+
+/// Whether `file` is a named pipe, including process substitutions such as
+/// `<(generate-report)`. A pipe can only be read once, front to back.
+#[cfg(unix)]
+fn is_fifo(file: &str) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    fs::metadata(file).is_ok_and(|m| m.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+fn is_fifo(_file: &str) -> bool {
+    false
+}
+
+/// Cleans a named pipe as it arrives instead of waiting for the writer to
+/// finish. Options that need the whole input (context-sensitive modes,
+/// `--filter-cmd`, UTF-16) fall back to reading it all first.
+fn process_fifo(file: &str, args: &Args) -> ProcessResult {
+    if args.writes_files() {
+        return ProcessResult::failed(file, 0, "Cannot edit a named pipe in place".to_string());
+    }
+    if args.suffix.is_some() {
+        return ProcessResult::failed(
+            file,
+            0,
+            "Cannot write a suffixed copy of a named pipe".to_string(),
+        );
+    }
+
+    let failed =
+        |e: io::Error| ProcessResult::failed(file, 0, format!("Failed to read file: {}", e));
+    let mut reader = match fs::File::open(file) {
+        Ok(pipe) => BufReader::new(pipe),
+        Err(e) => return failed(e),
+    };
+
+    let cleaner = args.cleaner(file);
+    let utf16_input = matches!(reader.fill_buf(), Ok([0xFF, 0xFE, ..] | [0xFE, 0xFF, ..]));
+    let utf16_output = matches!(
+        args.output_encoding,
+        Some(Encoding::Utf16Le | Encoding::Utf16Be)
+    );
+
+    let literal = args.escape_output.is_some();
+    if cleaner.is_line_local() && !args.decides() && !utf16_input && !utf16_output && !literal {
+        return stream_lines(file, reader, &cleaner, args).unwrap_or_else(failed);
+    }
+
+    let mut bytes = Vec::new();
+    match reader
+        .read_to_end(&mut bytes)
+        .and_then(|_| encoding::decode(bytes))
+    {
+        Ok((content, input_encoding)) => {
+            process_content(file, &content, input_encoding, None, args)
+        }
+        Err(e) => failed(e),
+    }
+}
+
+/// Cleans UTF-8 input one line at a time, writing each cleaned line to
+/// stdout as soon as it is complete.
+fn stream_lines(
+    file: &str,
+    mut reader: impl BufRead,
+    cleaner: &Cleaner,
+    args: &Args,
+) -> io::Result<ProcessResult> {
+    let encoding = args.output_encoding.unwrap_or(Encoding::Utf8);
+    let echo = args.streams_cleaned() && !args.dry_run;
+    let mut stdout = io::stdout().lock();
+    if echo {
+        stdout.write_all(&encoding.encode(&header(file, args))?)?;
+    }
+    let mut result = ProcessResult::succeeded(file, 0);
+    let mut bytes = Vec::new();
+    let mut line_number = 0;
+
+    while reader.read_until(b'\n', &mut bytes)? > 0 {
+        line_number += 1;
+        let line = String::from_utf8(std::mem::take(&mut bytes))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let cleaned = clean(&line, file, cleaner, args).map_err(io::Error::other)?;
+
+        result.emojis_found += cleaned.emojis;
+        result.bytes_read += line.len();
+        result.bytes_saved += cleaned.bytes_saved;
+        if args.wants_findings() {
+            let findings = report_findings(&line, cleaner);
+            result
+                .findings
+                .extend(findings.into_iter().map(|f| Finding {
+                    line: line_number,
+                    ..f
+                }));
+        }
+        if args.list {
+            result
+                .sequences
+                .extend(sequences(&line, cleaner.spans(&line)));
+        }
+        if echo {
+            stdout.write_all(&encoding.encode(&cleaned.text)?)?;
+        }
+    }
+
+    stdout.flush()?;
+    Ok(result)
+}
+
+/// Reads `file` back after writing and checks that it holds exactly the
+/// cleaned buffer, so encoding or truncation problems are reported instead of
+/// silently corrupting data. With a `cleaner`, anything it would still remove
+/// is also an error, except in the first `kept` bytes, which hold a gitmoji
+/// `--keep-gitmoji` left alone.
+fn verify_written(
+    file: &str,
+    cleaned: &str,
+    encoding: Encoding,
+    cleaner: Option<&Cleaner>,
+    kept: usize,
+) -> Result<(), String> {
+    let expected = encoding
+        .encode(cleaned)
+        .map_err(|e| format!("Verification failed: {}", e))?;
+    verify_bytes(file, &expected)?;
+
+    if let Some(cleaner) = cleaner {
+        let remaining = cleaner.count(&cleaned[kept..]);
+        if remaining > 0 {
+            return Err(format!(
+                "Verification failed: {} {} remain after cleaning",
+                remaining,
+                cleaner.describe()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that `file` holds exactly `expected`.
+fn verify_bytes(file: &str, expected: &[u8]) -> Result<(), String> {
+    let written = fs::read(file).map_err(|e| format!("Verification failed: {}", e))?;
+    if written.len() != expected.len() {
+        return Err(format!(
+            "Verification failed: expected {} bytes, found {}",
+            expected.len(),
+            written.len()
+        ));
+    }
+
+    if let Some(offset) = written.iter().zip(expected).position(|(a, b)| a != b) {
+        return Err(format!(
+            "Verification failed: content differs at byte {}",
+            offset
+        ));
+    }
+    Ok(())
+}
+
+fn process_stdin(args: &Args) -> io::Result<ProcessResult> {
+    let mut bytes = Vec::new();
+    io::stdin().read_to_end(&mut bytes)?;
+    let name = args.stdin_filename.as_deref().unwrap_or(STDIN_NAME);
+    process_document(bytes, name, name, args, &mut io::stdout())
+}
+
+/// Cleans stdin as `--split-documents` input, reporting each document as
+/// `label#N`. The cleaned documents go to stdout with the same separators.
+fn process_documents(args: &Args, separator: &[u8]) -> io::Result<Vec<ProcessResult>> {
+    let mut bytes = Vec::new();
+    io::stdin().read_to_end(&mut bytes)?;
+    clean_documents(&bytes, separator, args, &mut io::stdout().lock())
+}
+
+/// Cleans stdin as a `--tar-filter` archive into stdout, reporting each
+/// text member by its path in the archive.
+fn process_tar(args: &Args) -> io::Result<Vec<ProcessResult>> {
+    clean_tar(io::stdin().lock(), args, &mut io::stdout().lock())
+}
+
+fn clean_tar(input: impl Read, args: &Args, out: &mut dyn Write) -> io::Result<Vec<ProcessResult>> {
+    let mut results = Vec::new();
+    tar::filter(input, out, |name, content| {
+        // Members that don't decode are binaries, kept as they are.
+        encoding::decode(content.to_vec()).ok()?;
+        let mut cleaned = Vec::new();
+        let result = process_document(content.to_vec(), name, name, args, &mut cleaned);
+        let changed = result.is_ok() && cleaned != content;
+        results.push(result.unwrap_or_else(|e| ProcessResult::failed(name, 0, e.to_string())));
+        changed.then_some(cleaned)
+    })?;
+    Ok(results)
+}
+
+fn clean_documents(
+    bytes: &[u8],
+    separator: &[u8],
+    args: &Args,
+    out: &mut dyn Write,
+) -> io::Result<Vec<ProcessResult>> {
+    let label = args.stdin_filename.as_deref().unwrap_or(STDIN_NAME);
+    let documents = split_documents(bytes, separator);
+    let terminated = bytes.ends_with(separator);
+    let mut results = Vec::new();
+
+    for (i, document) in documents.iter().enumerate() {
+        let name = format!("{}#{}", label, i + 1);
+        let result = process_document(document.to_vec(), label, &name, args, out)
+            .unwrap_or_else(|e| ProcessResult::failed(&name, 0, e.to_string()));
+        if args.streams_cleaned() && !args.dry_run && (i + 1 < documents.len() || terminated) {
+            out.write_all(separator)?;
+        }
+        results.push(result);
+    }
+    out.flush()?;
+    Ok(results)
+}
+
+/// The documents in `bytes`; a trailing separator ends the last document
+/// rather than starting an empty one.
+fn split_documents<'a>(bytes: &'a [u8], separator: &[u8]) -> Vec<&'a [u8]> {
+    let mut documents = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        match rest.windows(separator.len()).position(|w| w == separator) {
+            Some(at) => {
+                documents.push(&rest[..at]);
+                rest = &rest[at + separator.len()..];
+            }
+            None => {
+                documents.push(rest);
+                break;
+            }
+        }
+    }
+    documents
+}
+
+/// Parses a `--split-documents` separator, which may spell NUL, newline,
+/// tab and backslash as `\0`, `\n`, `\t` and `\\`.
+fn parse_separator(value: &str) -> Result<Vec<u8>, String> {
+    let mut separator = Vec::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some('0') => '\0',
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('\\') => '\\',
+                other => {
+                    return Err(format!(
+                        "invalid escape '\\{}': use \\0, \\n, \\t or \\\\",
+                        other.map(String::from).unwrap_or_default()
+                    ));
+                }
+            },
+            c => c,
+        };
+        let mut buf = [0; 4];
+        separator.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+    }
+    if separator.is_empty() {
+        return Err("the separator can't be empty".to_string());
+    }
+    Ok(separator)
+}
+
+/// Cleans one document read from stdin, writing the cleaned text to `out`
+/// unless it is a dry run. Config rules match `label`; the result is
+/// reported as `name`.
+fn process_document(
+    bytes: Vec<u8>,
+    label: &str,
+    name: &str,
+    args: &Args,
+    out: &mut dyn Write,
+) -> io::Result<ProcessResult> {
+    if args.eml || args.mbox {
+        let (cleaned, counts) = clean_message(&bytes, &args.cleaner(label), args);
+        if args.streams_cleaned() && !args.dry_run {
+            out.write_all(&cleaned)?;
+        }
+        return Ok(ProcessResult {
+            bytes_read: bytes.len(),
+            bytes_saved: bytes.len().saturating_sub(cleaned.len()),
+            ..ProcessResult::succeeded(name, counts.iter().sum())
+        });
+    }
+
+    let (buffer, input_encoding) = encoding::decode(bytes)?;
+    let output_encoding = args.output_encoding.unwrap_or(input_encoding);
+
+    // Config rules match the label, so editors get per-file-type behaviour.
+    let cleaner = args.cleaner(label);
+    let cleaned = clean(&buffer, label, &cleaner, args).map_err(io::Error::other)?;
+    let count = cleaned.emojis;
+
+    if args.streams_cleaned() && !args.dry_run {
+        out.write_all(&output_encoding.encode(&args.printed(&cleaned.text))?)?;
+    }
+
+    let mut result = ProcessResult {
+        bytes_read: buffer.len(),
+        bytes_saved: cleaned.bytes_saved,
+        ..ProcessResult::succeeded(name, count)
+    };
+    if args.wants_findings() {
+        result.findings = report_findings(&buffer, &cleaner);
+    }
+    if args.list {
+        result.sequences = sequences(&buffer, cleaner.spans(&buffer));
+    }
+    Ok(result)
+}
+
+/// Prints `file:line:col:text` for every occurrence, the format Vim's
+/// `:cexpr` and most editors' grep integrations understand. Failures go to
+/// stderr so they don't pollute the quickfix list.
+fn print_vimgrep(
+    results: &[&ProcessResult],
+    paint: Palette,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    for result in results {
+        if let Some(ref error) = result.error {
+            eprintln!("nomoji: {}: {}", result.file, error);
+        }
+        if let Some(ref reason) = result.skipped {
+            eprintln!("nomoji: warning: {}: skipped, {}", result.file, reason);
+        }
+        if result.name_emojis > 0 {
+            eprintln!(
+                "nomoji: warning: {}: {} emojis in the file name",
+                result.file, result.name_emojis
+            );
+        }
+        for finding in &result.findings {
+            writeln!(
+                out,
+                "{}:{}:{}:{}",
+                paint.magenta(&result.file),
+                paint.green(finding.line),
+                paint.green(finding.column),
+                finding.line_text
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints the JSON report as a single line.
+fn print_json(results: &[ProcessResult], args: &Args, out: &mut dyn Write) -> io::Result<()> {
+    let mut report = report::to_report(results, listed_files(results, args));
+    if let Some(depth) = args.group_by_dir {
+        report.directories =
+            report::by_directory(results, usize::try_from(depth).unwrap_or(usize::MAX));
+    }
+    report.estimate = args.estimate(results);
+    writeln!(out, "{}", report::encode(report))
+}
+
+/// The results the per-file part of a report lists: all of them, or with
+/// `--top-files` the worst offenders by emoji count, ties in input order.
+/// `--only-matches` drops files with nothing to report, and `--sort` then
+/// orders what is left.
+fn listed_files<'a>(results: &'a [ProcessResult], args: &Args) -> Vec<&'a ProcessResult> {
+    let mut listed: Vec<&ProcessResult> = results.iter().collect();
+    if args.only_matches {
+        listed.retain(|r| r.emojis_found > 0 || r.name_emojis > 0 || !r.success);
+    }
+    if let Some(n) = args.top_files {
+        listed.retain(|r| r.emojis_found > 0);
+        listed.sort_by_key(|r| std::cmp::Reverse(r.emojis_found));
+        listed.truncate(usize::try_from(n).unwrap_or(usize::MAX));
+    }
+    if let Some(sort) = args.sort {
+        sort.apply(&mut listed);
+    }
+    listed
+}
+
+/// Writes the report for `results` in the format chosen by `--format`.
+fn write_report(results: &[ProcessResult], args: &Args, out: &mut dyn Write) -> io::Result<()> {
+    // Reports of every occurrence list every file; only the order follows
+    // `--sort`.
+    let sorted = || {
+        let mut sorted: Vec<&ProcessResult> = results.iter().collect();
+        if let Some(sort) = args.sort {
+            sort.apply(&mut sorted);
+        }
+        sorted
+    };
+    match args.format {
+        Format::Text => print_report(results, args, out)?,
+        Format::Vimgrep => print_vimgrep(&sorted(), args.palette, out)?,
+        Format::Json => print_json(results, args, out)?,
+        Format::Jsonl => jsonl::print(results, &sorted(), out)?,
+        Format::Teamcity => teamcity::print(&sorted(), out)?,
+        Format::Checkstyle => checkstyle::print(&sorted(), out)?,
+        Format::Rdjson => rdjson::print(&sorted(), out)?,
+    }
+    out.flush()
+}
+
+/// The `--pre-commit` and `--commit-msg` report: a line for each file
+/// changed or failed, and nothing for the rest.
+fn print_pre_commit(results: &[ProcessResult], args: &Args, out: &mut dyn Write) -> io::Result<()> {
+    let action = match args.conversion().is_some() || args.describe {
+        true => "converted",
+        false => "removed",
+    };
+    for result in results {
+        if let Some(ref error) = result.error {
+            writeln!(out, "nomoji: {}: {}", result.file, error)?;
+        } else if result.changed() {
+            writeln!(
+                out,
+                "Fixed {}: {} emojis {}",
+                result.file, result.emojis_found, action
+            )?;
+        }
+    }
+    out.flush()
+}
+
+/// The short text report for input read from stdin.
+fn print_stdin_report(result: &ProcessResult, args: &Args, out: &mut dyn Write) -> io::Result<()> {
+    let name = args.stdin_filename.as_deref().unwrap_or("stdin");
+    writeln!(out, "\n{}", args.palette.bold("=== nomoji Report ==="))?;
+    let action = match args.conversion().is_some() || args.describe {
+        true => "converted in",
+        false => "removed from",
+    };
+    match args.dry_run {
+        true => writeln!(
+            out,
+            "Emojis found in {}: {}, would shrink by {} bytes",
+            name, result.emojis_found, result.bytes_saved
+        )?,
+        false => writeln!(out, "Emojis {} {}: {}", action, name, result.emojis_found)?,
+    }
+    if args.list {
+        print_list(std::slice::from_ref(result), args.palette, out)?;
+    }
+    out.flush()
+}
+
+fn print_report(results: &[ProcessResult], args: &Args, out: &mut dyn Write) -> io::Result<()> {
+    let total_files = results.len();
+    let successful = results.iter().filter(|r| r.success).count();
+    let skipped = results.iter().filter(|r| r.skipped.is_some()).count();
+    let total_emojis: usize = results.iter().map(|r| r.emojis_found).sum();
+    let total_bytes_saved: usize = results.iter().map(|r| r.bytes_saved).sum();
+    let name_emojis: usize = results.iter().map(|r| r.name_emojis).sum();
+    let paint = args.palette;
+
+    writeln!(out, "\n{}", paint.bold("=== nomoji Report ==="))?;
+    writeln!(out, "Files processed: {}", total_files)?;
+    writeln!(out, "Successful: {}", successful)?;
+
+    if total_files != successful {
+        writeln!(
+            out,
+            "{}",
+            paint.red(format!("Failed: {}", total_files - successful))
+        )?;
+    }
+
+    if skipped > 0 {
+        writeln!(out, "Skipped: {}", skipped)?;
+    }
+
+    let fast_skipped = results.iter().filter(|r| r.fast_skipped).count();
+    if fast_skipped > 0 {
+        writeln!(out, "Fast-skipped (plain ASCII): {}", fast_skipped)?;
+    }
+
+    writeln!(out, "Total emojis found: {}", total_emojis)?;
+
+    if name_emojis > 0 {
+        writeln!(out, "Emojis in file names: {}", name_emojis)?;
+    }
+
+    if args.dry_run {
+        writeln!(out, "Bytes that would be saved: {}", total_bytes_saved)?;
+    }
+
+    if let Some(limit) = args.head_bytes {
+        writeln!(out, "Only the first {} bytes of each file were read", limit)?;
+    }
+
+    if let Some(estimate) = args.estimate(results) {
+        writeln!(
+            out,
+            "\n{}",
+            paint.bold(format!(
+                "Estimated for all {} files from a sample of {} (95% confidence):",
+                estimate.population, estimate.sampled
+            ))
+        )?;
+        for (what, interval) in [
+            ("Emojis", estimate.emojis),
+            ("Files with emojis", estimate.files_with_emojis),
+        ] {
+            writeln!(
+                out,
+                "  {}: {} ({} to {})",
+                what, interval.estimate, interval.low, interval.high
+            )?;
+        }
+    }
+
+    if args.verbose > 0 {
+        let mut timings = Timings::default();
+        for result in results {
+            timings += result.timings;
+        }
+        let bytes_read = results.iter().map(|r| r.bytes_read).sum();
+        writeln!(out, "Time: {}", timings.describe(bytes_read))?;
+    }
+
+    if args.list {
+        print_list(results, paint, out)?;
+    }
+
+    if let Some(depth) = args.group_by_dir {
+        writeln!(out, "\n{}", paint.bold("By directory:"))?;
+        for directory in report::by_directory(results, usize::try_from(depth).unwrap_or(usize::MAX))
+        {
+            writeln!(
+                out,
+                "  {}: {} emojis in {} files",
+                directory.directory, directory.emojis, directory.files
+            )?;
+        }
+    }
+
+    let listed = listed_files(results, args);
+    if !listed.is_empty() {
+        match args.top_files {
+            Some(n) => writeln!(
+                out,
+                "\n{}",
+                paint.bold(format!("Top {} files by emojis:", n))
+            )?,
+            None => writeln!(out, "\n{}", paint.bold("Per-file results:"))?,
+        }
+        for result in listed {
+            if let Some(ref error) = result.error {
+                writeln!(
+                    out,
+                    "  {}: {} emojis - {}",
+                    result.file,
+                    result.emojis_found,
+                    paint.red(format!("ERROR: {}", error))
+                )?;
+            } else if let Some(ref reason) = result.skipped {
+                writeln!(
+                    out,
+                    "  {}: {}",
+                    result.file,
+                    paint.yellow(format!("skipped - {}", reason))
+                )?;
+            } else if args.dry_run {
+                writeln!(
+                    out,
+                    "  {}: {} emojis found, would shrink by {} bytes",
+                    result.file, result.emojis_found, result.bytes_saved
+                )?;
+            } else {
+                let action = match args.conversion().is_some() || args.describe {
+                    true => "converted",
+                    false => "removed",
+                };
+                writeln!(
+                    out,
+                    "  {}: {} emojis {}",
+                    result.file, result.emojis_found, action
+                )?;
+            }
+            if result.name_emojis > 0 {
+                writeln!(
+                    out,
+                    "  {}: {} emojis in the file name (not renamed)",
+                    result.file, result.name_emojis
+                )?;
+            }
+            for (i, &count) in result.messages.iter().enumerate() {
+                if count > 0 {
+                    writeln!(out, "    message {}: {} emojis", i + 1, count)?;
+                }
+            }
+            if args.verbose > 1 && result.success && result.skipped.is_none() {
+                writeln!(out, "    {}", result.timings.describe(result.bytes_read))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Appends the `--github-summary` tables for `results` when running under
+/// GitHub Actions. Elsewhere the flag does nothing, so one command can serve
+/// both. A summary that can't be written is reported but doesn't fail the
+/// run.
+fn write_github_summary(results: &[ProcessResult], args: &Args) {
+    if !args.github_summary {
+        return;
+    }
+    let Some(path) = std::env::var_os(github::SUMMARY_VAR).filter(|path| !path.is_empty()) else {
+        return;
+    };
+    let verb = if args.dry_run {
+        "found"
+    } else if args.conversion().is_some() || args.describe {
+        "converted"
+    } else {
+        "removed"
+    };
+    if let Err(e) = github::append(Path::new(&path), &github::summary(results, verb)) {
+        eprintln!(
+            "nomoji: cannot write GitHub step summary {}: {}",
+            Path::new(&path).display(),
+            e
+        );
+    }
+}
+
+/// Cleans `content` twice and describes any change made by the second pass,
+/// which would mean removal left behind newly-matching sequences.
+fn check_idempotent(content: &str) -> Result<(), String> {
+    let (first, _) = remove_emojis(content);
+    let (second, count) = remove_emojis(&first);
+
+    if second == first {
+        Ok(())
+    } else {
+        Err(format!(
+            "second pass removed {} more emojis ({} bytes)",
+            count,
+            first.len() - second.len()
+        ))
+    }
+}
+
+fn run_check_idempotent(files: &[String]) -> bool {
+    let mut all_idempotent = true;
+
+    eprintln!("=== nomoji Idempotency Check ===");
+    for file in files {
+        let content = if file == "-" {
+            let mut buffer = String::new();
+            io::stdin().read_to_string(&mut buffer).map(|_| buffer)
+        } else {
+            read_input(file)
+        };
+
+        let outcome = content
+            .map_err(|e| format!("Failed to read file: {}", e))
+            .and_then(|content| check_idempotent(&content));
+
+        match outcome {
+            Ok(()) => eprintln!("  {}: idempotent", file),
+            Err(e) => {
+                all_idempotent = false;
+                eprintln!("  {}: FAILED - {}", file, e);
+            }
+        }
+    }
+
+    all_idempotent
+}
+
+/// Transforms one git blob. Cleaning only touches valid UTF-8 text; binary
+/// blobs pass through byte-for-byte so the filter is safe on any path.
+fn filter_blob(blob: Vec<u8>, clean: bool) -> Vec<u8> {
+    if !clean {
+        return blob;
+    }
+
+    match String::from_utf8(blob) {
+        Ok(text) => match remove_emojis_cow(&text).0 {
+            Cow::Borrowed(_) => text.into_bytes(),
+            Cow::Owned(cleaned) => cleaned.into_bytes(),
+        },
+        Err(e) => e.into_bytes(),
+    }
+}
+
+fn run_filter(clean: bool) -> io::Result<()> {
+    let mut blob = Vec::new();
+    io::stdin().read_to_end(&mut blob)?;
+
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(&filter_blob(blob, clean))?;
+    stdout.flush()
+}
+
+fn run_selftest(paint: Palette) -> bool {
+    let (checked, misses) = selftest::run(selftest::CORPUS);
+
+    eprintln!("{}", paint.bold("=== nomoji Self-Test ==="));
+    eprintln!("Sequences checked: {}", checked);
+    eprintln!("Fully removed: {}", checked - misses.len());
+    if !misses.is_empty() {
+        eprintln!("{}", paint.red(format!("Missed: {}", misses.len())));
+        for miss in &misses {
+            eprintln!(
+                "  {} ({}): left {}",
+                selftest::codepoints(&miss.sequence),
+                miss.description,
+                selftest::codepoints(&miss.leftover)
+            );
+        }
+    }
+
+    misses.is_empty()
+}
+
+/// Restores every file in the sidecar at `path`. Returns whether all of
+/// them were restored; a file that changed since the run is left alone.
+fn run_restore(path: &Path, paint: Palette) -> Result<bool, String> {
+    let entries = audit::load(path)?;
+    let mut restored = 0;
+
+    eprintln!("{}", paint.bold("=== nomoji Restore ==="));
+    for entry in &entries {
+        let outcome = read_decoded(&entry.file)
+            .map_err(|e| format!("Failed to read file: {}", e))
+            .and_then(|(cleaned, encoding)| {
+                let original = entry.restore(&cleaned)?;
+                let bytes = encoding
+                    .encode(&original)
+                    .map_err(|e| format!("Failed to encode output: {}", e))?;
+                write_output(&entry.file, bytes, None, false)
+                    .map_err(|e| format!("Failed to write file: {}", e))
+            });
+        match outcome {
+            Ok(()) => {
+                restored += 1;
+                eprintln!(
+                    "  {}: {} sequences restored",
+                    entry.file,
+                    entry.changes.len()
+                );
+            }
+            Err(e) => eprintln!("  {}: {}", entry.file, paint.red(format!("ERROR: {}", e))),
+        }
+    }
+    eprintln!("Restored: {} of {} files", restored, entries.len());
+
+    Ok(restored == entries.len())
+}
+
+/// Updates the running binary to the latest release, or with `check` only
+/// reports whether there is one.
+fn run_self_update(check: bool, curl: &str) -> Result<(), String> {
+    let current = env!("CARGO_PKG_VERSION");
+    let latest = self_update::fetch(curl, self_update::LATEST_RELEASE)?;
+    let release = self_update::parse_release(&String::from_utf8_lossy(&latest))?;
+    if !self_update::is_newer(&release.version, current) {
+        eprintln!("nomoji {} is up to date", current);
+        return Ok(());
+    }
+    if check {
+        eprintln!(
+            "nomoji {} is available (this is {})",
+            release.version, current
+        );
+        return Ok(());
+    }
+
+    let name = self_update::asset_name();
+    let url = release.asset(&name).ok_or_else(|| {
+        format!(
+            "release {} has no binary for this platform ({})",
+            release.version, name
+        )
+    })?;
+    let sums_url = release
+        .asset("SHA256SUMS")
+        .ok_or_else(|| format!("release {} has no SHA256SUMS", release.version))?;
+    let sums = String::from_utf8(self_update::fetch(curl, sums_url)?)
+        .map_err(|_| "SHA256SUMS is not UTF-8".to_string())?;
+    let expected = self_update::checksum(&sums, &name)
+        .ok_or_else(|| format!("SHA256SUMS has no checksum for {}", name))?;
+
+    let binary = self_update::fetch(curl, url)?;
+    self_update::verify(&binary, expected)?;
+    let target =
+        std::env::current_exe().map_err(|e| format!("cannot find the running binary: {}", e))?;
+    self_update::replace(&target, &binary)
+        .map_err(|e| format!("failed to replace {}: {}", target.display(), e))?;
+    eprintln!("Updated nomoji {} -> {}", current, release.version);
+    Ok(())
+}
+
+/// Cleans `columns` of `table` in `database`, applying every change in one
+/// transaction unless `dry_run` is set.
+fn run_db_clean(
+    args: &Args,
+    database: &Path,
+    table: &str,
+    columns: &[String],
+    dry_run: bool,
+    sqlite3: &str,
+) -> Result<(), String> {
+    let cleaner = args.cleaner(&database.to_string_lossy());
+    let mut checked = 0;
+    let mut updates = Vec::new();
+    let mut total = 0;
+
+    for column in columns {
+        for (rowid, old) in db::read(sqlite3, database, table, column)? {
+            checked += 1;
+            let (new, count) = cleaner.remove(&old);
+            if count > 0 {
+                total += count;
+                let new = new.into_owned();
+                updates.push(db::Update {
+                    rowid,
+                    column: column.clone(),
+                    old,
+                    new,
+                });
+            }
+        }
+    }
+
+    if !dry_run && !updates.is_empty() {
+        db::apply(sqlite3, database, table, &updates)?;
+    }
+
+    let paint = Palette::new(args.color, io::stderr().is_terminal());
+    eprintln!(
+        "{}",
+        paint.bold(format!(
+            "=== nomoji Database Clean{} ===",
+            if dry_run { " (dry run)" } else { "" }
+        ))
+    );
+    eprintln!("Values checked: {}", checked);
+    for update in &updates {
+        eprintln!("  {} row {}: {:?}", update.column, update.rowid, update.new);
+    }
+    eprintln!(
+        "Values {}: {}",
+        if dry_run {
+            "that would change"
+        } else {
+            "changed"
+        },
+        updates.len()
+    );
+    eprintln!("Total {}: {}", cleaner.describe(), total);
+
+    Ok(())
+}
+
+/// Describes each of `sequences` and what `cleaner` would do with it.
+fn run_info(
+    sequences: &[String],
+    cleaner: &Cleaner,
+    paint: Palette,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    for (i, arg) in sequences.iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        let sequence = info::parse(arg);
+        let info = info::of(&sequence);
+        writeln!(
+            out,
+            "{}",
+            paint.bold(format!(
+                "{} {}",
+                sequence,
+                info.name.as_deref().unwrap_or("(no name)")
+            ))
+        )?;
+        writeln!(out, "Codepoints:     {}", selftest::codepoints(&sequence))?;
+        for (j, (c, name)) in info.characters.iter().enumerate() {
+            let label = if j == 0 { "Names:" } else { "" };
+            writeln!(
+                out,
+                "{:<15} U+{:04X} {}",
+                label,
+                *c as u32,
+                name.as_deref().unwrap_or("(unknown)")
+            )?;
+        }
+        let none = |list: String| match list.is_empty() {
+            true => "none".to_string(),
+            false => list,
+        };
+        writeln!(out, "Categories:     {}", none(info.categories.join(", ")))?;
+        writeln!(out, "Status:         {}", info.qualification.name())?;
+        writeln!(out, "Role:           {}", info.role.name())?;
+        writeln!(
+            out,
+            "Shortcode:      {}",
+            info.shortcode.as_deref().unwrap_or("none")
+        )?;
+        writeln!(
+            out,
+            "Introduced:     {}",
+            info.version
+                .map_or("unknown".to_string(), |(major, minor)| {
+                    format!("Unicode {}.{}", major, minor)
+                })
+        )?;
+        let (cleaned, count) = cleaner.remove(&sequence);
+        let verdict = match (count, cleaned.is_empty()) {
+            (0, _) => paint.green("kept"),
+            (_, true) => paint.red("removed"),
+            (_, false) => paint.yellow(format!(
+                "replaced with {:?} ({})",
+                cleaned,
+                selftest::codepoints(&cleaned)
+            )),
+        };
+        writeln!(out, "Cleaning:       {}", verdict)?;
+    }
+    out.flush()
+}
+
+/// Writes the occurrences in `files` as `export` asks. Files that aren't
+/// text are passed over with a warning; returns whether every file could
+/// be read.
+fn run_stats(export: &[String], recursive: bool, files: &[String]) -> Result<bool, String> {
+    let format: stats::Export = export[0].parse()?;
+    let path = &export[1];
+    if path == "-" && format == stats::Export::Parquet && io::stdout().is_terminal() {
+        return Err("refusing to write Parquet to a terminal".to_string());
+    }
+
+    let mut inputs = Vec::new();
+    for file in files {
+        match recursive && Path::new(file).is_dir() {
+            true => inputs.extend(walk::files(Path::new(file)).map_err(|e| e.to_string())?),
+            false => inputs.push(file.clone()),
+        }
+    }
+
+    let mut rows = Vec::new();
+    let mut ok = true;
+    for file in &inputs {
+        match read_decoded(file) {
+            Ok((text, _)) => rows.extend(stats::occurrences(file, &text)),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                eprintln!("nomoji stats: warning: {}: skipped, not text", file);
+            }
+            Err(e) => {
+                eprintln!("nomoji stats: {}: {}", file, e);
+                ok = false;
+            }
+        }
+    }
+
+    let count = rows.len();
+    let written = match path.as_str() {
+        "-" => stats::write(rows, format, &mut io::stdout().lock()),
+        _ => fs::File::create(path)
+            .and_then(|file| stats::write(rows, format, &mut io::BufWriter::new(file))),
+    };
+    written.map_err(|e| format!("{}: {}", path, e))?;
+    eprintln!(
+        "nomoji stats: {} occurrences in {} files",
+        count,
+        inputs.len()
+    );
+    Ok(ok)
+}
+
+/// Prints how emoji counts changed between two saved reports. Returns
+/// whether there were no regressions.
+fn run_report_diff(old: &Path, new: &Path, when: color::When) -> Result<bool, String> {
+    let diff = report::diff(&report::load(old)?, &report::load(new)?);
+    let paint = Palette::new(when, io::stdout().is_terminal());
+    print_report_diff(&diff, paint, &mut io::stdout().lock())
+        .map_err(|e| format!("failed to write to stdout: {}", e))?;
+    Ok(!diff.has_regressions())
+}
+
+fn print_report_diff(diff: &report::Diff, paint: Palette, out: &mut impl Write) -> io::Result<()> {
+    // Counts come from files on disk, so differences saturate rather than
+    // overflow on absurd values.
+    writeln!(out, "{}", paint.bold("=== nomoji Report Diff ==="))?;
+    if !diff.worse.is_empty() {
+        writeln!(out, "{}", paint.red("Worse:"))?;
+        for (file, before, now) in &diff.worse {
+            let change = now.saturating_sub(*before);
+            writeln!(out, "  {}: {} -> {} (+{})", file, before, now, change)?;
+        }
+    }
+    if !diff.new_offenders.is_empty() {
+        writeln!(out, "{}", paint.red("New offenders:"))?;
+        for (file, now) in &diff.new_offenders {
+            writeln!(out, "  {}: {}", file, now)?;
+        }
+    }
+    if !diff.better.is_empty() {
+        writeln!(out, "{}", paint.green("Better:"))?;
+        for (file, before, now) in &diff.better {
+            let change = now.saturating_sub(*before);
+            writeln!(out, "  {}: {} -> {} ({})", file, before, now, change)?;
+        }
+    }
+    writeln!(
+        out,
+        "Total emojis: {} -> {} ({:+})",
+        diff.old_total,
+        diff.new_total,
+        diff.new_total.saturating_sub(diff.old_total)
+    )?;
+    out.flush()
+}
+
+fn run_pre_receive(paths: &[String]) -> bool {
+    let repo = Path::new(".");
+    let paths = if paths.is_empty() {
+        pre_receive::configured_paths(repo)
+    } else {
+        paths.to_vec()
+    };
+
+    // Fail closed: if the push cannot be inspected it is not accepted.
+    let violations = match pre_receive::check_push(io::stdin().lock(), repo, &paths) {
+        Ok(violations) => violations,
+        Err(e) => {
+            eprintln!("nomoji: unable to inspect push: {}", e);
+            return false;
+        }
+    };
+
+    if violations.is_empty() {
+        return true;
+    }
+
+    eprintln!("nomoji: push rejected, emojis introduced:");
+    for v in &violations {
+        let short = v.commit.get(..12).unwrap_or(&v.commit);
+        let mut added = Vec::new();
+        if v.emojis_added > 0 {
+            added.push(format!("{} emojis added", v.emojis_added));
+        }
+        if v.name_emojis > 0 {
+            added.push(format!("{} emojis in the path", v.name_emojis));
+        }
+        eprintln!("  {} {} {}: {}", v.refname, short, v.path, added.join(", "));
+    }
+    false
+}
+
+/// Processes each input file in turn, returning the results and the run
+/// limit that stopped it, if any. Once `interrupted` reports a signal, a
+/// limit is reached or, with `--fail-fast`, a file fails, no further file is
+/// started; the one in progress is always finished.
+fn process_files(
+    args: &Args,
+    interrupted: impl Fn() -> bool,
+) -> (Vec<ProcessResult>, Option<Limit>) {
+    let mut results = Vec::new();
+    let mut throttle = args.throttle.map(Throttle::new);
+    let limits = Limits::new(args.max_files, args.max_total_bytes, args.timeout);
+    let mut bytes = 0;
+    let mut seen: HashMap<FileId, &str> = HashMap::new();
+
+    for file in &args.files {
+        if interrupted() {
+            break;
+        }
+        if let Some(limit) = limits.reached(results.len() as u64, bytes) {
+            return (results, Some(limit));
+        }
+        // The same file named twice, or through a symlink or a hard link,
+        // is cleaned and counted once.
+        if let Some(id) = FileId::of(file) {
+            if let Some(first) = seen.get(&id) {
+                let reason = format!("same file as {}", first);
+                results.push(ProcessResult::skipped(file, &reason));
+                continue;
+            }
+            seen.insert(id, file);
+        }
+        let result = ProcessResult {
+            name_emojis: count_emojis(file),
+            ..process_file(file, args)
+        };
+        bytes += result.bytes_read as u64;
+        if let Some(throttle) = throttle.as_mut() {
+            throttle.record(result.bytes_read as u64);
+        }
+        let failed = !result.success;
+        results.push(result);
+        if failed && args.fail_fast {
+            break;
+        }
+    }
+
+    (results, None)
+}
+
+fn main() {
+    console::init();
+    let mut args = Args::parse();
+
+    if args.nice
+        && let Err(e) = throttle::lower_priority()
+    {
+        eprintln!("nomoji: warning: {}", e);
+    }
+
+    match &args.command {
+        Some(Command::CheckIdempotent { files }) => {
+            if !run_check_idempotent(files) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Filter { clean, .. }) => {
+            // Git treats anything on stderr as noise and a non-zero exit as a
+            // failed checkout or add, so only genuine I/O errors are reported.
+            if let Err(e) = run_filter(*clean) {
+                eprintln!("nomoji filter: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::PreReceive { paths }) => {
+            if !run_pre_receive(paths) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Run { pty, command }) => {
+            match child::run(command, *pty, &mut io::stdout(), &mut io::stderr()) {
+                Ok(code) => std::process::exit(code),
+                Err(e) => {
+                    eprintln!("nomoji run: {}: {}", command[0], e);
+                    // The codes a shell uses for a command it can't find or
+                    // can't execute.
+                    let code = match e.kind() {
+                        io::ErrorKind::NotFound => 127,
+                        _ => 126,
+                    };
+                    std::process::exit(code);
+                }
+            }
+        }
+        Some(Command::Selftest) => {
+            if !run_selftest(Palette::new(args.color, io::stderr().is_terminal())) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Restore { audit }) => {
+            match run_restore(audit, Palette::new(args.color, io::stderr().is_terminal())) {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("nomoji restore: {}", e);
+                    std::process::exit(2);
+                }
+            }
+            return;
+        }
+        Some(Command::Stats {
+            export,
+            recursive,
+            files,
+        }) => {
+            match run_stats(export, *recursive, files) {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("nomoji stats: {}", e);
+                    std::process::exit(2);
+                }
+            }
+            return;
+        }
+        Some(Command::ReportDiff { old, new }) => {
+            match run_report_diff(old, new, args.color) {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("nomoji report-diff: {}", e);
+                    std::process::exit(2);
+                }
+            }
+            return;
+        }
+        Some(Command::SelfUpdate { check, curl }) => {
+            if let Err(e) = run_self_update(*check, curl) {
+                eprintln!("nomoji self-update: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Db { .. } | Command::Info { .. }) | None => {}
+    }
+
+    if let Err(e) = args.load_config() {
+        eprintln!("nomoji: config error: {}", e);
+        std::process::exit(2);
+    }
+
+    if let Err(e) = args.load_manifest() {
+        eprintln!("nomoji: manifest error: {}", e);
+        std::process::exit(2);
+    }
+
+    match args.expand_recursive() {
+        Ok(true) => {
+            eprintln!("nomoji: --recursive without --write only reports; nothing will be changed")
+        }
+        Ok(false) => {}
+        Err(e) => {
+            eprintln!("nomoji: cannot read directory {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(sample) = args.sample {
+        let seed = args.sample_seed.unwrap_or_else(sample::random_seed);
+        let size = sample.size(args.files.len());
+        args.population = Some(args.files.len());
+        args.files = sample::pick(&args.files, size, seed);
+        // Only the totals are estimated, so nothing is written.
+        args.dry_run = true;
+    }
+
+    // With --staged, the files given only narrow down the staged paths.
+    let staged_changes = match args.staged {
+        true => staged::changes(Path::new("."), &args.files).unwrap_or_else(|e| {
+            eprintln!("nomoji: --staged: {}", e);
+            std::process::exit(2);
+        }),
+        false => Vec::new(),
+    };
+    let inputs: Vec<String> = args
+        .files
+        .iter()
+        .chain(&args.stdin_filename)
+        .cloned()
+        .chain(staged_changes.iter().map(|change| change.path.clone()))
+        .collect();
+    if let Err(e) = args.load_local_configs(&inputs) {
+        eprintln!("nomoji: config error: {}", e);
+        std::process::exit(2);
+    }
+
+    if let Some(Command::Db {
+        command:
+            DbCommand::Clean {
+                database,
+                table,
+                columns,
+                dry_run,
+                sqlite3,
+            },
+    }) = &args.command
+    {
+        if let Err(e) = run_db_clean(&args, database, table, columns, *dry_run, sqlite3) {
+            eprintln!("nomoji db: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(Command::Info { sequences, file }) = &args.command {
+        let cleaner = args.cleaner(file.as_deref().unwrap_or("-"));
+        let paint = Palette::new(args.color, io::stdout().is_terminal());
+        if let Err(e) = run_info(sequences, &cleaner, paint, &mut io::stdout().lock()) {
+            eprintln!("nomoji info: failed to write to stdout: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.rpc {
+        if let Some(addr) = &args.metrics_addr
+            && let Err(e) = metrics::serve(addr, &metrics::METRICS)
+        {
+            log::error(
+                args.log,
+                &format!("cannot serve metrics on {}: {}", addr, e),
+            );
+            std::process::exit(2);
+        }
+        if let Err(e) = rpc::serve(&args, io::stdin().lock(), io::stdout().lock()) {
+            log::error(args.log, &format!("rpc: {}", e));
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.audit.is_some() && !args.writes_files() && args.suffix.is_none() {
+        eprintln!(
+            "nomoji: --audit records changes to files, so it needs --inplace, --backup, \
+             --backup-dir, --trash or --suffix"
+        );
+        std::process::exit(2);
+    }
+
+    if args.tar_filter && args.report_destination().is_stdout() {
+        eprintln!(
+            "nomoji: --tar-filter writes the archive to stdout; send the report elsewhere with --report-to"
+        );
+        std::process::exit(2);
+    }
+
+    if let Some(dir) = &args.temp_dir
+        && !dir.is_dir()
+    {
+        eprintln!("nomoji: --temp-dir {}: not a directory", dir.display());
+        std::process::exit(2);
+    }
+
+    // Open the report destination up front so a bad one fails before any
+    // file is touched.
+    args.palette = Palette::new(args.color, args.report_destination().is_terminal());
+    let mut report_out = match args.report_destination().open() {
+        Ok(out) => out,
+        Err(e) => {
+            eprintln!("nomoji: cannot open report destination: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    // The framework passes batches of staged files; with none there is
+    // nothing to check, and waiting on stdin would hang the commit.
+    if args.pre_commit && args.files.is_empty() && args.files_from.is_none() && !args.staged {
+        return;
+    }
+
+    // Without an edit to make, the index is only checked.
+    if args.staged && !args.writes_files() {
+        args.dry_run = true;
+    }
+
+    if args.tar_filter {
+        let results = match process_tar(&args) {
+            Ok(results) => results,
+            Err(e) => {
+                eprintln!("nomoji: --tar-filter: {}", e);
+                std::process::exit(1);
+            }
+        };
+        if let Err(e) = write_report(&results, &args, &mut report_out) {
+            eprintln!("Error writing report: {}", e);
+            std::process::exit(1);
+        }
+        write_github_summary(&results, &args);
+        if results.iter().any(|r| !r.success) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // If no files specified or "-" is used, read from stdin
+    let only_stdin = args.files.len() == 1 && args.files[0] == "-";
+    if ((args.files.is_empty() && args.files_from.is_none()) || only_stdin) && !args.staged {
+        if let Some(separator) = &args.split_documents {
+            let results = match process_documents(&args, separator) {
+                Ok(results) => results,
+                Err(e) => {
+                    eprintln!("Error reading from stdin: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            if let Err(e) = write_report(&results, &args, &mut report_out) {
+                eprintln!("Error writing report: {}", e);
+                std::process::exit(1);
+            }
+            write_github_summary(&results, &args);
+            if results.iter().any(|r| !r.success) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        match process_stdin(&args) {
+            Ok(result) => {
+                let written = match args.format {
+                    Format::Text => print_stdin_report(&result, &args, &mut report_out),
+                    _ => write_report(std::slice::from_ref(&result), &args, &mut report_out),
+                };
+                if let Err(e) = written {
+                    eprintln!("Error writing report: {}", e);
+                    std::process::exit(1);
+                }
+                write_github_summary(std::slice::from_ref(&result), &args);
+            }
+            Err(e) => {
+                eprintln!("Error reading from stdin: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    interrupt::install();
+    let (results, limit) = match args.staged {
+        true => (
+            process_staged(&args, &staged_changes, || interrupt::requested().is_some()),
+            None,
+        ),
+        false => process_files(&args, || interrupt::requested().is_some()),
+    };
+
+    let reported = match args.pre_commit || args.commit_msg {
+        true => print_pre_commit(&results, &args, &mut report_out),
+        false => write_report(&results, &args, &mut report_out),
+    };
+    if let Err(e) = reported {
+        eprintln!("Error writing report: {}", e);
+        std::process::exit(1);
+    }
+    write_github_summary(&results, &args);
+
+    if let Some(ref path) = args.audit
+        && let Err(e) = audit::write(path, results.iter().filter_map(|r| r.audit.as_ref()))
+    {
+        eprintln!("nomoji: cannot write audit file {}: {}", path.display(), e);
+        std::process::exit(1);
+    }
+
+    if let Some(signal) = interrupt::requested() {
+        eprintln!(
+            "nomoji: interrupted after {} of {} files; the rest were not touched",
+            results.len(),
+            args.files.len()
+        );
+        std::process::exit(interrupt::exit_code(signal));
+    }
+
+    if let Some(limit) = limit {
+        eprintln!(
+            "nomoji: stopped after {} of {} files ({}); the rest were not touched",
+            results.len(),
+            args.files.len(),
+            limit
+        );
+        std::process::exit(limits::EXIT_CODE);
+    }
+
+    // Exit with error code if any file failed
+    let failures = results.iter().filter(|r| !r.success).count();
+    if failures > 0 {
+        let total = match args.staged {
+            true => staged_changes.len(),
+            false => args.files.len(),
+        };
+        if args.fail_fast && results.len() < total {
+            eprintln!(
+                "nomoji: stopped at the first failure after {} of {} files (--fail-fast); \
+                 the rest were not touched",
+                results.len(),
+                total
+            );
+        }
+        std::process::exit(1);
+    }
+    // A hook that changed files fails, so the fixes are staged and reviewed
+    // before the commit goes ahead. A check of the index that would change
+    // some fails the same way.
+    let checked = args.staged && args.dry_run;
+    if (args.pre_commit || checked) && results.iter().any(ProcessResult::changed) {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nomoji::is_emoji;
+    use std::io::Write;
+    use std::time::Duration;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_remove_emojis_basic() {
+        let input = "Hello 😀 World 🌍!";
+        let (result, count) = remove_emojis(input);
+        assert_eq!(result, "Hello  World !");
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_no_emojis() {
+        let input = "Hello World!";
+        let (result, count) = remove_emojis(input);
+        assert_eq!(result, "Hello World!");
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_unicode_preserved() {
+        let input = "Café résumé naïve 日本語";
+        let (result, count) = remove_emojis(input);
+        assert_eq!(result, "Café résumé naïve 日本語");
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_mixed_content() {
+        let input = "Test 🚀 rocket emoji 🔥 fire emoji";
+        let (result, count) = remove_emojis(input);
+        assert_eq!(result, "Test  rocket emoji  fire emoji");
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_empty_string() {
+        let (result, count) = remove_emojis("");
+        assert_eq!(result, "");
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_only_emojis() {
+        let input = "😀🎉🚀🌍🔥";
+        let (result, count) = remove_emojis(input);
+        assert_eq!(result, "");
+        assert_eq!(count, 5);
+    }
+
+    #[test]
+    fn test_emoticons_range() {
+        let input = "Faces: 😀😃😄😁😆😅😂🤣😊😇🙂🙃😉😌😍🥰😘😗😙😚😋😛😝😜🤪🤨🧐🤓😎🥸🤩🥳😏😒😞😔😟😕🙁☹️😣😖😫😩🥺😢😭😤😠😡🤬🤯😳🥵🥶😱😨😰😥😓🤗🤔🤭🤫🤥😶😐😑😬🙄😯😦😧😮😲🥱😴🤤😪😵🤐🥴🤢🤮🤧😷🤒🤕🤑🤠😈👿👹👺🤡💩👻💀☠️👽👾🤖🎃😺😸😹😻😼😽🙀😿😾";
+        let (result, count) = remove_emojis(input);
+        assert!(count > 50);
+        assert!(!result.contains("😀"));
+    }
+
+    #[test]
+    fn test_flags() {
+        let input = "Flags: 🇺🇸🇬🇧🇯🇵🇫🇷🇩🇪";
+        let (result, count) = remove_emojis(input);
+        assert_eq!(count, 10);
+        assert_eq!(result, "Flags: ");
+    }
+
+    #[test]
+    fn test_skin_tone_modifiers() {
+        let input = "People: 👋🏻👋🏼👋🏽👋🏾👋🏿";
+        let (result, count) = remove_emojis(input);
+        assert_eq!(count, 10);
+        assert_eq!(result, "People: ");
+    }
+
+    #[test]
+    fn test_symbols_and_pictographs() {
+        let input = "Symbols: ♥️♦️♠️♣️💯💢💥💫💦💨🕳️💣💬👁️‍🗨️🗨️🗯️💭💤";
+        let (result, count) = remove_emojis(input);
+        assert!(count > 10);
+        assert!(!result.contains("♥️"));
+    }
+
+    #[test]
+    fn test_variation_selectors() {
+        let input = "Text vs Emoji: #️⃣ *️⃣ 0️⃣ 1️⃣ 2️⃣";
+        let (_result, count) = remove_emojis(input);
+        assert!(count >= 5);
+    }
+
+    #[test]
+    fn test_geometric_shapes() {
+        let input = "Shapes: 🔴🔵⚪⚫🟥🟦🟧🟨🟩🟪⬛⬜◼️◻️🔶🔷🔸🔹";
+        let (_result, count) = remove_emojis(input);
+        assert!(count >= 10);
+    }
+
+    #[test]
+    fn test_travel_and_places() {
+        let input = "Travel: 🚗🚕🚙🚌🚎🏎️🚓🚑🚒🚐🛻🚚🚛🚜🦯🦽🦼🛴🚲🛵🏍️🛺🚨🚔🚍🚘🚖🚡🚠🚟🚃🚋🚞🚝🚄🚅🚈🚂🚆🚇🚊🚉✈️🛫🛬🛩️💺🛰️🚀🛸🚁🛶⛵🚤🛥️🛳️⛴️🚢⚓⛽🚧🚦🚥🚏🗺️🗿🗽🗼🏰🏯🏟️🎡🎢🎠⛲⛱️🏖️🏝️🏜️🌋⛰️🏔️🗻🏕️⛺🏠🏡🏘️🏚️🏗️🏭🏢🏬🏣🏤🏥🏦🏨🏪🏫🏩💒🏛️⛪🕌🕍🛕🕋⛩️🛤️🛣️🗾🎑🏞️🌅🌄🌠🎇🎆🌇🌆🏙️🌃🌌🌉🌁";
+        let (_result, count) = remove_emojis(input);
+        assert!(count > 50);
+    }
+
+    #[test]
+    fn test_food_and_drink() {
+        let input = "Food: 🍏🍎🍐🍊🍋🍌🍉🍇🍓🫐🍈🍒🍑🍍🥝🥑🍆🥔🥕🌽🌶️🫑🥒🥬🥦🧄🧅🍄🥜🌰🍞🥐🥖🥨🥯🥞🧇🧀🍖🍗🥩🥓🍔🍟🍕🌭🥪🌮🌯🫔🥙🧆🥚🍳🥘🍲🫕🥣🥗🍿🧈🧂🥫🍱🍘🍙🍚🍛🍜🍝🍠🍢🍣🍤🍥🥮🍡🥟🥠🥡🦀🦞🦐🦑🦪🍦🍧🍨🍩🍪🎂🍰🧁🥧🍫🍬🍭🍮🍯🍼🥛☕🫖🍵🍶🍾🍷🍸🍹🍺🍻🥂🥃🫗🥤🧋🧃🧉🧊";
+        let (_result, count) = remove_emojis(input);
+        assert!(count > 50);
+    }
+
+    #[test]
+    fn test_activities() {
+        let input = "Activities: ⚽🏀🏈⚾🥎🎾🏐🏉🥏🎱🪀🏓🏸🏒🏑🥍🏏🥅⛳🪁🏹🎣🤿🥊🥋🎽🛹🛼🛷⛸️🥌🎿⛷️🏂🪂🏋️‍♀️🏋️🏋️‍♂️🤼‍♀️🤼🤼‍♂️🤽‍♀️🤽🤽‍♂️🤾‍♀️🤾🤾‍♂️🌊🚣‍♀️🚣🚣‍♂️🧗‍♀️🧗🧗‍♂️🚵‍♀️🚵🚵‍♂️🚴‍♀️🚴🚴‍♂️🏆🥇🥈🥉🏅🎖️🏵️🎗️🎫🎟️🎪🤹‍♀️🤹🤹‍♂️🎭🩰🎨🎬🎤🎧🎼🎹🥁🪘🎷🎺🪗🎸🪕🎻🎲♟️🎯🎳🎮🎰🧩";
+        let (_result, count) = remove_emojis(input);
+        assert!(count > 50);
+    }
+
+    #[test]
+    fn test_objects() {
+        let input = "Objects: 👓🕶️🥽🥼🦺👔👕👖🧣🧤🧥🧦👗👘🥻🩱🩲🩳👙👚👛👜👝🛍️🎒🩴👞👟🥾🥿👠👡🩰👢👑👒🎩🎓🧢🪖⛑️📿💄💍💎🔇🔈🔉🔊📢📣📯🔔🔕🎼🎵🎶🎙️🎚️🎛️🎤🎧📻🎷🎸🎹🎺🎻🪕🥁🪘📱📲☎️📞📟📠🔋🔌💻🖥️🖨️⌨️🖱️🖲️💽💾💿📀🧮🎥🎞️📽️🎬📺📷📸📹📼🔍🔎🕯️💡🔦🏮🪔📔📕📖📗📘📙📚📓📒📃📜📄📰🗞️📑🔖🏷️💰🪙💴💵💶💷💸💳🧾💹✉️📧📨📩📤📥📦📫📪📬📭📮🗳️✏️✒️🖋️🖊️🖌️🖍️📝💼📁📂🗂️📅📆🗒️🗓️📇📈📉📊📋📌📍📎🖇️📏📐✂️🗃️🗄️🗑️🔒🔓🔏🔐🔑🗝️🔨🪓⛏️⚒️🛠️🗡️⚔️🔫🪃🏹🛡️🪚🔧🪛🔩⚙️🗜️⚖️🦯🔗⛓️🪝🧰🧲🪜⚗️🧪🧫🧬🔬🔭📡💉🩸💊🩹🩺🌡️🚽🚰🚿🛁🛀🧴🧵🧶🪡🧷🎽🥽🥼🦺";
+        let (_result, count) = remove_emojis(input);
+        assert!(count > 50);
+    }
+
+    #[test]
+    fn test_newline_and_whitespace_preserved() {
+        let input = "Line 1 😀\nLine 2 🌍\n\nLine 4 🔥";
+        let (result, count) = remove_emojis(input);
+        assert_eq!(result, "Line 1 \nLine 2 \n\nLine 4 ");
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_copyright_and_trademark() {
+        let input = "Legal: © ® ™";
+        let (result, count) = remove_emojis(input);
+        assert_eq!(count, 3);
+        assert_eq!(result, "Legal:   ");
+    }
+
+    #[test]
+    fn test_is_emoji_individual() {
+        assert!(is_emoji('😀'));
+        assert!(is_emoji('🚀'));
+        assert!(is_emoji('🌍'));
+        assert!(!is_emoji('a'));
+        assert!(!is_emoji('A'));
+        assert!(!is_emoji('1'));
+        assert!(!is_emoji('é'));
+        assert!(!is_emoji('日'));
+    }
+
+    #[test]
+    fn test_process_file_with_temp_file() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(temp_file, "Hello 😀 World 🌍!").unwrap();
+        let path = temp_file.path().to_str().unwrap();
+
+        let args = Args {
+            files: vec![path.to_string()],
+            backup: false,
+            inplace: true,
+            dry_run: false,
+            ..Default::default()
+        };
+
+        let result = process_file(path, &args);
+        assert!(result.success);
+        assert_eq!(result.emojis_found, 2);
+
+        let content = fs::read_to_string(path).unwrap();
+        assert_eq!(content.trim(), "Hello  World !");
+    }
+
+    #[test]
+    fn test_process_file_dry_run() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(temp_file, "Test 🚀 content").unwrap();
+        let path = temp_file.path().to_str().unwrap();
+
+        let args = Args {
+            files: vec![path.to_string()],
+            backup: false,
+            inplace: false,
+            dry_run: true,
+            ..Default::default()
+        };
+
+        let result = process_file(path, &args);
+        assert!(result.success);
+        assert_eq!(result.emojis_found, 1);
+        assert_eq!(result.bytes_saved, "🚀".len());
+
+        let content = fs::read_to_string(path).unwrap();
+        assert!(content.contains("🚀"));
+    }
+
+    #[test]
+    fn test_bytes_saved_counts_multibyte_sequences() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "Flags 🇺🇸 and ©").unwrap();
+        let path = temp_file.path().to_str().unwrap();
+
+        let args = Args::parse_from(["nomoji", "--dry-run", path]);
+        let result = process_file(path, &args);
+        assert_eq!(result.bytes_saved, 8 + 2);
+        print_report(&[result], &args, &mut io::sink()).unwrap();
+    }
+
+    #[test]
+    fn test_process_file_backup() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(temp_file, "Backup test 🔥").unwrap();
+        let path = temp_file.path().to_str().unwrap();
+        let backup_path = format!("{}.bak", path);
+
+        let args = Args {
+            files: vec![path.to_string()],
+            backup: true,
+            inplace: false,
+            dry_run: false,
+            ..Default::default()
+        };
+
+        let result = process_file(path, &args);
+        assert!(result.success);
+
+        assert!(fs::metadata(&backup_path).is_ok());
+        let backup_content = fs::read_to_string(&backup_path).unwrap();
+        assert!(backup_content.contains("🔥"));
+
+        fs::remove_file(&backup_path).ok();
+    }
+
+    #[test]
+    fn test_process_file_skips_unchanged_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clean.txt");
+        fs::write(&path, "Nothing to see here\n").unwrap();
+        let past = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(past)
+            .unwrap();
+        let path = path.to_str().unwrap();
+
+        let args = Args::parse_from(["nomoji", "-b", "-i", path]);
+        let result = process_file(path, &args);
+        assert!(result.success);
+        assert_eq!(result.emojis_found, 0);
+        assert_eq!(fs::metadata(path).unwrap().modified().unwrap(), past);
+        assert!(fs::metadata(format!("{}.bak", path)).is_err());
+
+        // Transcoding changes the bytes even without emojis.
+        let args = Args::parse_from(["nomoji", "-i", "--output-encoding", "utf-16le", path]);
+        assert!(process_file(path, &args).success);
+        assert_ne!(fs::metadata(path).unwrap().modified().unwrap(), past);
+    }
+
+    #[test]
+    fn test_process_file_backup_dir() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let backup_dir = tempfile::tempdir().unwrap();
+        let path = source_dir.path().join("notes.txt");
+        fs::write(&path, "Backup dir test 🔥").unwrap();
+        let path = path.to_str().unwrap();
+
+        let args = Args {
+            files: vec![path.to_string()],
+            backup_dir: Some(backup_dir.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        let result = process_file(path, &args);
+        assert!(result.success);
+        assert!(!Path::new(&format!("{}.bak", path)).exists());
+
+        let mirrored = backup_dir
+            .path()
+            .join(mirrored_path(Path::new(path)).unwrap());
+        assert!(mirrored.ends_with("notes.txt"));
+        assert_eq!(fs::read_to_string(mirrored).unwrap(), "Backup dir test 🔥");
+        assert_eq!(fs::read_to_string(path).unwrap(), "Backup dir test ");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_inplace_edits_through_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let real = dir.path().join("real.txt");
+        let link = dir.path().join("link.txt");
+        fs::write(&real, "linked 🔗").unwrap();
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+        let link = link.to_str().unwrap();
+
+        let args = Args::parse_from(["nomoji", "-i", link]);
+        assert!(process_file(link, &args).success);
+        assert!(fs::symlink_metadata(link).unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(&real).unwrap(), "linked ");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_inplace_edits_keep_hard_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("notes.txt");
+        let other = dir.path().join("other-name.txt");
+        fs::write(&file, "linked 🔗").unwrap();
+        fs::hard_link(&file, &other).unwrap();
+        let file = file.to_str().unwrap();
+
+        let args = Args::parse_from(["nomoji", "-i", file]);
+        assert!(process_file(file, &args).success);
+        assert_eq!(fs::read_to_string(&other).unwrap(), "linked ");
+
+        fs::write(file, "broken 🔗").unwrap();
+        let args = Args::parse_from(["nomoji", "-i", "--break-links", file]);
+        assert!(process_file(file, &args).success);
+        assert_eq!(fs::read_to_string(file).unwrap(), "broken ");
+        assert_eq!(fs::read_to_string(&other).unwrap(), "broken 🔗");
+    }
+
+    #[test]
+    fn test_mirrored_path_relative() {
+        let mirrored = mirrored_path(Path::new("./docs/guide.md")).unwrap();
+        assert_eq!(mirrored, PathBuf::from("docs/guide.md"));
+    }
+
+    #[test]
+    fn test_process_file_verify() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(temp_file, "Verify 🔥 me").unwrap();
+        let path = temp_file.path().to_str().unwrap();
+
+        let args = Args {
+            files: vec![path.to_string()],
+            inplace: true,
+            verify: true,
+            ..Default::default()
+        };
+
+        let result = process_file(path, &args);
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(fs::read_to_string(path).unwrap(), "Verify  me\n");
+    }
+
+    #[test]
+    fn test_verify_written_detects_mismatch() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "Truncated").unwrap();
+        let path = temp_file.path().to_str().unwrap();
+
+        let error = verify_written(
+            path,
+            "Truncated text",
+            Encoding::Utf8,
+            Some(&Cleaner::default()),
+            0,
+        )
+        .unwrap_err();
+        assert!(error.contains("expected 14 bytes, found 9"));
 
-#[derive(Debug)]
-struct ProcessResult {
-    file: String,
-    emojis_found: usize,
-    success: bool,
-    error: Option<String>,
-}
+        let error = verify_written(
+            path,
+            "Truncatid",
+            Encoding::Utf8,
+            Some(&Cleaner::default()),
+            0,
+        )
+        .unwrap_err();
+        assert!(error.contains("differs at byte 7"));
 
-fn is_emoji(c: char) -> bool {
-    // Emoji ranges based on Unicode standard
-    let code = c as u32;
-
-    // Miscellaneous Symbols and Pictographs
-    (0x1F300..=0x1F5FF).contains(&code)
-        // Supplemental Symbols and Pictographs
-        || (0x1F900..=0x1F9FF).contains(&code)
-        // Emoticons
-        || (0x1F600..=0x1F64F).contains(&code)
-        // Transport and Map Symbols
-        || (0x1F680..=0x1F6FF).contains(&code)
-        // Miscellaneous Symbols
-        || (0x2600..=0x26FF).contains(&code)
-        // Dingbats
-        || (0x2700..=0x27BF).contains(&code)
-        // Enclosed Alphanumeric Supplement
-        || (0x1F100..=0x1F1FF).contains(&code)
-        // Enclosed Ideographic Supplement
-        || (0x1F200..=0x1F2FF).contains(&code)
-        // Geometric Shapes Extended
-        || (0x1F780..=0x1F7FF).contains(&code)
-        // Symbols and Pictographs Extended-A
-        || (0x1FA00..=0x1FA6F).contains(&code)
-        // Symbols and Pictographs Extended-B
-        || (0x1FA70..=0x1FAFF).contains(&code)
-        // Flags (regional indicators)
-        || (0x1F1E6..=0x1F1FF).contains(&code)
-        // Keycap sequences
-        || code == 0x20E3
-        // Zero Width Joiner for emoji sequences
-        || code == 0x200D
-        // Variation Selectors
-        || (0xFE00..=0xFE0F).contains(&code)
-        // Emoji modifier fitzpatrick
-        || (0x1F3FB..=0x1F3FF).contains(&code)
-        // Some additional symbols commonly used as emoji
-        || matches!(code, 0x231A..=0x231B | 0x23E9..=0x23EC | 0x23F0 | 0x23F3 
-            | 0x25FD..=0x25FE | 0x2614..=0x2615 | 0x2648..=0x2653 | 0x267F 
-            | 0x2693 | 0x26A1 | 0x26AA..=0x26AB | 0x26BD..=0x26BE | 0x26C4..=0x26C5 
-            | 0x26CE | 0x26D4 | 0x26EA | 0x26F2..=0x26F3 | 0x26F5 | 0x26FA 
-            | 0x26FD | 0x2705 | 0x2728 | 0x274C | 0x274E | 0x2753..=0x2755 
-            | 0x2795..=0x2797 | 0x27B0 | 0x27BF | 0x2B50 | 0x2B55 | 0x00A9 | 0x00AE 
-            | 0x2122 | 0x3030 | 0x303D)
-}
-
-fn remove_emojis(input: &str) -> (String, usize) {
-    let mut result = String::with_capacity(input.len());
-    let mut count = 0;
-
-    for c in input.chars() {
-        if is_emoji(c) {
-            count += 1;
-        } else {
-            result.push(c);
-        }
+        assert!(
+            verify_written(
+                path,
+                "Truncated",
+                Encoding::Utf8,
+                Some(&Cleaner::default()),
+                0
+            )
+            .is_ok()
+        );
     }
 
-    (result, count)
-}
+    #[test]
+    fn test_process_file_transcodes_utf16_to_utf8() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file
+            .write_all(&Encoding::Utf16Le.encode("Export 🚀 café\r\n").unwrap())
+            .unwrap();
+        let path = temp_file.path().to_str().unwrap();
 
-fn read_input<P: AsRef<Path>>(path: P) -> io::Result<String> {
-    fs::read_to_string(path)
-}
+        let args = Args::parse_from(["nomoji", "-i", "--verify", path]);
+        let result = process_file(path, &args);
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(
+            fs::read(path).unwrap(),
+            Encoding::Utf16Le.encode("Export  café\r\n").unwrap()
+        );
 
-fn write_output<P: AsRef<Path>>(path: P, content: &str) -> io::Result<()> {
-    fs::write(path, content)
-}
+        let args = Args::parse_from(["nomoji", "-i", "--output-encoding", "utf-8", path]);
+        assert!(process_file(path, &args).success);
+        assert_eq!(fs::read(path).unwrap(), "Export  café\r\n".as_bytes());
 
-fn process_file(file: &str, args: &Args) -> ProcessResult {
-    let result = match read_input(file) {
-        Ok(content) => {
-            let (cleaned, emoji_count) = remove_emojis(&content);
-
-            if args.dry_run {
-                ProcessResult {
-                    file: file.to_string(),
-                    emojis_found: emoji_count,
-                    success: true,
-                    error: None,
-                }
-            } else {
-                let write_result = if args.backup {
-                    let backup_path = format!("{}.bak", file);
-                    if let Err(e) = fs::copy(file, &backup_path) {
-                        ProcessResult {
-                            file: file.to_string(),
-                            emojis_found: emoji_count,
-                            success: false,
-                            error: Some(format!("Failed to create backup: {}", e)),
-                        }
-                    } else {
-                        write_output(file, &cleaned)
-                            .map(|_| ProcessResult {
-                                file: file.to_string(),
-                                emojis_found: emoji_count,
-                                success: true,
-                                error: None,
-                            })
-                            .unwrap_or_else(|e| ProcessResult {
-                                file: file.to_string(),
-                                emojis_found: emoji_count,
-                                success: false,
-                                error: Some(format!("Failed to write file: {}", e)),
-                            })
-                    }
-                } else if args.inplace {
-                    write_output(file, &cleaned)
-                        .map(|_| ProcessResult {
-                            file: file.to_string(),
-                            emojis_found: emoji_count,
-                            success: true,
-                            error: None,
-                        })
-                        .unwrap_or_else(|e| ProcessResult {
-                            file: file.to_string(),
-                            emojis_found: emoji_count,
-                            success: false,
-                            error: Some(format!("Failed to write file: {}", e)),
-                        })
-                } else {
-                    // Output to stdout
-                    if let Err(e) = io::stdout().write_all(cleaned.as_bytes()) {
-                        ProcessResult {
-                            file: file.to_string(),
-                            emojis_found: emoji_count,
-                            success: false,
-                            error: Some(format!("Failed to write to stdout: {}", e)),
-                        }
-                    } else {
-                        ProcessResult {
-                            file: file.to_string(),
-                            emojis_found: emoji_count,
-                            success: true,
-                            error: None,
-                        }
-                    }
-                };
+        let args = Args::parse_from(["nomoji", "-i", "--output-encoding", "latin1", path]);
+        assert!(process_file(path, &args).success);
+        assert_eq!(fs::read(path).unwrap(), b"Export  caf\xE9\r\n");
+    }
 
-                write_result
-            }
-        }
-        Err(e) => ProcessResult {
-            file: file.to_string(),
-            emojis_found: 0,
-            success: false,
-            error: Some(format!("Failed to read file: {}", e)),
-        },
-    };
+    #[test]
+    fn test_process_file_normalize() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "Cafe\u{301} 🚀 re\u{301}sume\u{301}").unwrap();
+        let path = temp_file.path().to_str().unwrap();
 
-    result
-}
+        let args = Args::parse_from(["nomoji", "-i", "--verify", "--normalize", "nfc", path]);
+        let result = process_file(path, &args);
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.bytes_saved, "🚀".len());
+        assert_eq!(fs::read_to_string(path).unwrap(), "Café  résumé");
 
-fn process_stdin() -> io::Result<usize> {
-    let mut buffer = String::new();
-    io::stdin().read_to_string(&mut buffer)?;
+        let args = Args::parse_from(["nomoji", "-i", "--normalize", "nfd", path]);
+        assert!(process_file(path, &args).success);
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "Cafe\u{301}  re\u{301}sume\u{301}"
+        );
+    }
 
-    let (cleaned, count) = remove_emojis(&buffer);
+    #[test]
+    fn test_process_file_strip_selectors() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "A\u{FE0F} ❤️ 👁️‍🗨️ 1\u{FE0E}").unwrap();
+        let path = temp_file.path().to_str().unwrap();
 
-    io::stdout().write_all(cleaned.as_bytes())?;
+        let args = Args::parse_from(["nomoji", "-i", "--verify", "--strip-selectors", path]);
+        let result = process_file(path, &args);
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.emojis_found, 5);
+        assert_eq!(fs::read_to_string(path).unwrap(), "A ❤ 👁‍🗨 1");
+    }
 
-    Ok(count)
-}
+    #[test]
+    fn test_strip_selectors_spans() {
+        let text = "x\u{FE0F}y\u{FE00}";
+        let cleaner = Cleaner {
+            selectors_only: true,
+            ..Default::default()
+        };
+        assert_eq!(cleaner.spans(text), [1..4, 5..8]);
+        assert_eq!(cleaner.count("❤\u{FE0F}"), 1);
+    }
 
-fn print_report(results: &[ProcessResult]) {
-    let total_files = results.len();
-    let successful = results.iter().filter(|r| r.success).count();
-    let total_emojis: usize = results.iter().map(|r| r.emojis_found).sum();
+    #[test]
+    fn test_keep_flags() {
+        let cleaner = Args::parse_from(["nomoji", "--keep-flags"]).cleaner("-");
+        let (result, count) = cleaner.remove("Locale: 🇫🇷 fr 🚀 en 🇬🇧🇺🇸 🇺 ✅");
+        assert_eq!(result, "Locale: 🇫🇷 fr  en 🇬🇧🇺🇸  ");
+        assert_eq!(count, 3);
+        assert_eq!(cleaner.count(&result), 0);
+    }
 
-    eprintln!("\n=== nomoji Report ===");
-    eprintln!("Files processed: {}", total_files);
-    eprintln!("Successful: {}", successful);
+    #[test]
+    fn test_conservative() {
+        let cleaner = Args::parse_from(["nomoji", "--conservative"]).cleaner("-");
+        let (result, count) = cleaner
+            .remove("\u{2600} sunny \u{2600}\u{FE0F}, x \u{2665} y, (c) \u{A9}, 1\u{20E3} 🚀");
+        assert_eq!(
+            result,
+            "\u{2600} sunny , x \u{2665} y, (c) \u{A9}, 1\u{20E3} "
+        );
+        assert_eq!(count, 3);
+        assert_eq!(cleaner.count(&result), 0);
+    }
 
-    if total_files != successful {
-        eprintln!("Failed: {}", total_files - successful);
+    #[test]
+    fn test_aggressive() {
+        let cleaner = Args::parse_from(["nomoji", "--aggressive"]).cleaner("-");
+        let (result, count) = cleaner.remove(
+            "\u{203C} Note \u{2139}: press \u{23CF} or \u{25B6}\u{FE0E} \u{2B1B} 1\u{20E3} -> 🚀",
+        );
+        assert_eq!(result, " Note : press  or   1 -> ");
+        assert_eq!(count, 8);
     }
 
-    eprintln!("Total emojis found: {}", total_emojis);
+    #[test]
+    fn test_exclude_range() {
+        let args = Args::parse_from([
+            "nomoji",
+            "--exclude-range",
+            "U+2600..U+26FF",
+            "--exclude-range",
+            "U+2705",
+            "-",
+        ]);
+        let (result, count) = args
+            .cleaner("-")
+            .remove("\u{2600}\u{FE0F} \u{26A0} \u{2705} \u{274C} 🚀");
+        assert_eq!(result, "\u{2600}\u{FE0F} \u{26A0} \u{2705}  ");
+        assert_eq!(count, 2);
 
-    if !results.is_empty() {
-        eprintln!("\nPer-file results:");
-        for result in results {
-            if let Some(ref error) = result.error {
-                eprintln!(
-                    "  {}: {} emojis - ERROR: {}",
-                    result.file, result.emojis_found, error
-                );
-            } else {
-                eprintln!("  {}: {} emojis removed", result.file, result.emojis_found);
-            }
-        }
+        assert!(
+            Args::try_parse_from(["nomoji", "--exclude-range", "U+26FF..U+2600", "-"]).is_err()
+        );
     }
-}
 
-fn main() {
-    let args = Args::parse();
+    #[test]
+    fn test_process_file_keep_flags() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "de: 🇩🇪 🎉").unwrap();
+        let path = temp_file.path().to_str().unwrap();
 
-    // If no files specified or "-" is used, read from stdin
-    if args.files.is_empty() || (args.files.len() == 1 && args.files[0] == "-") {
-        match process_stdin() {
-            Ok(count) => {
-                eprintln!("\n=== nomoji Report ===");
-                eprintln!("Emojis removed from stdin: {}", count);
-            }
-            Err(e) => {
-                eprintln!("Error reading from stdin: {}", e);
-                std::process::exit(1);
-            }
-        }
-        return;
+        let args = Args::parse_from(["nomoji", "-i", "--verify", "--keep-flags", path]);
+        let result = process_file(path, &args);
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.emojis_found, 1);
+        assert_eq!(fs::read_to_string(path).unwrap(), "de: 🇩🇪 ");
     }
 
-    let mut results = Vec::new();
+    #[test]
+    fn test_category_precedence() {
+        let text = "✅ ❌ ➡ 🚀 🇫🇷";
+        let cleaner = |argv: &[&str], keep: &[Category]| {
+            let mut args = Args::parse_from(["nomoji", "-"].iter().chain(argv));
+            args.settings.keep = keep.to_vec();
+            args.cleaner("-").remove(text).0
+        };
 
-    for file in &args.files {
-        let result = process_file(file, &args);
-        results.push(result);
+        // Config keep alone: keep those categories, remove the rest.
+        assert_eq!(
+            cleaner(&[], &[Category::CheckMarks, Category::Arrows]),
+            "✅ ❌ ➡  "
+        );
+        // --only narrows removal; everything else stays.
+        assert_eq!(cleaner(&["--only", "transport"], &[]), "✅ ❌ ➡  🇫🇷");
+        // --only naming a kept category overrides the config for it only.
+        assert_eq!(
+            cleaner(
+                &["--only", "check-marks,arrows"],
+                &[Category::CheckMarks, Category::Flags]
+            ),
+            "   🚀 🇫🇷"
+        );
+        // Config keep still applies inside a broader --only.
+        assert_eq!(
+            cleaner(&["--only", "dingbats"], &[Category::CheckMarks]),
+            "✅ ❌  🚀 🇫🇷"
+        );
+        // --skip wins over --only.
+        assert_eq!(
+            cleaner(&["--only", "dingbats", "--skip", "arrows"], &[]),
+            "  ➡ 🚀 🇫🇷"
+        );
     }
 
-    print_report(&results);
+    #[test]
+    fn test_load_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("nomoji.toml");
+        fs::write(&config, "keep = [\"check-marks\"]\n").unwrap();
+        let path = dir.path().join("status.md");
+        fs::write(&path, "build ✅ 🎉").unwrap();
+        let path = path.to_str().unwrap();
 
-    // Exit with error code if any file failed
-    let failures = results.iter().filter(|r| !r.success).count();
-    if failures > 0 {
-        std::process::exit(1);
+        let mut args =
+            Args::parse_from(["nomoji", "-i", "--config", config.to_str().unwrap(), path]);
+        args.load_config().unwrap();
+        let result = process_file(path, &args);
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.emojis_found, 1);
+        assert_eq!(fs::read_to_string(path).unwrap(), "build ✅ ");
+
+        fs::write(&config, "keep = [\"smileys\"]\n").unwrap();
+        let error = args.load_config().unwrap_err();
+        assert!(error.contains("unknown category 'smileys'"));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Write;
-    use tempfile::NamedTempFile;
+    #[test]
+    fn test_load_config_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("nomoji.toml");
+        fs::write(
+            &config,
+            "keep = [\"check-marks\"]\n\n[profiles.strict]\nkeep = []\naggressive = true\n",
+        )
+        .unwrap();
+        let config = config.to_str().unwrap();
+
+        let mut args = Args::parse_from(["nomoji", "--config", config, "--profile", "strict"]);
+        args.load_config().unwrap();
+        assert!(args.settings.keep.is_empty());
+        assert!(args.aggressive);
+
+        // The command line wins over the profile.
+        let mut args = Args::parse_from([
+            "nomoji",
+            "--config",
+            config,
+            "--profile",
+            "strict",
+            "--conservative",
+        ]);
+        args.load_config().unwrap();
+        assert!(args.conservative && !args.aggressive);
+
+        let mut args = Args::parse_from(["nomoji", "--config", config, "--profile", "docs"]);
+        let error = args.load_config().unwrap_err();
+        assert!(
+            error.ends_with("unknown profile 'docs' (defined: strict)"),
+            "{}",
+            error
+        );
+    }
 
     #[test]
-    fn test_remove_emojis_basic() {
-        let input = "Hello 😀 World 🌍!";
-        let (result, count) = remove_emojis(input);
-        assert_eq!(result, "Hello  World !");
-        assert_eq!(count, 2);
+    fn test_config_rules_per_file_type() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("nomoji.toml");
+        fs::write(
+            &config,
+            r#"
+[[rules]]
+glob = "*.md"
+mode = "markdown"
+
+[[rules]]
+glob = "*.py"
+mode = "comments"
+
+[[rules]]
+glob = "*.json"
+mode = "strings"
+"#,
+        )
+        .unwrap();
+
+        let files = [
+            (
+                "notes.md",
+                "Ship it 🚀 `🚀`
+",
+                "Ship it :rocket: `🚀`
+",
+            ),
+            (
+                "tool.py",
+                "x = \"🎉\"  # done 🎉\n",
+                "x = \"🎉\"  # done \n",
+            ),
+            ("data.json", r#"{"🔑": "ok ✅"}"#, r#"{"🔑": "ok "}"#),
+            ("plain.txt", "all 🎉 gone", "all  gone"),
+        ];
+        let paths: Vec<String> = files
+            .iter()
+            .map(|(name, content, _)| {
+                let path = dir.path().join(name);
+                fs::write(&path, content).unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect();
+
+        let mut args = Args::parse_from(
+            [
+                "nomoji",
+                "-i",
+                "--verify",
+                "--config",
+                config.to_str().unwrap(),
+            ]
+            .into_iter()
+            .chain(paths.iter().map(String::as_str)),
+        );
+        args.load_config().unwrap();
+
+        for (path, (name, _, expected)) in paths.iter().zip(files) {
+            let result = process_file(path, &args);
+            assert!(result.success, "{}: {:?}", name, result.error);
+            assert_eq!(fs::read_to_string(path).unwrap(), expected, "{}", name);
+        }
     }
 
     #[test]
-    fn test_no_emojis() {
-        let input = "Hello World!";
-        let (result, count) = remove_emojis(input);
-        assert_eq!(result, "Hello World!");
-        assert_eq!(count, 0);
+    fn test_manifest_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("inputs.toml");
+        fs::write(
+            &manifest,
+            "files = [\"a.md\", { path = \"b.md\", mode = \"markdown\", skip = [] }]\n",
+        )
+        .unwrap();
+
+        let mut args = Args::parse_from([
+            "nomoji",
+            "--skip",
+            "flags",
+            "--files-from",
+            manifest.to_str().unwrap(),
+            "c.md",
+        ]);
+        args.load_manifest().unwrap();
+        assert_eq!(args.files, ["c.md", "a.md", "b.md"]);
+
+        let a = args.cleaner("a.md");
+        assert_eq!((a.mode, a.skip), (Mode::All, vec![Category::Flags]));
+        let b = args.cleaner("b.md");
+        assert_eq!((b.mode, b.skip), (Mode::Markdown, vec![]));
     }
 
+    #[cfg(unix)]
     #[test]
-    fn test_unicode_preserved() {
-        let input = "Café résumé naïve 日本語";
-        let (result, count) = remove_emojis(input);
-        assert_eq!(result, "Café résumé naïve 日本語");
-        assert_eq!(count, 0);
+    fn test_process_file_filter_cmd() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "CI ✅ ok 🚀 ship 🎉").unwrap();
+        let path = temp_file.path().to_str().unwrap();
+
+        let command = r#"read -r o; case "$o" in *'"emoji":"✅"'*) echo keep;; *rocket*) echo replace [launch];; *) echo remove;; esac"#;
+        let args = Args::parse_from(["nomoji", "-i", "--verify", "--filter-cmd", command, path]);
+        let result = process_file(path, &args);
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.emojis_found, 2);
+        assert_eq!(fs::read_to_string(path).unwrap(), "CI ✅ ok [launch] ship ");
+
+        let args = Args::parse_from(["nomoji", "-i", "--filter-cmd", "echo nope", path]);
+        let result = process_file(path, &args);
+        assert!(!result.success);
+        assert!(
+            result
+                .error
+                .unwrap()
+                .contains("unexpected filter command output")
+        );
     }
 
+    #[cfg(feature = "wasm")]
     #[test]
-    fn test_mixed_content() {
-        let input = "Test 🚀 rocket emoji 🔥 fire emoji";
-        let (result, count) = remove_emojis(input);
-        assert_eq!(result, "Test  rocket emoji  fire emoji");
-        assert_eq!(count, 2);
+    fn test_process_file_plugin() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin = dir.path().join("keep-bmp.wasm");
+        fs::write(&plugin, plugin::tests::keep_bmp()).unwrap();
+        let plugin = plugin.to_str().unwrap();
+        let file = dir.path().join("notes.md");
+        fs::write(&file, "CI ✅\nship 🚀\n").unwrap();
+        let path = file.to_str().unwrap();
+
+        let args = Args::parse_from(["nomoji", "-i", "--verify", "--plugin", plugin, path]);
+        let result = process_file(path, &args);
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.emojis_found, 1);
+        assert_eq!(fs::read_to_string(path).unwrap(), "CI ✅\nship *\n");
+
+        let both = ["nomoji", "--plugin", plugin, "--filter-cmd", "true", path];
+        assert!(Args::try_parse_from(both).is_err());
+        let missing = ["nomoji", "--plugin", "missing.wasm", path];
+        assert!(Args::try_parse_from(missing).is_err());
     }
 
+    #[cfg(unix)]
     #[test]
-    fn test_empty_string() {
-        let (result, count) = remove_emojis("");
-        assert_eq!(result, "");
-        assert_eq!(count, 0);
+    fn test_process_file_fifo_streams() {
+        let dir = tempfile::tempdir().unwrap();
+        let fifo = dir.path().join("report.pipe");
+        let c_path = std::ffi::CString::new(fifo.to_str().unwrap()).unwrap();
+        // SAFETY: c_path is a valid NUL-terminated path.
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+        let path = fifo.to_str().unwrap();
+
+        let writer = {
+            let fifo = fifo.clone();
+            std::thread::spawn(move || fs::write(fifo, "one 🚀\ntwo\nthree 🎉🎉\n").unwrap())
+        };
+        let args = Args::parse_from(["nomoji", "--format", "vimgrep", path]);
+        let result = process_file(path, &args);
+        writer.join().unwrap();
+
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.emojis_found, 3);
+        let positions: Vec<_> = result.findings.iter().map(|f| (f.line, f.column)).collect();
+        assert_eq!(positions, [(1, 5), (3, 7), (3, 11)]);
+
+        // Editing in place is refused without opening the pipe.
+        let args = Args::parse_from(["nomoji", "-i", path]);
+        let result = process_file(path, &args);
+        assert!(result.error.unwrap().contains("named pipe"));
     }
 
     #[test]
-    fn test_only_emojis() {
-        let input = "😀🎉🚀🌍🔥";
-        let (result, count) = remove_emojis(input);
-        assert_eq!(result, "");
-        assert_eq!(count, 5);
+    fn test_process_files_stops_when_interrupted() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<String> = (0..3)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.txt", i));
+                fs::write(&path, "stop 🛑").unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect();
+
+        let args = Args::parse_from(
+            ["nomoji", "-i"]
+                .into_iter()
+                .chain(paths.iter().map(String::as_str)),
+        );
+        let started = std::cell::Cell::new(0);
+        let (results, limit) = process_files(&args, || {
+            started.set(started.get() + 1);
+            started.get() > 2
+        });
+        assert_eq!(limit, None);
+
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.success));
+        assert_eq!(fs::read_to_string(&paths[1]).unwrap(), "stop ");
+        assert_eq!(fs::read_to_string(&paths[2]).unwrap(), "stop 🛑");
     }
 
     #[test]
-    fn test_emoticons_range() {
-        let input = "Faces: 😀😃😄😁😆😅😂🤣😊😇🙂🙃😉😌😍🥰😘😗😙😚😋😛😝😜🤪🤨🧐🤓😎🥸🤩🥳😏😒😞😔😟😕🙁☹️😣😖😫😩🥺😢😭😤😠😡🤬🤯😳🥵🥶😱😨😰😥😓🤗🤔🤭🤫🤥😶😐😑😬🙄😯😦😧😮😲🥱😴🤤😪😵🤐🥴🤢🤮🤧😷🤒🤕🤑🤠😈👿👹👺🤡💩👻💀☠️👽👾🤖🎃😺😸😹😻😼😽🙀😿😾";
-        let (result, count) = remove_emojis(input);
-        assert!(count > 50);
-        assert!(!result.contains("😀"));
+    fn test_duplicate_inputs_are_processed_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("notes.txt");
+        fs::write(&file, "Ship 🚀").unwrap();
+        let path = file.to_str().unwrap().to_string();
+        let mut files = vec![path.clone(), path.clone()];
+        #[cfg(unix)]
+        {
+            let link = dir.path().join("link.txt");
+            let hard = dir.path().join("hard.txt");
+            std::os::unix::fs::symlink(&file, &link).unwrap();
+            fs::hard_link(&file, &hard).unwrap();
+            files.push(link.to_str().unwrap().to_string());
+            files.push(hard.to_str().unwrap().to_string());
+        }
+
+        let args = Args::parse_from(
+            ["nomoji", "-i"]
+                .into_iter()
+                .chain(files.iter().map(String::as_str)),
+        );
+        let (results, _) = process_files(&args, || false);
+        assert_eq!(results.len(), files.len());
+        assert_eq!(results.iter().map(|r| r.emojis_found).sum::<usize>(), 1);
+        for duplicate in &results[1..] {
+            assert_eq!(
+                duplicate.skipped.as_deref(),
+                Some(format!("same file as {}", path).as_str())
+            );
+        }
+        assert_eq!(fs::read_to_string(&file).unwrap(), "Ship ");
     }
 
     #[test]
-    fn test_flags() {
-        let input = "Flags: 🇺🇸🇬🇧🇯🇵🇫🇷🇩🇪";
-        let (result, count) = remove_emojis(input);
-        assert_eq!(count, 10);
-        assert_eq!(result, "Flags: ");
+    fn test_fail_fast() {
+        let dir = tempfile::tempdir().unwrap();
+        let good = dir.path().join("good.txt");
+        let later = dir.path().join("later.txt");
+        fs::write(&good, "ok 🚀").unwrap();
+        fs::write(&later, "later 🚀").unwrap();
+        let missing = dir.path().join("missing.txt");
+        let files = [&good, &missing, &later].map(|p| p.to_str().unwrap().to_string());
+
+        let args = Args::parse_from(
+            ["nomoji", "-i"]
+                .into_iter()
+                .chain(files.iter().map(String::as_str)),
+        );
+        assert_eq!(process_files(&args, || false).0.len(), 3);
+
+        fs::write(&later, "later 🚀").unwrap();
+        let args = Args::parse_from(
+            ["nomoji", "-i", "--fail-fast"]
+                .into_iter()
+                .chain(files.iter().map(String::as_str)),
+        );
+        let (results, limit) = process_files(&args, || false);
+        assert_eq!(limit, None);
+        assert_eq!(results.len(), 2);
+        assert!(!results[1].success);
+        assert_eq!(fs::read_to_string(&later).unwrap(), "later 🚀");
     }
 
     #[test]
-    fn test_skin_tone_modifiers() {
-        let input = "People: 👋🏻👋🏼👋🏽👋🏾👋🏿";
-        let (result, count) = remove_emojis(input);
-        assert_eq!(count, 10);
-        assert_eq!(result, "People: ");
+    fn test_escape_output() {
+        let args = Args::parse_from(["nomoji", "--escape-output", "shell", "-"]);
+        assert_eq!(args.printed("it's done \n"), "'it'\\''s done \n'\n");
+        let args = Args::parse_from(["nomoji", "--escape-output", "json", "-"]);
+        assert_eq!(args.printed("it's done \n"), "\"it's done \\n\"\n");
+        assert!(Args::try_parse_from(["nomoji", "-i", "--escape-output", "c", "-"]).is_err());
     }
 
     #[test]
-    fn test_symbols_and_pictographs() {
-        let input = "Symbols: ♥️♦️♠️♣️💯💢💥💫💦💨🕳️💣💬👁️‍🗨️🗨️🗯️💭💤";
-        let (result, count) = remove_emojis(input);
-        assert!(count > 10);
-        assert!(!result.contains("♥️"));
+    fn test_split_documents() {
+        assert_eq!(split_documents(b"a\0b\0", b"\0"), [b"a", b"b"]);
+        assert_eq!(split_documents(b"a\0\0b", b"\0"), [&b"a"[..], b"", b"b"]);
+        assert_eq!(split_documents(b"x\n---\ny", b"\n---\n"), [b"x", b"y"]);
+        assert!(split_documents(b"", b"\0").is_empty());
+
+        assert_eq!(parse_separator(r"\0"), Ok(vec![0]));
+        assert_eq!(parse_separator(r"\n---\n"), Ok(b"\n---\n".to_vec()));
+        assert_eq!(parse_separator(r"\\"), Ok(b"\\".to_vec()));
+        assert!(parse_separator(r"\q").is_err());
+        assert!(parse_separator("").is_err());
     }
 
     #[test]
-    fn test_variation_selectors() {
-        let input = "Text vs Emoji: #️⃣ *️⃣ 0️⃣ 1️⃣ 2️⃣";
-        let (_result, count) = remove_emojis(input);
-        assert!(count >= 5);
+    fn test_clean_documents() {
+        let args = Args::parse_from([
+            "nomoji",
+            "--split-documents",
+            "--stdin-filename",
+            "snip",
+            "-",
+        ]);
+        let mut out = Vec::new();
+        let results = clean_documents("a 🚀\0b".as_bytes(), b"\0", &args, &mut out).unwrap();
+        assert_eq!(out, b"a \0b");
+        let names: Vec<_> = results
+            .iter()
+            .map(|r| (r.file.as_str(), r.emojis_found))
+            .collect();
+        assert_eq!(names, [("snip#1", 1), ("snip#2", 0)]);
     }
 
     #[test]
-    fn test_geometric_shapes() {
-        let input = "Shapes: 🔴🔵⚪⚫🟥🟦🟧🟨🟩🟪⬛⬜◼️◻️🔶🔷🔸🔹";
-        let (_result, count) = remove_emojis(input);
-        assert!(count >= 10);
+    fn test_process_document_dry_run() {
+        let args = Args::parse_from(["nomoji", "--dry-run", "-"]);
+        let mut out = Vec::new();
+        let result = process_document("a 🚀\n".into(), "-", "-", &args, &mut out).unwrap();
+        assert!(out.is_empty());
+        assert_eq!((result.emojis_found, result.bytes_saved), (1, 4));
+
+        let mut report = Vec::new();
+        print_stdin_report(&result, &args, &mut report).unwrap();
+        let report = String::from_utf8(report).unwrap();
+        assert!(report.contains("Emojis found in stdin: 1, would shrink by 4 bytes"));
+
+        let args = Args::parse_from(["nomoji", "--dry-run", "--split-documents", "-"]);
+        let results = clean_documents("a 🚀\0b".as_bytes(), b"\0", &args, &mut out).unwrap();
+        assert!(out.is_empty());
+        assert_eq!(results[0].bytes_saved, 4);
     }
 
     #[test]
-    fn test_travel_and_places() {
-        let input = "Travel: 🚗🚕🚙🚌🚎🏎️🚓🚑🚒🚐🛻🚚🚛🚜🦯🦽🦼🛴🚲🛵🏍️🛺🚨🚔🚍🚘🚖🚡🚠🚟🚃🚋🚞🚝🚄🚅🚈🚂🚆🚇🚊🚉✈️🛫🛬🛩️💺🛰️🚀🛸🚁🛶⛵🚤🛥️🛳️⛴️🚢⚓⛽🚧🚦🚥🚏🗺️🗿🗽🗼🏰🏯🏟️🎡🎢🎠⛲⛱️🏖️🏝️🏜️🌋⛰️🏔️🗻🏕️⛺🏠🏡🏘️🏚️🏗️🏭🏢🏬🏣🏤🏥🏦🏨🏪🏫🏩💒🏛️⛪🕌🕍🛕🕋⛩️🛤️🛣️🗾🎑🏞️🌅🌄🌠🎇🎆🌇🌆🏙️🌃🌌🌉🌁";
-        let (_result, count) = remove_emojis(input);
-        assert!(count > 50);
+    fn test_process_files_stops_at_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<String> = (0..3)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.txt", i));
+                fs::write(&path, "0123456789 🛑").unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect();
+        let parse = |extra: &[&str]| {
+            Args::parse_from(
+                ["nomoji", "-i"]
+                    .into_iter()
+                    .chain(extra.iter().copied())
+                    .chain(paths.iter().map(String::as_str)),
+            )
+        };
+
+        let (results, limit) = process_files(&parse(&["--max-files", "2"]), || false);
+        assert_eq!(results.len(), 2);
+        assert_eq!(limit, Some(Limit::Files(2)));
+        assert_eq!(fs::read_to_string(&paths[2]).unwrap(), "0123456789 🛑");
+
+        // The file that crosses the byte limit is still finished.
+        let (results, limit) = process_files(&parse(&["--max-total-bytes", "20"]), || false);
+        assert_eq!(results.len(), 2);
+        assert_eq!(limit, Some(Limit::TotalBytes(20)));
+
+        let (results, limit) = process_files(&parse(&["--max-files", "3"]), || false);
+        assert_eq!((results.len(), limit), (3, None));
     }
 
     #[test]
-    fn test_food_and_drink() {
-        let input = "Food: 🍏🍎🍐🍊🍋🍌🍉🍇🍓🫐🍈🍒🍑🍍🥝🥑🍆🥔🥕🌽🌶️🫑🥒🥬🥦🧄🧅🍄🥜🌰🍞🥐🥖🥨🥯🥞🧇🧀🍖🍗🥩🥓🍔🍟🍕🌭🥪🌮🌯🫔🥙🧆🥚🍳🥘🍲🫕🥣🥗🍿🧈🧂🥫🍱🍘🍙🍚🍛🍜🍝🍠🍢🍣🍤🍥🥮🍡🥟🥠🥡🦀🦞🦐🦑🦪🍦🍧🍨🍩🍪🎂🍰🧁🥧🍫🍬🍭🍮🍯🍼🥛☕🫖🍵🍶🍾🍷🍸🍹🍺🍻🥂🥃🫗🥤🧋🧃🧉🧊";
-        let (_result, count) = remove_emojis(input);
-        assert!(count > 50);
+    fn test_process_file_skip_locked() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "busy 📝").unwrap();
+        let path = temp_file.path().to_str().unwrap();
+
+        let holder = fs::File::open(path).unwrap();
+        holder.lock().unwrap();
+
+        let args = Args::parse_from(["nomoji", "-i", "--skip-locked", path]);
+        let result = process_file(path, &args);
+        assert!(result.success);
+        assert_eq!(result.skipped.as_deref(), Some("locked by another process"));
+        assert_eq!(fs::read_to_string(path).unwrap(), "busy 📝");
+        print_report(&[result], &args, &mut io::sink()).unwrap();
+
+        holder.unlock().unwrap();
+        let result = process_file(path, &args);
+        assert!(result.skipped.is_none());
+        assert_eq!(fs::read_to_string(path).unwrap(), "busy ");
     }
 
     #[test]
-    fn test_activities() {
-        let input = "Activities: ⚽🏀🏈⚾🥎🎾🏐🏉🥏🎱🪀🏓🏸🏒🏑🥍🏏🥅⛳🪁🏹🎣🤿🥊🥋🎽🛹🛼🛷⛸️🥌🎿⛷️🏂🪂🏋️‍♀️🏋️🏋️‍♂️🤼‍♀️🤼🤼‍♂️🤽‍♀️🤽🤽‍♂️🤾‍♀️🤾🤾‍♂️🌊🚣‍♀️🚣🚣‍♂️🧗‍♀️🧗🧗‍♂️🚵‍♀️🚵🚵‍♂️🚴‍♀️🚴🚴‍♂️🏆🥇🥈🥉🏅🎖️🏵️🎗️🎫🎟️🎪🤹‍♀️🤹🤹‍♂️🎭🩰🎨🎬🎤🎧🎼🎹🥁🪘🎷🎺🪗🎸🪕🎻🎲♟️🎯🎳🎮🎰🧩";
-        let (_result, count) = remove_emojis(input);
-        assert!(count > 50);
+    fn test_list() {
+        let args = Args::parse_from(["nomoji", "--dry-run", "--list", "-"]);
+        let results = [
+            process_content("a.md", "🚀 ok 👍🏽 🚀", Encoding::Utf8, None, &args),
+            process_content("b.md", "👍🏽 🚀", Encoding::Utf8, None, &args),
+        ];
+        let mut out = Vec::new();
+        write_report(&results, &args, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains(
+            "Distinct emojis:\n  🚀  3  U+1F680  rocket  (Unicode 6.0)\n  \
+             👍🏽  2  U+1F44D U+1F3FD  thumbs up sign: emoji modifier fitzpatrick type-4  (Unicode 8.0)\n"
+        ));
+
+        assert!(Args::try_parse_from(["nomoji", "--list", "-"]).is_err());
     }
 
     #[test]
-    fn test_objects() {
-        let input = "Objects: 👓🕶️🥽🥼🦺👔👕👖🧣🧤🧥🧦👗👘🥻🩱🩲🩳👙👚👛👜👝🛍️🎒🩴👞👟🥾🥿👠👡🩰👢👑👒🎩🎓🧢🪖⛑️📿💄💍💎🔇🔈🔉🔊📢📣📯🔔🔕🎼🎵🎶🎙️🎚️🎛️🎤🎧📻🎷🎸🎹🎺🎻🪕🥁🪘📱📲☎️📞📟📠🔋🔌💻🖥️🖨️⌨️🖱️🖲️💽💾💿📀🧮🎥🎞️📽️🎬📺📷📸📹📼🔍🔎🕯️💡🔦🏮🪔📔📕📖📗📘📙📚📓📒📃📜📄📰🗞️📑🔖🏷️💰🪙💴💵💶💷💸💳🧾💹✉️📧📨📩📤📥📦📫📪📬📭📮🗳️✏️✒️🖋️🖊️🖌️🖍️📝💼📁📂🗂️📅📆🗒️🗓️📇📈📉📊📋📌📍📎🖇️📏📐✂️🗃️🗄️🗑️🔒🔓🔏🔐🔑🗝️🔨🪓⛏️⚒️🛠️🗡️⚔️🔫🪃🏹🛡️🪚🔧🪛🔩⚙️🗜️⚖️🦯🔗⛓️🪝🧰🧲🪜⚗️🧪🧫🧬🔬🔭📡💉🩸💊🩹🩺🌡️🚽🚰🚿🛁🛀🧴🧵🧶🪡🧷🎽🥽🥼🦺";
-        let (_result, count) = remove_emojis(input);
-        assert!(count > 50);
+    fn test_color() {
+        let vimgrep = Args::parse_from(["nomoji", "--format", "vimgrep", "-"]);
+        let results = [
+            process_content("a.md", "x 🚀", Encoding::Utf8, None, &vimgrep),
+            ProcessResult::failed("b.md", 0, "denied".to_string()),
+        ];
+        // Neither destination is a terminal here.
+        let report = |extra: &[&str]| {
+            let mut args = Args::parse_from([&["nomoji"], extra, &["-"]].concat());
+            args.palette = Palette::new(args.color, false);
+            let mut out = Vec::new();
+            write_report(&results, &args, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert!(!report(&[]).contains('\x1b'));
+        assert!(!report(&["--color", "never", "--format", "vimgrep"]).contains('\x1b'));
+
+        let text = report(&["--color", "always"]);
+        assert!(text.contains("\x1b[1m=== nomoji Report ===\x1b[0m"));
+        assert!(text.contains("  b.md: 0 emojis - \x1b[31mERROR: denied\x1b[0m"));
+        let vimgrep = report(&["--color", "always", "--format", "vimgrep"]);
+        assert_eq!(
+            vimgrep,
+            "\x1b[35ma.md\x1b[0m:\x1b[32m1\x1b[0m:\x1b[32m3\x1b[0m:x 🚀\n"
+        );
     }
 
     #[test]
-    fn test_newline_and_whitespace_preserved() {
-        let input = "Line 1 😀\nLine 2 🌍\n\nLine 4 🔥";
-        let (result, count) = remove_emojis(input);
-        assert_eq!(result, "Line 1 \nLine 2 \n\nLine 4 ");
-        assert_eq!(count, 3);
+    fn test_top_files() {
+        let results = [
+            ProcessResult::succeeded("one.md", 1),
+            ProcessResult::succeeded("clean.md", 0),
+            ProcessResult::succeeded("five.md", 5),
+            ProcessResult::succeeded("also-one.md", 1),
+        ];
+        let report = |extra: &[&str]| {
+            let args = Args::parse_from([&["nomoji"], extra, &["-"]].concat());
+            let mut out = Vec::new();
+            write_report(&results, &args, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let text = report(&["--top-files", "2"]);
+        assert!(text.contains("Files processed: 4"));
+        assert!(text.contains(
+            "Top 2 files by emojis:\n  five.md: 5 emojis removed\n  one.md: 1 emojis removed\n"
+        ));
+        assert!(!text.contains("also-one.md"));
+
+        let json = report(&["--top-files", "9", "--format", "json"]);
+        assert!(json.contains(r#""total_emojis":7"#));
+        let files: Vec<_> = json::parse(&json)
+            .unwrap()
+            .get("files")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| {
+                f.get("file")
+                    .and_then(json::Value::as_str)
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(files, ["five.md", "one.md", "also-one.md"]);
+
+        assert!(report(&[]).contains("  clean.md: 0 emojis removed"));
+        assert!(report(&["--group-by-dir"]).contains("By directory:\n  ./: 7 emojis in 4 files\n"));
+        assert!(Args::try_parse_from(["nomoji", "--group-by-dir=0", "-"]).is_err());
+
+        let matches = report(&["--only-matches"]);
+        assert!(matches.contains("Files processed: 4"));
+        assert!(!matches.contains("clean.md"));
+        assert!(matches.contains("  also-one.md: 1 emojis removed"));
+        assert!(report(&["--top-files", "2", "--sort", "name"]).contains(
+            "Top 2 files by emojis:\n  five.md: 5 emojis removed\n  one.md: 1 emojis removed\n"
+        ));
+        assert!(
+            report(&["--sort", "name"]).contains(
+                "  also-one.md: 1 emojis removed\n  clean.md: 0 emojis removed\n  five.md"
+            )
+        );
+        assert!(Args::try_parse_from(["nomoji", "--top-files", "0", "-"]).is_err());
+    }
+
+    #[test]
+    fn test_run_report_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("old.json");
+        let new = dir.path().join("new.json");
+        let write = |path: &Path, results: &[ProcessResult]| {
+            fs::write(
+                path,
+                report::encode(report::to_report(results, results)).to_string(),
+            )
+            .unwrap()
+        };
+
+        write(&old, &[ProcessResult::succeeded("a.md", 3)]);
+        write(&new, &[ProcessResult::succeeded("a.md", 1)]);
+        assert_eq!(run_report_diff(&old, &new, color::When::Never), Ok(true));
+
+        write(
+            &new,
+            &[
+                ProcessResult::succeeded("a.md", 3),
+                ProcessResult::succeeded("b.md", 1),
+            ],
+        );
+        assert_eq!(run_report_diff(&old, &new, color::When::Never), Ok(false));
+
+        fs::write(&new, "not json").unwrap();
+        assert!(
+            run_report_diff(&old, &new, color::When::Never)
+                .unwrap_err()
+                .contains("new.json")
+        );
+
+        let diff = report::Diff {
+            worse: vec![("a.md".to_string(), i64::MIN, i64::MAX)],
+            old_total: i64::MIN,
+            new_total: i64::MAX,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        print_report_diff(&diff, Palette::default(), &mut out).unwrap();
+        assert!(
+            String::from_utf8(out)
+                .unwrap()
+                .contains(&format!("(+{})", i64::MAX))
+        );
     }
 
     #[test]
-    fn test_copyright_and_trademark() {
-        let input = "Legal: © ® ™";
-        let (result, count) = remove_emojis(input);
-        assert_eq!(count, 3);
-        assert_eq!(result, "Legal:   ");
+    fn test_run_info() {
+        let info = |argv: &[&str], sequence: &str| {
+            let args = Args::parse_from(argv);
+            let mut out = Vec::new();
+            run_info(
+                &[sequence.to_string()],
+                &args.cleaner("-"),
+                Palette::default(),
+                &mut out,
+            )
+            .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let out = info(&["nomoji", "info", "x"], "U+1F469 U+200D U+1F680");
+        assert!(out.starts_with("👩\u{200D}🚀 woman rocket\n"), "{}", out);
+        assert!(out.contains("               U+200D ZERO WIDTH JOINER\n"));
+        assert!(out.contains("Status:         fully-qualified\n"));
+        assert!(out.contains("Shortcode:      :woman_rocket:\n"));
+        assert!(out.contains("Introduced:     Unicode 6.0\n"));
+        assert!(out.ends_with("Cleaning:       removed\n"));
+
+        let out = info(&["nomoji", "--skip", "check-marks"], "✅");
+        assert!(out.ends_with("Cleaning:       kept\n"), "{}", out);
+        let out = info(&["nomoji", "--describe"], "🚀");
+        assert!(out.contains("replaced with \"(rocket)\""), "{}", out);
     }
 
     #[test]
-    fn test_is_emoji_individual() {
-        assert!(is_emoji('😀'));
-        assert!(is_emoji('🚀'));
-        assert!(is_emoji('🌍'));
-        assert!(!is_emoji('a'));
-        assert!(!is_emoji('A'));
-        assert!(!is_emoji('1'));
-        assert!(!is_emoji('é'));
-        assert!(!is_emoji('日'));
+    fn test_process_files_counts_emojis_in_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let named = dir.path().join("🚀 launch");
+        fs::create_dir(&named).unwrap();
+        let file = named.join("notes✅.txt");
+        fs::write(&file, "plain\n").unwrap();
+
+        let args = Args::parse_from(["nomoji", "--dry-run", file.to_str().unwrap()]);
+        let (results, _) = process_files(&args, || false);
+        assert_eq!(results[0].emojis_found, 0);
+        assert_eq!(results[0].name_emojis, 2);
+        assert!(
+            report::encode(report::to_report(&results, &results))
+                .to_string()
+                .contains(r#""name_emojis":2"#)
+        );
     }
 
     #[test]
-    fn test_process_file_with_temp_file() {
+    fn test_process_file_fast_skips_ascii() {
         let mut temp_file = NamedTempFile::new().unwrap();
-        writeln!(temp_file, "Hello 😀 World 🌍!").unwrap();
+        writeln!(temp_file, "plain \\u2705 text").unwrap();
         let path = temp_file.path().to_str().unwrap();
 
-        let args = Args {
-            files: vec![path.to_string()],
-            backup: false,
-            inplace: true,
-            dry_run: false,
+        let result = |flags: &[&str]| {
+            let args = Args::parse_from([&["nomoji"], flags, &[path]].concat());
+            process_file(path, &args)
         };
+        let fast = result(&["--dry-run"]);
+        assert!(fast.fast_skipped);
+        assert_eq!(fast.bytes_read, 18);
+        assert!(result(&["-i"]).fast_skipped);
 
-        let result = process_file(path, &args);
-        assert!(result.success);
-        assert_eq!(result.emojis_found, 2);
-
-        let content = fs::read_to_string(path).unwrap();
-        assert_eq!(content.trim(), "Hello  World !");
+        // Escapes spell emojis in ASCII, and stdout needs the text echoed.
+        let escapes = result(&["--dry-run", "--escapes"]);
+        assert!(!escapes.fast_skipped);
+        assert_eq!(escapes.emojis_found, 1);
+        assert!(!result(&["--format", "json"]).fast_skipped);
     }
 
     #[test]
-    fn test_process_file_dry_run() {
+    fn test_process_file_records_audit() {
         let mut temp_file = NamedTempFile::new().unwrap();
-        writeln!(temp_file, "Test 🚀 content").unwrap();
+        write!(temp_file, "Ship 🚀 it").unwrap();
         let path = temp_file.path().to_str().unwrap();
 
-        let args = Args {
-            files: vec![path.to_string()],
-            backup: false,
-            inplace: false,
-            dry_run: true,
-        };
+        let args = Args::parse_from(["nomoji", "-i", "--to-entities", "--audit", "a.json", path]);
+        let entry = process_file(path, &args).audit.unwrap();
+        assert_eq!(entry.file, path);
+        assert_eq!(
+            entry.changes,
+            [audit::Change {
+                offset: 5,
+                removed: "🚀".to_string(),
+                inserted: "&#x1F680;".to_string(),
+            }]
+        );
+        assert_eq!(
+            entry.after_sha256,
+            sha256::hex(fs::read(path).unwrap().as_slice())
+        );
 
-        let result = process_file(path, &args);
-        assert!(result.success);
-        assert_eq!(result.emojis_found, 1);
+        // A second run changes nothing, so there is nothing to record.
+        assert!(process_file(path, &args).audit.is_none());
+    }
 
-        let content = fs::read_to_string(path).unwrap();
-        assert!(content.contains("🚀"));
+    #[test]
+    fn test_json_report_has_checksums_of_rewritten_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let changed = dir.path().join("a.md");
+        let clean = dir.path().join("b.md");
+        fs::write(&changed, "Ship 🚀 it\n").unwrap();
+        fs::write(&clean, "Nothing here\n").unwrap();
+        let (changed, clean) = (changed.to_str().unwrap(), clean.to_str().unwrap());
+
+        let args = Args::parse_from([
+            "nomoji", "-i", "--format", "json", "--audit", "a.json", changed, clean,
+        ]);
+        let (results, _) = process_files(&args, || false);
+        let entry = results[0].audit.as_ref().unwrap();
+        assert_eq!(
+            results[0].sha256,
+            Some((entry.before_sha256.clone(), entry.after_sha256.clone()))
+        );
+        assert_eq!(
+            results[0].sha256.as_ref().unwrap().0,
+            sha256::hex("Ship 🚀 it\n".as_bytes())
+        );
+        assert!(results[1].sha256.is_none());
+
+        let mut report = Vec::new();
+        write_report(&results, &args, &mut report).unwrap();
+        let report = json::parse(std::str::from_utf8(&report).unwrap()).unwrap();
+        let files = report.get("files").and_then(|f| f.as_array()).unwrap();
+        assert_eq!(
+            files[0].get("after_sha256").and_then(|h| h.as_str()),
+            Some(entry.after_sha256.as_str())
+        );
+        assert_eq!(files[1].get("before_sha256"), Some(&json::Value::Null));
+
+        // A dry run rewrites nothing, so there is nothing to hash.
+        let args = Args::parse_from(["nomoji", "--dry-run", "--format", "json", changed]);
+        assert!(process_file(changed, &args).sha256.is_none());
     }
 
     #[test]
-    fn test_process_file_backup() {
+    fn test_head_bytes() {
         let mut temp_file = NamedTempFile::new().unwrap();
-        writeln!(temp_file, "Backup test 🔥").unwrap();
+        write!(temp_file, "ab 🚀 cd ✅ ef 🎉").unwrap();
         let path = temp_file.path().to_str().unwrap();
-        let backup_path = format!("{}.bak", path);
-
-        let args = Args {
-            files: vec![path.to_string()],
-            backup: true,
-            inplace: false,
-            dry_run: false,
-        };
 
+        // The limit falls inside the second emoji, which isn't counted.
+        let args = Args::parse_from(["nomoji", "--dry-run", "--head-bytes", "13", path]);
         let result = process_file(path, &args);
         assert!(result.success);
+        assert_eq!(result.emojis_found, 1);
+        assert_eq!(result.bytes_read, 11);
 
-        assert!(fs::metadata(&backup_path).is_ok());
-        let backup_content = fs::read_to_string(&backup_path).unwrap();
-        assert!(backup_content.contains("🔥"));
+        assert!(Args::try_parse_from(["nomoji", "--head-bytes", "1K", path]).is_err());
 
-        fs::remove_file(&backup_path).ok();
+        let mut utf16 = vec![0xFF, 0xFE];
+        for unit in "a😀".encode_utf16() {
+            utf16.extend(unit.to_le_bytes());
+        }
+        let mut cut = utf16[..6].to_vec();
+        trim_partial_char(&mut cut);
+        assert_eq!(cut, [0xFF, 0xFE, b'a', 0]);
+        let mut odd = utf16[..5].to_vec();
+        trim_partial_char(&mut odd);
+        assert_eq!(odd, [0xFF, 0xFE, b'a', 0]);
+    }
+
+    #[test]
+    fn test_run_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.md");
+        let sidecar = dir.path().join("run.json");
+        let path = file.to_str().unwrap();
+        fs::write(&file, "Ship 🚀 it ✅\n").unwrap();
+
+        let args = Args::parse_from(["nomoji", "-i", "--audit", "run.json", path]);
+        let (results, _) = process_files(&args, || false);
+        audit::write(&sidecar, results.iter().filter_map(|r| r.audit.as_ref())).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "Ship  it \n");
+
+        assert_eq!(run_restore(&sidecar, Palette::default()), Ok(true));
+        assert_eq!(fs::read_to_string(&file).unwrap(), "Ship 🚀 it ✅\n");
+
+        // Already restored, so the cleaned hash no longer matches.
+        assert_eq!(run_restore(&sidecar, Palette::default()), Ok(false));
+        assert_eq!(fs::read_to_string(&file).unwrap(), "Ship 🚀 it ✅\n");
+    }
+
+    #[test]
+    fn test_process_file_unencodable_output_fails() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "日本 🚀").unwrap();
+        let path = temp_file.path().to_str().unwrap();
+
+        let args = Args::parse_from(["nomoji", "-i", "--output-encoding", "ascii", path]);
+        let result = process_file(path, &args);
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("Failed to encode output"));
+        assert_eq!(fs::read_to_string(path).unwrap(), "日本 🚀");
     }
 
     #[test]
@@ -489,6 +5169,7 @@ mod tests {
             backup: false,
             inplace: false,
             dry_run: false,
+            ..Default::default()
         };
 
         let result = process_file("nonexistent_file.txt", &args);
@@ -505,7 +5186,7 @@ mod tests {
         let content = read_input(path).unwrap();
         assert!(content.contains("Test content"));
 
-        write_output(path, "New content").unwrap();
+        write_output(path, "New content", None, false).unwrap();
         let new_content = fs::read_to_string(path).unwrap();
         assert_eq!(new_content, "New content");
     }
@@ -513,7 +5194,7 @@ mod tests {
     #[test]
     fn test_print_report_empty() {
         let results: Vec<ProcessResult> = vec![];
-        print_report(&results);
+        print_report(&results, &Args::default(), &mut io::sink()).unwrap();
     }
 
     #[test]
@@ -524,15 +5205,492 @@ mod tests {
                 emojis_found: 5,
                 success: true,
                 error: None,
+                ..Default::default()
             },
             ProcessResult {
                 file: "test2.txt".to_string(),
                 emojis_found: 0,
                 success: false,
                 error: Some("File not found".to_string()),
+                ..Default::default()
             },
         ];
-        print_report(&results);
+        let mut out = Vec::new();
+        print_report(&results, &Args::default(), &mut out).unwrap();
+        let report = String::from_utf8(out).unwrap();
+        assert!(report.contains("Failed: 1"));
+        assert!(report.contains("  test2.txt: 0 emojis - ERROR: File not found"));
+    }
+
+    #[test]
+    fn test_print_report_timings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "Ship 🚀").unwrap();
+        let file = path.to_str().unwrap();
+
+        let args = Args::parse_from(["nomoji", "-i", file]);
+        let result = process_file(file, &args);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Ship ");
+        assert!(result.timings.total() > Duration::ZERO);
+
+        let report = |args: &Args| {
+            let mut out = Vec::new();
+            print_report(std::slice::from_ref(&result), args, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert!(!report(&args).contains("Time:"));
+        let once = report(&Args::parse_from(["nomoji", "-v", file]));
+        assert!(once.contains("Time: read "));
+        assert!(!once.contains("    read "));
+        let twice = report(&Args::parse_from(["nomoji", "-vv", file]));
+        assert!(twice.contains("    read "));
+        assert!(twice.contains(" MB/s)"));
+    }
+
+    #[test]
+    fn test_process_content_refuses_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("draft.md");
+        fs::write(&path, "v1 🚀\n").unwrap();
+        let path = path.to_str().unwrap();
+        let stamp = Stamp::of(path).unwrap();
+        // An editor saves while nomoji is working on the old contents.
+        fs::write(path, "v2, edited 🚀\n").unwrap();
+
+        let args = Args::parse_from(["nomoji", "-i", path]);
+        let result = process_content(path, "v1 🚀\n", Encoding::Utf8, Some(stamp), &args);
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("--force-write"));
+        assert_eq!(fs::read_to_string(path).unwrap(), "v2, edited 🚀\n");
+
+        let args = Args::parse_from(["nomoji", "-i", "--force-write", path]);
+        let result = process_content(path, "v1 🚀\n", Encoding::Utf8, Some(stamp), &args);
+        assert!(result.success);
+        assert_eq!(fs::read_to_string(path).unwrap(), "v1 \n");
+    }
+
+    #[test]
+    fn test_process_file_suffix() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "ship it 🚀\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        let args = Args::parse_from(["nomoji", "--suffix", ".clean", "--verify", path]);
+        let result = process_file(path, &args);
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(fs::read_to_string(path).unwrap(), "ship it 🚀\n");
+        assert_eq!(
+            fs::read_to_string(format!("{}.clean", path)).unwrap(),
+            "ship it \n"
+        );
+
+        assert!(Args::try_parse_from(["nomoji", "--suffix", ".clean", "-i", path]).is_err());
+        assert!(Args::try_parse_from(["nomoji", "--suffix", "", path]).is_err());
+    }
+
+    #[test]
+    fn test_to_entities() {
+        let args = Args::parse_from(["nomoji", "--to-entities", "-"]);
+        let cleaner = args.cleaner("page.html");
+        let (text, count) = cleaner.remove("Launch 🚀 👍🏽!");
+        assert_eq!(text, "Launch &#x1F680; &#x1F44D;&#x1F3FD;!");
+        assert_eq!(count, 3);
+
+        // Conversion wins over Markdown shortcodes and respects --skip.
+        let cleaner = Cleaner {
+            mode: Mode::Markdown,
+            skip: vec![Category::Flags],
+            ..args.cleaner("README.md")
+        };
+        let (text, _) = cleaner.remove("Go 🚀 🇫🇷\n");
+        assert_eq!(text, "Go &#x1F680; 🇫🇷\n");
+    }
+
+    #[test]
+    fn test_to_escape() {
+        let args = Args::parse_from(["nomoji", "--to-escape", "python", "-"]);
+        let (text, _) = args.cleaner("app.py").remove("print(\"done ✅\")\n");
+        assert_eq!(text, "print(\"done \\u2705\")\n");
+
+        assert!(
+            Args::try_parse_from(["nomoji", "--to-escape", "js", "--to-entities", "-"]).is_err()
+        );
+        assert!(Args::try_parse_from(["nomoji", "--to-escape", "go", "-"]).is_err());
+    }
+
+    #[test]
+    fn test_describe() {
+        let args = Args::parse_from(["nomoji", "--describe", "--lang", "fr", "-"]);
+        let (text, count) = args.cleaner("mail.txt").remove("Lancement 🚀 !");
+        assert_eq!(text, "Lancement (fusée) !");
+        assert_eq!(count, 1);
+
+        assert!(Args::try_parse_from(["nomoji", "--lang", "fr", "-"]).is_err());
+        assert!(Args::try_parse_from(["nomoji", "--describe", "--to-entities", "-"]).is_err());
+    }
+
+    #[test]
+    fn test_escapes() {
+        let source = r#"const msg = "done \u2705 \uD83D\uDE80 \\u2705 \u00e9 🎉";"#;
+        let cleaner = Args::parse_from(["nomoji", "--escapes", "-"]).cleaner("app.js");
+        assert_eq!(cleaner.count(source), 3);
+        let (text, count) = cleaner.remove(source);
+        assert_eq!(text, r#"const msg = "done   \\u2705 \u00e9 ";"#);
+        assert_eq!(count, 3);
+
+        // Conversions and shortcodes see the decoded sequence.
+        let cleaner = Cleaner {
+            conversion: Some(Conversion::Entities),
+            ..cleaner
+        };
+        assert_eq!(cleaner.remove(r"\U0001F680").0, "&#x1F680;");
+
+        // Without --escapes the ASCII source is left alone.
+        let cleaner = Args::parse_from(["nomoji", "-"]).cleaner("app.js");
+        assert_eq!(cleaner.count(source), 1);
+    }
+
+    #[test]
+    fn test_skip_urls_and_inline_code() {
+        let text = "Ship 🚀 via https://🚀.example/✅ and `echo 🎉` ✅";
+        let cleaner = |extra: &[&str]| {
+            Args::parse_from([&["nomoji"], extra, &["-"]].concat()).cleaner("notes.md")
+        };
+
+        assert_eq!(
+            cleaner(&["--skip-urls"]).remove(text),
+            (
+                Cow::Owned("Ship  via https://🚀.example/✅ and `echo ` ".to_string()),
+                3
+            )
+        );
+        assert_eq!(
+            cleaner(&["--skip-urls", "--skip-inline-code"])
+                .remove(text)
+                .0,
+            "Ship  via https://🚀.example/✅ and `echo 🎉` "
+        );
+        assert!(!cleaner(&["--skip-inline-code"]).is_line_local());
+
+        let raw = "🚀 BEGIN-RAW 🎉\n✅ END-RAW 🚀";
+        assert_eq!(
+            cleaner(&["--skip-pattern", "BEGIN-RAW.*?END-RAW"])
+                .remove(raw)
+                .0,
+            " BEGIN-RAW 🎉\n✅ END-RAW "
+        );
+        assert!(Args::try_parse_from(["nomoji", "--skip-pattern", "(", "-"]).is_err());
+        assert_eq!(cleaner(&[]).count(text), 5);
+    }
+
+    #[test]
+    fn test_process_file_ipynb() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("launch.ipynb");
+        let notebook = "{\n \"cells\": [\n  {\"cell_type\": \"markdown\", \"metadata\": {\"tag\": \"🚀\"}, \"source\": [\"# Launch 🚀\\n\", \"Done ✅\"]},\n  {\"cell_type\": \"code\", \"execution_count\": 1, \"metadata\": {}, \"outputs\": [{\"name\": \"stdout\", \"output_type\": \"stream\", \"text\": \"ok ✅\"}], \"source\": \"print('ok ✅')\"}\n ],\n \"nbformat\": 4,\n \"nbformat_minor\": 5\n}\n";
+        fs::write(&path, notebook).unwrap();
+        let path = path.to_str().unwrap();
+
+        let args = Args::parse_from(["nomoji", "--ipynb", "-i", "--verify", path]);
+        let result = process_file(path, &args);
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.emojis_found, 3);
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            notebook
+                .replace("# Launch 🚀", "# Launch ")
+                .replace("Done ✅", "Done ")
+                .replace("\"ok ✅\"", "\"ok \"")
+        );
+
+        // A replacement is escaped to stay inside its JSON string.
+        let args = Args::parse_from([
+            "nomoji",
+            "--ipynb",
+            "--ipynb-code",
+            "--to-escape",
+            "python",
+            "-",
+        ]);
+        let (text, _) = args.cleaner(path).remove(notebook);
+        assert!(
+            text.contains("\"source\": \"print('ok \\\\u2705')\""),
+            "{}",
+            text
+        );
+        assert!(text.contains("{\"tag\": \"🚀\"}"));
+
+        fs::write(path, "{\"data\": []}").unwrap();
+        let result = process_file(path, &args);
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("Not a Jupyter notebook"));
+    }
+
+    #[test]
+    fn test_xml() {
+        let strings = "<resources>\n  <string name=\"go 🚀\" title=\"Go 🚀\">Launch 🚀</string>\n  <!-- 🚀 -->\n  <string name=\"done\"><![CDATA[Done ✅]]></string>\n</resources>\n";
+        let run = |argv: &[&str]| {
+            let args = Args::parse_from(argv);
+            args.cleaner("strings.xml").remove(strings).0.into_owned()
+        };
+
+        assert_eq!(
+            run(&["nomoji", "--xml", "-"]),
+            strings.replace("Launch 🚀", "Launch ")
+        );
+        assert_eq!(
+            run(&["nomoji", "--xml", "--xpath", "//string/@title", "-"]),
+            strings.replace("\"Go 🚀\"", "\"Go \"")
+        );
+        assert_eq!(
+            run(&["nomoji", "--xml", "--xml-cdata", "-"]),
+            strings
+                .replace("Launch 🚀", "Launch ")
+                .replace("Done ✅", "Done ")
+        );
+        // A replacement can't open a tag.
+        assert!(
+            run(&["nomoji", "--xml", "--to-escape", "rust", "-"]).contains(">Launch \\u{1F680}<")
+        );
+        assert_eq!(
+            run(&[
+                "nomoji",
+                "--xml",
+                "--describe",
+                "--xpath",
+                "string/@name",
+                "-"
+            ]),
+            strings.replace("go 🚀", "go (rocket)")
+        );
+
+        let args = Args::parse_from(["nomoji", "--xml", "-"]);
+        let cleaner = args.cleaner("strings.xml");
+        let error = clean("<a><b></a>", "strings.xml", &cleaner, &args).err();
+        assert!(error.unwrap().contains("Not well-formed XML"));
+        assert!(Args::try_parse_from(["nomoji", "--xpath", "//string", "-"]).is_err());
+        assert!(Args::try_parse_from(["nomoji", "--xml", "--xpath", "a[1]", "-"]).is_err());
+    }
+
+    #[test]
+    fn test_auto() {
+        let mut args = Args::parse_from(["nomoji", "--auto", "-"]);
+        let run =
+            |args: &Args, file: &str, text: &str| args.cleaner(file).remove(text).0.into_owned();
+
+        assert_eq!(
+            run(&args, "README.md", "Go 🚀 `🚀`\n"),
+            "Go :rocket: `🚀`\n"
+        );
+        assert_eq!(
+            run(&args, "data.json", "{\"🚀\": \"🚀\"}"),
+            "{\"🚀\": \"\"}"
+        );
+        assert_eq!(run(&args, "ci.yml", "🚀: 🚀\n"), "🚀: \n");
+        assert_eq!(run(&args, "a.csv", "🚀\n🚀\n"), "🚀\n\n");
+        // Without an extension it goes by the content.
+        assert_eq!(run(&args, "-", "{\"🚀\": \"🚀\"}"), "{\"🚀\": \"\"}");
+        assert_eq!(run(&args, "notes.txt", "Go 🚀\n"), "Go \n");
+        assert!(run(&args, "page.html", "<p>Go 🚀</p>").starts_with("<p>Go </p>"));
+
+        // From the configuration file, with --no-auto and formats on the
+        // command line winning over it.
+        let mut settings = Args::parse_from(["nomoji", "-"]);
+        settings.settings.auto = true;
+        assert_eq!(run(&settings, "data.json", "{\"🚀\": 1}"), "{\"🚀\": 1}");
+        let mut off = Args::parse_from(["nomoji", "--no-auto", "-"]);
+        off.settings.auto = true;
+        assert_eq!(run(&off, "data.json", "{\"🚀\": 1}"), "{\"\": 1}");
+        let mut xml = Args::parse_from(["nomoji", "--xml", "-"]);
+        xml.settings.auto = true;
+        assert!(!xml.auto());
+
+        // A mode from a config rule wins over the guess.
+        args.settings =
+            Config::parse("[[rules]]\nglob = \"*.json\"\nmode = \"comments\"\n").unwrap();
+        assert_eq!(
+            run(&args, "data.json", "{\"a\": \"🚀\"}"),
+            "{\"a\": \"🚀\"}"
+        );
+        assert!(Args::try_parse_from(["nomoji", "--auto", "--xml", "-"]).is_err());
+    }
+
+    #[test]
+    fn test_gitmoji_convert() {
+        let mut args = Args::parse_from(["nomoji", "--gitmoji-convert", "-"]);
+        let cleaner = args.cleaner("COMMIT_EDITMSG");
+        let cleaned = clean("🐛 Fix crash 🔥\n\nSee #12 ✅\n", "-", &cleaner, &args).unwrap();
+        assert_eq!(cleaned.text, "fix: Fix crash \n\nSee #12 \n");
+        assert_eq!(cleaned.emojis, 3);
+        let cleaned = clean("Fix 🐛 crash", "-", &cleaner, &args).unwrap();
+        assert_eq!(cleaned.text, "Fix  crash");
+
+        args.settings.gitmoji = vec![("🐛".to_string(), "bugfix".to_string())];
+        let cleaned = clean("🐛 Fix crash", "-", &cleaner, &args).unwrap();
+        assert_eq!(cleaned.text, "bugfix: Fix crash");
+    }
+
+    #[test]
+    fn test_gitmoji_convert_ascii_code_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("MSG");
+        let path_str = path.to_str().unwrap();
+        fs::write(&path, ":sparkles: add thing\n").unwrap();
+
+        let args = Args::parse_from(["nomoji", "--gitmoji-convert", "-i", path_str]);
+        let result = process_file(path_str, &args);
+        assert!(result.success);
+        assert!(!result.fast_skipped);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "feat: add thing\n");
+    }
+
+    #[test]
+    fn test_commit_msg() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("COMMIT_EDITMSG");
+        let message = "✨ Add sampling 🎉\n\nFaster ⚡\n# Please enter the commit message 📝\n";
+        let path_str = path.to_str().unwrap();
+
+        fs::write(&path, message).unwrap();
+        let args = Args::parse_from(["nomoji", "--commit-msg", path_str]);
+        assert!(process_file(path_str, &args).success);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            " Add sampling \n\nFaster \n# Please enter the commit message \n"
+        );
+
+        fs::write(&path, message).unwrap();
+        let args = Args::parse_from(["nomoji", "--commit-msg", "--keep-gitmoji", path_str]);
+        let result = process_file(path_str, &args);
+        assert_eq!(result.emojis_found, 3);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "✨ Add sampling \n\nFaster \n# Please enter the commit message \n"
+        );
+
+        assert!(Args::try_parse_from(["nomoji", "--commit-msg"]).is_err());
+        assert!(Args::try_parse_from(["nomoji", "--keep-gitmoji", "x"]).is_err());
+        assert!(Args::try_parse_from(["nomoji", "--commit-msg", "--pre-commit", "x"]).is_err());
+    }
+
+    #[test]
+    fn test_process_file_eml() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("msg.eml");
+        let message = "Subject: =?UTF-8?B?TGF1bmNoIPCfmoA=?=\r\n\
+                       Content-Type: text/plain; charset=utf-8\r\n\
+                       Content-Transfer-Encoding: quoted-printable\r\n\
+                       \r\n\
+                       Go =F0=9F=9A=80!\r\n";
+        fs::write(&path, message).unwrap();
+        let path = path.to_str().unwrap();
+
+        let args = Args::parse_from(["nomoji", "--eml", "-i", "--verify", path]);
+        let result = process_file(path, &args);
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.emojis_found, 2);
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "Subject: =?UTF-8?B?TGF1bmNoIA==?=\r\n\
+             Content-Type: text/plain; charset=utf-8\r\n\
+             Content-Transfer-Encoding: quoted-printable\r\n\
+             \r\n\
+             Go !\r\n"
+        );
+    }
+
+    #[test]
+    fn test_process_file_mbox_dry_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("archive.mbox");
+        let mbox = "From a@example.com Mon Jan 1 00:00:00 2024\n\nfirst\n\n\
+                    From b@example.com Tue Jan 2 00:00:00 2024\n\nsecond 🚀🎉\n";
+        fs::write(&path, mbox).unwrap();
+        let path = path.to_str().unwrap();
+
+        let args = Args::parse_from(["nomoji", "--mbox", "--dry-run", path]);
+        let result = process_file(path, &args);
+        assert_eq!(
+            (result.emojis_found, result.messages.clone()),
+            (2, vec![0, 2])
+        );
+        assert_eq!(fs::read_to_string(path).unwrap(), mbox);
+
+        let mut out = Vec::new();
+        print_report(&[result], &args, &mut out).unwrap();
+        assert!(
+            String::from_utf8(out)
+                .unwrap()
+                .contains("    message 2: 2 emojis\n")
+        );
+    }
+
+    #[test]
+    fn test_header() {
+        let args = Args::parse_from(["nomoji", "a.txt", "b.txt"]);
+        assert_eq!(header("a.txt", &args), "==> a.txt <==\n");
+        assert_eq!(header("b.txt", &args), "\n==> b.txt <==\n");
+
+        let args = Args::parse_from(["nomoji", "--no-filename", "a.txt", "b.txt"]);
+        assert_eq!(header("b.txt", &args), "");
+        let args = Args::parse_from(["nomoji", "a.txt"]);
+        assert_eq!(header("a.txt", &args), "");
+    }
+
+    #[test]
+    fn test_report_destination() {
+        let args = Args::parse_from(["nomoji", "a.txt"]);
+        assert_eq!(args.report_destination(), Destination::Stderr);
+        assert!(args.streams_cleaned());
+
+        let args = Args::parse_from(["nomoji", "--format", "json", "a.txt"]);
+        assert_eq!(args.report_destination(), Destination::Stdout);
+        assert!(!args.streams_cleaned());
+
+        // Moving a machine-readable report off stdout frees it for output.
+        let args = Args::parse_from(["nomoji", "--format", "json", "--report-to", "fd:3", "-"]);
+        assert!(args.streams_cleaned());
+        let args = Args::parse_from(["nomoji", "--report-to", "stdout", "a.txt"]);
+        assert!(!args.streams_cleaned());
+    }
+
+    #[test]
+    fn test_expand_recursive() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("docs")).unwrap();
+        let nested = dir.path().join("docs/guide.md");
+        fs::write(&nested, "Ship 🚀").unwrap();
+        let root = dir.path().to_str().unwrap();
+        let file = nested.to_str().unwrap();
+
+        let mut args = Args::parse_from(["nomoji", "-r", root]);
+        assert!(args.expand_recursive().unwrap());
+        assert_eq!(args.files, [file]);
+        assert!(args.dry_run && !args.writes_files());
+        process_files(&args, || false);
+        assert_eq!(fs::read_to_string(&nested).unwrap(), "Ship 🚀");
+
+        let mut args = Args::parse_from(["nomoji", "-r", "--write", root]);
+        assert!(!args.expand_recursive().unwrap());
+        assert!(!args.dry_run);
+        process_files(&args, || false);
+        assert_eq!(fs::read_to_string(&nested).unwrap(), "Ship ");
+
+        // The config file can restore editing in place by default.
+        let mut args = Args::parse_from(["nomoji", "-r", root]);
+        args.settings.write_recursive = true;
+        assert!(!args.expand_recursive().unwrap());
+        assert!(args.inplace);
+
+        let mut args = Args::parse_from(["nomoji", "-r", "--suffix", ".clean", file]);
+        assert!(!args.expand_recursive().unwrap());
+        assert_eq!(args.files, [file]);
+
+        let mut args = Args::parse_from(["nomoji", file]);
+        assert!(!args.expand_recursive().unwrap());
+        assert!(!args.dry_run);
     }
 
     #[test]
@@ -549,6 +5707,283 @@ mod tests {
 
         let args = Args::parse_from(["nomoji", "--dry-run", "file.txt"]);
         assert!(args.dry_run);
+
+        let args = Args::parse_from(["nomoji", "--throttle", "2M", "--nice", "file.txt"]);
+        assert_eq!(args.throttle, Some(Rate::BytesPerSec(2 << 20)));
+        assert!(args.nice);
+        assert!(Args::try_parse_from(["nomoji", "--throttle", "soon", "file.txt"]).is_err());
+
+        let args = Args::parse_from(["nomoji", "--label", "src/lib.rs", "-"]);
+        assert_eq!(args.stdin_filename.as_deref(), Some("src/lib.rs"));
+        let args = Args::parse_from(["nomoji", "--stdin-filename", "a.md", "-"]);
+        assert_eq!(args.stdin_filename.as_deref(), Some("a.md"));
+    }
+
+    #[test]
+    fn test_check_idempotent() {
+        assert!(check_idempotent("").is_ok());
+        assert!(check_idempotent("Plain text").is_ok());
+        assert!(check_idempotent("Family: 👨‍👩‍👧‍👦 #️⃣ 🇺🇸 👋🏽").is_ok());
+    }
+
+    #[test]
+    fn test_cli_check_idempotent_subcommand() {
+        let args = Args::parse_from(["nomoji", "check-idempotent", "a.txt", "-"]);
+        match args.command {
+            Some(Command::CheckIdempotent { files }) => assert_eq!(files, ["a.txt", "-"]),
+            other => panic!("unexpected command: {:?}", other),
+        }
+
+        let args = Args::parse_from(["nomoji", "a.txt"]);
+        assert!(args.command.is_none());
+        assert_eq!(args.files, ["a.txt"]);
+    }
+
+    #[test]
+    fn test_filter_blob() {
+        let blob = "Status: ✅ done 🚀\n".as_bytes().to_vec();
+        assert_eq!(filter_blob(blob.clone(), true), b"Status:  done \n");
+        assert_eq!(filter_blob(blob.clone(), false), blob);
+
+        let binary = vec![0xF0, 0x9F, 0x98, 0x80, 0xFF, 0x00];
+        assert_eq!(filter_blob(binary.clone(), true), binary);
+    }
+
+    #[test]
+    fn test_cli_filter_requires_mode() {
+        assert!(Args::try_parse_from(["nomoji", "filter"]).is_err());
+        assert!(Args::try_parse_from(["nomoji", "filter", "--clean", "--smudge"]).is_err());
+
+        let args = Args::parse_from(["nomoji", "filter", "--smudge"]);
+        assert!(matches!(
+            args.command,
+            Some(Command::Filter {
+                clean: false,
+                smudge: true
+            })
+        ));
+    }
+
+    #[test]
+    fn test_emoji_spans_group_sequences() {
+        let input = "a👨‍👩‍👧b 🇺🇸🇬🇧 👋🏽 #️⃣ 😀😃";
+        let spans: Vec<&str> = emoji_spans(input).into_iter().map(|r| &input[r]).collect();
+        assert_eq!(
+            spans,
+            ["👨‍👩‍👧", "🇺🇸", "🇬🇧", "👋🏽", "\u{FE0F}\u{20E3}", "😀", "😃"]
+        );
+    }
+
+    #[test]
+    fn test_emoji_spans_cover_every_removed_char() {
+        let input = "Couple: 👩‍❤️‍👨 Profession: 👨‍🚀👩‍⚕️ ✅ done";
+        let covered: usize = emoji_spans(input)
+            .into_iter()
+            .map(|r| input[r].chars().count())
+            .sum();
+        assert_eq!(covered, remove_emojis(input).1);
+    }
+
+    #[test]
+    fn test_find_occurrences_positions() {
+        let content = "plain\nok 🚀 and 🔥\r\n😀\n";
+        let findings = find_occurrences(content, emoji_spans(content));
+        assert_eq!(
+            findings,
+            vec![
+                Finding {
+                    line: 2,
+                    column: 4,
+                    line_text: "ok 🚀 and 🔥".to_string(),
+                    emoji: "🚀".to_string(),
+                    replacement: String::new(),
+                },
+                Finding {
+                    line: 2,
+                    column: 13,
+                    line_text: "ok 🚀 and 🔥".to_string(),
+                    emoji: "🔥".to_string(),
+                    replacement: String::new(),
+                },
+                Finding {
+                    line: 3,
+                    column: 1,
+                    line_text: "😀".to_string(),
+                    emoji: "😀".to_string(),
+                    replacement: String::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_file_vimgrep_leaves_file_untouched() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(temp_file, "Title 🎉").unwrap();
+        let path = temp_file.path().to_str().unwrap();
+
+        let args = Args::parse_from(["nomoji", "--format", "vimgrep", path]);
+        let result = process_file(path, &args);
+        assert!(result.success);
+        assert_eq!(result.findings.len(), 1);
+        assert_eq!(result.findings[0].column, 7);
+        assert!(fs::read_to_string(path).unwrap().contains("🎉"));
+    }
+
+    #[test]
+    fn test_teamcity_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("it's.md");
+        fs::write(&path, "ok\nShip 🚀 [now]\n").unwrap();
+        let file = path.to_str().unwrap();
+
+        let args = Args::parse_from(["nomoji", "--format", "teamcity", file]);
+        let results = vec![
+            process_file(file, &args),
+            ProcessResult::failed("gone", 0, "missing".to_string()),
+        ];
+        let mut out = Vec::new();
+        write_report(&results, &args, &mut out).unwrap();
+        let report = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert!(lines[0].starts_with("##teamcity[inspectionType id='nomoji.emoji' "));
+        assert_eq!(
+            lines[1],
+            format!(
+                "##teamcity[inspection typeId='nomoji.emoji' message='Emoji 🚀 (U+1F680) at column 6' \
+                 file='{}' line='2' SEVERITY='WARNING']",
+                file.replace('\'', "|'")
+            )
+        );
+        assert_eq!(
+            lines[2],
+            "##teamcity[buildProblem description='nomoji: gone: missing']"
+        );
+        assert_eq!(
+            lines[3],
+            "##teamcity[buildStatisticValue key='nomoji.emojis' value='1']"
+        );
+        assert!(fs::read_to_string(&path).unwrap().contains("🚀"));
+    }
+
+    #[test]
+    fn test_jsonl_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        fs::write(&path, "ok\nShip 🚀\n\u{FE0F}\n").unwrap();
+        let file = path.to_str().unwrap();
+
+        let args = Args::parse_from(["nomoji", "--format", "jsonl", file]);
+        let results = vec![
+            process_file(file, &args),
+            ProcessResult::failed("gone", 0, "missing".to_string()),
+        ];
+        let mut out = Vec::new();
+        write_report(&results, &args, &mut out).unwrap();
+        let report = String::from_utf8(out).unwrap();
+        let lines: Vec<json::Value> = report.lines().map(|l| json::parse(l).unwrap()).collect();
+        let field = |line: usize, key: &str| lines[line].get(key).cloned();
+        assert_eq!(lines.len(), 5);
+        assert!(
+            lines
+                .iter()
+                .all(|l| l.get("schema_version").unwrap().as_i64() == Some(1))
+        );
+
+        assert_eq!(field(0, "type"), Some("occurrence".into()));
+        assert_eq!(field(0, "file"), Some(file.into()));
+        assert_eq!(field(0, "line"), Some(2.into()));
+        assert_eq!(field(0, "column"), Some(6.into()));
+        assert_eq!(field(0, "shortcode"), Some(":rocket:".into()));
+        assert_eq!(field(0, "role"), Some("pictograph".into()));
+        assert_eq!(field(1, "line"), Some(3.into()));
+        assert_eq!(field(1, "role"), Some("variation_selector".into()));
+        assert_eq!(field(2, "type"), Some("file".into()));
+        assert_eq!(field(2, "emojis"), Some(2.into()));
+        assert_eq!(field(3, "file"), Some("gone".into()));
+        assert_eq!(field(3, "error"), Some("missing".into()));
+        assert_eq!(field(4, "type"), Some("summary".into()));
+        assert_eq!(field(4, "files_processed"), Some(2.into()));
+        assert!(fs::read_to_string(&path).unwrap().contains("🚀"));
+    }
+
+    #[test]
+    fn test_rdjson_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        fs::write(&path, "ok\nShip 🚀 now 🔥!\nDone ✅\n").unwrap();
+        let file = path.to_str().unwrap();
+
+        let args = Args::parse_from(["nomoji", "--format", "rdjson", "--to-entities", file]);
+        let results = vec![process_file(file, &args)];
+        let mut out = Vec::new();
+        write_report(&results, &args, &mut out).unwrap();
+        let report = json::parse(&String::from_utf8(out).unwrap()).unwrap();
+        assert_eq!(
+            report.get("source").and_then(|s| s.get("name")),
+            Some(&json::Value::from("nomoji"))
+        );
+        let diagnostics = report.get("diagnostics").unwrap().as_array().unwrap();
+        assert_eq!(diagnostics.len(), 2);
+
+        let first = &diagnostics[0];
+        assert_eq!(
+            first.get("message").unwrap().as_str(),
+            Some("2 emojis: 🚀 🔥")
+        );
+        let range = first.get("location").unwrap().get("range").unwrap();
+        let column = |position: &str| range.get(position).unwrap().get("column").unwrap().as_i64();
+        assert_eq!((column("start"), column("end")), (Some(6), Some(19)));
+        let suggestion = &first.get("suggestions").unwrap().as_array().unwrap()[0];
+        assert_eq!(
+            suggestion.get("text").unwrap().as_str(),
+            Some("Ship &#x1F680; now &#x1F525;!")
+        );
+        let end = suggestion.get("range").unwrap().get("end").unwrap();
+        assert_eq!(end.get("column").unwrap().as_i64(), Some(20));
+
+        assert_eq!(
+            diagnostics[1].get("message").unwrap().as_str(),
+            Some("Emoji ✅ (U+2705)")
+        );
+        assert!(fs::read_to_string(&path).unwrap().contains("🚀"));
+    }
+
+    #[test]
+    fn test_checkstyle_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a&b.md");
+        fs::write(&path, "ok\nShip 🚀\n").unwrap();
+        let file = path.to_str().unwrap();
+        let clean = dir.path().join("clean.md");
+        fs::write(&clean, "plain\n").unwrap();
+        let clean = clean.to_str().unwrap();
+
+        let args = Args::parse_from(["nomoji", "--format", "checkstyle", file, clean]);
+        let results = vec![
+            process_file(file, &args),
+            process_file(clean, &args),
+            ProcessResult::failed("gone", 0, "Failed to read file: <missing>".to_string()),
+        ];
+        let mut out = Vec::new();
+        write_report(&results, &args, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <checkstyle version=\"8.0\">\n  \
+                 <file name=\"{}\">\n    \
+                 <error line=\"2\" column=\"6\" severity=\"warning\" message=\"Emoji 🚀 (U+1F680)\" source=\"nomoji.emoji\"/>\n  \
+                 </file>\n  \
+                 <file name=\"{}\"/>\n  \
+                 <file name=\"gone\">\n    \
+                 <error severity=\"error\" message=\"Failed to read file: &lt;missing&gt;\" source=\"nomoji.failed\"/>\n  \
+                 </file>\n\
+                 </checkstyle>\n",
+                file.replace('&', "&amp;"),
+                clean
+            )
+        );
     }
 
     #[test]
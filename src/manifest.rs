@@ -0,0 +1,220 @@
+//! Input manifests for `--files-from`.
+//!
+//! A plain text manifest lists one path per line; blank lines and lines
+//! starting with `#` are skipped. A `.toml` or `.json` manifest holds a
+//! `files` array whose entries are paths or tables with per-file overrides:
+//!
+//! ```toml
+//! files = [
+//!     "CHANGELOG.md",
+//!     { path = "docs/intro.md", mode = "markdown", skip = ["flags"] },
+//! ]
+//! ```
+//!
+//! An override replaces the command-line and configuration file setting of
+//! the same name for that file.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::config::categories;
+use crate::json;
+use crate::mode::Mode;
+use crate::toml::{self, Table, Value};
+use nomoji::category::Category;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Entry {
+    pub path: String,
+    pub mode: Option<Mode>,
+    pub only: Option<Vec<Category>>,
+    pub skip: Option<Vec<Category>>,
+    pub keep: Option<Vec<Category>>,
+}
+
+/// Reads the manifest at `path`, or a plain list from stdin for `-`.
+pub fn load(path: &Path) -> Result<Vec<Entry>, String> {
+    let describe = |e: String| format!("{}: {}", path.display(), e);
+
+    if path == Path::new("-") {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| describe(e.to_string()))?;
+        return Ok(parse_list(&text));
+    }
+
+    let text = fs::read_to_string(path).map_err(|e| describe(e.to_string()))?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => parse_toml(&text).map_err(describe),
+        Some("json") => parse_json(&text).map_err(describe),
+        _ => Ok(parse_list(&text)),
+    }
+}
+
+pub fn parse_list(text: &str) -> Vec<Entry> {
+    text.lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|path| Entry {
+            path: path.to_string(),
+            ..Entry::default()
+        })
+        .collect()
+}
+
+pub fn parse_toml(text: &str) -> Result<Vec<Entry>, String> {
+    files(toml::parse(text)?)
+}
+
+/// JSON manifests have the same shape as TOML ones; a bare array is taken
+/// as the `files` list.
+pub fn parse_json(text: &str) -> Result<Vec<Entry>, String> {
+    match from_json(json::parse(text)?)? {
+        Value::Table(table) => files(table),
+        array @ Value::Array(_) => files(BTreeMap::from([("files".to_string(), array)])),
+        other => Err(format!(
+            "manifest must be an object or array, not {}",
+            other.type_name()
+        )),
+    }
+}
+
+fn files(table: Table) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+
+    for (key, value) in table {
+        if key != "files" {
+            return Err(format!("unknown key '{}'", key));
+        }
+        let Value::Array(items) = value else {
+            return Err("'files' must be an array".to_string());
+        };
+        for (i, item) in items.into_iter().enumerate() {
+            entries.push(entry(item).map_err(|e| format!("files[{}]: {}", i, e))?);
+        }
+    }
+
+    Ok(entries)
+}
+
+fn entry(item: Value) -> Result<Entry, String> {
+    let table = match item {
+        Value::String(path) => {
+            return Ok(Entry {
+                path,
+                ..Entry::default()
+            });
+        }
+        Value::Table(table) => table,
+        other => {
+            return Err(format!(
+                "must be a path or a table, not {}",
+                other.type_name()
+            ));
+        }
+    };
+
+    let mut entry = Entry::default();
+    let mut has_path = false;
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("path", Value::String(path)) => {
+                entry.path = path;
+                has_path = true;
+            }
+            ("mode", Value::String(mode)) => entry.mode = Some(Mode::from_name(&mode)?),
+            ("only", value) => entry.only = Some(categories(&key, value)?),
+            ("skip", value) => entry.skip = Some(categories(&key, value)?),
+            ("keep", value) => entry.keep = Some(categories(&key, value)?),
+            ("path" | "mode", value) => {
+                return Err(format!(
+                    "'{}' must be a string, not {}",
+                    key,
+                    value.type_name()
+                ));
+            }
+            _ => return Err(format!("unknown key '{}'", key)),
+        }
+    }
+
+    if !has_path {
+        return Err("missing 'path'".to_string());
+    }
+    Ok(entry)
+}
+
+/// Converts JSON to the TOML value model so both formats share one reader.
+fn from_json(value: json::Value) -> Result<Value, String> {
+    Ok(match value {
+        json::Value::Null => return Err("null is not allowed in a manifest".to_string()),
+        json::Value::Bool(b) => Value::Boolean(b),
+        json::Value::Number(n) => Value::Integer(n),
+        json::Value::String(s) => Value::String(s),
+        json::Value::Array(items) => {
+            Value::Array(items.into_iter().map(from_json).collect::<Result<_, _>>()?)
+        }
+        json::Value::Object(fields) => Value::Table(
+            fields
+                .into_iter()
+                .map(|(k, v)| Ok((k, from_json(v)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_list() {
+        let entries = parse_list("# inputs\na.txt\r\n\n  \ndocs/b c.md\n");
+        let paths: Vec<_> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["a.txt", "docs/b c.md"]);
+    }
+
+    #[test]
+    fn test_parse_toml_and_json_agree() {
+        let toml = parse_toml(
+            r#"
+files = [
+  "a.txt",
+  { path = "b.md", mode = "markdown", skip = ["flags"] },
+]
+"#,
+        )
+        .unwrap();
+        let json =
+            parse_json(r#"["a.txt", {"path": "b.md", "mode": "markdown", "skip": ["flags"]}]"#)
+                .unwrap();
+
+        assert_eq!(toml, json);
+        assert_eq!(
+            toml[1],
+            Entry {
+                path: "b.md".to_string(),
+                mode: Some(Mode::Markdown),
+                skip: Some(vec![Category::Flags]),
+                ..Entry::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_toml("[[files]]\nmode = \"comments\"").unwrap_err(),
+            "files[0]: missing 'path'"
+        );
+        assert!(
+            parse_json(r#"{"files": [{"path": "a", "colour": 1}]}"#)
+                .unwrap_err()
+                .contains("unknown key 'colour'")
+        );
+        assert!(parse_json(r#"{"files": [null]}"#).is_err());
+        assert!(parse_toml("inputs = []").is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//! mbox archives for `--mbox`: each message is cleaned as by `--eml`.
+//!
+//! A message starts at a `From ` line at the beginning of the file or after
+//! a blank line. The `From ` lines themselves are kept as they are.
+
+use crate::eml::{self, Clean};
+
+/// The messages in `mbox`, each starting with its `From ` line. Anything
+/// before the first one comes first, as a message of its own.
+pub fn messages(mbox: &[u8]) -> Vec<&[u8]> {
+    let mut messages = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+    let mut after_blank = true;
+
+    for line in mbox.split_inclusive(|&b| b == b'\n') {
+        if after_blank && line.starts_with(b"From ") && offset > start {
+            messages.push(&mbox[start..offset]);
+            start = offset;
+        }
+        after_blank = line == b"\n" || line == b"\r\n";
+        offset += line.len();
+    }
+    if offset > start {
+        messages.push(&mbox[start..]);
+    }
+    messages
+}
+
+/// Cleans every message in `mbox`, returning the rewritten archive and the
+/// number of characters removed from each message.
+pub fn clean(mbox: &[u8], clean: &Clean) -> (Vec<u8>, Vec<usize>) {
+    let mut out = Vec::with_capacity(mbox.len());
+    let mut counts = Vec::new();
+
+    for message in messages(mbox) {
+        let split = if message.starts_with(b"From ") {
+            message
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(message.len(), |i| i + 1)
+        } else {
+            0
+        };
+        let (from_line, content) = message.split_at(split);
+        let (cleaned, count) = eml::clean(content, clean);
+
+        out.extend_from_slice(from_line);
+        if count == 0 {
+            out.extend_from_slice(content);
+        } else {
+            out.extend(quote_from_lines(&cleaned));
+        }
+        counts.push(count);
+    }
+    (out, counts)
+}
+
+/// Quotes body lines that now start with `From `, which removing an emoji
+/// in front of them can produce, so they don't split the message.
+fn quote_from_lines(message: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(message.len());
+    for line in message.split_inclusive(|&b| b == b'\n') {
+        if line.starts_with(b"From ") {
+            out.push(b'>');
+        }
+        out.extend_from_slice(line);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip(text: &str) -> (String, usize) {
+        crate::remove_emojis(text)
+    }
+
+    #[test]
+    fn test_messages() {
+        let mbox = b"From a@example.com Mon Jan 1 00:00:00 2024\n\
+                     Subject: one\n\
+                     \n\
+                     Hello\n\
+                     From the team\n\
+                     \n\
+                     From b@example.com Tue Jan 2 00:00:00 2024\n\
+                     Subject: two\n\
+                     \n\
+                     body\n";
+        let messages = messages(mbox);
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].ends_with(b"From the team\n\n"));
+        assert!(messages[1].starts_with(b"From b@example.com"));
+    }
+
+    #[test]
+    fn test_clean() {
+        let mbox = "From a@example.com Mon Jan 1 00:00:00 2024\n\
+                    Subject: hi 🎉\n\
+                    \n\
+                    🚀From here on\n\
+                    \n\
+                    From b@example.com Tue Jan 2 00:00:00 2024\n\
+                    Subject: plain\n\
+                    \n\
+                    Hello\n\
+                    From me, unquoted\n";
+        let (cleaned, counts) = clean(mbox.as_bytes(), &strip);
+        assert_eq!(counts, [2, 0]);
+        assert_eq!(
+            String::from_utf8(cleaned).unwrap(),
+            "From a@example.com Mon Jan 1 00:00:00 2024\n\
+             Subject: hi \n\
+             \n\
+             >From here on\n\
+             \n\
+             From b@example.com Tue Jan 2 00:00:00 2024\n\
+             Subject: plain\n\
+             \n\
+             Hello\n\
+             From me, unquoted\n"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Prometheus metrics for long-running `--rpc` sessions, served over HTTP at
+//! `/metrics` when `--metrics-addr` is given.
+
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Upper bounds of the latency histogram buckets, in seconds.
+const BUCKETS: [f64; 8] = [0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0];
+
+/// Counters the RPC server updates, shared with the HTTP thread.
+pub struct Metrics {
+    files: AtomicU64,
+    emojis: AtomicU64,
+    errors: AtomicU64,
+    /// Cumulative counts per bucket, as Prometheus histograms expect.
+    buckets: [AtomicU64; BUCKETS.len()],
+    latency_micros: AtomicU64,
+}
+
+/// The metrics of this process.
+pub static METRICS: Metrics = Metrics::new();
+
+impl Metrics {
+    pub const fn new() -> Self {
+        Metrics {
+            files: AtomicU64::new(0),
+            emojis: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            buckets: [const { AtomicU64::new(0) }; BUCKETS.len()],
+            latency_micros: AtomicU64::new(0),
+        }
+    }
+
+    /// Records one processed document: how many emojis were removed from
+    /// it, how long it took, and whether it failed.
+    pub fn record(&self, emojis: usize, elapsed: Duration, failed: bool) {
+        self.files.fetch_add(1, Ordering::Relaxed);
+        self.emojis.fetch_add(emojis as u64, Ordering::Relaxed);
+        if failed {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        let seconds = elapsed.as_secs_f64();
+        for (bucket, &bound) in self.buckets.iter().zip(&BUCKETS) {
+            if seconds <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.latency_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// The metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let get = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let mut out = String::new();
+        let mut counter = |name: &str, help: &str, value: u64| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, value);
+        };
+        counter(
+            "nomoji_files_processed_total",
+            "Documents processed.",
+            get(&self.files),
+        );
+        counter(
+            "nomoji_emojis_removed_total",
+            "Emoji characters removed.",
+            get(&self.emojis),
+        );
+        counter(
+            "nomoji_errors_total",
+            "Requests that failed.",
+            get(&self.errors),
+        );
+
+        let name = "nomoji_processing_seconds";
+        let _ = writeln!(out, "# HELP {} Time spent processing a document.", name);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS) {
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, get(bucket));
+        }
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, get(&self.files));
+        let _ = writeln!(
+            out,
+            "{}_sum {}",
+            name,
+            get(&self.latency_micros) as f64 / 1e6
+        );
+        let _ = writeln!(out, "{}_count {}", name, get(&self.files));
+        out
+    }
+}
+
+/// Serves `metrics` at `http://addr/metrics` from a background thread and
+/// returns the bound address.
+pub fn serve(addr: &str, metrics: &'static Metrics) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(addr)?;
+    let local = listener.local_addr()?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // A misbehaving scraper only loses its own response.
+            let _ = respond(stream, metrics);
+        }
+    });
+    Ok(local)
+}
+
+fn respond(mut stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+
+    let (status, body) = if path == "/metrics" {
+        ("200 OK", metrics.render())
+    } else {
+        ("404 Not Found", "not found\n".to_string())
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\n\
+         Content-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::new();
+        metrics.record(3, Duration::from_micros(800), false);
+        metrics.record(0, Duration::from_millis(20), true);
+        let text = metrics.render();
+
+        assert!(text.contains("nomoji_files_processed_total 2\n"));
+        assert!(text.contains("nomoji_emojis_removed_total 3\n"));
+        assert!(text.contains("nomoji_errors_total 1\n"));
+        assert!(text.contains("nomoji_processing_seconds_bucket{le=\"0.0005\"} 0\n"));
+        assert!(text.contains("nomoji_processing_seconds_bucket{le=\"0.001\"} 1\n"));
+        assert!(text.contains("nomoji_processing_seconds_bucket{le=\"0.05\"} 2\n"));
+        assert!(text.contains("nomoji_processing_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(text.contains("nomoji_processing_seconds_sum 0.0208\n"));
+    }
+
+    #[test]
+    fn test_serve() {
+        static SERVED: Metrics = Metrics::new();
+        SERVED.record(1, Duration::ZERO, false);
+        let addr = serve("127.0.0.1:0", &SERVED).unwrap();
+
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: x\r\n\r\n", path).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let response = get("/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(&SERVED.render()));
+        assert!(get("/").starts_with("HTTP/1.1 404"));
+    }
+}
@@ -0,0 +1,379 @@
+//! File-type aware cleaning modes selected by configuration rules.
+//!
+//! A mode limits cleaning to the parts of a file where emoji are unwanted:
+//! prose outside code in Markdown, comments in source code, or string values
+//! in JSON-like data. Everything outside those regions is left byte-for-byte.
+
+use std::ops::Range;
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use crate::emoji_names::EMOJI_NAMES;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Remove emojis anywhere in the file
+    #[default]
+    All,
+    /// Replace emojis in Markdown prose with :shortcodes:, leaving code
+    /// blocks and inline code alone
+    Markdown,
+    /// Remove emojis only inside comments
+    Comments,
+    /// Remove emojis only inside string values, not keys
+    Strings,
+}
+
+impl Mode {
+    pub fn from_name(name: &str) -> Result<Mode, String> {
+        Mode::from_str(name, false).map_err(|_| {
+            format!(
+                "unknown mode '{}' (expected all, markdown, comments or strings)",
+                name
+            )
+        })
+    }
+}
+
+/// Comment and string syntax of a source language, chosen by extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Syntax {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+}
+
+const HASH: Syntax = Syntax {
+    line: &["#"],
+    block: None,
+    quotes: &['"', '\''],
+};
+
+const C_LIKE: Syntax = Syntax {
+    line: &["//"],
+    block: Some(("/*", "*/")),
+    quotes: &['"', '\''],
+};
+
+/// Rust lifetimes make `'` unusable as a string delimiter.
+const RUST: Syntax = Syntax {
+    line: &["//"],
+    block: Some(("/*", "*/")),
+    quotes: &['"'],
+};
+
+const JS_LIKE: Syntax = Syntax {
+    line: &["//"],
+    block: Some(("/*", "*/")),
+    quotes: &['"', '\'', '`'],
+};
+
+const DASH: Syntax = Syntax {
+    line: &["--"],
+    block: Some(("/*", "*/")),
+    quotes: &['"', '\''],
+};
+
+const MARKUP: Syntax = Syntax {
+    line: &[],
+    block: Some(("<!--", "-->")),
+    quotes: &[],
+};
+
+/// Used for unrecognised extensions: both common comment styles.
+const GENERIC: Syntax = Syntax {
+    line: &["#", "//"],
+    block: Some(("/*", "*/")),
+    quotes: &['"'],
+};
+
+impl Default for Syntax {
+    fn default() -> Self {
+        GENERIC
+    }
+}
+
+impl Syntax {
+    pub fn for_path(path: &str) -> Syntax {
+        let path = Path::new(path);
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match extension.as_str() {
+            "py" | "sh" | "bash" | "zsh" | "rb" | "pl" | "r" | "yaml" | "yml" | "toml" | "ini"
+            | "cfg" | "conf" | "ps1" | "cmake" | "tf" | "nix" | "mk" => HASH,
+            "rs" => RUST,
+            "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "java" | "go" | "swift" | "kt" | "kts"
+            | "cs" | "scala" | "dart" | "php" | "css" | "scss" => C_LIKE,
+            "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" => JS_LIKE,
+            "sql" | "lua" | "hs" => DASH,
+            "html" | "htm" | "xml" | "svg" | "vue" => MARKUP,
+            _ if path
+                .file_name()
+                .is_some_and(|name| name == "Makefile" || name == "Dockerfile") =>
+            {
+                HASH
+            }
+            _ => GENERIC,
+        }
+    }
+}
+
+/// Byte ranges of `text` that `mode` allows to change, in order, or `None`
+/// when the whole text is editable.
+pub fn editable_regions(text: &str, mode: Mode, syntax: Syntax) -> Option<Vec<Range<usize>>> {
+    match mode {
+        Mode::All => None,
+        Mode::Markdown => Some(markdown_prose(text)),
+        Mode::Comments => Some(comments(text, syntax)),
+        Mode::Strings => Some(string_values(text)),
+    }
+}
+
+/// Everything outside fenced code blocks and inline code spans.
+fn markdown_prose(text: &str) -> Vec<Range<usize>> {
+    let mut regions = Vec::new();
+    let mut fence: Option<(char, usize)> = None;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+
+        let trimmed = line.trim_start_matches(' ');
+        let indent = line.len() - trimmed.len();
+        let marker = trimmed.chars().next().filter(|&c| c == '`' || c == '~');
+        if let Some(marker) = marker.filter(|_| indent < 4) {
+            let run = trimmed.chars().take_while(|&c| c == marker).count();
+            if run >= 3 {
+                match fence {
+                    None => {
+                        fence = Some((marker, run));
+                        continue;
+                    }
+                    Some((open, len))
+                        if open == marker && run >= len && trimmed[run..].trim().is_empty() =>
+                    {
+                        fence = None;
+                        continue;
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        if fence.is_none() {
+            regions.extend(outside_code_spans(line, start));
+        }
+    }
+
+    regions
+}
+
+/// Splits one line around backtick code spans. A run of backticks opens a
+/// span closed by the next run of the same length; an unmatched run is text.
+fn outside_code_spans(line: &str, offset: usize) -> Vec<Range<usize>> {
+    let bytes = line.as_bytes();
+    let run_at = |i: usize| bytes[i..].iter().take_while(|&&b| b == b'`').count();
+
+    let mut regions = Vec::new();
+    let mut prose_start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'`' {
+            i += 1;
+            continue;
+        }
+
+        let run = run_at(i);
+        let mut j = i + run;
+        let mut close = None;
+        while j < bytes.len() {
+            if bytes[j] == b'`' {
+                let other = run_at(j);
+                if other == run {
+                    close = Some(j + other);
+                    break;
+                }
+                j += other;
+            } else {
+                j += 1;
+            }
+        }
+
+        match close {
+            Some(end) => {
+                regions.push(offset + prose_start..offset + i);
+                prose_start = end;
+                i = end;
+            }
+            None => i += run,
+        }
+    }
+
+    regions.push(offset + prose_start..offset + bytes.len());
+    regions.retain(|r| !r.is_empty());
+    regions
+}
+
+fn comments(text: &str, syntax: Syntax) -> Vec<Range<usize>> {
+    let mut regions = Vec::new();
+    let mut i = 0;
+
+    while let Some(c) = text[i..].chars().next() {
+        let rest = &text[i..];
+        if syntax.line.iter().any(|marker| rest.starts_with(marker)) {
+            let end = rest.find('\n').map_or(text.len(), |n| i + n);
+            regions.push(i..end);
+            i = end;
+        } else if let Some((open, close)) = syntax.block.filter(|(open, _)| rest.starts_with(open))
+        {
+            let end = rest[open.len()..]
+                .find(close)
+                .map_or(text.len(), |n| i + open.len() + n + close.len());
+            regions.push(i..end);
+            i = end;
+        } else if syntax.quotes.contains(&c) {
+            i = skip_string(text, i, c);
+        } else {
+            i += c.len_utf8();
+        }
+    }
+
+    regions
+}
+
+/// The contents of double-quoted strings not followed by `:`, i.e. values
+/// rather than object keys.
+fn string_values(text: &str) -> Vec<Range<usize>> {
+    let mut regions = Vec::new();
+    let mut i = 0;
+
+    while let Some(c) = text[i..].chars().next() {
+        if c == '"' {
+            let end = skip_string(text, i, '"');
+            if !text[end..].trim_start().starts_with(':') {
+                regions.push(i..end);
+            }
+            i = end;
+        } else {
+            i += c.len_utf8();
+        }
+    }
+
+    regions
+}
+
+/// Returns the offset just past the string literal opened by the ASCII
+/// `quote` at `start`. Strings end at an unescaped newline so a stray
+/// apostrophe cannot swallow the rest of the file.
+fn skip_string(text: &str, start: usize, quote: char) -> usize {
+    let body = start + quote.len_utf8();
+    let mut chars = text[body..].char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\n' => return body + i,
+            c if c == quote => return body + i + c.len_utf8(),
+            _ => {}
+        }
+    }
+
+    text.len()
+}
+
+/// The `:shortcode:` for an emoji sequence, built from the Unicode names of
+/// its characters; `None` when nothing in it has a name (a lone component).
+pub fn shortcode(sequence: &str) -> Option<String> {
+    if crate::is_flag(sequence) {
+        let letters: String = sequence
+            .chars()
+            .filter_map(|c| char::from_u32(c as u32 - 0x1F1E6 + 'a' as u32))
+            .collect();
+        return Some(format!(":flag_{}:", letters));
+    }
+
+    let names: Vec<&str> = sequence
+        .chars()
+        .filter(|&c| !crate::is_emoji_component(c) && !crate::is_regional_indicator(c))
+        .filter_map(name)
+        .collect();
+    if names.is_empty() {
+        None
+    } else {
+        Some(format!(":{}:", names.join("_")))
+    }
+}
+
+fn name(c: char) -> Option<&'static str> {
+    EMOJI_NAMES
+        .binary_search_by_key(&c, |&(emoji, _)| emoji)
+        .ok()
+        .map(|i| EMOJI_NAMES[i].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pieces(text: &str, regions: Vec<Range<usize>>) -> Vec<&str> {
+        regions.into_iter().map(|r| &text[r]).collect()
+    }
+
+    #[test]
+    fn test_markdown_prose() {
+        let text = "Intro `code 🚀` end\n```rust\nlet x = \"🚀\";\n```\nafter ``a ` b`` 🎉\n";
+        let prose = pieces(text, markdown_prose(text));
+        assert_eq!(prose, ["Intro ", " end\n", "after ", " 🎉\n"]);
+
+        // An unclosed fence runs to the end of the file.
+        assert_eq!(
+            pieces("a\n~~~~\n🚀\n", markdown_prose("a\n~~~~\n🚀\n")),
+            ["a\n"]
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        let text = "x = \"# not 🚀\"  # yes 🎉\ny = 'it''s' # ok\n";
+        assert_eq!(
+            pieces(text, comments(text, Syntax::for_path("a.py"))),
+            ["# yes 🎉", "# ok"]
+        );
+
+        let text = "let s = \"// no\"; /* one\ntwo */ f(); // three 🦀";
+        assert_eq!(
+            pieces(text, comments(text, Syntax::for_path("src/lib.rs"))),
+            ["/* one\ntwo */", "// three 🦀"]
+        );
+    }
+
+    #[test]
+    fn test_string_values() {
+        let text = r#"{"🚀 key": "value 🎉", "n": 1, "list": ["a\"b", "c"]}"#;
+        assert_eq!(
+            pieces(text, string_values(text)),
+            [r#""value 🎉""#, r#""a\"b""#, r#""c""#]
+        );
+    }
+
+    #[test]
+    fn test_shortcode() {
+        assert_eq!(shortcode("🚀").as_deref(), Some(":rocket:"));
+        assert_eq!(shortcode("👍🏽").as_deref(), Some(":thumbs_up_sign:"));
+        assert_eq!(shortcode("🇫🇷").as_deref(), Some(":flag_fr:"));
+        assert_eq!(
+            shortcode("👨\u{200D}💻").as_deref(),
+            Some(":man_personal_computer:")
+        );
+        assert_eq!(shortcode("\u{FE0F}\u{20E3}"), None);
+    }
+}
@@ -0,0 +1,188 @@
+//! Unicode canonical normalization (NFC and NFD) for cleaned output.
+//!
+//! Implements the algorithms from UAX #15 over tables generated by
+//! `scripts/gen_normalization_tables.py`. Hangul syllables are handled
+//! arithmetically rather than through the tables.
+
+use clap::ValueEnum;
+
+use crate::normalize_tables::{COMBINING_CLASS, COMPOSITION, DECOMPOSITION};
+
+/// Normalization forms accepted by `--normalize`.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Form {
+    /// Leave text as it is
+    #[default]
+    None,
+    /// Canonical composition
+    Nfc,
+    /// Canonical decomposition
+    Nfd,
+}
+
+pub fn normalize(text: &str, form: Form) -> String {
+    match form {
+        Form::None => text.to_string(),
+        Form::Nfd => decompose(text).into_iter().collect(),
+        Form::Nfc => compose(decompose(text)).into_iter().collect(),
+    }
+}
+
+const HANGUL_S_BASE: u32 = 0xAC00;
+const HANGUL_L_BASE: u32 = 0x1100;
+const HANGUL_V_BASE: u32 = 0x1161;
+const HANGUL_T_BASE: u32 = 0x11A7;
+const HANGUL_L_COUNT: u32 = 19;
+const HANGUL_V_COUNT: u32 = 21;
+const HANGUL_T_COUNT: u32 = 28;
+const HANGUL_N_COUNT: u32 = HANGUL_V_COUNT * HANGUL_T_COUNT;
+const HANGUL_S_COUNT: u32 = HANGUL_L_COUNT * HANGUL_N_COUNT;
+
+fn combining_class(c: char) -> u8 {
+    COMBINING_CLASS
+        .binary_search_by(|&(first, last, _)| {
+            if last < c {
+                std::cmp::Ordering::Less
+            } else if first > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .map(|i| COMBINING_CLASS[i].2)
+        .unwrap_or(0)
+}
+
+/// Full canonical decomposition followed by canonical ordering.
+fn decompose(text: &str) -> Vec<char> {
+    let mut out = Vec::with_capacity(text.len());
+
+    for c in text.chars() {
+        let s_index = (c as u32).wrapping_sub(HANGUL_S_BASE);
+        if s_index < HANGUL_S_COUNT {
+            let l = HANGUL_L_BASE + s_index / HANGUL_N_COUNT;
+            let v = HANGUL_V_BASE + (s_index % HANGUL_N_COUNT) / HANGUL_T_COUNT;
+            let t = HANGUL_T_BASE + s_index % HANGUL_T_COUNT;
+            out.extend([l, v].into_iter().filter_map(char::from_u32));
+            if t != HANGUL_T_BASE {
+                out.extend(char::from_u32(t));
+            }
+        } else {
+            match DECOMPOSITION.binary_search_by_key(&c, |&(from, _)| from) {
+                Ok(i) => out.extend(DECOMPOSITION[i].1.chars()),
+                Err(_) => out.push(c),
+            }
+        }
+    }
+
+    // Canonical ordering: stable-sort each run of non-starters by class.
+    let mut start = 0;
+    while start < out.len() {
+        if combining_class(out[start]) == 0 {
+            start += 1;
+            continue;
+        }
+        let end = out[start..]
+            .iter()
+            .position(|&c| combining_class(c) == 0)
+            .map_or(out.len(), |n| start + n);
+        out[start..end].sort_by_key(|&c| combining_class(c));
+        start = end;
+    }
+
+    out
+}
+
+fn compose_pair(first: char, second: char) -> Option<char> {
+    let (f, s) = (first as u32, second as u32);
+
+    let l_index = f.wrapping_sub(HANGUL_L_BASE);
+    let v_index = s.wrapping_sub(HANGUL_V_BASE);
+    if l_index < HANGUL_L_COUNT && v_index < HANGUL_V_COUNT {
+        let lv = HANGUL_S_BASE + (l_index * HANGUL_V_COUNT + v_index) * HANGUL_T_COUNT;
+        return char::from_u32(lv);
+    }
+
+    let s_index = f.wrapping_sub(HANGUL_S_BASE);
+    let t_index = s.wrapping_sub(HANGUL_T_BASE);
+    if s_index < HANGUL_S_COUNT
+        && s_index % HANGUL_T_COUNT == 0
+        && t_index > 0
+        && t_index < HANGUL_T_COUNT
+    {
+        return char::from_u32(f + t_index);
+    }
+
+    COMPOSITION
+        .binary_search_by_key(&(first, second), |&(a, b, _)| (a, b))
+        .ok()
+        .map(|i| COMPOSITION[i].2)
+}
+
+/// Canonical composition of decomposed, canonically ordered text.
+fn compose(decomposed: Vec<char>) -> Vec<char> {
+    let mut out: Vec<char> = Vec::with_capacity(decomposed.len());
+    let mut starter: Option<usize> = None;
+    let mut last_class = 0u8;
+
+    for c in decomposed {
+        let class = combining_class(c);
+
+        if let Some(index) = starter {
+            // A character is blocked from the starter by any intervening
+            // character of equal or higher class, or by an intervening starter.
+            let blocked = out.len() > index + 1 && (last_class == 0 || last_class >= class);
+            if !blocked && let Some(composite) = compose_pair(out[index], c) {
+                out[index] = composite;
+                continue;
+            }
+        }
+
+        if class == 0 {
+            starter = Some(out.len());
+        }
+        last_class = class;
+        out.push(c);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nfc_composes() {
+        assert_eq!(normalize("cafe\u{301}", Form::Nfc), "café");
+        assert_eq!(normalize("A\u{30A}", Form::Nfc), "\u{C5}");
+        // Ordering happens before composition: dot below (220) sorts first.
+        assert_eq!(normalize("q\u{307}\u{323}", Form::Nfc), "q\u{323}\u{307}");
+        assert_eq!(normalize("s\u{307}\u{323}", Form::Nfc), "\u{1E69}");
+        // Composition exclusions stay decomposed.
+        assert_eq!(normalize("\u{958}", Form::Nfc), "\u{915}\u{93C}");
+    }
+
+    #[test]
+    fn test_nfd_decomposes() {
+        assert_eq!(normalize("café", Form::Nfd), "cafe\u{301}");
+        assert_eq!(normalize("\u{1E69}", Form::Nfd), "s\u{323}\u{307}");
+        assert_eq!(normalize("\u{212B}", Form::Nfd), "A\u{30A}");
+    }
+
+    #[test]
+    fn test_hangul() {
+        assert_eq!(normalize("\u{D4DB}", Form::Nfd), "\u{1111}\u{1171}\u{11B6}");
+        assert_eq!(normalize("\u{1111}\u{1171}\u{11B6}", Form::Nfc), "\u{D4DB}");
+        assert_eq!(normalize("한국어", Form::Nfc), "한국어");
+    }
+
+    #[test]
+    fn test_none_and_unaffected_text() {
+        assert_eq!(normalize("cafe\u{301}", Form::None), "cafe\u{301}");
+        assert_eq!(
+            normalize("日本語 plain ascii", Form::Nfc),
+            "日本語 plain ascii"
+        );
+    }
+}
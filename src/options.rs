@@ -0,0 +1,125 @@
+//! Options for `remove_emojis_with`, the configurable form of
+//! `remove_emojis`.
+//!
+//! ```
+//! use nomoji::category::Category;
+//! use nomoji::{RemoveOptions, Replacement, remove_emojis_with};
+//!
+//! let options = RemoveOptions::new()
+//!     .skip(Category::CheckMarks)
+//!     .replacement(Replacement::Text("*".to_string()));
+//! assert_eq!(remove_emojis_with("Ship 🚀 ✅", &options).0, "Ship * ✅");
+//! ```
+
+use crate::category::Category;
+use crate::convert::{self, Language};
+
+/// What a removed sequence is replaced with.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Replacement {
+    /// Nothing: the sequence is deleted.
+    #[default]
+    Remove,
+    /// HTML numeric character references, `&#x1F680;`.
+    Entities,
+    /// String literal escapes in a programming language.
+    Escape(Language),
+    /// The same text for every sequence.
+    Text(String),
+}
+
+/// What the count `remove_emojis_with` returns adds up.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CountMode {
+    /// Characters removed, as `remove_emojis` counts them: 👍🏽 is two.
+    #[default]
+    Characters,
+    /// Whole sequences removed: 👍🏽 is one.
+    Sequences,
+}
+
+/// Which sequences `remove_emojis_with` removes and what it puts in their
+/// place. The defaults behave like `remove_emojis`; each setter consumes
+/// and returns the options so they can be chained.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RemoveOptions {
+    pub(crate) replacement: Replacement,
+    pub(crate) only: Vec<Category>,
+    pub(crate) skip: Vec<Category>,
+    pub(crate) allow: Vec<String>,
+    pub(crate) count: CountMode,
+    pub(crate) conservative: bool,
+    pub(crate) aggressive: bool,
+}
+
+impl RemoveOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces each removed sequence as `replacement` says.
+    pub fn replacement(mut self, replacement: Replacement) -> Self {
+        self.replacement = replacement;
+        self
+    }
+
+    /// Removes only sequences in `category`; may be given several times.
+    pub fn only(mut self, category: Category) -> Self {
+        self.only.push(category);
+        self
+    }
+
+    /// Keeps sequences in `category`, even if `only` names it.
+    pub fn skip(mut self, category: Category) -> Self {
+        self.skip.push(category);
+        self
+    }
+
+    /// Keeps every occurrence of `sequence`, with or without a trailing
+    /// VS16, e.g. `"✅"` or `"👍🏽"`.
+    pub fn allow(mut self, sequence: impl Into<String>) -> Self {
+        self.allow.push(sequence.into());
+        self
+    }
+
+    pub fn count(mut self, mode: CountMode) -> Self {
+        self.count = mode;
+        self
+    }
+
+    /// Keeps sequences shown as text by default, like ☀ or © without VS16.
+    pub fn conservative(mut self, conservative: bool) -> Self {
+        self.conservative = conservative;
+        self
+    }
+
+    /// Also removes pictographs `is_emoji` leaves alone; see
+    /// `aggressive::matches`.
+    pub fn aggressive(mut self, aggressive: bool) -> Self {
+        self.aggressive = aggressive;
+        self
+    }
+
+    /// Whether `sequence`, one span from `emoji_spans`, stays in the text.
+    pub(crate) fn keeps(&self, sequence: &str) -> bool {
+        let in_any = |categories: &[Category]| categories.iter().any(|c| c.matches(sequence));
+        let bare = sequence.strip_suffix('\u{FE0F}').unwrap_or(sequence);
+        in_any(&self.skip)
+            || (!self.only.is_empty() && !in_any(&self.only))
+            || (self.conservative && crate::presentation::is_text(sequence))
+            || self
+                .allow
+                .iter()
+                .any(|allowed| allowed.strip_suffix('\u{FE0F}').unwrap_or(allowed) == bare)
+    }
+
+    /// What replaces `sequence`.
+    pub(crate) fn replace(&self, sequence: &str) -> String {
+        match &self.replacement {
+            Replacement::Remove => String::new(),
+            Replacement::Entities => convert::entities(sequence),
+            Replacement::Escape(language) => convert::escape(sequence, *language),
+            Replacement::Text(text) => text.clone(),
+        }
+    }
+}
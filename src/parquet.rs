@@ -0,0 +1,339 @@
+//! A minimal Parquet writer, for `nomoji stats --export parquet`.
+//!
+//! Everything goes in one row group, each column in a single uncompressed
+//! data page with PLAIN encoding, the subset of the format every reader
+//! supports. The file metadata is Thrift's compact protocol, written by
+//! hand for the handful of structures needed.
+
+use std::io::{self, Write};
+
+const MAGIC: &[u8] = b"PAR1";
+
+/// One column of values, all the same length.
+pub enum Column {
+    Int64(Vec<i64>),
+    Utf8(Vec<String>),
+    /// Strings that may be missing, written as nulls.
+    OptionalUtf8(Vec<Option<String>>),
+}
+
+impl Column {
+    fn len(&self) -> usize {
+        match self {
+            Column::Int64(values) => values.len(),
+            Column::Utf8(values) => values.len(),
+            Column::OptionalUtf8(values) => values.len(),
+        }
+    }
+
+    /// The Parquet physical type.
+    fn physical_type(&self) -> i32 {
+        match self {
+            Column::Int64(_) => 2,
+            Column::Utf8(_) | Column::OptionalUtf8(_) => 6,
+        }
+    }
+
+    /// The data page body: definition levels for an optional column, then
+    /// the present values.
+    fn page(&self) -> Vec<u8> {
+        let mut page = Vec::new();
+        match self {
+            Column::Int64(values) => {
+                for value in values {
+                    page.extend(value.to_le_bytes());
+                }
+            }
+            Column::Utf8(values) => {
+                for value in values {
+                    plain_string(&mut page, value);
+                }
+            }
+            Column::OptionalUtf8(values) => {
+                let levels = definition_levels(values.iter().map(Option::is_some));
+                page.extend((levels.len() as u32).to_le_bytes());
+                page.extend(levels);
+                for value in values.iter().flatten() {
+                    plain_string(&mut page, value);
+                }
+            }
+        }
+        page
+    }
+}
+
+fn plain_string(page: &mut Vec<u8>, value: &str) {
+    page.extend((value.len() as u32).to_le_bytes());
+    page.extend(value.as_bytes());
+}
+
+/// Definition levels of bit width 1 as RLE runs: each a varint of the run
+/// length shifted left once, then the level in a byte.
+fn definition_levels(present: impl Iterator<Item = bool>) -> Vec<u8> {
+    let mut levels = Vec::new();
+    let mut run: Option<(bool, u64)> = None;
+    let flush = |levels: &mut Vec<u8>, (level, length): (bool, u64)| {
+        varint(levels, length << 1);
+        levels.push(u8::from(level));
+    };
+    for level in present {
+        run = match run {
+            Some((current, length)) if current == level => Some((current, length + 1)),
+            Some(done) => {
+                flush(&mut levels, done);
+                Some((level, 1))
+            }
+            None => Some((level, 1)),
+        };
+    }
+    if let Some(done) = run {
+        flush(&mut levels, done);
+    }
+    levels
+}
+
+/// Writes `columns`, named and in order, as a Parquet file.
+pub fn write(out: &mut dyn Write, columns: &[(&str, Column)]) -> io::Result<()> {
+    let rows = columns.first().map_or(0, |(_, column)| column.len());
+    assert!(columns.iter().all(|(_, column)| column.len() == rows));
+
+    let mut offset = MAGIC.len() as i64;
+    out.write_all(MAGIC)?;
+    let mut chunks = Vec::new();
+    for (_, column) in columns {
+        let page = column.page();
+        let mut header = Thrift::new();
+        header.i32(1, 0); // DATA_PAGE
+        header.i32(2, page.len() as i32);
+        header.i32(3, page.len() as i32);
+        header.begin_struct(5);
+        header.i32(1, rows as i32);
+        header.i32(2, 0); // PLAIN
+        header.i32(3, 3); // RLE definition levels
+        header.i32(4, 3); // RLE repetition levels
+        header.end_struct();
+        let header = header.finish();
+
+        out.write_all(&header)?;
+        out.write_all(&page)?;
+        let size = (header.len() + page.len()) as i64;
+        chunks.push((offset, size));
+        offset += size;
+    }
+
+    let mut meta = Thrift::new();
+    meta.i32(1, 1);
+    meta.list(2, STRUCT, columns.len() + 1);
+    meta.begin_element();
+    meta.binary(4, b"schema");
+    meta.i32(5, columns.len() as i32);
+    meta.end_struct();
+    for (name, column) in columns {
+        meta.begin_element();
+        meta.i32(1, column.physical_type());
+        let optional = matches!(column, Column::OptionalUtf8(_));
+        meta.i32(3, i32::from(optional));
+        meta.binary(4, name.as_bytes());
+        if !matches!(column, Column::Int64(_)) {
+            meta.i32(6, 0); // UTF8
+        }
+        meta.end_struct();
+    }
+    meta.i64(3, rows as i64);
+    meta.list(4, STRUCT, 1);
+    meta.begin_element();
+    meta.list(1, STRUCT, columns.len());
+    for ((name, column), &(offset, size)) in columns.iter().zip(&chunks) {
+        meta.begin_element();
+        meta.i64(2, offset);
+        meta.begin_struct(3);
+        meta.i32(1, column.physical_type());
+        meta.list(2, I32, 2);
+        meta.element_i32(0); // PLAIN
+        meta.element_i32(3); // RLE
+        meta.list(3, BINARY, 1);
+        meta.element_binary(name.as_bytes());
+        meta.i32(4, 0); // UNCOMPRESSED
+        meta.i64(5, rows as i64);
+        meta.i64(6, size);
+        meta.i64(7, size);
+        meta.i64(9, offset);
+        meta.end_struct();
+        meta.end_struct();
+    }
+    meta.i64(2, chunks.iter().map(|&(_, size)| size).sum());
+    meta.i64(3, rows as i64);
+    meta.end_struct();
+    meta.binary(
+        6,
+        concat!("nomoji version ", env!("CARGO_PKG_VERSION")).as_bytes(),
+    );
+    let meta = meta.finish();
+
+    out.write_all(&meta)?;
+    out.write_all(&(meta.len() as u32).to_le_bytes())?;
+    out.write_all(MAGIC)?;
+    out.flush()
+}
+
+const I32: u8 = 5;
+const I64: u8 = 6;
+const BINARY: u8 = 8;
+const LIST: u8 = 9;
+const STRUCT: u8 = 12;
+
+/// A struct in Thrift's compact protocol, built field by field in
+/// ascending field id order.
+struct Thrift {
+    out: Vec<u8>,
+    /// The last field id written in each open struct, innermost last.
+    last: Vec<i16>,
+}
+
+impl Thrift {
+    fn new() -> Self {
+        Thrift {
+            out: Vec::new(),
+            last: vec![0],
+        }
+    }
+
+    fn field(&mut self, id: i16, kind: u8) {
+        let last = self.last.last_mut().expect("a struct is open");
+        match id - *last {
+            delta @ 1..=15 => self.out.push((delta as u8) << 4 | kind),
+            _ => {
+                self.out.push(kind);
+                varint(&mut self.out, zigzag(i64::from(id)));
+            }
+        }
+        *last = id;
+    }
+
+    fn i32(&mut self, id: i16, value: i32) {
+        self.field(id, I32);
+        self.element_i32(value);
+    }
+
+    fn i64(&mut self, id: i16, value: i64) {
+        self.field(id, I64);
+        varint(&mut self.out, zigzag(value));
+    }
+
+    fn binary(&mut self, id: i16, value: &[u8]) {
+        self.field(id, BINARY);
+        self.element_binary(value);
+    }
+
+    fn begin_struct(&mut self, id: i16) {
+        self.field(id, STRUCT);
+        self.last.push(0);
+    }
+
+    fn end_struct(&mut self) {
+        self.out.push(0);
+        self.last.pop();
+    }
+
+    fn list(&mut self, id: i16, kind: u8, len: usize) {
+        self.field(id, LIST);
+        match len {
+            0..15 => self.out.push((len as u8) << 4 | kind),
+            _ => {
+                self.out.push(0xF0 | kind);
+                varint(&mut self.out, len as u64);
+            }
+        }
+    }
+
+    /// Starts a struct that is an element of a list.
+    fn begin_element(&mut self) {
+        self.last.push(0);
+    }
+
+    fn element_i32(&mut self, value: i32) {
+        varint(&mut self.out, zigzag(i64::from(value)));
+    }
+
+    fn element_binary(&mut self, value: &[u8]) {
+        varint(&mut self.out, value.len() as u64);
+        self.out.extend(value);
+    }
+
+    /// The encoded struct, closed with its stop field.
+    fn finish(mut self) -> Vec<u8> {
+        self.out.push(0);
+        self.out
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thrift_compact_encoding() {
+        let mut thrift = Thrift::new();
+        thrift.i32(1, -1);
+        thrift.i64(20, 300);
+        thrift.begin_struct(21);
+        thrift.binary(1, b"ab");
+        thrift.end_struct();
+        thrift.list(22, I32, 2);
+        thrift.element_i32(0);
+        thrift.element_i32(3);
+        assert_eq!(
+            thrift.finish(),
+            [
+                0x15, 0x01, // field 1, i32 -1
+                0x06, 0x28, 0xD8, 0x04, // field 20 by id, i64 300
+                0x1C, 0x18, 0x02, b'a', b'b', 0x00, // field 21, struct
+                0x19, 0x25, 0x00, 0x06, // field 22, list of two i32
+                0x00,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_definition_levels() {
+        let levels = definition_levels([true, true, false, true].into_iter());
+        assert_eq!(levels, [0x04, 1, 0x02, 0, 0x02, 1]);
+        assert!(definition_levels(std::iter::empty()).is_empty());
+    }
+
+    #[test]
+    fn test_write_frames_the_file() {
+        let mut file = Vec::new();
+        write(
+            &mut file,
+            &[
+                ("offset", Column::Int64(vec![1, 2])),
+                ("name", Column::Utf8(vec!["a".into(), "b".into()])),
+                ("note", Column::OptionalUtf8(vec![None, Some("c".into())])),
+            ],
+        )
+        .unwrap();
+        assert!(file.starts_with(MAGIC) && file.ends_with(MAGIC));
+        let footer = file.len() - 8;
+        let meta_len = u32::from_le_bytes(file[footer..footer + 4].try_into().unwrap()) as usize;
+        let meta = &file[footer - meta_len..footer];
+        // version 1, then a schema list of four structs.
+        assert_eq!(&meta[..4], [0x15, 0x02, 0x19, 0x4C]);
+        // The first page: the two offsets follow its header.
+        let values = [1i64.to_le_bytes(), 2i64.to_le_bytes()].concat();
+        let at = file.windows(16).position(|w| w == values).unwrap();
+        assert!(at > MAGIC.len() && at < 40);
+    }
+}
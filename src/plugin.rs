@@ -0,0 +1,180 @@
+//! WebAssembly decision plugins for `--plugin`: the `--filter-cmd` protocol,
+//! answered in process instead of by a command started per occurrence.
+//!
+//! A plugin imports nothing and exports:
+//!
+//! - `memory`, its linear memory,
+//! - `alloc(len: i32) -> i32`, the address of `len` bytes nomoji may fill,
+//! - `decide(ptr: i32, len: i32) -> i64`, called with the occurrence written
+//!   there as the JSON object `--filter-cmd` reads, returning where its
+//!   answer lies as `ptr << 32 | len`.
+//!
+//! The answer is UTF-8 whose first line is `keep`, `remove` (or nothing) or
+//! `replace TEXT`. Each file gets a fresh instance, and the memory `alloc`
+//! handed out belongs to the plugin again once `decide` returns.
+
+use std::sync::Arc;
+
+use crate::filter_cmd::{self, Action};
+use crate::json;
+use crate::wasm::{FuncType, Instance, Module, ValType};
+
+/// Instructions one call into a plugin may run.
+const FUEL: u64 = 100_000_000;
+
+/// Reads the module at `path` and checks it exports what a plugin must.
+pub fn load(path: &str) -> Result<Arc<Module>, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let module = Module::parse(&bytes)?;
+    if !module.exports_memory("memory") {
+        return Err("the module doesn't export its memory as 'memory'".to_string());
+    }
+    expect(&module, "alloc", &[ValType::I32], ValType::I32)?;
+    expect(
+        &module,
+        "decide",
+        &[ValType::I32, ValType::I32],
+        ValType::I64,
+    )?;
+    Ok(Arc::new(module))
+}
+
+fn expect(module: &Module, name: &str, params: &[ValType], result: ValType) -> Result<(), String> {
+    let expected = FuncType {
+        params: params.to_vec(),
+        results: vec![result],
+    };
+    match module.export_type(name) {
+        Some(ty) if *ty == expected => Ok(()),
+        Some(_) => Err(format!("the module's '{}' has the wrong signature", name)),
+        None => Err(format!(
+            "the module doesn't export a function named '{}'",
+            name
+        )),
+    }
+}
+
+/// A plugin instantiated for one file.
+pub struct Plugin {
+    instance: Instance,
+}
+
+impl Plugin {
+    pub fn new(module: &Arc<Module>) -> Result<Plugin, String> {
+        Instance::new(Arc::clone(module), FUEL)
+            .map(|instance| Plugin { instance })
+            .map_err(|e| format!("plugin: {}", e))
+    }
+
+    /// Asks the plugin about one occurrence.
+    pub fn decide(&mut self, occurrence: &json::Value) -> Result<Action, String> {
+        let answer = self
+            .answer(occurrence.to_string().as_bytes())
+            .map_err(|e| format!("plugin: {}", e))?;
+        let line = answer.lines().next().unwrap_or_default();
+        filter_cmd::parse_action(line).map_err(|_| {
+            format!(
+                "unexpected plugin answer '{}' (expected keep, remove or replace TEXT)",
+                line
+            )
+        })
+    }
+
+    fn answer(&mut self, input: &[u8]) -> Result<String, String> {
+        let len = u32::try_from(input.len()).map_err(|_| "occurrence too large".to_string())?;
+        let ptr = self.instance.call("alloc", &[u64::from(len)], FUEL)?[0] as u32 as usize;
+        self.instance
+            .memory_mut()
+            .get_mut(ptr..ptr + input.len())
+            .ok_or("alloc returned memory out of bounds")?
+            .copy_from_slice(input);
+
+        let args = [ptr as u64, u64::from(len)];
+        let packed = self.instance.call("decide", &args, FUEL)?[0];
+        let (ptr, len) = ((packed >> 32) as usize, packed as u32 as usize);
+        let answer = self
+            .instance
+            .memory()
+            .get(ptr..ptr + len)
+            .ok_or("decide answered with memory out of bounds")?;
+        String::from_utf8(answer.to_vec()).map_err(|_| "the answer is not UTF-8".to_string())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::wasm::tests::{Func, Spec, module, sleb};
+
+    /// A plugin that keeps an occurrence if its JSON has a 0xE2 byte, as ✅
+    /// and most other symbols below U+10000 do, and replaces it with `*`
+    /// otherwise. `alloc` is a bump allocator over a global.
+    pub fn keep_bmp() -> Vec<u8> {
+        let answer = |ptr: i64, len: i64| [vec![0x42], sleb(ptr << 32 | len)].concat();
+        let alloc = vec![0x23, 0, 0x23, 0, 0x20, 0, 0x6A, 0x24, 0, 0x0B];
+        let decide = [
+            // end = ptr + len
+            vec![0x20, 0, 0x20, 1, 0x6A, 0x21, 1],
+            vec![0x02, 0x40, 0x03, 0x40],
+            vec![0x20, 0, 0x20, 1, 0x4F, 0x0D, 1],
+            vec![0x20, 0, 0x2D, 0, 0, 0x41, 0xE2, 0x01, 0x46],
+            [vec![0x04, 0x40], answer(16, 4), vec![0x0F, 0x0B]].concat(),
+            vec![0x20, 0, 0x41, 1, 0x6A, 0x21, 0, 0x0C, 0],
+            vec![0x0B, 0x0B],
+            answer(32, 9),
+            vec![0x0B],
+        ]
+        .concat();
+        module(&Spec {
+            funcs: vec![
+                Func(&[0x7F], &[0x7F], &[], alloc),
+                Func(&[0x7F, 0x7F], &[0x7E], &[], decide),
+            ],
+            exports: vec![("memory", 2, 0), ("alloc", 0, 0), ("decide", 0, 1)],
+            memory: Some(&[0, 1]),
+            globals: vec![[vec![0x7F, 1, 0x41], sleb(1024), vec![0x0B]].concat()],
+            data: vec![(16, b"keep"), (32, b"replace *")],
+            ..Spec::default()
+        })
+    }
+
+    fn load_bytes(bytes: &[u8]) -> Result<Arc<Module>, String> {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), bytes).unwrap();
+        load(file.path().to_str().unwrap())
+    }
+
+    #[test]
+    fn test_decide() {
+        let module = load_bytes(&keep_bmp()).unwrap();
+        let mut plugin = Plugin::new(&module).unwrap();
+        for _ in 0..3 {
+            let check = json::Value::object().with("emoji", "✅");
+            assert_eq!(plugin.decide(&check), Ok(Action::Keep));
+            let rocket = json::Value::object().with("emoji", "🚀");
+            assert_eq!(plugin.decide(&rocket), Ok(Action::Replace("*".to_string())));
+        }
+    }
+
+    #[test]
+    fn test_load_checks_the_interface() {
+        let only_memory = module(&Spec {
+            exports: vec![("memory", 2, 0)],
+            memory: Some(&[0, 1]),
+            ..Spec::default()
+        });
+        assert!(
+            load_bytes(&only_memory)
+                .unwrap_err()
+                .contains("function named 'alloc'")
+        );
+        let wrong = module(&Spec {
+            funcs: vec![Func(&[], &[], &[], vec![0x0B])],
+            exports: vec![("memory", 2, 0), ("alloc", 0, 0)],
+            memory: Some(&[0, 1]),
+            ..Spec::default()
+        });
+        assert!(load_bytes(&wrong).unwrap_err().contains("wrong signature"));
+        assert!(load_bytes(b"not wasm").is_err());
+    }
+}
@@ -0,0 +1,363 @@
+//! Server-side `pre-receive` hook support.
+//!
+//! Git feeds the hook one `<old-rev> <new-rev> <ref>` line per updated ref.
+//! Every commit the push introduces is diffed against its parent with git
+//! plumbing, a merge against its first parent, and each added or modified
+//! text blob is compared with the blob it replaces. A file whose emoji count
+//! goes up is a violation, and so is a path that gains emojis in its name
+//! when added, copied or renamed.
+
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::process::Command;
+
+use crate::count_emojis;
+use crate::glob::glob_match;
+
+/// A file that a pushed commit changed to contain more emojis than before,
+/// in its content or its path.
+#[derive(Debug, PartialEq)]
+pub struct Violation {
+    pub refname: String,
+    pub commit: String,
+    pub path: String,
+    pub emojis_added: usize,
+    /// Emojis the path gained over the path it was renamed or copied from.
+    pub name_emojis: usize,
+}
+
+/// Checks every ref update read from `input` against the repository at
+/// `repo`. Only paths matching one of `paths` are checked; an empty list
+/// checks everything.
+pub fn check_push<R: BufRead>(
+    input: R,
+    repo: &Path,
+    paths: &[String],
+) -> io::Result<Vec<Violation>> {
+    let mut violations = Vec::new();
+
+    for line in input.lines() {
+        let line = line?;
+        let mut fields = line.split_whitespace();
+        let (Some(_old), Some(new), Some(refname)) = (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+
+        // Ref deletions introduce no content.
+        if is_null_sha(new) {
+            continue;
+        }
+
+        // Commits not reachable from any existing ref are exactly the ones
+        // this push brings in, for new and updated branches alike.
+        let commits = git(repo, &["rev-list", new, "--not", "--all"])?;
+        for commit in String::from_utf8_lossy(&commits).lines() {
+            check_commit(repo, refname, commit, paths, &mut violations)?;
+        }
+    }
+
+    Ok(violations)
+}
+
+/// Reads the protected path patterns from `git config nomoji.path`.
+pub fn configured_paths(repo: &Path) -> Vec<String> {
+    // `git config` exits 1 when the key is unset, which simply means no
+    // restriction.
+    git(repo, &["config", "--get-all", "nomoji.path"])
+        .map(|out| {
+            String::from_utf8_lossy(&out)
+                .lines()
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn check_commit(
+    repo: &Path,
+    refname: &str,
+    commit: &str,
+    paths: &[String],
+    violations: &mut Vec<Violation>,
+) -> io::Result<()> {
+    let diff = git(
+        repo,
+        &[
+            "diff-tree",
+            "-r",
+            "-z",
+            "--no-commit-id",
+            "--root",
+            // Plain diff-tree shows nothing for a merge, which would let
+            // emojis added while resolving one through unchecked.
+            "--diff-merges=first-parent",
+            // Detect renames so a moved file is compared with its source
+            // rather than counted as new.
+            "-M",
+            "--diff-filter=AMCR",
+            commit,
+        ],
+    )?;
+
+    for change in parse_raw_diff(&diff) {
+        if !paths.is_empty() && !paths.iter().any(|p| glob_match(p, &change.path)) {
+            continue;
+        }
+
+        // Added files bring a new name; modified ones keep theirs.
+        let old_name = match change.old_path {
+            Some(ref old) => old.as_str(),
+            None if is_null_sha(&change.old_sha) => "",
+            None => &change.path,
+        };
+        let name_emojis = count_emojis(&change.path).saturating_sub(count_emojis(old_name));
+
+        // Only regular files hold text; symlinks and submodules are checked
+        // by name alone.
+        let emojis_added = if change.new_mode.starts_with("100") {
+            let after = blob_emojis(repo, &change.new_sha)?;
+            let before = if is_null_sha(&change.old_sha) {
+                0
+            } else {
+                blob_emojis(repo, &change.old_sha)?
+            };
+            after.saturating_sub(before)
+        } else {
+            0
+        };
+
+        if emojis_added > 0 || name_emojis > 0 {
+            violations.push(Violation {
+                refname: refname.to_string(),
+                commit: commit.to_string(),
+                path: change.path,
+                emojis_added,
+                name_emojis,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// One record of a raw diff.
+pub struct RawChange {
+    pub new_mode: String,
+    pub old_sha: String,
+    pub new_sha: String,
+    /// The source path of a copy or rename.
+    pub old_path: Option<String>,
+    pub path: String,
+}
+
+/// Parses `git diff-tree -r -z` raw output. Each record is
+/// `:<old-mode> <new-mode> <old-sha> <new-sha> <status>` followed by one path,
+/// or two for copies and renames, all NUL-terminated.
+pub fn parse_raw_diff(raw: &[u8]) -> Vec<RawChange> {
+    let mut fields = raw
+        .split(|&b| b == 0)
+        .map(|f| String::from_utf8_lossy(f).into_owned());
+    let mut changes = Vec::new();
+
+    while let Some(meta) = fields.next() {
+        let Some(meta) = meta.strip_prefix(':') else {
+            continue;
+        };
+        let parts: Vec<&str> = meta.split(' ').collect();
+        let [_old_mode, new_mode, old_sha, new_sha, status] = parts[..] else {
+            continue;
+        };
+
+        let old_path = if status.starts_with('C') || status.starts_with('R') {
+            fields.next()
+        } else {
+            None
+        };
+        let Some(path) = fields.next() else {
+            break;
+        };
+
+        changes.push(RawChange {
+            new_mode: new_mode.to_string(),
+            old_sha: old_sha.to_string(),
+            new_sha: new_sha.to_string(),
+            old_path,
+            path,
+        });
+    }
+
+    changes
+}
+
+/// Counts emojis in a blob. Blobs that are not UTF-8 text count as zero.
+fn blob_emojis(repo: &Path, sha: &str) -> io::Result<usize> {
+    let blob = git(repo, &["cat-file", "blob", sha])?;
+    Ok(std::str::from_utf8(&blob).map(count_emojis).unwrap_or(0))
+}
+
+/// The all-zero object name git uses for "no object" (SHA-1 or SHA-256).
+fn is_null_sha(sha: &str) -> bool {
+    !sha.is_empty() && sha.bytes().all(|b| b == b'0')
+}
+
+/// Runs git in `repo` and returns its stdout, failing with its stderr.
+pub fn git(repo: &Path, args: &[&str]) -> io::Result<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()?;
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(io::Error::other(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const NULL_SHA: &str = "0000000000000000000000000000000000000000";
+
+    fn run_git(repo: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args([
+                "-c",
+                "user.name=nomoji",
+                "-c",
+                "user.email=nomoji@example.com",
+            ])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    fn commit_file(repo: &Path, path: &str, content: &str) -> String {
+        let full = repo.join(path);
+        fs::create_dir_all(full.parent().unwrap()).unwrap();
+        fs::write(full, content).unwrap();
+        run_git(repo, &["add", path]);
+        run_git(repo, &["commit", "-q", "-m", path]);
+        run_git(repo, &["rev-parse", "HEAD"])
+    }
+
+    #[test]
+    fn test_check_push_rejects_new_emoji() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path();
+        run_git(repo, &["init", "-q", "-b", "main"]);
+        let base = commit_file(repo, "docs/intro.md", "Already here 🚀\n");
+        let pushed = commit_file(repo, "docs/intro.md", "Already here 🚀 and 🔥 ✅\n");
+        commit_file(repo, "src/lib.rs", "// 🎉\n");
+        let tip = run_git(repo, &["rev-parse", "HEAD"]);
+
+        // Rewind the branch so the new commits look like an incoming push.
+        run_git(repo, &["update-ref", "refs/heads/main", &base]);
+        let input = format!("{} {} refs/heads/main\n", base, tip);
+
+        let violations = check_push(input.as_bytes(), repo, &["docs/**".to_string()]).unwrap();
+        assert_eq!(
+            violations,
+            vec![Violation {
+                refname: "refs/heads/main".to_string(),
+                commit: pushed,
+                path: "docs/intro.md".to_string(),
+                emojis_added: 2,
+                name_emojis: 0,
+            }]
+        );
+
+        let violations = check_push(input.as_bytes(), repo, &[]).unwrap();
+        assert_eq!(violations.len(), 2);
+    }
+
+    #[test]
+    fn test_check_push_rejects_emoji_in_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path();
+        run_git(repo, &["init", "-q", "-b", "main"]);
+        let base = commit_file(repo, "a 🚀.txt", "plain\n");
+        run_git(repo, &["mv", "a 🚀.txt", "b 🚀✅.txt"]);
+        run_git(repo, &["commit", "-q", "-m", "rename"]);
+        let renamed = run_git(repo, &["rev-parse", "HEAD"]);
+        let tip = commit_file(repo, "b 🚀✅.txt", "still plain\n");
+
+        run_git(repo, &["update-ref", "refs/heads/main", &base]);
+        let input = format!("{} {} refs/heads/main\n", base, tip);
+
+        // Only the rename adds an emoji; editing the file keeps its name.
+        let violations = check_push(input.as_bytes(), repo, &[]).unwrap();
+        assert_eq!(
+            violations,
+            vec![Violation {
+                refname: "refs/heads/main".to_string(),
+                commit: renamed,
+                path: "b 🚀✅.txt".to_string(),
+                emojis_added: 0,
+                name_emojis: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_check_push_checks_merges() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path();
+        run_git(repo, &["init", "-q", "-b", "main"]);
+        let base = commit_file(repo, "a.txt", "plain\n");
+        run_git(repo, &["checkout", "-q", "-b", "side"]);
+        commit_file(repo, "b.txt", "side\n");
+        run_git(repo, &["checkout", "-q", "main"]);
+        commit_file(repo, "a.txt", "main\n");
+        run_git(repo, &["merge", "-q", "--no-commit", "side"]);
+        // An emoji added while resolving the merge, in no parent.
+        fs::write(repo.join("c.txt"), "evil 🚀\n").unwrap();
+        run_git(repo, &["add", "c.txt"]);
+        run_git(repo, &["commit", "-q", "-m", "merge"]);
+        let merge = run_git(repo, &["rev-parse", "HEAD"]);
+
+        run_git(repo, &["update-ref", "refs/heads/main", &base]);
+        run_git(repo, &["branch", "-q", "-D", "side"]);
+        let input = format!("{} {} refs/heads/main\n", base, merge);
+
+        let violations = check_push(input.as_bytes(), repo, &[]).unwrap();
+        assert_eq!(
+            violations,
+            vec![Violation {
+                refname: "refs/heads/main".to_string(),
+                commit: merge,
+                path: "c.txt".to_string(),
+                emojis_added: 1,
+                name_emojis: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_check_push_ignores_deletions_and_clean_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path();
+        run_git(repo, &["init", "-q", "-b", "main"]);
+        let base = commit_file(repo, "notes.txt", "Launch 🚀\n");
+        let tip = commit_file(repo, "notes.txt", "Launch\n");
+        run_git(repo, &["update-ref", "refs/heads/main", &base]);
+
+        let input = format!(
+            "{} {} refs/heads/main\n{} {} refs/heads/old\n",
+            base, tip, base, NULL_SHA
+        );
+        assert!(check_push(input.as_bytes(), repo, &[]).unwrap().is_empty());
+    }
+}
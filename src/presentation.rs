@@ -0,0 +1,57 @@
+//! Default presentation of emoji characters, for `--conservative`.
+//!
+//! Per UTS #51, a character like ☀, ✈, ♥ or © is text unless VS16 asks for
+//! the emoji form, and so is a keycap without it. Such sequences are left
+//! alone in conservative mode.
+
+use crate::text_default::TEXT_DEFAULT;
+
+/// Whether `c` is shown as text unless a variation selector says otherwise.
+pub fn is_text_default(c: char) -> bool {
+    TEXT_DEFAULT
+        .binary_search_by(|&(first, last)| {
+            if last < c {
+                std::cmp::Ordering::Less
+            } else if first > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Whether `sequence`, one span from `emoji_spans`, is shown as text: a
+/// text-default character not followed by VS16, or a keycap mark without
+/// one. Keycap bases aren't emoji characters, so a bare keycap's span
+/// starts at the mark.
+pub fn is_text(sequence: &str) -> bool {
+    let mut chars = sequence.chars();
+    match (chars.next(), chars.next()) {
+        (Some('\u{20E3}'), _) => true,
+        (Some(first), second) => is_text_default(first) && second != Some('\u{FE0F}'),
+        (None, _) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_text() {
+        for text in ["☀", "✈", "♥", "©", "™", "☀\u{FE0E}", "\u{20E3}"] {
+            assert!(is_text(text), "{}", text);
+        }
+        for emoji in [
+            "☀\u{FE0F}",
+            "✈\u{FE0F}",
+            "🚀",
+            "✅",
+            "\u{FE0F}\u{20E3}",
+            "❤\u{FE0F}\u{200D}🔥",
+        ] {
+            assert!(!is_text(emoji), "{}", emoji);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Regions that pass through cleaning untouched: URLs for `--skip-urls` and
+//! backticked code for `--skip-inline-code`. An occurrence that overlaps a
+//! region is kept whole.
+
+use std::ops::Range;
+
+/// Byte ranges of the URLs in `text`: a scheme followed by `://`, a
+/// `mailto:` address, or a bare `www.` host, up to the next whitespace,
+/// quote or angle bracket. Trailing sentence punctuation is not part of the
+/// URL, and neither is a closing parenthesis without a matching opening one.
+pub fn urls(text: &str) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let mut urls = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let at_word_start = i == 0 || !bytes[i - 1].is_ascii_alphanumeric();
+        let start = if at_word_start
+            && (starts_with_ci(&bytes[i..], b"www.") || starts_with_ci(&bytes[i..], b"mailto:"))
+        {
+            Some(i)
+        } else if bytes[i..].starts_with(b"://") {
+            scheme_start(bytes, i)
+        } else {
+            None
+        };
+
+        match start {
+            Some(start) => {
+                let end = url_end(text, start);
+                urls.push(start..end);
+                i = end.max(i + 1);
+            }
+            None => i += 1,
+        }
+    }
+    urls
+}
+
+fn starts_with_ci(bytes: &[u8], prefix: &[u8]) -> bool {
+    bytes.len() >= prefix.len() && bytes[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+/// Where the scheme ending at `colon` begins: ASCII letters, digits, `+`,
+/// `-` and `.`, starting with a letter.
+fn scheme_start(bytes: &[u8], colon: usize) -> Option<usize> {
+    let mut start = colon;
+    while start > 0
+        && matches!(bytes[start - 1], b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'+' | b'-' | b'.')
+    {
+        start -= 1;
+    }
+    (start < colon && bytes[start].is_ascii_alphabetic()).then_some(start)
+}
+
+fn url_end(text: &str, start: usize) -> usize {
+    let mut end = text[start..]
+        .find(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '<' | '>' | '`'))
+        .map_or(text.len(), |n| start + n);
+
+    loop {
+        let url = &text[start..end];
+        let unmatched_paren =
+            url.ends_with(')') && url.matches('(').count() < url.matches(')').count();
+        if url.ends_with(['.', ',', ';', ':', '!', '?']) || unmatched_paren {
+            end -= 1;
+        } else {
+            return end;
+        }
+    }
+}
+
+/// Byte ranges of backticked code, backticks included: a run of backticks
+/// up to the next run of the same length, as in Markdown. An unclosed run
+/// is plain text.
+pub fn inline_code(text: &str) -> Vec<Range<usize>> {
+    let bytes = text.as_bytes();
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'`' {
+            i += 1;
+            continue;
+        }
+        let open = backtick_run(bytes, i);
+        let mut j = i + open;
+        let mut close = None;
+        while j < bytes.len() {
+            if bytes[j] == b'`' {
+                let run = backtick_run(bytes, j);
+                if run == open {
+                    close = Some(j + run);
+                    break;
+                }
+                j += run;
+            } else {
+                j += 1;
+            }
+        }
+        match close {
+            Some(end) => {
+                spans.push(i..end);
+                i = end;
+            }
+            None => i += open,
+        }
+    }
+    spans
+}
+
+fn backtick_run(bytes: &[u8], start: usize) -> usize {
+    bytes[start..].iter().take_while(|&&b| b == b'`').count()
+}
+
+/// Sorts `regions` and merges any that overlap, for `overlaps`.
+pub fn merge(mut regions: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    regions.sort_by_key(|r| r.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(regions.len());
+    for region in regions {
+        match merged.last_mut() {
+            Some(last) if region.start <= last.end => last.end = last.end.max(region.end),
+            _ => merged.push(region),
+        }
+    }
+    merged
+}
+
+/// Whether `span` overlaps any of `regions`, which `merge` has sorted.
+pub fn overlaps(regions: &[Range<usize>], span: &Range<usize>) -> bool {
+    let first_after = regions.partition_point(|r| r.end <= span.start);
+    regions.get(first_after).is_some_and(|r| r.start < span.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(text: &str, ranges: Vec<Range<usize>>) -> Vec<&str> {
+        ranges.into_iter().map(|r| &text[r]).collect()
+    }
+
+    #[test]
+    fn test_urls() {
+        let text = "See https://example.com/🚀?q=✅, (www.xn--ls8h.la) or \
+                    mailto:me@example.org. Not a url: ://x or 9p://y";
+        assert_eq!(
+            found(text, urls(text)),
+            [
+                "https://example.com/🚀?q=✅",
+                "www.xn--ls8h.la",
+                "mailto:me@example.org",
+            ]
+        );
+        let wiki = "[link](https://en.wikipedia.org/wiki/Rust_(language))";
+        assert_eq!(
+            found(wiki, urls(wiki)),
+            ["https://en.wikipedia.org/wiki/Rust_(language)"]
+        );
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let regions = merge(vec![10..20, 0..4, 15..25, 30..31]);
+        assert_eq!(regions, [0..4, 10..25, 30..31]);
+        assert!(overlaps(&regions, &(3..5)));
+        assert!(overlaps(&regions, &(24..26)));
+        assert!(!overlaps(&regions, &(4..10)));
+        assert!(!overlaps(&regions, &(31..40)));
+    }
+
+    #[test]
+    fn test_inline_code() {
+        let text = "Run `nomoji 🚀` or ``a ` b`` but not `this";
+        assert_eq!(found(text, inline_code(text)), ["`nomoji 🚀`", "``a ` b``"]);
+    }
+}
@@ -0,0 +1,123 @@
+//! Reviewdog Diagnostic Format reports (`--format rdjson`): a diagnostic per
+//! line with emojis, suggesting the cleaned line as a fix, so reviewdog can
+//! post review comments that apply with one click.
+
+use std::io::{self, Write};
+
+use crate::json::Value;
+use crate::selftest::codepoints;
+use crate::{Finding, ProcessResult};
+
+pub fn print(results: &[&ProcessResult], out: &mut dyn Write) -> io::Result<()> {
+    let mut diagnostics = Vec::new();
+    for result in results {
+        for line in result.findings.chunk_by(|a, b| a.line == b.line) {
+            diagnostics.push(line_diagnostic(&result.file, line));
+        }
+        if let Some(ref error) = result.error {
+            diagnostics.push(file_diagnostic(
+                &result.file,
+                error,
+                "ERROR",
+                "nomoji.failed",
+            ));
+        }
+        if let Some(ref reason) = result.skipped {
+            let message = format!("Skipped, {}", reason);
+            diagnostics.push(file_diagnostic(
+                &result.file,
+                &message,
+                "INFO",
+                "nomoji.skipped",
+            ));
+        }
+        if result.name_emojis > 0 {
+            let message = format!("{} emojis in the file name", result.name_emojis);
+            diagnostics.push(file_diagnostic(
+                &result.file,
+                &message,
+                "WARNING",
+                "nomoji.file-name",
+            ));
+        }
+    }
+
+    let report = Value::object()
+        .with("source", Value::object().with("name", "nomoji"))
+        .with("severity", "WARNING")
+        .with("diagnostics", diagnostics);
+    writeln!(out, "{}", report)
+}
+
+/// The diagnostic for `findings`, all on one line of `file`.
+fn line_diagnostic(file: &str, findings: &[Finding]) -> Value {
+    let first = &findings[0];
+    let last = &findings[findings.len() - 1];
+    let message = match findings {
+        [only] => format!("Emoji {} ({})", only.emoji, codepoints(&only.emoji)),
+        _ => {
+            let emojis: Vec<&str> = findings.iter().map(|f| f.emoji.as_str()).collect();
+            format!("{} emojis: {}", findings.len(), emojis.join(" "))
+        }
+    };
+    let line = first.line;
+
+    Value::object()
+        .with("message", message)
+        .with(
+            "location",
+            location(
+                file,
+                Some(range(line, first.column, last.column + last.emoji.len())),
+            ),
+        )
+        .with("severity", "WARNING")
+        .with("code", Value::object().with("value", "nomoji.emoji"))
+        .with(
+            "suggestions",
+            vec![
+                Value::object()
+                    .with("range", range(line, 1, first.line_text.len() + 1))
+                    .with("text", cleaned_line(findings)),
+            ],
+        )
+}
+
+fn file_diagnostic(file: &str, message: &str, severity: &str, code: &str) -> Value {
+    Value::object()
+        .with("message", message)
+        .with("location", location(file, None))
+        .with("severity", severity)
+        .with("code", Value::object().with("value", code))
+}
+
+fn location(file: &str, range: Option<Value>) -> Value {
+    let location = Value::object().with("path", file);
+    match range {
+        Some(range) => location.with("range", range),
+        None => location,
+    }
+}
+
+/// Lines and byte columns from 1, the end exclusive.
+fn range(line: usize, start: usize, end: usize) -> Value {
+    let position = |column: usize| Value::object().with("line", line).with("column", column);
+    Value::object()
+        .with("start", position(start))
+        .with("end", position(end))
+}
+
+/// The line `findings` are on, with each replaced as cleaning replaces it.
+fn cleaned_line(findings: &[Finding]) -> String {
+    let text = &findings[0].line_text;
+    let mut cleaned = String::with_capacity(text.len());
+    let mut copied = 0;
+    for finding in findings {
+        let start = finding.column - 1;
+        cleaned.push_str(&text[copied..start]);
+        cleaned.push_str(&finding.replacement);
+        copied = start + finding.emoji.len();
+    }
+    cleaned.push_str(&text[copied..]);
+    cleaned
+}
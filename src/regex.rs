@@ -0,0 +1,523 @@
+//! A small regular expression engine for `--skip-pattern`.
+//!
+//! Supported syntax: literals, `.`, classes (`[a-z]`, `[^,]`), `\d \w \s`
+//! and their negations, `^` and `$`, groups `(...)` and `(?:...)`,
+//! alternation, and the quantifiers `* + ? {n} {n,} {n,m}`, each with a lazy
+//! `?` form. Patterns run over the whole text: `.` matches newlines too,
+//! and `^` and `$` match at the start and end of every line.
+//!
+//! Matching is a Pike VM, so it takes time linear in the text and never
+//! recurses per character, whatever the pattern.
+
+use std::fmt;
+
+/// Upper bound on a counted repetition, which is compiled by copying.
+const MAX_REPEAT: usize = 1000;
+
+#[derive(Clone)]
+pub struct Regex {
+    source: String,
+    program: Vec<Inst>,
+}
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Regex({:?})", self.source)
+    }
+}
+
+impl PartialEq for Regex {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    LineStart,
+    LineEnd,
+    /// Try the first target before the second.
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+#[derive(Clone, Debug)]
+struct Class {
+    ranges: Vec<(char, char)>,
+    negated: bool,
+}
+
+impl Class {
+    fn matches(&self, c: char) -> bool {
+        self.ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != self.negated
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    LineStart,
+    LineEnd,
+    Concat(Vec<Node>),
+    Alt(Box<Node>, Box<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+        lazy: bool,
+    },
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        let node = parser.alternation()?;
+        if parser.pos < parser.chars.len() {
+            return Err(format!("unmatched ')' in pattern '{}'", pattern));
+        }
+
+        let mut program = Vec::new();
+        compile(&node, &mut program);
+        program.push(Inst::Match);
+        Ok(Regex {
+            source: pattern.to_string(),
+            program,
+        })
+    }
+
+    /// Byte ranges of the non-overlapping, non-empty matches in `text`,
+    /// leftmost first.
+    pub fn find_all(&self, text: &str) -> Vec<std::ops::Range<usize>> {
+        let mut found = Vec::new();
+        let mut pos = 0;
+        while let Some(m) = self.find_at(text, pos) {
+            if m.is_empty() {
+                match text[m.start..].chars().next() {
+                    Some(c) => pos = m.start + c.len_utf8(),
+                    None => break,
+                }
+            } else {
+                pos = m.end;
+                found.push(m);
+            }
+        }
+        found
+    }
+
+    /// The leftmost match starting at or after `from`, preferring what the
+    /// pattern prefers (greedy or lazy) among matches starting there.
+    fn find_at(&self, text: &str, from: usize) -> Option<std::ops::Range<usize>> {
+        let mut current = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+        let mut matched = None;
+        let mut pos = from;
+
+        loop {
+            if matched.is_none() {
+                self.add(&mut current, 0, pos, text, pos);
+            }
+            if current.list.is_empty() && matched.is_some() {
+                break;
+            }
+
+            let c = text[pos..].chars().next();
+            let after = pos + c.map_or(0, char::len_utf8);
+            for &(pc, start) in &current.list {
+                let advances = match (&self.program[pc], c) {
+                    (Inst::Match, _) => {
+                        matched = Some(start..pos);
+                        // Lower-priority threads can't win any more.
+                        break;
+                    }
+                    (Inst::Char(want), Some(c)) => *want == c,
+                    (Inst::Any, Some(_)) => true,
+                    (Inst::Class(class), Some(c)) => class.matches(c),
+                    _ => false,
+                };
+                if advances {
+                    self.add(&mut next, pc + 1, start, text, after);
+                }
+            }
+
+            if c.is_none() {
+                break;
+            }
+            pos = after;
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+        }
+        matched
+    }
+
+    /// Adds the thread at `pc` to `threads`, following jumps, splits and
+    /// assertions at `pos` in priority order.
+    fn add(&self, threads: &mut Threads, pc: usize, start: usize, text: &str, pos: usize) {
+        let mut stack = vec![pc];
+        while let Some(pc) = stack.pop() {
+            if threads.seen[pc] {
+                continue;
+            }
+            threads.seen[pc] = true;
+            match self.program[pc] {
+                Inst::Jump(to) => stack.push(to),
+                Inst::Split(first, second) => {
+                    stack.push(second);
+                    stack.push(first);
+                }
+                Inst::LineStart => {
+                    if pos == 0 || text.as_bytes()[pos - 1] == b'\n' {
+                        stack.push(pc + 1);
+                    }
+                }
+                Inst::LineEnd => {
+                    if pos == text.len() || text.as_bytes()[pos] == b'\n' {
+                        stack.push(pc + 1);
+                    }
+                }
+                _ => threads.list.push((pc, start)),
+            }
+        }
+    }
+}
+
+/// Running threads as (instruction, match start), highest priority first.
+struct Threads {
+    list: Vec<(usize, usize)>,
+    seen: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Threads {
+            list: Vec::new(),
+            seen: vec![false; len],
+        }
+    }
+
+    fn clear(&mut self) {
+        self.list.clear();
+        self.seen.fill(false);
+    }
+}
+
+fn compile(node: &Node, program: &mut Vec<Inst>) {
+    match node {
+        Node::Empty => {}
+        Node::Char(c) => program.push(Inst::Char(*c)),
+        Node::Any => program.push(Inst::Any),
+        Node::Class(class) => program.push(Inst::Class(class.clone())),
+        Node::LineStart => program.push(Inst::LineStart),
+        Node::LineEnd => program.push(Inst::LineEnd),
+        Node::Concat(nodes) => nodes.iter().for_each(|n| compile(n, program)),
+        Node::Alt(first, second) => {
+            let split = placeholder(program);
+            compile(first, program);
+            let jump = placeholder(program);
+            let second_start = program.len();
+            compile(second, program);
+            program[split] = Inst::Split(split + 1, second_start);
+            program[jump] = Inst::Jump(program.len());
+        }
+        Node::Repeat {
+            node,
+            min,
+            max,
+            lazy,
+        } => {
+            for _ in 0..*min {
+                compile(node, program);
+            }
+            match max {
+                None => {
+                    // L: split(body, out); body; jump L
+                    let split = placeholder(program);
+                    compile(node, program);
+                    program.push(Inst::Jump(split));
+                    program[split] = choice(split + 1, program.len(), *lazy);
+                }
+                Some(max) => {
+                    for _ in *min..*max {
+                        let split = placeholder(program);
+                        compile(node, program);
+                        program[split] = choice(split + 1, program.len(), *lazy);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn placeholder(program: &mut Vec<Inst>) -> usize {
+    program.push(Inst::Match);
+    program.len() - 1
+}
+
+fn choice(more: usize, done: usize, lazy: bool) -> Inst {
+    if lazy {
+        Inst::Split(done, more)
+    } else {
+        Inst::Split(more, done)
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn alternation(&mut self) -> Result<Node, String> {
+        let mut node = self.concat()?;
+        while self.eat('|') {
+            node = Node::Alt(Box::new(node), Box::new(self.concat()?));
+        }
+        Ok(node)
+    }
+
+    fn concat(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.quantified(atom)?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.remove(0),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn quantified(&mut self, atom: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => match self.counted()? {
+                Some(bounds) => bounds,
+                None => return Ok(atom),
+            },
+            _ => return Ok(atom),
+        };
+        // Past the quantifier, or the '}' that closes it.
+        self.pos += 1;
+        if matches!(atom, Node::LineStart | Node::LineEnd | Node::Empty) {
+            return Err("nothing to repeat".to_string());
+        }
+        let lazy = self.eat('?');
+        Ok(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+            lazy,
+        })
+    }
+
+    /// Parses `{n}`, `{n,}` or `{n,m}` up to, not including, the `}`. A `{`
+    /// that doesn't start one is a literal.
+    fn counted(&mut self) -> Result<Option<(usize, Option<usize>)>, String> {
+        let rest: String = self.chars[self.pos + 1..].iter().collect();
+        let Some(close) = rest.find('}') else {
+            return Ok(None);
+        };
+        let body = &rest[..close];
+        let number = |s: &str| s.parse::<usize>().ok();
+        let bounds = match body.split_once(',') {
+            None => number(body).map(|n| (n, Some(n))),
+            Some((min, "")) => number(min).map(|n| (n, None)),
+            Some((min, max)) => number(min).zip(number(max)).map(|(a, b)| (a, Some(b))),
+        };
+        let Some((min, max)) = bounds else {
+            return Ok(None);
+        };
+        if max.is_some_and(|max| max < min) || min.max(max.unwrap_or(0)) > MAX_REPEAT {
+            return Err(format!("invalid repetition {{{}}}", body));
+        }
+        self.pos += body.chars().count() + 1;
+        Ok(Some((min, max)))
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let c = self.peek().ok_or("unexpected end of pattern")?;
+        self.pos += 1;
+        Ok(match c {
+            '.' => Node::Any,
+            '^' => Node::LineStart,
+            '$' => Node::LineEnd,
+            '(' => {
+                if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                }
+                let node = self.alternation()?;
+                if !self.eat(')') {
+                    return Err("missing ')'".to_string());
+                }
+                node
+            }
+            '[' => Node::Class(self.class()?),
+            '\\' => match self.escape()? {
+                Escaped::Char(c) => Node::Char(c),
+                Escaped::Class(class) => Node::Class(class),
+            },
+            '*' | '+' | '?' => return Err("nothing to repeat".to_string()),
+            c => Node::Char(c),
+        })
+    }
+
+    fn class(&mut self) -> Result<Class, String> {
+        let negated = self.eat('^');
+        let mut ranges = Vec::new();
+        let mut first = true;
+
+        loop {
+            let c = self.peek().ok_or("missing ']'")?;
+            self.pos += 1;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+
+            let lo = match c {
+                '\\' => match self.escape()? {
+                    Escaped::Char(c) => c,
+                    Escaped::Class(class) if !class.negated => {
+                        ranges.extend(class.ranges);
+                        continue;
+                    }
+                    Escaped::Class(_) => {
+                        return Err("negated escapes are not supported in classes".to_string());
+                    }
+                },
+                c => c,
+            };
+            let is_range = self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']');
+            if !is_range {
+                ranges.push((lo, lo));
+                continue;
+            }
+            self.pos += 1;
+            let hi = match self.peek().ok_or("missing ']'")? {
+                '\\' => {
+                    self.pos += 1;
+                    match self.escape()? {
+                        Escaped::Char(c) => c,
+                        Escaped::Class(_) => return Err("invalid class range".to_string()),
+                    }
+                }
+                c => {
+                    self.pos += 1;
+                    c
+                }
+            };
+            if hi < lo {
+                return Err(format!("invalid class range {}-{}", lo, hi));
+            }
+            ranges.push((lo, hi));
+        }
+        Ok(Class { ranges, negated })
+    }
+
+    fn escape(&mut self) -> Result<Escaped, String> {
+        let c = self.peek().ok_or("trailing backslash")?;
+        self.pos += 1;
+        let class = |ranges: &[(char, char)], negated| {
+            Escaped::Class(Class {
+                ranges: ranges.to_vec(),
+                negated,
+            })
+        };
+        const DIGIT: &[(char, char)] = &[('0', '9')];
+        const WORD: &[(char, char)] = &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+        const SPACE: &[(char, char)] = &[('\t', '\r'), (' ', ' ')];
+        Ok(match c {
+            'd' => class(DIGIT, false),
+            'D' => class(DIGIT, true),
+            'w' => class(WORD, false),
+            'W' => class(WORD, true),
+            's' => class(SPACE, false),
+            'S' => class(SPACE, true),
+            'n' => Escaped::Char('\n'),
+            'r' => Escaped::Char('\r'),
+            't' => Escaped::Char('\t'),
+            c if c.is_ascii_alphanumeric() => {
+                return Err(format!("unsupported escape \\{}", c));
+            }
+            c => Escaped::Char(c),
+        })
+    }
+}
+
+enum Escaped {
+    Char(char),
+    Class(Class),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches<'a>(pattern: &str, text: &'a str) -> Vec<&'a str> {
+        let regex = Regex::new(pattern).unwrap();
+        regex.find_all(text).into_iter().map(|r| &text[r]).collect()
+    }
+
+    #[test]
+    fn test_lazy_and_greedy() {
+        let text = "a BEGIN-RAW 🚀\n✅ END-RAW b BEGIN-RAW 🎉 END-RAW";
+        assert_eq!(
+            matches("BEGIN-RAW.*?END-RAW", text),
+            ["BEGIN-RAW 🚀\n✅ END-RAW", "BEGIN-RAW 🎉 END-RAW"]
+        );
+        assert_eq!(
+            matches("BEGIN-RAW.*END-RAW", text),
+            ["BEGIN-RAW 🚀\n✅ END-RAW b BEGIN-RAW 🎉 END-RAW"]
+        );
+    }
+
+    #[test]
+    fn test_syntax() {
+        assert_eq!(matches(r"\d{2,3}", "1 22 4444"), ["22", "444"]);
+        assert_eq!(
+            matches("^# .*?$", "# one\ntext # no\n# two"),
+            ["# one", "# two"]
+        );
+        assert_eq!(matches("(?:ab|c)+", "xababcx"), ["ababc"]);
+        assert_eq!(matches(r"[^\s,]+", "a,b c"), ["a", "b", "c"]);
+        assert_eq!(matches("[a-c-]+", "x-abc-y"), ["-abc-"]);
+        assert_eq!(matches(r"\.\*", "a.*b"), [".*"]);
+        assert_eq!(matches("x*", "abc"), Vec::<&str>::new());
+        assert_eq!(matches("a{,2}", "a{,2}"), ["a{,2}"]);
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        for pattern in ["(a", "a)", "[a", "*a", r"\q", "a{3,1}", "[z-a]", "a\\"] {
+            assert!(Regex::new(pattern).is_err(), "{}", pattern);
+        }
+    }
+}
@@ -0,0 +1,534 @@
+//! JSON reports (`--format json`), comparing two saved reports, where
+//! `--report-to` sends a report, and the order `--sort` lists files in.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+use crate::ProcessResult;
+use crate::json::{self, Value};
+use nomoji::schema::{DirectoryReport, FileReport, Interval, Report, SCHEMA_VERSION};
+
+/// A report destination: `stdout`, `stderr`, an inherited descriptor
+/// `fd:N`, or any other value as a file path.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Destination {
+    Stdout,
+    Stderr,
+    Fd(i32),
+    File(PathBuf),
+}
+
+impl FromStr for Destination {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Err("destination must not be empty".to_string()),
+            "stdout" | "-" => Ok(Destination::Stdout),
+            "stderr" => Ok(Destination::Stderr),
+            _ => match s.strip_prefix("fd:") {
+                Some(fd) => fd
+                    .parse()
+                    .ok()
+                    .filter(|&fd| fd >= 0)
+                    .map(Destination::Fd)
+                    .ok_or_else(|| format!("invalid file descriptor '{}'", fd)),
+                None => Ok(Destination::File(PathBuf::from(s))),
+            },
+        }
+    }
+}
+
+impl Destination {
+    pub fn is_stdout(&self) -> bool {
+        *self == Destination::Stdout || *self == Destination::Fd(1)
+    }
+
+    /// Whether the destination is a terminal. Descriptors other than 1 and
+    /// 2 and files count as not.
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            Destination::Stdout | Destination::Fd(1) => io::stdout().is_terminal(),
+            Destination::Stderr | Destination::Fd(2) => io::stderr().is_terminal(),
+            Destination::Fd(_) | Destination::File(_) => false,
+        }
+    }
+
+    /// Opens the destination for writing; a file is created or truncated.
+    pub fn open(&self) -> io::Result<Box<dyn Write>> {
+        Ok(match self {
+            Destination::Stdout => Box::new(io::stdout()),
+            Destination::Stderr => Box::new(io::stderr()),
+            Destination::Fd(fd) => Box::new(open_fd(*fd)?),
+            Destination::File(path) => Box::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+}
+
+/// A duplicate of inherited descriptor `fd`, so the original stays open for
+/// whoever passed it in.
+#[cfg(unix)]
+fn open_fd(fd: i32) -> io::Result<File> {
+    use std::os::fd::BorrowedFd;
+
+    // SAFETY: F_GETFD only queries the descriptor, and a successful call
+    // proves it is open for the borrow below, which ends before we return.
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let fd = unsafe { BorrowedFd::borrow_raw(fd) };
+    Ok(File::from(fd.try_clone_to_owned()?))
+}
+
+#[cfg(not(unix))]
+fn open_fd(_fd: i32) -> io::Result<File> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "fd: destinations are only supported on Unix",
+    ))
+}
+
+/// What `--sort` orders per-file lines by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Count,
+    Errors,
+}
+
+/// A `--sort` value: `name`, `count` or `errors`, optionally followed by
+/// `:asc` or `:desc`. Names default to ascending, and counts and errors to
+/// descending, so the worst files come first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl FromStr for Sort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, order) = match s.split_once(':') {
+            Some((key, order)) => (key, Some(order)),
+            None => (s, None),
+        };
+        let key = match key {
+            "name" => SortKey::Name,
+            "count" => SortKey::Count,
+            "errors" => SortKey::Errors,
+            _ => {
+                return Err(format!(
+                    "invalid sort key '{}': use name, count or errors",
+                    key
+                ));
+            }
+        };
+        let descending = match order {
+            None => key != SortKey::Name,
+            Some("asc") => false,
+            Some("desc") => true,
+            Some(order) => {
+                return Err(format!("invalid sort order '{}': use asc or desc", order));
+            }
+        };
+        Ok(Sort { key, descending })
+    }
+}
+
+impl Sort {
+    /// Sorts `results` in place; ties keep their order.
+    pub fn apply(self, results: &mut [&ProcessResult]) {
+        results.sort_by(|a, b| {
+            let order = match self.key {
+                SortKey::Name => a.file.cmp(&b.file),
+                SortKey::Count => a.emojis_found.cmp(&b.emojis_found),
+                SortKey::Errors => b.success.cmp(&a.success),
+            };
+            if self.descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+    }
+}
+
+/// The report for `results`: totals over all of them and an entry under
+/// `files` for each of `listed`.
+pub fn to_report<'a>(
+    results: &[ProcessResult],
+    listed: impl IntoIterator<Item = &'a ProcessResult>,
+) -> Report {
+    let files = listed
+        .into_iter()
+        .map(|r| FileReport {
+            file: r.file.clone(),
+            emojis: r.emojis_found,
+            success: r.success,
+            error: r.error.clone(),
+            skipped: r.skipped.clone(),
+            bytes_saved: r.bytes_saved,
+            name_emojis: r.name_emojis,
+            before_sha256: r.sha256.as_ref().map(|(before, _)| before.clone()),
+            after_sha256: r.sha256.as_ref().map(|(_, after)| after.clone()),
+        })
+        .collect();
+
+    Report {
+        files_processed: results.len(),
+        successful: results.iter().filter(|r| r.success).count(),
+        total_emojis: results.iter().map(|r| r.emojis_found).sum(),
+        bytes_saved: results.iter().map(|r| r.bytes_saved).sum(),
+        name_emojis: results.iter().map(|r| r.name_emojis).sum(),
+        fast_skipped: results.iter().filter(|r| r.fast_skipped).count(),
+        files,
+        ..Report::default()
+    }
+}
+
+/// Totals for `results` per directory, keeping the first `depth`
+/// components of each file's directory, most emojis first and ties by name.
+pub fn by_directory(results: &[ProcessResult], depth: usize) -> Vec<DirectoryReport> {
+    let mut directories: BTreeMap<String, DirectoryReport> = BTreeMap::new();
+    for result in results {
+        let directory = directory_of(&result.file, depth);
+        let entry = directories
+            .entry(directory.clone())
+            .or_insert_with(|| DirectoryReport {
+                directory,
+                ..DirectoryReport::default()
+            });
+        entry.files += 1;
+        entry.emojis += result.emojis_found;
+    }
+    let mut directories: Vec<_> = directories.into_values().collect();
+    directories.sort_by_key(|d| std::cmp::Reverse(d.emojis));
+    directories
+}
+
+/// `file`'s directory cut to `depth` components below any root, with a
+/// trailing `/`; `./` for files given without one.
+fn directory_of(file: &str, depth: usize) -> String {
+    let parent = Path::new(file).parent().unwrap_or(Path::new(""));
+    let mut directory = PathBuf::new();
+    let mut kept = 0;
+    for component in parent.components() {
+        match component {
+            Component::Normal(_) if kept == depth => break,
+            Component::Normal(_) => kept += 1,
+            Component::CurDir => continue,
+            _ => {}
+        }
+        directory.push(component);
+    }
+    let directory = directory.to_string_lossy();
+    match directory.as_ref() {
+        "" => "./".to_string(),
+        "/" => directory.into_owned(),
+        _ => format!("{}/", directory),
+    }
+}
+
+/// `report` as JSON, with fields in schema order.
+pub fn encode(report: Report) -> Value {
+    let files: Vec<Value> = report
+        .files
+        .iter()
+        .map(|f| with_file(Value::object(), f))
+        .collect();
+
+    let json = Value::object().with("schema_version", report.schema_version as usize);
+    let json = with_totals(json, &report).with("files", files);
+    let json = match report.estimate {
+        Some(estimate) => {
+            let interval = |i: Interval| {
+                Value::object()
+                    .with("estimate", i.estimate)
+                    .with("low", i.low)
+                    .with("high", i.high)
+            };
+            json.with(
+                "estimate",
+                Value::object()
+                    .with("population", estimate.population)
+                    .with("sampled", estimate.sampled)
+                    .with("confidence_percent", 95)
+                    .with("emojis", interval(estimate.emojis))
+                    .with("files_with_emojis", interval(estimate.files_with_emojis)),
+            )
+        }
+        None => json,
+    };
+    if report.directories.is_empty() {
+        return json;
+    }
+
+    let directories: Vec<Value> = report
+        .directories
+        .into_iter()
+        .map(|d| {
+            Value::object()
+                .with("directory", d.directory)
+                .with("files", d.files)
+                .with("emojis", d.emojis)
+        })
+        .collect();
+    json.with("directories", directories)
+}
+
+/// `json` with the totals of `report` added, in schema order.
+pub fn with_totals(json: Value, report: &Report) -> Value {
+    json.with("files_processed", report.files_processed)
+        .with("successful", report.successful)
+        .with("total_emojis", report.total_emojis)
+        .with("bytes_saved", report.bytes_saved)
+        .with("name_emojis", report.name_emojis)
+        .with("fast_skipped", report.fast_skipped)
+}
+
+/// `json` with the fields of one `files` entry added, in schema order.
+pub fn with_file(json: Value, f: &FileReport) -> Value {
+    json.with("file", f.file.as_str())
+        .with("emojis", f.emojis)
+        .with("success", f.success)
+        .with("error", f.error.clone())
+        .with("skipped", f.skipped.clone())
+        .with("bytes_saved", f.bytes_saved)
+        .with("name_emojis", f.name_emojis)
+        .with("before_sha256", f.before_sha256.clone())
+        .with("after_sha256", f.after_sha256.clone())
+}
+
+/// Emoji counts per file from a saved JSON report.
+pub fn load(path: &Path) -> Result<BTreeMap<String, i64>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn parse(text: &str) -> Result<BTreeMap<String, i64>, String> {
+    let report = json::parse(text)?;
+    // Reports from before versioning have no schema_version and read as 1.
+    if let Some(version) = report.get("schema_version").and_then(Value::as_i64)
+        && version > i64::from(SCHEMA_VERSION)
+    {
+        return Err(format!(
+            "report schema version {} is newer than this nomoji supports ({})",
+            version, SCHEMA_VERSION
+        ));
+    }
+    let files = report
+        .get("files")
+        .and_then(Value::as_array)
+        .ok_or("not a nomoji JSON report (no \"files\" array)")?;
+
+    files
+        .iter()
+        .map(|entry| {
+            let file = entry.get("file").and_then(Value::as_str);
+            let emojis = entry.get("emojis").and_then(Value::as_i64);
+            match (file, emojis) {
+                (Some(file), Some(emojis)) => Ok((file.to_string(), emojis)),
+                _ => Err("report entry without \"file\" and \"emojis\"".to_string()),
+            }
+        })
+        .collect()
+}
+
+/// How emoji counts changed between two reports.
+#[derive(Debug, Default, PartialEq)]
+pub struct Diff {
+    /// Files that had emojis before and now have more: (file, old, new).
+    pub worse: Vec<(String, i64, i64)>,
+    /// Files with emojis that were clean or absent before: (file, count).
+    pub new_offenders: Vec<(String, i64)>,
+    /// Files whose count went down, including to zero: (file, old, new).
+    pub better: Vec<(String, i64, i64)>,
+    pub old_total: i64,
+    pub new_total: i64,
+}
+
+impl Diff {
+    pub fn has_regressions(&self) -> bool {
+        !self.worse.is_empty() || !self.new_offenders.is_empty()
+    }
+}
+
+pub fn diff(old: &BTreeMap<String, i64>, new: &BTreeMap<String, i64>) -> Diff {
+    let total =
+        |counts: &BTreeMap<String, i64>| counts.values().fold(0i64, |a, &n| a.saturating_add(n));
+    let mut diff = Diff {
+        old_total: total(old),
+        new_total: total(new),
+        ..Diff::default()
+    };
+
+    for (file, &now) in new {
+        let before = old.get(file).copied().unwrap_or(0);
+        if before == 0 && now > 0 {
+            diff.new_offenders.push((file.clone(), now));
+        } else if now > before {
+            diff.worse.push((file.clone(), before, now));
+        } else if now < before {
+            diff.better.push((file.clone(), before, now));
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_round_trip() {
+        let results = [
+            ProcessResult {
+                bytes_saved: 4,
+                ..ProcessResult::succeeded("a.md", 1)
+            },
+            ProcessResult::failed("b.md", 0, "Failed to read file".to_string()),
+        ];
+        let report = encode(to_report(&results, &results)).to_string();
+        assert!(report.starts_with(
+            r#"{"schema_version":1,"files_processed":2,"successful":1,"total_emojis":1"#
+        ));
+
+        let counts = parse(&report).unwrap();
+        assert_eq!(counts["a.md"], 1);
+        assert_eq!(counts["b.md"], 0);
+        assert!(parse("{}").is_err());
+        assert!(parse(r#"{"files":[]}"#).is_ok());
+        assert!(
+            parse(r#"{"schema_version":2,"files":[]}"#)
+                .unwrap_err()
+                .contains("newer")
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let counts = |entries: &[(&str, i64)]| -> BTreeMap<String, i64> {
+            entries.iter().map(|&(f, n)| (f.to_string(), n)).collect()
+        };
+        let old = counts(&[
+            ("same.md", 2),
+            ("worse.md", 1),
+            ("fixed.md", 3),
+            ("clean.md", 0),
+        ]);
+        let new = counts(&[
+            ("same.md", 2),
+            ("worse.md", 4),
+            ("fixed.md", 0),
+            ("clean.md", 1),
+            ("added.md", 2),
+        ]);
+
+        let diff = diff(&old, &new);
+        assert_eq!(diff.worse, [("worse.md".to_string(), 1, 4)]);
+        assert_eq!(
+            diff.new_offenders,
+            [("added.md".to_string(), 2), ("clean.md".to_string(), 1)]
+        );
+        assert_eq!(diff.better, [("fixed.md".to_string(), 3, 0)]);
+        assert_eq!((diff.old_total, diff.new_total), (6, 9));
+        assert!(diff.has_regressions());
+    }
+
+    #[test]
+    fn test_destination_from_str() {
+        let parse = |s: &str| s.parse::<Destination>();
+        assert_eq!(parse("stdout"), Ok(Destination::Stdout));
+        assert_eq!(parse("stderr"), Ok(Destination::Stderr));
+        assert_eq!(parse("fd:3"), Ok(Destination::Fd(3)));
+        assert_eq!(
+            parse("out/report.json"),
+            Ok(Destination::File(PathBuf::from("out/report.json")))
+        );
+        assert!(parse("fd:-1").is_err());
+        assert!(parse("fd:x").is_err());
+        assert!(parse("").is_err());
+        assert!(parse("fd:1").unwrap().is_stdout());
+    }
+
+    #[test]
+    fn test_by_directory() {
+        let results = [
+            ProcessResult::succeeded("src/main.rs", 2),
+            ProcessResult::succeeded("./docs/guide/intro.md", 7),
+            ProcessResult::succeeded("docs/api.md", 3),
+            ProcessResult::succeeded("README.md", 1),
+            ProcessResult::succeeded("/etc/motd", 0),
+        ];
+        let grouped = |depth| {
+            by_directory(&results, depth)
+                .into_iter()
+                .map(|d| (d.directory, d.files, d.emojis))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            grouped(1),
+            [
+                ("docs/".to_string(), 2, 10),
+                ("src/".to_string(), 1, 2),
+                ("./".to_string(), 1, 1),
+                ("/etc/".to_string(), 1, 0),
+            ]
+        );
+        assert_eq!(grouped(2)[0], ("docs/guide/".to_string(), 1, 7));
+        assert_eq!(grouped(2)[1], ("docs/".to_string(), 1, 3));
+
+        let mut report = to_report(&results, &results);
+        report.directories = by_directory(&results, 1);
+        let json = encode(report).to_string();
+        assert!(json.contains(r#""directories":[{"directory":"docs/","files":2,"emojis":10},"#));
+        assert!(
+            !encode(to_report(&results, &results))
+                .to_string()
+                .contains("directories")
+        );
+    }
+
+    #[test]
+    fn test_sort() {
+        let parse = |s: &str| s.parse::<Sort>();
+        assert_eq!(
+            parse("name"),
+            Ok(Sort {
+                key: SortKey::Name,
+                descending: false
+            })
+        );
+        assert_eq!(
+            parse("count:asc"),
+            Ok(Sort {
+                key: SortKey::Count,
+                descending: false
+            })
+        );
+        assert!(parse("errors").unwrap().descending);
+        assert!(parse("size").is_err());
+        assert!(parse("name:up").is_err());
+
+        let results = [
+            ProcessResult::succeeded("b.md", 2),
+            ProcessResult::failed("c.md", 0, "denied".to_string()),
+            ProcessResult::succeeded("a.md", 2),
+        ];
+        let sorted = |sort: &str| {
+            let mut listed: Vec<&ProcessResult> = results.iter().collect();
+            parse(sort).unwrap().apply(&mut listed);
+            listed.iter().map(|r| r.file.as_str()).collect::<Vec<_>>()
+        };
+        assert_eq!(sorted("name"), ["a.md", "b.md", "c.md"]);
+        assert_eq!(sorted("name:desc"), ["c.md", "b.md", "a.md"]);
+        assert_eq!(sorted("count"), ["b.md", "a.md", "c.md"]);
+        assert_eq!(sorted("errors"), ["c.md", "b.md", "a.md"]);
+        assert_eq!(sorted("errors:asc"), ["b.md", "a.md", "c.md"]);
+    }
+}
@@ -0,0 +1,97 @@
+//! What part a found sequence plays: a whole emoji, or debris left from
+//! one, such as a variation selector whose base was deleted.
+
+use crate::{is_regional_indicator, is_variation_selector};
+
+/// The role of one sequence from `emoji_spans`, decided by the character
+/// it starts with. Only `Pictograph` and `Flag` are whole emoji; the rest
+/// are components standing alone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// A base emoji with whatever is attached to it, e.g. `👍🏽` or `👨‍👩‍👧`.
+    Pictograph,
+    /// A pair of regional indicators, e.g. `🇫🇷`.
+    Flag,
+    /// A zero width joiner with no emoji before it.
+    Zwj,
+    VariationSelector,
+    SkinToneModifier,
+    /// A regional indicator without its pair.
+    RegionalIndicator,
+    /// A tag character, as used in subdivision flags.
+    Tag,
+    /// U+20E3 COMBINING ENCLOSING KEYCAP with no key before it.
+    KeycapMark,
+}
+
+impl Role {
+    pub fn of(sequence: &str) -> Role {
+        let mut chars = sequence.chars();
+        let Some(first) = chars.next() else {
+            return Role::Pictograph;
+        };
+        match first as u32 {
+            0x200D => Role::Zwj,
+            _ if is_variation_selector(first) => Role::VariationSelector,
+            0x1F3FB..=0x1F3FF => Role::SkinToneModifier,
+            0xE0020..=0xE007F => Role::Tag,
+            0x20E3 => Role::KeycapMark,
+            _ if is_regional_indicator(first) => match chars.next() {
+                Some(next) if is_regional_indicator(next) => Role::Flag,
+                _ => Role::RegionalIndicator,
+            },
+            _ => Role::Pictograph,
+        }
+    }
+
+    /// Whether the sequence is a whole emoji rather than debris.
+    pub fn is_whole(self) -> bool {
+        matches!(self, Role::Pictograph | Role::Flag)
+    }
+
+    /// The role's name in machine-readable output, e.g. `skin_tone_modifier`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Role::Pictograph => "pictograph",
+            Role::Flag => "flag",
+            Role::Zwj => "zwj",
+            Role::VariationSelector => "variation_selector",
+            Role::SkinToneModifier => "skin_tone_modifier",
+            Role::RegionalIndicator => "regional_indicator",
+            Role::Tag => "tag",
+            Role::KeycapMark => "keycap_mark",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find_emoji;
+
+    #[test]
+    fn test_role_of() {
+        let text =
+            "👍🏽 🇫🇷 🏴\u{E0067}\u{E007F} a\u{FE0F} \u{1F3FD} \u{200D} \u{1F1EF} \u{20E3} \u{E0067}";
+        let roles: Vec<&str> = find_emoji(text)
+            .iter()
+            .map(|found| found.role().name())
+            .collect();
+        assert_eq!(
+            roles,
+            [
+                "pictograph",
+                "flag",
+                "pictograph",
+                "variation_selector",
+                "skin_tone_modifier",
+                "zwj",
+                "regional_indicator",
+                "keycap_mark",
+                "tag",
+            ]
+        );
+        assert!(Role::Flag.is_whole());
+        assert!(!Role::Tag.is_whole());
+    }
+}
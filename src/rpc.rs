@@ -0,0 +1,225 @@
+//! JSON-RPC 2.0 over stdio for `--rpc`, so editor plugins can keep one
+//! nomoji process running instead of spawning one per buffer.
+//!
+//! Each line on stdin is one request and each response is one line on
+//! stdout. Requests without an `id` are notifications and get no response,
+//! but a line that isn't a request object with a `method` always gets an
+//! error, with a null `id` if it has none.
+//! Every method takes `{"text": ..., "file": ...}`; `file` is optional and
+//! only selects the matching config rule.
+//!
+//! - `clean` returns `{"text", "emojis", "bytes_saved"}`,
+//! - `count` returns `{"emojis"}`,
+//! - `spans` returns `{"spans": [{"start", "end", "line", "column",
+//!   "emoji", "shortcode", "role"}]}` with byte offsets and 1-based
+//!   positions. `role` is `pictograph` or `flag` for a whole emoji, or
+//!   names the component a stray one starts with, as `nomoji::Role` does.
+//!
+//! Every request with a method counts as one processed document in the
+//! `--metrics-addr` metrics.
+
+use std::io::{self, BufRead, Write};
+use std::time::Instant;
+
+use nomoji::Role;
+
+use crate::json::{self, Value};
+use crate::metrics::METRICS;
+use crate::{Args, STDIN_NAME, clean, find_occurrences, mode};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// Serves requests from `input` until it is exhausted.
+pub fn serve(args: &Args, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle(args, &line) {
+            writeln!(output, "{}", response)?;
+            output.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// The response to one request line, or `None` for a notification.
+pub fn handle(args: &Args, line: &str) -> Option<Value> {
+    let request = match json::parse(line) {
+        Ok(request) => request,
+        Err(e) => return Some(error(Value::Null, PARSE_ERROR, &e)),
+    };
+    let id = request.get("id").cloned();
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        let message = match request {
+            Value::Object(_) => "missing 'method'",
+            _ => "request is not an object",
+        };
+        return Some(error(id.unwrap_or(Value::Null), INVALID_REQUEST, message));
+    };
+
+    let started = Instant::now();
+    let outcome = call(args, method, request.get("params"));
+    let removed = outcome.as_ref().map_or(0, |&(_, removed)| removed);
+    METRICS.record(removed, started.elapsed(), outcome.is_err());
+    let outcome = outcome.map(|(result, _)| result);
+
+    let id = id?;
+    Some(match outcome {
+        Ok(result) => Value::object()
+            .with("jsonrpc", "2.0")
+            .with("id", id)
+            .with("result", result),
+        Err((code, message)) => error(id, code, &message),
+    })
+}
+
+/// The result of one method and how many emojis it removed.
+fn call(
+    args: &Args,
+    method: &str,
+    params: Option<&Value>,
+) -> Result<(Value, usize), (i64, String)> {
+    // Params are read once the method is known, so an unknown method is
+    // reported as such whatever it was sent with.
+    let text = || {
+        params
+            .and_then(|p| p.get("text"))
+            .and_then(Value::as_str)
+            .ok_or((INVALID_PARAMS, "missing string param 'text'".to_string()))
+    };
+    let file = params
+        .and_then(|p| p.get("file"))
+        .and_then(Value::as_str)
+        .unwrap_or(STDIN_NAME);
+    let cleaner = || args.cleaner(file);
+
+    match method {
+        "clean" => {
+            let (text, cleaner) = (text()?, cleaner());
+            let cleaned = clean(text, file, &cleaner, args).map_err(|e| (INTERNAL_ERROR, e))?;
+            let result = Value::object()
+                .with("text", cleaned.text.into_owned())
+                .with("emojis", cleaned.emojis)
+                .with("bytes_saved", cleaned.bytes_saved);
+            Ok((result, cleaned.emojis))
+        }
+        "count" => Ok((Value::object().with("emojis", cleaner().count(text()?)), 0)),
+        "spans" => {
+            let (text, cleaner) = (text()?, cleaner());
+            let spans = cleaner.spans(text);
+            let findings = find_occurrences(text, spans.clone());
+            let spans: Vec<Value> = spans
+                .into_iter()
+                .zip(findings)
+                .map(|(span, finding)| {
+                    let sequence = &text[span.clone()];
+                    Value::object()
+                        .with("start", span.start)
+                        .with("end", span.end)
+                        .with("line", finding.line)
+                        .with("column", finding.column)
+                        .with("emoji", sequence)
+                        .with("shortcode", mode::shortcode(&cleaner.sequence(sequence)))
+                        .with("role", Role::of(sequence).name())
+                })
+                .collect();
+            Ok((Value::object().with("spans", spans), 0))
+        }
+        _ => Err((METHOD_NOT_FOUND, format!("unknown method '{}'", method))),
+    }
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    Value::object().with("jsonrpc", "2.0").with("id", id).with(
+        "error",
+        Value::object()
+            .with("code", Value::Number(code))
+            .with("message", message),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn args() -> Args {
+        Args::parse_from(["nomoji", "--rpc"])
+    }
+
+    #[test]
+    fn test_methods() {
+        let args = args();
+        let respond = |line: &str| handle(&args, line).unwrap().to_string();
+
+        assert_eq!(
+            respond(r#"{"jsonrpc":"2.0","id":1,"method":"clean","params":{"text":"Hi 🚀!"}}"#),
+            r#"{"jsonrpc":"2.0","id":1,"result":{"text":"Hi !","emojis":1,"bytes_saved":4}}"#
+        );
+        assert_eq!(
+            respond(r#"{"id":"a","method":"count","params":{"text":"🎉 🇫🇷"}}"#),
+            r#"{"jsonrpc":"2.0","id":"a","result":{"emojis":3}}"#
+        );
+        assert_eq!(
+            respond(r#"{"id":2,"method":"spans","params":{"text":"a\nb 🚀"}}"#),
+            r#"{"jsonrpc":"2.0","id":2,"result":{"spans":[{"start":4,"end":8,"line":2,"column":3,"emoji":"🚀","shortcode":":rocket:","role":"pictograph"}]}}"#
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let args = args();
+        let code = |line: &str| {
+            handle(&args, line)
+                .unwrap()
+                .get("error")
+                .and_then(|e| e.get("code"))
+                .and_then(Value::as_i64)
+        };
+
+        assert_eq!(code("{"), Some(PARSE_ERROR));
+        assert_eq!(code(r#"{"id":1}"#), Some(INVALID_REQUEST));
+        for invalid in ["[]", "5", r#"{"foo":1}"#, r#"{"method":7}"#] {
+            assert_eq!(
+                handle(&args, invalid).unwrap().to_string(),
+                format!(
+                    r#"{{"jsonrpc":"2.0","id":null,"error":{{"code":{},"message":"{}"}}}}"#,
+                    INVALID_REQUEST,
+                    match invalid.starts_with('{') {
+                        true => "missing 'method'",
+                        false => "request is not an object",
+                    }
+                ),
+                "{}",
+                invalid
+            );
+        }
+        assert_eq!(
+            code(r#"{"id":1,"method":"nope","params":{"text":""}}"#),
+            Some(METHOD_NOT_FOUND)
+        );
+        assert_eq!(code(r#"{"id":3,"method":"nope"}"#), Some(METHOD_NOT_FOUND));
+        assert_eq!(code(r#"{"id":1,"method":"clean"}"#), Some(INVALID_PARAMS));
+        assert!(handle(&args, r#"{"method":"clean","params":{"text":"🚀"}}"#).is_none());
+    }
+
+    #[test]
+    fn test_serve() {
+        let input = "{\"id\":1,\"method\":\"count\",\"params\":{\"text\":\"🚀\"}}\n\n\
+                     {\"method\":\"count\",\"params\":{\"text\":\"\"}}\n\
+                     {\"id\":2,\"method\":\"count\",\"params\":{\"text\":\"\"}}\n";
+        let mut output = Vec::new();
+        serve(&args(), input.as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{\"emojis\":1}}\n\
+             {\"jsonrpc\":\"2.0\",\"id\":2,\"result\":{\"emojis\":0}}\n"
+        );
+    }
+}
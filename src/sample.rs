@@ -0,0 +1,187 @@
+//! `--sample`: a dry run over a random subset of the inputs, with totals
+//! estimated for all of them, for a first look at a corpus too big to read.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use nomoji::schema::{Estimate, Interval};
+
+/// How much of the input a `--sample` run reads.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sample {
+    Percent(f64),
+    Files(usize),
+}
+
+/// Parses a `--sample` value: a percentage such as `5%` or `0.5%`, or a
+/// number of files.
+pub fn parse(value: &str) -> Result<Sample, String> {
+    let value = value.trim();
+    match value.strip_suffix('%') {
+        Some(percent) => match percent.trim().parse::<f64>() {
+            Ok(percent) if percent > 0.0 && percent <= 100.0 => Ok(Sample::Percent(percent)),
+            _ => Err(format!(
+                "invalid sample '{}': use a percentage above 0 and up to 100",
+                value
+            )),
+        },
+        None => match value.parse::<usize>() {
+            Ok(files) if files > 0 => Ok(Sample::Files(files)),
+            _ => Err(format!(
+                "invalid sample '{}': use a percentage such as 5% or a number of files",
+                value
+            )),
+        },
+    }
+}
+
+impl Sample {
+    /// How many of `population` files to read; at least one of any.
+    pub fn size(self, population: usize) -> usize {
+        let size = match self {
+            Sample::Percent(percent) => (population as f64 * percent / 100.0).ceil() as usize,
+            Sample::Files(files) => files,
+        };
+        size.clamp(population.min(1), population)
+    }
+}
+
+/// A seed that differs from run to run.
+pub fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// `size` of `items` picked at random with every subset equally likely,
+/// kept in their original order.
+pub fn pick<T: Clone>(items: &[T], size: usize, seed: u64) -> Vec<T> {
+    let mut rng = SplitMix(seed);
+    let mut indices: Vec<usize> = (0..items.len()).collect();
+    // A partial Fisher-Yates shuffle: the first `size` slots end up a
+    // uniform sample.
+    for i in 0..size.min(items.len()) {
+        let j = i + rng.below(items.len() - i);
+        indices.swap(i, j);
+    }
+    let mut chosen = indices[..size.min(items.len())].to_vec();
+    chosen.sort_unstable();
+    chosen.into_iter().map(|i| items[i].clone()).collect()
+}
+
+/// SplitMix64, which is plenty for choosing files.
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number below `n`, without the bias of `next() % n`.
+    fn below(&mut self, n: usize) -> usize {
+        ((u128::from(self.next()) * n as u128) >> 64) as usize
+    }
+}
+
+/// The totals over `population` files estimated from the emoji `counts`
+/// of the files sampled from them.
+pub fn estimate(counts: &[usize], population: usize) -> Estimate {
+    let emojis: Vec<f64> = counts.iter().map(|&n| n as f64).collect();
+    let hits: Vec<f64> = counts.iter().map(|&n| f64::from(n > 0)).collect();
+    let misses = counts.iter().filter(|&&n| n == 0).count();
+    Estimate {
+        population,
+        sampled: counts.len(),
+        emojis: total(&emojis, population, usize::MAX),
+        // The files sampled without emojis can't be among those with them.
+        files_with_emojis: total(&hits, population, population - misses),
+    }
+}
+
+/// The population total estimated from `values`, a simple random sample
+/// without replacement, with a 95% interval from the normal approximation
+/// and the finite population correction. The low end is never below what
+/// the sample itself holds, nor the high end above `most`.
+fn total(values: &[f64], population: usize, most: usize) -> Interval {
+    if values.is_empty() {
+        return Interval::default();
+    }
+    let n = values.len() as f64;
+    let sum: f64 = values.iter().sum();
+    let mean = sum / n;
+    let variance = match values.len() {
+        1 => 0.0,
+        _ => values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0),
+    };
+    let big_n = population as f64;
+    let error = big_n * ((1.0 - n / big_n) * variance / n).sqrt();
+    let estimate = big_n * mean;
+    Interval {
+        estimate: estimate.round() as usize,
+        low: ((estimate - 1.96 * error).round() as usize).max(sum as usize),
+        high: ((estimate + 1.96 * error).round() as usize).min(most),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("5%"), Ok(Sample::Percent(5.0)));
+        assert_eq!(parse("0.5 %"), Ok(Sample::Percent(0.5)));
+        assert_eq!(parse("200"), Ok(Sample::Files(200)));
+        for bad in ["0%", "101%", "0", "-3", "lots", "%"] {
+            assert!(parse(bad).is_err(), "{}", bad);
+        }
+        assert_eq!(Sample::Percent(5.0).size(1000), 50);
+        assert_eq!(Sample::Percent(5.0).size(3), 1);
+        assert_eq!(Sample::Files(200).size(50), 50);
+        assert_eq!(Sample::Files(200).size(0), 0);
+    }
+
+    #[test]
+    fn test_pick() {
+        let items: Vec<usize> = (0..100).collect();
+        let picked = pick(&items, 10, 42);
+        assert_eq!(picked.len(), 10);
+        assert!(picked.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(pick(&items, 10, 42), picked);
+        assert_ne!(pick(&items, 10, 43), picked);
+        assert_eq!(pick(&items, 500, 1), items);
+    }
+
+    #[test]
+    fn test_estimate() {
+        // Everything read: the totals are exact.
+        let exact = estimate(&[3, 0, 1], 3);
+        assert_eq!(
+            exact.emojis,
+            Interval {
+                estimate: 4,
+                low: 4,
+                high: 4
+            }
+        );
+        assert_eq!(
+            exact.files_with_emojis,
+            Interval {
+                estimate: 2,
+                low: 2,
+                high: 2
+            }
+        );
+
+        // A tenth read, half of it with 2 emojis each.
+        let counts: Vec<usize> = (0..100).map(|i| if i % 2 == 0 { 2 } else { 0 }).collect();
+        let sampled = estimate(&counts, 1000);
+        assert_eq!(sampled.emojis.estimate, 1000);
+        assert!(sampled.emojis.low < 1000 && sampled.emojis.low >= 100);
+        assert!(sampled.emojis.high > 1000);
+        assert_eq!(sampled.files_with_emojis.estimate, 500);
+        assert!(sampled.files_with_emojis.high <= 950);
+    }
+}
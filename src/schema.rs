@@ -0,0 +1,121 @@
+//! The structure of `--format json` reports and `--format jsonl` streams,
+//! for integrations that read them.
+//!
+//! Every report, and every line of a stream, carries `schema_version`.
+//! Within one version fields are only ever added; removing or renaming a
+//! field, or changing what it means, bumps the version. Readers should
+//! ignore fields they don't know.
+//!
+//! The types are plain structs. They don't implement serde's `Serialize`
+//! and `Deserialize` yet; the reports are written without serde.
+
+use crate::Role;
+
+/// The version of the report structure this crate writes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A whole report: totals over every input, and per-file entries.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    pub schema_version: u32,
+    pub files_processed: usize,
+    pub successful: usize,
+    pub total_emojis: usize,
+    /// Bytes removed, or that would be removed in a dry run.
+    pub bytes_saved: usize,
+    /// Emojis in file names, which are counted but never renamed.
+    pub name_emojis: usize,
+    /// Files passed over without cleaning because they were plain ASCII.
+    pub fast_skipped: usize,
+    /// One entry per listed file, which with `--top-files` is a subset of
+    /// `files_processed`.
+    pub files: Vec<FileReport>,
+    /// Totals per directory with `--group-by-dir`, most emojis first; empty
+    /// and left out of the JSON otherwise.
+    pub directories: Vec<DirectoryReport>,
+    /// With `--sample`, the totals estimated for every input from the files
+    /// read; left out of the JSON otherwise.
+    pub estimate: Option<Estimate>,
+}
+
+/// The outcome for one input file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FileReport {
+    pub file: String,
+    pub emojis: usize,
+    pub success: bool,
+    /// Why processing failed; set only when `success` is false.
+    pub error: Option<String>,
+    /// Why the file was left alone, e.g. a lock held elsewhere.
+    pub skipped: Option<String>,
+    pub bytes_saved: usize,
+    pub name_emojis: usize,
+    /// SHA-256 of the file's text, as UTF-8, before and after cleaning; set
+    /// only for files the run rewrote. They match the hashes of the file's
+    /// entry in an `--audit` sidecar.
+    pub before_sha256: Option<String>,
+    pub after_sha256: Option<String>,
+}
+
+/// One occurrence in a `--format jsonl` stream, written as a line with
+/// `"type":"occurrence"`. The stream goes on with a line per file, holding a
+/// `FileReport` with `"type":"file"`, and ends with the totals of a `Report`
+/// under `"type":"summary"`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Occurrence {
+    pub file: String,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based byte column within the line.
+    pub column: usize,
+    pub emoji: String,
+    /// The GitHub shortcode, e.g. `:rocket:`, if the sequence has one.
+    pub shortcode: Option<String>,
+    /// A whole emoji, or the component standing alone.
+    pub role: Role,
+}
+
+/// Totals over all inputs estimated from a random sample of them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Estimate {
+    /// The inputs the sample was drawn from.
+    pub population: usize,
+    /// The inputs read, which are what `files` lists.
+    pub sampled: usize,
+    pub emojis: Interval,
+    pub files_with_emojis: Interval,
+}
+
+/// An estimated count and its 95% confidence bounds.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Interval {
+    pub estimate: usize,
+    pub low: usize,
+    pub high: usize,
+}
+
+/// Totals over the inputs under one directory.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DirectoryReport {
+    /// The directory, cut to the grouping depth, with a trailing `/`.
+    pub directory: String,
+    pub files: usize,
+    pub emojis: usize,
+}
+
+impl Default for Report {
+    fn default() -> Self {
+        Report {
+            schema_version: SCHEMA_VERSION,
+            files_processed: 0,
+            successful: 0,
+            total_emojis: 0,
+            bytes_saved: 0,
+            name_emojis: 0,
+            fast_skipped: 0,
+            files: Vec::new(),
+            directories: Vec::new(),
+            estimate: None,
+        }
+    }
+}
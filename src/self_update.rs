@@ -0,0 +1,233 @@
+//! `nomoji self-update`: replacing a prebuilt binary with the latest GitHub
+//! release.
+//!
+//! Downloads go through the `curl` command-line tool, so no HTTP or TLS
+//! library is linked in. A release is expected to carry one binary per
+//! platform, named by `asset_name`, and a `SHA256SUMS` file in the
+//! `sha256sum` format. The download must match its listed checksum before it
+//! replaces anything. There is no signature check: the checksum only guards
+//! against a corrupt or truncated download, and trust rests on GitHub's TLS.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::json::{self, Value};
+use crate::{sha256, staging};
+
+pub const LATEST_RELEASE: &str = "https://api.github.com/repos/brianshumate/nomoji/releases/latest";
+
+/// The newest published release.
+#[derive(Debug, PartialEq)]
+pub struct Release {
+    /// The tag's version, without a leading `v`.
+    pub version: String,
+    /// Download URLs by asset name.
+    pub assets: Vec<(String, String)>,
+}
+
+impl Release {
+    pub fn asset(&self, name: &str) -> Option<&str> {
+        self.assets
+            .iter()
+            .find(|(asset, _)| asset == name)
+            .map(|(_, url)| url.as_str())
+    }
+}
+
+/// The release asset built for this platform, e.g. `nomoji-x86_64-linux`.
+pub fn asset_name() -> String {
+    format!(
+        "nomoji-{}-{}{}",
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        std::env::consts::EXE_SUFFIX
+    )
+}
+
+/// Parses the GitHub API's description of a release.
+pub fn parse_release(text: &str) -> Result<Release, String> {
+    let release = json::parse(text)?;
+    let tag = release
+        .get("tag_name")
+        .and_then(Value::as_str)
+        .ok_or("release has no tag")?;
+    let assets = release
+        .get("assets")
+        .and_then(Value::as_array)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|asset| {
+            let name = asset.get("name").and_then(Value::as_str)?;
+            let url = asset.get("browser_download_url").and_then(Value::as_str)?;
+            Some((name.to_string(), url.to_string()))
+        })
+        .collect();
+    Ok(Release {
+        version: tag.strip_prefix('v').unwrap_or(tag).to_string(),
+        assets,
+    })
+}
+
+/// Whether `candidate` is a later version than `current`, comparing
+/// dot-separated numbers; anything after a `-` is ignored, and so are
+/// trailing zeros, so `1.0.0` is the same release as `1.0`.
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    let numbers = |version: &str| -> Vec<u64> {
+        let release = version.split('-').next().unwrap_or_default();
+        let mut numbers: Vec<u64> = release.split('.').map(|n| n.parse().unwrap_or(0)).collect();
+        while numbers.last() == Some(&0) {
+            numbers.pop();
+        }
+        numbers
+    };
+    numbers(candidate) > numbers(current)
+}
+
+/// The checksum `sums`, in `sha256sum` output format, lists for `name`.
+pub fn checksum<'a>(sums: &'a str, name: &str) -> Option<&'a str> {
+    sums.lines().find_map(|line| {
+        let (sum, file) = line.split_once(char::is_whitespace)?;
+        // A leading `*` marks binary mode.
+        let file = file.trim_start().trim_start_matches('*');
+        (file == name).then_some(sum)
+    })
+}
+
+/// Checks `binary` against `expected`, a hex SHA-256.
+pub fn verify(binary: &[u8], expected: &str) -> Result<(), String> {
+    let actual = sha256::hex(binary);
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(format!(
+            "checksum mismatch: expected {}, downloaded {}",
+            expected, actual
+        ))
+    }
+}
+
+/// Fetches `url` with `curl`, failing on HTTP errors.
+pub fn fetch(curl: &str, url: &str) -> Result<Vec<u8>, String> {
+    let output = Command::new(curl)
+        .args(["--silent", "--show-error", "--fail", "--location"])
+        .args(["--proto", "=https", "--header", "User-Agent: nomoji"])
+        .arg(url)
+        .output()
+        .map_err(|e| format!("failed to run {}: {}", curl, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("failed to download {}: {}", url, stderr.trim()));
+    }
+    Ok(output.stdout)
+}
+
+/// Replaces the executable at `target` with `binary`. The new file is
+/// written next to it and renamed over it, so `target` is never left half
+/// written; a running process keeps its old image. The staging file is
+/// created exclusively and never through a symlink, since self-update often
+/// runs with rights an attacker would like to borrow.
+pub fn replace(target: &Path, binary: &[u8]) -> io::Result<()> {
+    let like = fs::metadata(target)?;
+    let staged = staging_path(target);
+    staging::write_new(&staged, binary, &like)?;
+    let renamed = fs::rename(&staged, target);
+    if renamed.is_err() {
+        let _ = fs::remove_file(&staged);
+    }
+    renamed
+}
+
+fn staging_path(target: &Path) -> PathBuf {
+    let mut name = target.file_name().unwrap_or_default().to_os_string();
+    name.push(".update");
+    target.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_release() {
+        let release = parse_release(
+            r#"{"tag_name":"v0.3.1","draft":false,"id":42,"assets":[
+                {"name":"SHA256SUMS","browser_download_url":"https://example.com/SHA256SUMS"},
+                {"name":"nomoji-x86_64-linux","browser_download_url":"https://example.com/bin","size":10}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(release.version, "0.3.1");
+        assert_eq!(
+            release.asset("nomoji-x86_64-linux"),
+            Some("https://example.com/bin")
+        );
+        assert_eq!(release.asset("nomoji-aarch64-macos"), None);
+        assert!(parse_release(r#"{"message":"Not Found"}"#).is_err());
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.2.0", "0.1.0"));
+        assert!(is_newer("0.10.0", "0.9.3"));
+        assert!(is_newer("1.0", "0.9.9"));
+        assert!(!is_newer("0.1.0", "0.1.0"));
+        assert!(!is_newer("0.1.0-rc1", "0.1.0"));
+        assert!(!is_newer("0.0.9", "0.1.0"));
+        assert!(!is_newer("1.0.0", "1.0"));
+        assert!(!is_newer("1.0", "1.0.0"));
+        assert!(is_newer("1.0.1", "1.0"));
+    }
+
+    #[test]
+    fn test_checksum_and_verify() {
+        let sums = format!(
+            "{}  nomoji-x86_64-linux\n{} *nomoji-x86_64-windows.exe\n",
+            sha256::hex(b"linux"),
+            sha256::hex(b"windows")
+        );
+        let linux = checksum(&sums, "nomoji-x86_64-linux").unwrap();
+        assert!(verify(b"linux", linux).is_ok());
+        assert!(verify(b"linux!", linux).is_err());
+        let windows = checksum(&sums, "nomoji-x86_64-windows.exe").unwrap();
+        assert!(verify(b"windows", windows).is_ok());
+        assert_eq!(checksum(&sums, "nomoji"), None);
+    }
+
+    #[test]
+    fn test_replace() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("nomoji");
+        fs::write(&target, "old").unwrap();
+        replace(&target, b"new").unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+        assert!(!staging_path(&target).exists());
+
+        assert!(replace(&dir.path().join("missing"), b"new").is_err());
+        assert!(!dir.path().join("missing.update").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_refuses_a_planted_link() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("nomoji");
+        let victim = dir.path().join("victim");
+        fs::write(&target, "old").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(&victim, "precious").unwrap();
+        std::os::unix::fs::symlink(&victim, staging_path(&target)).unwrap();
+
+        assert!(replace(&target, b"new").is_err());
+        assert_eq!(fs::read(&victim).unwrap(), b"precious");
+        assert_eq!(fs::read(&target).unwrap(), b"old");
+
+        fs::remove_file(staging_path(&target)).unwrap();
+        replace(&target, b"new").unwrap();
+        let mode = fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
+}
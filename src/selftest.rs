@@ -0,0 +1,155 @@
+//! `nomoji selftest`: checks the detector against an embedded corpus of
+//! fully-qualified emoji sequences in the Unicode `emoji-test.txt` format.
+//!
+//! Each sequence is placed between two letters and cleaned with the default
+//! settings; anything left between the letters is a miss.
+
+use crate::emoji_names::EMOJI_NAMES;
+use crate::remove_emojis;
+
+/// Generated by `scripts/gen_emoji_test.py`.
+pub const CORPUS: &str = include_str!("emoji-test.txt");
+
+/// A sequence the detector does not remove completely.
+#[derive(Debug, PartialEq)]
+pub struct Miss {
+    pub sequence: String,
+    pub description: String,
+    /// What was left of the sequence after cleaning.
+    pub leftover: String,
+}
+
+/// The fully-qualified sequences in `corpus` with their descriptions.
+/// Malformed lines are skipped.
+pub fn sequences(corpus: &str) -> impl Iterator<Item = (String, &str)> {
+    corpus.lines().filter_map(|line| {
+        let (fields, comment) = line.split_once('#')?;
+        let (points, status) = fields.split_once(';')?;
+        if status.trim() != "fully-qualified" {
+            return None;
+        }
+        let sequence = points
+            .split_whitespace()
+            .map(|p| u32::from_str_radix(p, 16).ok().and_then(char::from_u32))
+            .collect::<Option<String>>()?;
+        // The comment repeats the sequence before its name.
+        let description = comment.trim().strip_prefix(sequence.as_str())?.trim();
+        Some((sequence, description))
+    })
+}
+
+/// Cleans every sequence in `corpus`, returning how many were checked and
+/// the ones not fully removed.
+pub fn run(corpus: &str) -> (usize, Vec<Miss>) {
+    let mut checked = 0;
+    let mut misses = Vec::new();
+
+    for (sequence, description) in sequences(corpus) {
+        checked += 1;
+        let (cleaned, _) = remove_emojis(&format!("a{}b", sequence));
+        let leftover = &cleaned[1..cleaned.len() - 1];
+        if !leftover.is_empty() {
+            misses.push(Miss {
+                leftover: leftover.to_string(),
+                sequence,
+                description: description.to_string(),
+            });
+        }
+    }
+
+    (checked, misses)
+}
+
+/// The name of `sequence` as the corpus gives it, with or without its
+/// variation selector, or else its Unicode character name.
+pub fn name(sequence: &str) -> Option<String> {
+    let qualified = format!("{}\u{FE0F}", sequence);
+    sequences(CORPUS)
+        .find(|(candidate, _)| *candidate == sequence || *candidate == qualified)
+        .map(|(_, description)| description.to_string())
+        .or_else(|| {
+            let mut chars = sequence.trim_end_matches('\u{FE0F}').chars();
+            let c = chars.next().filter(|_| chars.next().is_none())?;
+            EMOJI_NAMES
+                .binary_search_by_key(&c, |&(emoji, _)| emoji)
+                .ok()
+                .map(|i| EMOJI_NAMES[i].1.replace('_', " "))
+        })
+}
+
+/// `U+XXXX` notation for each character of `text`.
+pub fn codepoints(text: &str) -> String {
+    text.chars()
+        .map(|c| format!("U+{:04X}", c as u32))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequences() {
+        let corpus = "# group: Smileys\n\
+                      1F600 ; fully-qualified # 😀 grinning face\n\
+                      263A ; unqualified # ☺ smiling face\n\
+                      1F44D 1F3FD ; fully-qualified # 👍🏽 thumbs up: medium skin tone\n\
+                      ZZZZ ; fully-qualified # ? broken\n";
+        let found: Vec<_> = sequences(corpus).collect();
+        assert_eq!(
+            found,
+            [
+                ("😀".to_string(), "grinning face"),
+                ("👍🏽".to_string(), "thumbs up: medium skin tone"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_name() {
+        assert_eq!(name("🚀").as_deref(), Some("rocket"));
+        // Text-default symbols are named whether or not VS16 follows.
+        assert_eq!(name("☀").as_deref(), Some("black sun with rays"));
+        assert_eq!(name("©").as_deref(), Some("copyright sign"));
+        assert_eq!(name("\u{2607}").as_deref(), Some("lightning"));
+        assert_eq!(name("a"), None);
+    }
+
+    #[test]
+    fn test_run_reports_leftovers() {
+        let corpus = "1F680 ; fully-qualified # 🚀 rocket\n\
+                      0031 FE0F 20E3 ; fully-qualified # 1️⃣ keycap: 1\n";
+        let (checked, misses) = run(corpus);
+        assert_eq!(checked, 2);
+        assert_eq!(
+            misses,
+            [Miss {
+                sequence: "1\u{FE0F}\u{20E3}".to_string(),
+                description: "keycap: 1".to_string(),
+                leftover: "1".to_string(),
+            }]
+        );
+        assert_eq!(codepoints(&misses[0].leftover), "U+0031");
+    }
+
+    #[test]
+    fn test_embedded_corpus_core_coverage() {
+        let (checked, misses) = run(CORPUS);
+        assert!(checked > 3000);
+        // Sequences built from pictographs, components and regional
+        // indicators are fully removed; the known gaps are text-default
+        // symbols and keycap digits.
+        let covered =
+            |c: char| (0x1F1E6..=0x1FAFF).contains(&(c as u32)) || crate::is_emoji_component(c);
+        for (sequence, _) in sequences(CORPUS) {
+            if sequence.chars().all(covered) {
+                assert!(
+                    misses.iter().all(|m| m.sequence != sequence),
+                    "{}",
+                    codepoints(&sequence)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! `--staged`: checking what is about to be committed, read from the git
+//! index instead of the working tree, and staging the cleaned blobs back.
+
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::pre_receive::{RawChange, git, parse_raw_diff};
+
+/// The regular files added or modified in the index relative to `HEAD`,
+/// or to the empty tree before the first commit, restricted to `paths` if
+/// any are given. Paths are relative to `dir`, and only files beneath it
+/// are listed, as `git diff --relative` does.
+pub fn changes(dir: &Path, paths: &[String]) -> io::Result<Vec<RawChange>> {
+    let mut args = vec![
+        "diff",
+        "--cached",
+        "--relative",
+        "--raw",
+        "-z",
+        "--no-renames",
+        "--diff-filter=AMT",
+        "--",
+    ];
+    args.extend(paths.iter().map(String::as_str));
+    let diff = git(dir, &args)?;
+
+    // Symlinks and submodules hold no text to clean.
+    let mut changes = parse_raw_diff(&diff);
+    changes.retain(|change| change.new_mode.starts_with("100"));
+    Ok(changes)
+}
+
+/// The content of the blob `sha`.
+pub fn blob(dir: &Path, sha: &str) -> io::Result<Vec<u8>> {
+    git(dir, &["cat-file", "blob", sha])
+}
+
+/// Replaces the staged content of `change` with `content`, keeping its
+/// mode. Clean filters aren't run again, as `content` came from the index.
+pub fn stage(dir: &Path, change: &RawChange, content: &[u8]) -> io::Result<()> {
+    let mut hash = Command::new("git")
+        .current_dir(dir)
+        .args(["hash-object", "-w", "--no-filters", "--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    hash.stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(content)?;
+    let output = hash.wait_with_output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git hash-object failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let sha = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    let info = format!("{},{},{}", change.new_mode, sha, change.path);
+    git(dir, &["update-index", "--cacheinfo", &info]).map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn run_git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args([
+                "-c",
+                "user.name=nomoji",
+                "-c",
+                "user.email=nomoji@example.com",
+            ])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn test_stage_leaves_the_working_tree_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path();
+        run_git(repo, &["init", "-q"]);
+        fs::create_dir(repo.join("docs")).unwrap();
+        fs::write(repo.join("docs/notes.md"), "Ship it 🚀\n").unwrap();
+        fs::write(repo.join("plain.txt"), "plain\n").unwrap();
+        run_git(repo, &["add", "."]);
+        fs::write(repo.join("docs/notes.md"), "Ship it 🚀\nUnstaged ✅\n").unwrap();
+
+        let staged = changes(repo, &[]).unwrap();
+        let paths: Vec<&str> = staged.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, ["docs/notes.md", "plain.txt"]);
+        assert_eq!(
+            blob(repo, &staged[0].new_sha).unwrap(),
+            "Ship it 🚀\n".as_bytes()
+        );
+        assert_eq!(
+            changes(&repo.join("docs"), &[]).unwrap()[0].path,
+            "notes.md"
+        );
+
+        stage(repo, &staged[0], b"Ship it \n").unwrap();
+        assert_eq!(run_git(repo, &["show", ":docs/notes.md"]), "Ship it \n");
+        assert_eq!(
+            fs::read_to_string(repo.join("docs/notes.md")).unwrap(),
+            "Ship it 🚀\nUnstaged ✅\n"
+        );
+        assert_eq!(changes(repo, &["plain.txt".to_string()]).unwrap().len(), 1);
+    }
+}
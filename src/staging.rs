@@ -0,0 +1,346 @@
+//! Writing files without trusting whatever already sits at the destination.
+//!
+//! New files, backups and the temporary copies that replace an edited file,
+//! are created exclusively, readable only by their owner, and never through
+//! a symlink: a link planted at the path, or a file that appears there
+//! between a check and the write, makes the write fail rather than be
+//! followed or clobbered. A file is replaced by renaming a finished copy
+//! over it, which swaps out the name without following it and never leaves
+//! the file half written.
+//!
+//! Renaming gives the path a new inode, so other hard links to the file
+//! would keep the old content. A file with other links is written in place
+//! through its inode instead, unless the caller chooses to break the links.
+//!
+//! Copies are staged next to the file they replace unless a temporary
+//! directory is given. A rename can't cross filesystems or land in a
+//! directory that isn't writable, so such a copy is written over the file
+//! instead, giving up atomicity rather than failing.
+
+use std::ffi::OsString;
+use std::fs::{self, File, Metadata, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// How many names `stage` tries before giving up.
+const ATTEMPTS: u32 = 100;
+
+/// Creates `path`, which must not exist yet, readable and writable only by
+/// its owner.
+pub fn create_new(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600).custom_flags(libc::O_NOFOLLOW);
+    }
+    options.open(path)
+}
+
+/// Writes `content` to `path`, which must not exist yet, with the
+/// permissions and, where allowed, the owner of `like`.
+pub fn write_new(path: &Path, content: &[u8], like: &Metadata) -> io::Result<()> {
+    let mut file = create_new(path)?;
+    let written = file
+        .write_all(content)
+        .and_then(|()| file.sync_all())
+        .and_then(|()| copy_metadata(&file, like));
+    if written.is_err() {
+        let _ = fs::remove_file(path);
+    }
+    written
+}
+
+/// Replaces `target` with `content`, staged in `temp_dir` if given, taking
+/// the permissions and, where allowed, the owner of `like`.
+pub fn replace(
+    target: &Path,
+    content: &[u8],
+    like: &Metadata,
+    temp_dir: Option<&Path>,
+) -> io::Result<()> {
+    let (staged, mut file) = stage(target, temp_dir)?;
+    let written = file
+        .write_all(content)
+        .and_then(|()| file.sync_all())
+        .and_then(|()| copy_metadata(&file, like))
+        .and_then(|()| settle(&staged, target));
+    if written.is_err() {
+        let _ = fs::remove_file(&staged);
+    }
+    written
+}
+
+/// Writes `content` over `target` through its inode, keeping every hard
+/// link to it. Unlike `replace` this isn't atomic: a crash part way leaves
+/// the file half written.
+pub fn rewrite(target: &Path, content: &[u8]) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NOFOLLOW);
+    }
+    let mut file = options.open(target)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+/// How many hard links the file described by `metadata` has; always 1
+/// where that isn't known.
+pub fn links(metadata: &Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.nlink()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        1
+    }
+}
+
+/// Copies `source` to `backup`, staged in `temp_dir` if given, replacing
+/// any earlier backup. The copy is readable only by its owner, whatever the
+/// original's permissions.
+pub fn back_up(source: &Path, backup: &Path, temp_dir: Option<&Path>) -> io::Result<()> {
+    let mut original = File::open(source)?;
+    let (staged, mut file) = stage(backup, temp_dir)?;
+    let written = io::copy(&mut original, &mut file)
+        .and_then(|_| file.sync_all())
+        .and_then(|()| settle(&staged, backup));
+    if written.is_err() {
+        let _ = fs::remove_file(&staged);
+    }
+    written
+}
+
+/// Creates a temporary file named after `target`, in `temp_dir` or else
+/// next to it.
+fn stage(target: &Path, temp_dir: Option<&Path>) -> io::Result<(PathBuf, File)> {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    let name = target.file_name().unwrap_or_default();
+    for _ in 0..ATTEMPTS {
+        let mut staged = OsString::from(".");
+        staged.push(name);
+        staged.push(format!(
+            ".nomoji-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let staged = match temp_dir {
+            Some(dir) => dir.join(staged),
+            None => target.with_file_name(staged),
+        };
+        match create_new(&staged) {
+            Ok(file) => return Ok((staged, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("no free temporary name for {}", target.display()),
+    ))
+}
+
+/// Moves the finished copy at `staged` onto `target`.
+fn settle(staged: &Path, target: &Path) -> io::Result<()> {
+    match fs::rename(staged, target) {
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::CrossesDevices | io::ErrorKind::PermissionDenied
+            ) =>
+        {
+            overwrite(staged, target)?;
+            let _ = fs::remove_file(staged);
+            Ok(())
+        }
+        renamed => renamed,
+    }
+}
+
+/// Writes the bytes, permissions and owner of `staged` over `target`, which
+/// is created if missing but never followed if it is a symlink.
+fn overwrite(staged: &Path, target: &Path) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600).custom_flags(libc::O_NOFOLLOW);
+    }
+    let mut file = options.open(target)?;
+    io::copy(&mut File::open(staged)?, &mut file)?;
+    file.sync_all()?;
+    copy_metadata(&file, &fs::metadata(staged)?)
+}
+
+fn copy_metadata(file: &File, like: &Metadata) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{MetadataExt, fchown};
+        // Only root can give a file away; anyone else keeps it as their own.
+        let _ = fchown(file, Some(like.uid()), Some(like.gid()));
+    }
+    file.set_permissions(like.permissions())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leftovers(dir: &Path) -> Vec<OsString> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .filter(|name| name.to_string_lossy().contains(".nomoji-"))
+            .collect()
+    }
+
+    #[test]
+    fn test_create_new_refuses_existing_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("taken");
+        fs::write(&path, "mine").unwrap();
+        let err = create_new(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "mine");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_new_does_not_follow_symlinks() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+
+        let dir = tempfile::tempdir().unwrap();
+        let victim = dir.path().join("victim");
+        let link = dir.path().join("link");
+        symlink(&victim, &link).unwrap();
+        assert!(create_new(&link).is_err());
+        assert!(!victim.exists());
+
+        let fresh = dir.path().join("fresh");
+        create_new(&fresh).unwrap();
+        let mode = fs::metadata(&fresh).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_keeps_permissions_and_replaces_links() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("script.sh");
+        fs::write(&target, "old").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o755)).unwrap();
+        let like = fs::metadata(&target).unwrap();
+        replace(&target, b"new", &like, None).unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+        let mode = fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+
+        // A link at the path is swapped out, not written through.
+        let victim = dir.path().join("victim");
+        fs::write(&victim, "untouched").unwrap();
+        let link = dir.path().join("copy");
+        symlink(&victim, &link).unwrap();
+        replace(&link, b"copy", &like, None).unwrap();
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "untouched");
+        assert!(!fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert!(leftovers(dir.path()).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rewrite_keeps_hard_links() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        fs::write(&first, "old content").unwrap();
+        fs::hard_link(&first, &second).unwrap();
+        assert_eq!(links(&fs::metadata(&first).unwrap()), 2);
+
+        rewrite(&first, b"new").unwrap();
+        assert_eq!(fs::read(&second).unwrap(), b"new");
+        assert_eq!(links(&fs::metadata(&first).unwrap()), 2);
+
+        let link = dir.path().join("link");
+        symlink(&first, &link).unwrap();
+        assert!(rewrite(&link, b"through").is_err());
+        assert!(rewrite(&dir.path().join("missing"), b"").is_err());
+        assert!(!dir.path().join("missing").exists());
+    }
+
+    #[test]
+    fn test_failed_writes_leave_nothing_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let like = fs::metadata(dir.path()).unwrap();
+        // Renaming a file over a directory fails.
+        let occupied = dir.path().join("occupied");
+        fs::create_dir(&occupied).unwrap();
+        fs::write(occupied.join("inside"), "").unwrap();
+        assert!(replace(&occupied, b"new", &like, None).is_err());
+        assert!(back_up(&dir.path().join("missing"), &dir.path().join("b"), None).is_err());
+        assert!(leftovers(dir.path()).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_back_up_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("notes.txt");
+        let backup = dir.path().join("notes.txt.bak");
+        fs::write(&source, "first 🚀").unwrap();
+        fs::set_permissions(&source, fs::Permissions::from_mode(0o644)).unwrap();
+        back_up(&source, &backup, None).unwrap();
+        fs::write(&source, "second 🚀").unwrap();
+        back_up(&source, &backup, None).unwrap();
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "second 🚀");
+        let mode = fs::metadata(&backup).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_stages_in_temp_dir() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+
+        let dir = tempfile::tempdir().unwrap();
+        let temp = tempfile::tempdir().unwrap();
+        let target = dir.path().join("notes.txt");
+        fs::write(&target, "old").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o640)).unwrap();
+        let like = fs::metadata(&target).unwrap();
+        replace(&target, b"new", &like, Some(temp.path())).unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 0);
+
+        // The fallback for a copy staged on another filesystem.
+        let (staged, mut file) = stage(&target, Some(temp.path())).unwrap();
+        file.write_all(b"newer").unwrap();
+        copy_metadata(&file, &like).unwrap();
+        overwrite(&staged, &target).unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"newer");
+        let mode = fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+
+        let victim = dir.path().join("victim");
+        fs::write(&victim, "untouched").unwrap();
+        let link = dir.path().join("link");
+        symlink(&victim, &link).unwrap();
+        assert!(overwrite(&staged, &link).is_err());
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "untouched");
+    }
+}
@@ -0,0 +1,176 @@
+//! `nomoji stats`: every emoji occurrence in a corpus as a row of a CSV or
+//! Parquet file, for analysis in a data team's own tools.
+
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use nomoji::category::Category;
+use nomoji::{Role, emoji_spans};
+
+use crate::age::age;
+use crate::parquet::{self, Column};
+
+/// A file format `--export` writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Export {
+    Csv,
+    Parquet,
+}
+
+impl FromStr for Export {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Export::Csv),
+            "parquet" => Ok(Export::Parquet),
+            _ => Err(format!("unknown export format '{}': use csv or parquet", s)),
+        }
+    }
+}
+
+/// One emoji sequence found in a file.
+#[derive(Debug, PartialEq)]
+pub struct Occurrence {
+    pub file: String,
+    /// Byte offset into the file's text, as UTF-8, from 0.
+    pub offset: usize,
+    pub emoji: String,
+    /// The first of the categories it belongs to, in `--only`'s order.
+    pub category: Option<&'static str>,
+    /// The Unicode version that introduced its newest character.
+    pub version: Option<(u8, u8)>,
+    pub role: Role,
+}
+
+/// The occurrences in `text`, the content of `file`, in order.
+pub fn occurrences(file: &str, text: &str) -> Vec<Occurrence> {
+    emoji_spans(text)
+        .into_iter()
+        .map(|span| {
+            let emoji = &text[span.clone()];
+            Occurrence {
+                file: file.to_string(),
+                offset: span.start,
+                emoji: emoji.to_string(),
+                category: Category::ALL
+                    .into_iter()
+                    .find(|c| c.matches(emoji))
+                    .map(Category::name),
+                version: age(emoji),
+                role: Role::of(emoji),
+            }
+        })
+        .collect()
+}
+
+const COLUMNS: [&str; 6] = [
+    "file",
+    "offset",
+    "emoji",
+    "category",
+    "unicode_version",
+    "role",
+];
+
+/// Writes `rows` as `format` to `out`.
+pub fn write(rows: Vec<Occurrence>, format: Export, out: &mut dyn Write) -> io::Result<()> {
+    match format {
+        Export::Csv => write_csv(&rows, out),
+        Export::Parquet => write_parquet(rows, out),
+    }
+}
+
+/// RFC 4180 CSV with a header row; a missing category or version is an
+/// empty field.
+fn write_csv(rows: &[Occurrence], out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "{}", COLUMNS.join(","))?;
+    for row in rows {
+        writeln!(
+            out,
+            "{},{},{},{},{},{}",
+            csv_field(&row.file),
+            row.offset,
+            csv_field(&row.emoji),
+            row.category.unwrap_or_default(),
+            row.version.map(version).unwrap_or_default(),
+            row.role.name()
+        )?;
+    }
+    out.flush()
+}
+
+fn csv_field(value: &str) -> std::borrow::Cow<'_, str> {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")).into(),
+        false => value.into(),
+    }
+}
+
+fn write_parquet(rows: Vec<Occurrence>, out: &mut dyn Write) -> io::Result<()> {
+    let mut files = Vec::with_capacity(rows.len());
+    let mut offsets = Vec::with_capacity(rows.len());
+    let mut emojis = Vec::with_capacity(rows.len());
+    let mut categories = Vec::with_capacity(rows.len());
+    let mut versions = Vec::with_capacity(rows.len());
+    let mut roles = Vec::with_capacity(rows.len());
+    for row in rows {
+        files.push(row.file);
+        offsets.push(row.offset as i64);
+        emojis.push(row.emoji);
+        categories.push(row.category.map(String::from));
+        versions.push(row.version.map(version));
+        roles.push(row.role.name().to_string());
+    }
+    let [file, offset, emoji, category, unicode_version, role] = COLUMNS;
+    parquet::write(
+        out,
+        &[
+            (file, Column::Utf8(files)),
+            (offset, Column::Int64(offsets)),
+            (emoji, Column::Utf8(emojis)),
+            (category, Column::OptionalUtf8(categories)),
+            (unicode_version, Column::OptionalUtf8(versions)),
+            (role, Column::Utf8(roles)),
+        ],
+    )
+}
+
+fn version((major, minor): (u8, u8)) -> String {
+    format!("{}.{}", major, minor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_occurrences_and_csv() {
+        let rows = occurrences("a,b.md", "Ship 🚀 ✅\n© 🫠 \u{FE0F}");
+        assert_eq!(
+            rows.iter()
+                .map(|r| (r.offset, r.emoji.as_str(), r.category, r.version))
+                .collect::<Vec<_>>(),
+            [
+                (5, "🚀", Some("transport"), Some((6, 0))),
+                (10, "✅", Some("check-marks"), Some((6, 0))),
+                (14, "©", Some("legal"), Some((1, 1))),
+                (17, "🫠", Some("supplemental"), Some((14, 0))),
+                (22, "\u{FE0F}", None, None),
+            ]
+        );
+
+        let mut csv = Vec::new();
+        write(rows, Export::Csv, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "file,offset,emoji,category,unicode_version,role\n\
+             \"a,b.md\",5,🚀,transport,6.0,pictograph\n\
+             \"a,b.md\",10,✅,check-marks,6.0,pictograph\n\
+             \"a,b.md\",14,©,legal,1.1,pictograph\n\
+             \"a,b.md\",17,🫠,supplemental,14.0,pictograph\n\
+             \"a,b.md\",22,\u{FE0F},,,variation_selector\n"
+        );
+        assert!("tsv".parse::<Export>().is_err());
+    }
+}
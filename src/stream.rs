@@ -0,0 +1,242 @@
+//! Cleaning a stream as it arrives, in chunks of any size, for sockets,
+//! pipes and child processes whose end isn't known in advance.
+//!
+//! `StreamCleaner` doesn't read or write anything itself: it takes chunks
+//! and returns the cleaned bytes that are safe to pass on, so it fits a
+//! blocking loop and an async task alike. Bytes that could still be the
+//! start of a sequence are held back until the next chunk shows how it
+//! ends. `CleanWriter` wraps it around a `std::io::Write`; there are no
+//! `AsyncRead`/`AsyncWrite` wrappers yet.
+//!
+//! ```
+//! use nomoji::stream::StreamCleaner;
+//!
+//! let rocket = "🚀".as_bytes();
+//! let mut cleaner = StreamCleaner::default();
+//! let mut out = cleaner.feed(b"Ship ");
+//! out.extend(cleaner.feed(&rocket[..2]));
+//! out.extend(cleaner.feed(&rocket[2..]));
+//! out.extend(cleaner.feed(b" it\n"));
+//! out.extend(cleaner.finish());
+//! assert_eq!(out, b"Ship  it\n");
+//! ```
+
+use std::io::{self, Write};
+
+use crate::{is_emoji, is_emoji_component, remove_emojis_cow};
+
+/// How much of a line without a safe break is held back before it is passed
+/// on regardless.
+const MAX_PENDING: usize = 64 * 1024;
+
+/// Cleans a stream of bytes that arrives in chunks of any size.
+#[derive(Default)]
+pub struct StreamCleaner {
+    pending: Vec<u8>,
+    /// Emojis removed so far.
+    pub emojis: usize,
+}
+
+impl StreamCleaner {
+    /// Takes the next chunk and returns the cleaned output that is safe to
+    /// pass on, holding back what could still be the start of a sequence.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(chunk);
+        let ready = match safe_break(&self.pending) {
+            Some(end) => end,
+            None if self.pending.len() > MAX_PENDING => forced_break(&self.pending),
+            None => return Vec::new(),
+        };
+        let rest = self.pending.split_off(ready);
+        let ready = std::mem::replace(&mut self.pending, rest);
+        self.clean(&ready)
+    }
+
+    /// Cleans and returns whatever was held back, at the end of the stream.
+    pub fn finish(&mut self) -> Vec<u8> {
+        let rest = std::mem::take(&mut self.pending);
+        self.clean(&rest)
+    }
+
+    /// `bytes` with the emojis in its UTF-8 text removed. Bytes that aren't
+    /// UTF-8 are passed on as they are.
+    fn clean(&mut self, mut bytes: &[u8]) -> Vec<u8> {
+        let mut cleaned = Vec::with_capacity(bytes.len());
+        while !bytes.is_empty() {
+            let (valid, invalid) = match std::str::from_utf8(bytes) {
+                Ok(text) => (text, bytes.len()),
+                Err(e) => {
+                    let valid = e.valid_up_to();
+                    let invalid = valid + e.error_len().unwrap_or(bytes.len() - valid);
+                    // SAFETY: from_utf8 checked the bytes up to `valid`.
+                    (
+                        unsafe { std::str::from_utf8_unchecked(&bytes[..valid]) },
+                        invalid,
+                    )
+                }
+            };
+            let (text, emojis) = remove_emojis_cow(valid);
+            self.emojis += emojis;
+            cleaned.extend_from_slice(text.as_bytes());
+            cleaned.extend_from_slice(&bytes[valid.len()..invalid]);
+            bytes = &bytes[invalid..];
+        }
+        cleaned
+    }
+}
+
+/// A writer that cleans what is written to it before passing it on to the
+/// writer it wraps. `finish` writes out what is still held back; dropping
+/// it without that loses up to the last partial sequence.
+pub struct CleanWriter<W: Write> {
+    inner: W,
+    cleaner: StreamCleaner,
+}
+
+impl<W: Write> CleanWriter<W> {
+    pub fn new(inner: W) -> Self {
+        CleanWriter {
+            inner,
+            cleaner: StreamCleaner::default(),
+        }
+    }
+
+    /// Emojis removed so far.
+    pub fn emojis(&self) -> usize {
+        self.cleaner.emojis
+    }
+
+    /// Writes what is held back, flushes, and returns the wrapped writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.write_all(&self.cleaner.finish())?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for CleanWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write_all(&self.cleaner.feed(buf))?;
+        Ok(buf.len())
+    }
+
+    /// Flushes the wrapped writer. Bytes held back for the next write stay
+    /// held back.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Where `bytes` can be cut without splitting an emoji sequence: after the
+/// last ASCII byte that can't begin a keycap, as no sequence continues past
+/// one.
+fn safe_break(bytes: &[u8]) -> Option<usize> {
+    bytes
+        .iter()
+        .rposition(|b| b.is_ascii() && !matches!(b, b'0'..=b'9' | b'#' | b'*'))
+        .map(|last| last + 1)
+}
+
+/// Where to cut `bytes` that grew past `MAX_PENDING` without a safe break:
+/// before the emoji sequence they may end in, and never inside a
+/// character. With nothing but emoji to hold back, at the last character.
+fn forced_break(bytes: &[u8]) -> usize {
+    // The start of the character that ends at `end`.
+    let lead = |end: usize| {
+        (end.saturating_sub(4)..end)
+            .rev()
+            .find(|&i| bytes[i] & 0xC0 != 0x80)
+    };
+    let last_char = |end: usize| {
+        let start = lead(end)?;
+        let c = std::str::from_utf8(&bytes[start..end])
+            .ok()?
+            .chars()
+            .next()?;
+        Some((start, c))
+    };
+    let mut end = bytes.len();
+    if let Some(lead) = lead(end) {
+        let width = match bytes[lead] {
+            0x00..=0x7F => 1,
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            _ => 4,
+        };
+        if lead + width > end {
+            end = lead;
+        }
+    }
+    let char_boundary = end;
+    while let Some((start, c)) = last_char(end) {
+        let joins = is_emoji(c) || is_emoji_component(c) || matches!(c, '0'..='9' | '#' | '*');
+        if !joins {
+            break;
+        }
+        end = start;
+    }
+    match end {
+        0 => char_boundary,
+        _ => end,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_cleaner_across_chunks() {
+        let rocket = "🚀".as_bytes();
+        let mut stripper = StreamCleaner::default();
+        let mut out = stripper.feed(b"launch ");
+        out.extend(stripper.feed(&rocket[..2]));
+        out.extend(stripper.feed(&rocket[2..]));
+        out.extend(stripper.feed(" now\nprompt 👩\u{200D}".as_bytes()));
+        assert_eq!(out, b"launch  now\nprompt ");
+        out.extend(stripper.feed("💻> ".as_bytes()));
+        out.extend(stripper.finish());
+        assert_eq!(out, b"launch  now\nprompt > ");
+        assert_eq!(stripper.emojis, crate::count_emojis("🚀 👩\u{200D}💻"));
+    }
+
+    #[test]
+    fn test_clean_writer() {
+        let mut writer = CleanWriter::new(Vec::new());
+        for byte in "Ship 🚀 ✅\n".bytes() {
+            writer.write_all(&[byte]).unwrap();
+        }
+        write!(writer, "done 👍🏽").unwrap();
+        assert_eq!(writer.emojis(), 2);
+        assert_eq!(writer.finish().unwrap(), b"Ship  \ndone ");
+    }
+
+    #[test]
+    fn test_stream_cleaner_long_line_split_emoji() {
+        let rocket = "🚀".as_bytes();
+        let mut stripper = StreamCleaner::default();
+        let long = "日".repeat(22_000);
+        let mut out = stripper.feed(&[long.as_bytes(), &rocket[..2]].concat());
+        out.extend(stripper.feed(&rocket[2..]));
+        out.extend(stripper.feed("\u{FE0F}日".as_bytes()));
+        out.extend(stripper.finish());
+        assert_eq!(String::from_utf8(out).unwrap(), format!("{}日", long));
+        assert_eq!(stripper.emojis, crate::count_emojis("🚀\u{FE0F}"));
+
+        // A cut never splits a long run of emoji mid-character.
+        let mut stripper = StreamCleaner::default();
+        let rockets = "🚀".repeat(17_000);
+        let mut out = stripper.feed(&rockets.as_bytes()[..rockets.len() - 1]);
+        out.extend(stripper.feed(&rockets.as_bytes()[rockets.len() - 1..]));
+        out.extend(stripper.finish());
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_stream_cleaner_keeps_invalid_utf8() {
+        let mut stripper = StreamCleaner::default();
+        let out = stripper.feed(b"\xff\xfe ok \xe2\x9c\x85\n");
+        assert_eq!(out, b"\xff\xfe ok \n");
+        assert_eq!(stripper.emojis, 1);
+    }
+}
@@ -0,0 +1,301 @@
+//! Filtering a tar stream member by member (`--tar-filter`), without
+//! unpacking it.
+//!
+//! Headers and everything that isn't a regular file, such as directories,
+//! links, pax and GNU extension records, are copied through as they are.
+//! Each regular file is offered to a callback, and a member it changes gets
+//! a new size and checksum in its header. Members too large to hold in
+//! memory, and those whose size a pax record overrides, are copied without
+//! being offered.
+
+use std::io::{self, Read, Write};
+
+const BLOCK: usize = 512;
+
+/// Members larger than this are copied through without being cleaned.
+const MAX_MEMBER: u64 = 64 * 1024 * 1024;
+
+/// Copies the tar stream `input` to `output`. `clean` is called with the
+/// path and content of every regular file and returns the content to write
+/// instead, or `None` to keep it.
+pub fn filter(
+    mut input: impl Read,
+    mut output: impl Write,
+    mut clean: impl FnMut(&str, &[u8]) -> Option<Vec<u8>>,
+) -> io::Result<()> {
+    let mut header = [0u8; BLOCK];
+    // Set by GNU long name and pax records for the member that follows.
+    let mut long_name: Option<String> = None;
+    let mut pax_path: Option<String> = None;
+    let mut pax_size = false;
+
+    loop {
+        if !read_block(&mut input, &mut header)? {
+            // A stream cut short without its end-of-archive blocks.
+            break;
+        }
+        if header.iter().all(|&b| b == 0) {
+            output.write_all(&header)?;
+            io::copy(&mut input, &mut output)?;
+            break;
+        }
+        if checksum(&header) != parse_octal(&header[148..156]) {
+            return Err(invalid("bad header checksum; not a tar stream"));
+        }
+
+        let kind = header[156];
+        let size = size(&header)?;
+        let regular = matches!(kind, b'0' | b'\0' | b'7');
+        let name = pax_path
+            .take()
+            .or(long_name.take())
+            .unwrap_or_else(|| header_name(&header));
+        let sized_by_pax = std::mem::take(&mut pax_size);
+
+        if matches!(kind, b'L' | b'x') || (regular && size <= MAX_MEMBER && !sized_by_pax) {
+            let content = read_content(&mut input, size)?;
+            match kind {
+                b'L' => long_name = Some(c_string(&content)),
+                b'x' => {
+                    let records = pax_records(&content);
+                    pax_path = records
+                        .iter()
+                        .find(|(k, _)| k == "path")
+                        .map(|(_, v)| v.clone());
+                    pax_size = records.iter().any(|(k, _)| k == "size");
+                }
+                _ => {
+                    if let Some(cleaned) = clean(&name, &content) {
+                        set_size(&mut header, cleaned.len() as u64);
+                        output.write_all(&header)?;
+                        write_content(&mut output, &cleaned)?;
+                        continue;
+                    }
+                }
+            }
+            output.write_all(&header)?;
+            write_content(&mut output, &content)?;
+        } else {
+            output.write_all(&header)?;
+            let padded = size.div_ceil(BLOCK as u64) * BLOCK as u64;
+            let copied = io::copy(&mut (&mut input).take(padded), &mut output)?;
+            if copied < padded {
+                return Err(truncated());
+            }
+        }
+    }
+    output.flush()
+}
+
+/// Fills `block`, or returns `false` at the end of the input.
+fn read_block(input: &mut impl Read, block: &mut [u8; BLOCK]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < BLOCK {
+        match input.read(&mut block[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(truncated()),
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+/// Reads a member's `size` bytes and the padding after them.
+fn read_content(input: &mut impl Read, size: u64) -> io::Result<Vec<u8>> {
+    let padded = size.div_ceil(BLOCK as u64) * BLOCK as u64;
+    let mut content = Vec::with_capacity(padded as usize);
+    input.take(padded).read_to_end(&mut content)?;
+    if (content.len() as u64) < padded {
+        return Err(truncated());
+    }
+    content.truncate(size as usize);
+    Ok(content)
+}
+
+fn write_content(output: &mut impl Write, content: &[u8]) -> io::Result<()> {
+    output.write_all(content)?;
+    let padding = content.len().next_multiple_of(BLOCK) - content.len();
+    output.write_all(&[0; BLOCK][..padding])
+}
+
+/// The member size, in octal or, for large members, base-256.
+fn size(header: &[u8; BLOCK]) -> io::Result<u64> {
+    let field = &header[124..136];
+    if field[0] & 0x80 == 0 {
+        return Ok(parse_octal(field));
+    }
+    field[1..]
+        .iter()
+        .try_fold(u64::from(field[0] & 0x7F), |size, &b| {
+            size.checked_mul(256).map(|size| size + u64::from(b))
+        })
+        .ok_or_else(|| invalid("member size out of range"))
+}
+
+/// Writes `size` and the checksum that goes with it into `header`.
+fn set_size(header: &mut [u8; BLOCK], size: u64) {
+    header[124..136].copy_from_slice(format!("{:011o}\0", size).as_bytes());
+    let sum = checksum(header);
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", sum).as_bytes());
+}
+
+/// The unsigned sum of the header bytes, counting the checksum field as
+/// spaces.
+fn checksum(header: &[u8; BLOCK]) -> u64 {
+    header
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| match i {
+            148..156 => u64::from(b' '),
+            _ => u64::from(b),
+        })
+        .sum()
+}
+
+/// An octal field, ignoring the spaces and NULs around the digits.
+fn parse_octal(field: &[u8]) -> u64 {
+    field
+        .iter()
+        .skip_while(|&&b| b == b' ')
+        .take_while(|b| (b'0'..=b'7').contains(b))
+        .fold(0, |n, &b| n * 8 + u64::from(b - b'0'))
+}
+
+/// The path in a ustar header: the prefix, if any, and the name.
+fn header_name(header: &[u8; BLOCK]) -> String {
+    let name = c_string(&header[0..100]);
+    let prefix = match &header[257..263] {
+        b"ustar\0" => c_string(&header[345..500]),
+        _ => String::new(),
+    };
+    match prefix.is_empty() {
+        true => name,
+        false => format!("{}/{}", prefix, name),
+    }
+}
+
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+/// The `key=value` records of a pax extended header, each written as
+/// `<length> <key>=<value>\n`.
+fn pax_records(content: &[u8]) -> Vec<(String, String)> {
+    let mut records = Vec::new();
+    let mut rest = content;
+    while let Some(space) = rest.iter().position(|&b| b == b' ') {
+        let Some(length) = std::str::from_utf8(&rest[..space])
+            .ok()
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|&n| n > space && n <= rest.len())
+        else {
+            break;
+        };
+        let record = String::from_utf8_lossy(&rest[space + 1..length]);
+        if let Some((key, value)) = record.trim_end_matches('\n').split_once('=') {
+            records.push((key.to_string(), value.to_string()));
+        }
+        rest = &rest[length..];
+    }
+    records
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "tar stream ends inside a member",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ustar header for a member of `kind` called `name`.
+    fn header(name: &str, kind: u8, size: u64) -> [u8; BLOCK] {
+        let mut header = [0u8; BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..108].copy_from_slice(b"0000644\0");
+        header[156] = kind;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        set_size(&mut header, size);
+        header
+    }
+
+    fn member(name: &str, kind: u8, content: &[u8]) -> Vec<u8> {
+        let mut out = header(name, kind, content.len() as u64).to_vec();
+        write_content(&mut out, content).unwrap();
+        out
+    }
+
+    fn archive(members: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = members.concat();
+        out.extend([0; 2 * BLOCK]);
+        out
+    }
+
+    #[test]
+    fn test_filter_resizes_changed_members() {
+        let pax = "31 path=very/long/name 🚀.md\n".as_bytes();
+        let input = archive(&[
+            member("docs/", b'5', b""),
+            member("docs/a.md", b'0', "Ship 🚀 ✅\n".as_bytes()),
+            member("PaxHeader", b'x', pax),
+            member("short", b'0', "Done ✅\n".as_bytes()),
+            member("bin", b'0', b"\xff\xfe binary"),
+        ]);
+
+        let mut seen = Vec::new();
+        let mut output = Vec::new();
+        filter(&input[..], &mut output, |name, content| {
+            seen.push(name.to_string());
+            let text = std::str::from_utf8(content).ok()?;
+            let (cleaned, count) = nomoji::remove_emojis(text);
+            (count > 0).then(|| cleaned.into_bytes())
+        })
+        .unwrap();
+
+        assert_eq!(seen, ["docs/a.md", "very/long/name 🚀.md", "bin"]);
+        let expected = archive(&[
+            member("docs/", b'5', b""),
+            member("docs/a.md", b'0', b"Ship  \n"),
+            member("PaxHeader", b'x', pax),
+            member("short", b'0', b"Done \n"),
+            member("bin", b'0', b"\xff\xfe binary"),
+        ]);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_filter_rejects_what_isnt_tar() {
+        let mut garbage = vec![b'x'; BLOCK];
+        garbage.extend([0; BLOCK]);
+        let error = filter(&garbage[..], io::sink(), |_, _| None).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut cut = member("a.txt", b'0', &[b'a'; 1000]);
+        cut.truncate(BLOCK + 100);
+        let error = filter(&cut[..], io::sink(), |_, _| None).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_pax_records() {
+        assert_eq!(
+            pax_records(b"16 path=a/b.txt\n11 size=42\n"),
+            [
+                ("path".to_string(), "a/b.txt".to_string()),
+                ("size".to_string(), "42".to_string())
+            ]
+        );
+        assert!(pax_records(b"99 path=x\n").is_empty());
+    }
+}
@@ -0,0 +1,103 @@
+//! TeamCity service messages (`--format teamcity`): an inspection per
+//! occurrence, a build problem per failed file and the total as a build
+//! statistic, all read by TeamCity from the build log.
+
+use std::io::{self, Write};
+
+use crate::ProcessResult;
+use crate::selftest::codepoints;
+
+/// The inspection type every occurrence is reported under.
+const INSPECTION: &str = "nomoji.emoji";
+
+/// The build statistic holding the number of emojis found.
+const STATISTIC: &str = "nomoji.emojis";
+
+pub fn print(results: &[&ProcessResult], out: &mut dyn Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "##teamcity[inspectionType id='{}' name='Emoji' description='Emoji in text' category='nomoji']",
+        INSPECTION
+    )?;
+    for result in results {
+        for finding in &result.findings {
+            writeln!(
+                out,
+                "##teamcity[inspection typeId='{}' message='{}' file='{}' line='{}' SEVERITY='WARNING']",
+                INSPECTION,
+                escape(&format!(
+                    "Emoji {} ({}) at column {}",
+                    finding.emoji,
+                    codepoints(&finding.emoji),
+                    finding.column
+                )),
+                escape(&result.file),
+                finding.line
+            )?;
+        }
+        if let Some(ref error) = result.error {
+            writeln!(
+                out,
+                "##teamcity[buildProblem description='{}']",
+                escape(&format!("nomoji: {}: {}", result.file, error))
+            )?;
+        }
+        if let Some(ref reason) = result.skipped {
+            warn(out, &format!("{}: skipped, {}", result.file, reason))?;
+        }
+        if result.name_emojis > 0 {
+            warn(
+                out,
+                &format!(
+                    "{}: {} emojis in the file name",
+                    result.file, result.name_emojis
+                ),
+            )?;
+        }
+    }
+    let total: usize = results.iter().map(|r| r.emojis_found).sum();
+    writeln!(
+        out,
+        "##teamcity[buildStatisticValue key='{}' value='{}']",
+        STATISTIC, total
+    )
+}
+
+fn warn(out: &mut dyn Write, text: &str) -> io::Result<()> {
+    writeln!(
+        out,
+        "##teamcity[message text='{}' status='WARNING']",
+        escape(&format!("nomoji: {}", text))
+    )
+}
+
+/// `value` escaped for a quoted service message attribute.
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '|' => out.push_str("||"),
+            '\'' => out.push_str("|'"),
+            '[' => out.push_str("|["),
+            ']' => out.push_str("|]"),
+            '\n' => out.push_str("|n"),
+            '\r' => out.push_str("|r"),
+            '\u{0085}' => out.push_str("|x"),
+            '\u{2028}' => out.push_str("|l"),
+            '\u{2029}' => out.push_str("|p"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("it's [a|b]\r\n"), "it|'s |[a||b|]|r|n");
+        assert_eq!(escape("plain 🚀"), "plain 🚀");
+    }
+}
@@ -0,0 +1,186 @@
+//! Pacing and priority controls for long unattended scans.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::interrupt;
+
+/// A processing rate limit given with `--throttle`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rate {
+    BytesPerSec(u64),
+    FilesPerSec(u64),
+}
+
+/// Parses `--throttle` values such as `512K`, `4MB/s`, `1G` (bytes per
+/// second, binary multiples) or `20files/s`.
+pub fn parse_rate(value: &str) -> Result<Rate, String> {
+    let value = value.trim();
+    let value = value.strip_suffix("/s").unwrap_or(value).trim_end();
+    let digits = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(digits);
+
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid throttle '{}': expected a number", value))?;
+    if number == 0 {
+        return Err("throttle rate must be greater than zero".to_string());
+    }
+
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "files" | "file" | "f" => return Ok(Rate::FilesPerSec(number)),
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        other => {
+            return Err(format!(
+                "invalid throttle unit '{}': use K, M, G or files",
+                other
+            ));
+        }
+    };
+
+    number
+        .checked_mul(multiplier)
+        .map(Rate::BytesPerSec)
+        .ok_or_else(|| format!("throttle '{}' is too large", value))
+}
+
+/// Sleeps between files so the average rate since the first file stays at or
+/// below the configured limit.
+pub struct Throttle {
+    rate: Rate,
+    started: Instant,
+    consumed: u64,
+}
+
+impl Throttle {
+    pub fn new(rate: Rate) -> Self {
+        Throttle {
+            rate,
+            started: Instant::now(),
+            consumed: 0,
+        }
+    }
+
+    /// Accounts for one processed file of `bytes` bytes and waits as long as
+    /// needed to honour the rate, or until the run is interrupted.
+    pub fn record(&mut self, bytes: u64) {
+        self.consumed += match self.rate {
+            Rate::BytesPerSec(_) => bytes,
+            Rate::FilesPerSec(_) => 1,
+        };
+
+        let deadline =
+            Instant::now() + pending_delay(self.rate, self.consumed, self.started.elapsed());
+        while interrupt::requested().is_none() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            thread::sleep(remaining.min(Duration::from_millis(100)));
+        }
+    }
+}
+
+/// How much longer to wait so `consumed` units spread over at least the
+/// time the rate allows.
+fn pending_delay(rate: Rate, consumed: u64, elapsed: Duration) -> Duration {
+    let per_sec = match rate {
+        Rate::BytesPerSec(n) | Rate::FilesPerSec(n) => n,
+    };
+    let target = Duration::from_secs_f64(consumed as f64 / per_sec as f64);
+    target.saturating_sub(elapsed)
+}
+
+/// Lowers CPU and I/O scheduling priority for this process where the
+/// platform supports it, returning a description of what could not be
+/// applied.
+#[cfg(unix)]
+pub fn lower_priority() -> Result<(), String> {
+    // SAFETY: setpriority only adjusts the calling process's nice value.
+    let niced = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, 19) } == 0;
+    if !niced {
+        return Err(format!(
+            "unable to lower CPU priority: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+        const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+        const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+
+        // SAFETY: ioprio_set with IOPRIO_WHO_PROCESS and pid 0 only affects
+        // the calling process.
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_ioprio_set,
+                IOPRIO_WHO_PROCESS,
+                0 as libc::c_long,
+                IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+            )
+        };
+        if result != 0 {
+            return Err(format!(
+                "unable to lower I/O priority: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn lower_priority() -> Result<(), String> {
+    Err("lowering priority is not supported on this platform".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate("100"), Ok(Rate::BytesPerSec(100)));
+        assert_eq!(parse_rate("512K"), Ok(Rate::BytesPerSec(512 * 1024)));
+        assert_eq!(parse_rate("4MB/s"), Ok(Rate::BytesPerSec(4 << 20)));
+        assert_eq!(parse_rate("1G"), Ok(Rate::BytesPerSec(1 << 30)));
+        assert_eq!(parse_rate("20files/s"), Ok(Rate::FilesPerSec(20)));
+        assert_eq!(parse_rate("3 files"), Ok(Rate::FilesPerSec(3)));
+
+        assert!(parse_rate("0").is_err());
+        assert!(parse_rate("fast").is_err());
+        assert!(parse_rate("10X").is_err());
+        assert!(parse_rate("99999999999999G").is_err());
+    }
+
+    #[test]
+    fn test_pending_delay() {
+        let rate = Rate::BytesPerSec(1000);
+        assert_eq!(
+            pending_delay(rate, 500, Duration::ZERO),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            pending_delay(rate, 500, Duration::from_millis(200)),
+            Duration::from_millis(300)
+        );
+        assert_eq!(
+            pending_delay(rate, 500, Duration::from_secs(1)),
+            Duration::ZERO
+        );
+
+        let rate = Rate::FilesPerSec(4);
+        assert_eq!(
+            pending_delay(rate, 2, Duration::ZERO),
+            Duration::from_millis(500)
+        );
+    }
+}
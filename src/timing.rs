@@ -0,0 +1,90 @@
+//! Where the time of a run goes, for `-v` and `-vv`: reading, cleaning and
+//! writing each file, so a slow network filesystem shows up as slow reads
+//! or writes rather than a slow run.
+
+use std::fmt;
+use std::ops::AddAssign;
+use std::time::Duration;
+
+/// Time spent on one file, or summed over several.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Timings {
+    pub read: Duration,
+    pub clean: Duration,
+    pub write: Duration,
+}
+
+impl Timings {
+    pub fn total(&self) -> Duration {
+        self.read + self.clean + self.write
+    }
+
+    /// The timings with the throughput of `bytes` over their total, e.g.
+    /// `read 1.2ms, clean 0.3ms, write 2.0ms (1.52 MB/s)`.
+    pub fn describe(&self, bytes: usize) -> String {
+        format!("{} ({})", self, throughput(bytes, self.total()))
+    }
+}
+
+impl AddAssign for Timings {
+    fn add_assign(&mut self, other: Self) {
+        self.read += other.read;
+        self.clean += other.clean;
+        self.write += other.write;
+    }
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "read {}, clean {}, write {}",
+            duration(self.read),
+            duration(self.clean),
+            duration(self.write)
+        )
+    }
+}
+
+/// `d` in milliseconds under a second, else in seconds.
+pub fn duration(d: Duration) -> String {
+    if d < Duration::from_secs(1) {
+        format!("{:.1}ms", d.as_secs_f64() * 1000.0)
+    } else {
+        format!("{:.2}s", d.as_secs_f64())
+    }
+}
+
+/// `bytes` over `elapsed` in decimal megabytes per second.
+pub fn throughput(bytes: usize, elapsed: Duration) -> String {
+    if elapsed.is_zero() {
+        return "- MB/s".to_string();
+    }
+    format!("{:.2} MB/s", bytes as f64 / 1e6 / elapsed.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        let mut timings = Timings {
+            read: Duration::from_micros(1200),
+            clean: Duration::from_micros(300),
+            write: Duration::from_micros(500),
+        };
+        assert_eq!(
+            timings.describe(4_000),
+            "read 1.2ms, clean 0.3ms, write 0.5ms (2.00 MB/s)"
+        );
+
+        timings += Timings {
+            read: Duration::from_secs(2),
+            ..Timings::default()
+        };
+        assert_eq!(duration(timings.read), "2.00s");
+        assert_eq!(timings.total(), Duration::from_micros(2_002_000));
+        assert_eq!(throughput(10, Duration::ZERO), "- MB/s");
+    }
+}
@@ -0,0 +1,455 @@
+//! A small TOML reader covering what nomoji configuration files use.
+//!
+//! Supported: comments, bare/quoted/dotted keys, `[table]` and
+//! `[[array-of-tables]]` headers, basic and literal strings (single line),
+//! integers, booleans, arrays (which may span lines) and inline tables.
+//! Floats, dates and multi-line strings are rejected with an error.
+
+use std::collections::BTreeMap;
+
+pub type Table = BTreeMap<String, Value>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Integer(_) => "integer",
+            Value::Boolean(_) => "boolean",
+            Value::Array(_) => "array",
+            Value::Table(_) => "table",
+        }
+    }
+}
+
+/// Parses a TOML document into its root table. Errors carry the line number.
+pub fn parse(input: &str) -> Result<Table, String> {
+    let mut parser = Parser {
+        chars: input.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+    parser
+        .document()
+        .map_err(|e| format!("line {}: {}", parser.line, e))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(match self.peek() {
+                Some(c) => format!("expected '{}', found '{}'", expected, c),
+                None => format!("expected '{}', found end of file", expected),
+            })
+        }
+    }
+
+    /// Skips spaces and tabs on the current line.
+    fn skip_blank(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.bump();
+            }
+        }
+    }
+
+    /// Skips whitespace, newlines and comments, as allowed inside arrays.
+    fn skip_space_and_comments(&mut self) {
+        loop {
+            self.skip_blank();
+            self.skip_comment();
+            if !(self.eat('\n') || self.eat('\r')) {
+                break;
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_blank();
+        self.skip_comment();
+        self.eat('\r');
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => Err(format!("unexpected '{}' after value", c)),
+        }
+    }
+
+    fn document(&mut self) -> Result<Table, String> {
+        let mut root = Table::new();
+        let mut current: Vec<String> = Vec::new();
+
+        loop {
+            self.skip_space_and_comments();
+            match self.peek() {
+                None => return Ok(root),
+                Some('[') => {
+                    self.bump();
+                    let array = self.eat('[');
+                    self.skip_blank();
+                    let path = self.key_path()?;
+                    self.skip_blank();
+                    self.expect(']')?;
+                    if array {
+                        self.expect(']')?;
+                        push_table_array(&mut root, &path)?;
+                    } else {
+                        table_at(&mut root, &path, true)?;
+                    }
+                    current = path;
+                    self.end_of_line()?;
+                }
+                Some(_) => {
+                    let path = self.key_path()?;
+                    self.skip_blank();
+                    self.expect('=')?;
+                    self.skip_blank();
+                    let value = self.value()?;
+                    let table = table_at(&mut root, &current, false)?;
+                    insert(table, &path, value)?;
+                    self.end_of_line()?;
+                }
+            }
+        }
+    }
+
+    fn key_path(&mut self) -> Result<Vec<String>, String> {
+        let mut path = vec![self.key()?];
+        loop {
+            self.skip_blank();
+            if !self.eat('.') {
+                return Ok(path);
+            }
+            self.skip_blank();
+            path.push(self.key()?);
+        }
+    }
+
+    fn key(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some('"') => self.basic_string(),
+            Some('\'') => self.literal_string(),
+            _ => {
+                let start = self.pos;
+                while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                {
+                    self.bump();
+                }
+                if self.pos == start {
+                    return Err(match self.peek() {
+                        Some(c) => format!("expected a key, found '{}'", c),
+                        None => "expected a key".to_string(),
+                    });
+                }
+                Ok(self.chars[start..self.pos].iter().collect())
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            Some('t' | 'f') => self.boolean(),
+            Some(c) if c.is_ascii_digit() || c == '+' || c == '-' => self.integer(),
+            Some(c) => Err(format!("unexpected '{}' where a value was expected", c)),
+            None => Err("missing value".to_string()),
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        if self.peek() == Some('"') && self.chars.get(self.pos + 1) == Some(&'"') {
+            return Err("multi-line strings are not supported".to_string());
+        }
+
+        let mut out = String::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return Err("unterminated string".to_string()),
+                Some('"') => return Ok(out),
+                Some('\\') => out.push(self.escape()?),
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, String> {
+        match self.bump() {
+            Some('"') => Ok('"'),
+            Some('\\') => Ok('\\'),
+            Some('n') => Ok('\n'),
+            Some('t') => Ok('\t'),
+            Some('r') => Ok('\r'),
+            Some('b') => Ok('\u{8}'),
+            Some('f') => Ok('\u{C}'),
+            Some('u') => self.unicode_escape(4),
+            Some('U') => self.unicode_escape(8),
+            Some(c) => Err(format!("invalid escape '\\{}'", c)),
+            None => Err("unterminated string".to_string()),
+        }
+    }
+
+    fn unicode_escape(&mut self, digits: usize) -> Result<char, String> {
+        let hex: String = (0..digits).filter_map(|_| self.bump()).collect();
+        u32::from_str_radix(&hex, 16)
+            .ok()
+            .filter(|_| hex.len() == digits)
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("invalid unicode escape '{}'", hex))
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        self.expect('\'')?;
+        let mut out = String::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return Err("unterminated string".to_string()),
+                Some('\'') => return Ok(out),
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    fn boolean(&mut self) -> Result<Value, String> {
+        for (word, value) in [("true", true), ("false", false)] {
+            let end = self.pos + word.len();
+            if self.chars.len() >= end && self.chars[self.pos..end].iter().copied().eq(word.chars())
+            {
+                self.pos = end;
+                return Ok(Value::Boolean(value));
+            }
+        }
+        Err("expected a value".to_string())
+    }
+
+    fn integer(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '_' | '.'))
+        {
+            self.bump();
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.replace('_', "")
+            .parse()
+            .map(Value::Integer)
+            .map_err(|_| format!("invalid number '{}' (only integers are supported)", text))
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        loop {
+            self.skip_space_and_comments();
+            if self.eat(']') {
+                return Ok(Value::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_space_and_comments();
+            if !self.eat(',') {
+                self.skip_space_and_comments();
+                self.expect(']')?;
+                return Ok(Value::Array(items));
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Value, String> {
+        self.expect('{')?;
+        let mut table = Table::new();
+        self.skip_blank();
+        if self.eat('}') {
+            return Ok(Value::Table(table));
+        }
+        loop {
+            self.skip_blank();
+            let path = self.key_path()?;
+            self.skip_blank();
+            self.expect('=')?;
+            self.skip_blank();
+            let value = self.value()?;
+            insert(&mut table, &path, value)?;
+            self.skip_blank();
+            if self.eat('}') {
+                return Ok(Value::Table(table));
+            }
+            self.expect(',')?;
+        }
+    }
+}
+
+/// Returns the table at `path`, creating intermediate tables. Entering an
+/// array of tables selects its last element.
+fn table_at<'a>(
+    root: &'a mut Table,
+    path: &[String],
+    header: bool,
+) -> Result<&'a mut Table, String> {
+    let mut table = root;
+    for (i, key) in path.iter().enumerate() {
+        let entry = table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Table(Table::new()));
+        table = match entry {
+            Value::Table(t) => t,
+            Value::Array(items) if !(header && i == path.len() - 1) => match items.last_mut() {
+                Some(Value::Table(t)) => t,
+                _ => return Err(format!("'{}' is not a table", key)),
+            },
+            _ => return Err(format!("'{}' is already defined", path[..=i].join("."))),
+        };
+    }
+    Ok(table)
+}
+
+fn push_table_array(root: &mut Table, path: &[String]) -> Result<(), String> {
+    let (last, parents) = path.split_last().ok_or("empty table name")?;
+    let parent = table_at(root, parents, false)?;
+    match parent
+        .entry(last.clone())
+        .or_insert_with(|| Value::Array(Vec::new()))
+    {
+        Value::Array(items) => {
+            items.push(Value::Table(Table::new()));
+            Ok(())
+        }
+        _ => Err(format!("'{}' is already defined", path.join("."))),
+    }
+}
+
+fn insert(table: &mut Table, path: &[String], value: Value) -> Result<(), String> {
+    let (last, parents) = path.split_last().ok_or("empty key")?;
+    let table = table_at(table, parents, false)?;
+    if table.contains_key(last) {
+        return Err(format!("duplicate key '{}'", path.join(".")));
+    }
+    table.insert(last.clone(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Value {
+        Value::Array(
+            values
+                .iter()
+                .map(|s| Value::String(s.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_parse_values() {
+        let table = parse(
+            r#"
+# nomoji settings
+keep = ["check-marks", 'arrows'] # trailing comment
+depth = 1_000
+strict = true
+name = "tab\tand é"
+
+[profiles.docs]
+only = [
+    "faces",   # smileys
+    "hearts",
+]
+inline = { a = 1, b.c = false }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(table["keep"], strings(&["check-marks", "arrows"]));
+        assert_eq!(table["depth"], Value::Integer(1000));
+        assert_eq!(table["strict"], Value::Boolean(true));
+        assert_eq!(table["name"], Value::String("tab\tand é".to_string()));
+
+        let Value::Table(profiles) = &table["profiles"] else {
+            panic!("profiles should be a table");
+        };
+        let Value::Table(docs) = &profiles["docs"] else {
+            panic!("docs should be a table");
+        };
+        assert_eq!(docs["only"], strings(&["faces", "hearts"]));
+        let Value::Table(inline) = &docs["inline"] else {
+            panic!("inline should be a table");
+        };
+        assert_eq!(inline["a"], Value::Integer(1));
+    }
+
+    #[test]
+    fn test_parse_array_of_tables() {
+        let table = parse("[[rule]]\nglob = \"*.md\"\n[[rule]]\nglob = \"*.py\"\n").unwrap();
+        let Value::Array(rules) = &table["rule"] else {
+            panic!("rule should be an array");
+        };
+        assert_eq!(rules.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse("a = 1\na = 2").unwrap_err(),
+            "line 2: duplicate key 'a'"
+        );
+        assert!(parse("a = ").unwrap_err().contains("missing value"));
+        assert!(
+            parse("a = \"open")
+                .unwrap_err()
+                .contains("unterminated string")
+        );
+        assert!(parse("a = 1.5").unwrap_err().contains("only integers"));
+        assert!(parse("a = 1 b = 2").unwrap_err().contains("after value"));
+        assert!(parse("a = [1, 2").is_err());
+    }
+}
@@ -0,0 +1,236 @@
+//! Moving files to the desktop trash.
+//!
+//! On Linux and other XDG desktops this follows the freedesktop.org Trash
+//! specification for the home trash (`$XDG_DATA_HOME/Trash`). On macOS files
+//! are moved into `~/.Trash`. Other platforms report `Unsupported`.
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Moves `file` into the user's trash and returns its new location.
+pub fn move_to_trash<P: AsRef<Path>>(file: P) -> io::Result<PathBuf> {
+    let trash_dir = home_trash_dir()?;
+
+    if cfg!(target_os = "macos") {
+        move_into_macos_trash(file.as_ref(), &trash_dir)
+    } else {
+        move_into_xdg_trash(file.as_ref(), &trash_dir)
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn home_trash_dir() -> io::Result<PathBuf> {
+    home_dir().map(|home| home.join(".Trash"))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn home_trash_dir() -> io::Result<PathBuf> {
+    match env::var_os("XDG_DATA_HOME").filter(|v| !v.is_empty()) {
+        Some(data_home) => Ok(PathBuf::from(data_home).join("Trash")),
+        None => home_dir().map(|home| home.join(".local/share/Trash")),
+    }
+}
+
+#[cfg(not(unix))]
+fn home_trash_dir() -> io::Result<PathBuf> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "moving files to the trash is not supported on this platform",
+    ))
+}
+
+#[cfg(unix)]
+fn home_dir() -> io::Result<PathBuf> {
+    env::var_os("HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))
+}
+
+/// Moves `file` into a freedesktop.org trash directory, writing the matching
+/// `.trashinfo` record so desktop file managers can restore it.
+fn move_into_xdg_trash(file: &Path, trash_dir: &Path) -> io::Result<PathBuf> {
+    let original = fs::canonicalize(file)?;
+    let files_dir = trash_dir.join("files");
+    let info_dir = trash_dir.join("info");
+    fs::create_dir_all(&files_dir)?;
+    fs::create_dir_all(&info_dir)?;
+
+    let name = file_name(&original)?;
+    for attempt in 1.. {
+        let candidate = unique_name(&name, attempt);
+
+        // The info file is created exclusively first; it reserves the name
+        // against concurrent trashers as described by the specification.
+        let info_path = info_dir.join(format!("{}.trashinfo", candidate));
+        let mut info = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(info) => info,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        };
+
+        let target = files_dir.join(&candidate);
+        if target.exists() {
+            drop(info);
+            fs::remove_file(&info_path)?;
+            continue;
+        }
+
+        let written = write!(
+            info,
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            percent_encode(&original),
+            deletion_date(SystemTime::now())
+        );
+        if let Err(e) = written.and_then(|_| move_file(&original, &target)) {
+            fs::remove_file(&info_path).ok();
+            return Err(e);
+        }
+        return Ok(target);
+    }
+
+    unreachable!("attempt counter is unbounded")
+}
+
+fn move_into_macos_trash(file: &Path, trash_dir: &Path) -> io::Result<PathBuf> {
+    let original = fs::canonicalize(file)?;
+    fs::create_dir_all(trash_dir)?;
+
+    let name = file_name(&original)?;
+    for attempt in 1.. {
+        let target = trash_dir.join(unique_name(&name, attempt));
+        if !target.exists() {
+            move_file(&original, &target)?;
+            return Ok(target);
+        }
+    }
+
+    unreachable!("attempt counter is unbounded")
+}
+
+fn file_name(path: &Path) -> io::Result<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))
+}
+
+fn unique_name(name: &str, attempt: usize) -> String {
+    if attempt == 1 {
+        name.to_string()
+    } else {
+        format!("{}.{}", name, attempt)
+    }
+}
+
+/// Renames `from` to `to`, falling back to copy-and-delete when the trash
+/// lives on another filesystem.
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Percent-encodes a path for the `Path=` key of a `.trashinfo` file.
+fn percent_encode(path: &Path) -> String {
+    let mut encoded = String::new();
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Formats `time` as `YYYY-MM-DDThh:mm:ss`. The specification asks for local
+/// time; without a timezone database we record UTC, which file managers
+/// display correctly apart from the offset.
+fn deletion_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// Converts days since the Unix epoch into a proleptic Gregorian date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_move_into_xdg_trash() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let trash_dir = tempfile::tempdir().unwrap();
+        let path = source_dir.path().join("notes 1.txt");
+        fs::write(&path, "trashed").unwrap();
+
+        let target = move_into_xdg_trash(&path, trash_dir.path()).unwrap();
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "trashed");
+
+        let info = fs::read_to_string(trash_dir.path().join("info/notes 1.txt.trashinfo")).unwrap();
+        assert!(info.starts_with("[Trash Info]\nPath=/"));
+        assert!(info.contains("notes%201.txt\n"));
+        assert!(info.contains("DeletionDate="));
+    }
+
+    #[test]
+    fn test_move_into_xdg_trash_name_collision() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let trash_dir = tempfile::tempdir().unwrap();
+        let path = source_dir.path().join("a.txt");
+
+        fs::write(&path, "first").unwrap();
+        move_into_xdg_trash(&path, trash_dir.path()).unwrap();
+        fs::write(&path, "second").unwrap();
+        let target = move_into_xdg_trash(&path, trash_dir.path()).unwrap();
+
+        assert!(target.ends_with("files/a.txt.2"));
+        assert_eq!(fs::read_to_string(target).unwrap(), "second");
+    }
+
+    #[test]
+    fn test_deletion_date() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_093_991_528);
+        assert_eq!(deletion_date(time), "2004-08-31T22:32:08");
+    }
+}
@@ -0,0 +1,82 @@
+//! A callback over each emoji sequence in a text, for policies the options
+//! don't cover.
+//!
+//! ```
+//! use nomoji::{Action, visit};
+//!
+//! // Keep emoji inside double quotes.
+//! let text = r#"Ship 🚀 "as is ✅" ⭐"#;
+//! let (cleaned, _) = visit(text, |occurrence| {
+//!     let quotes = text[..occurrence.span.start].matches('"').count();
+//!     if quotes % 2 == 1 { Action::Keep } else { Action::Remove }
+//! });
+//! assert_eq!(cleaned, r#"Ship  "as is ✅" "#);
+//! ```
+
+use crate::find::{EmojiSpan, find_emoji};
+use crate::replace_spans;
+
+/// What `visit` does with one occurrence.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Keep,
+    Remove,
+    Replace(String),
+}
+
+/// Calls `decide` with each emoji sequence in `input`, in order, and
+/// applies what it returns. Returns the new text and the number of
+/// characters removed or replaced, counted as `remove_emojis` does.
+pub fn visit<'a>(
+    input: &'a str,
+    mut decide: impl FnMut(&EmojiSpan<'a>) -> Action,
+) -> (String, usize) {
+    let mut spans = Vec::new();
+    let mut replacements = Vec::new();
+    for occurrence in find_emoji(input) {
+        match decide(&occurrence) {
+            Action::Keep => {}
+            Action::Remove => {
+                spans.push(occurrence.span);
+                replacements.push(String::new());
+            }
+            Action::Replace(text) => {
+                spans.push(occurrence.span);
+                replacements.push(text);
+            }
+        }
+    }
+
+    let mut replacements = replacements.into_iter();
+    replace_spans(input, &spans, |_| replacements.next().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visit() {
+        let mut seen = Vec::new();
+        let (cleaned, count) = visit("a👍🏽b🚀c🇫🇷", |occurrence| {
+            seen.push((occurrence.codepoints(), occurrence.span.clone()));
+            match occurrence.sequence {
+                "🚀" => Action::Replace("[rocket]".to_string()),
+                "🇫🇷" => Action::Keep,
+                _ => Action::Remove,
+            }
+        });
+        assert_eq!(cleaned, "ab[rocket]c🇫🇷");
+        assert_eq!(count, 3);
+        assert_eq!(
+            seen,
+            [
+                (vec![0x1F44D, 0x1F3FD], 1..9),
+                (vec![0x1F680], 10..14),
+                (vec![0x1F1EB, 0x1F1F7], 15..23),
+            ]
+        );
+
+        assert_eq!(visit("plain", |_| Action::Remove), ("plain".to_string(), 0));
+    }
+}
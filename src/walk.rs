@@ -0,0 +1,68 @@
+//! Expanding directories given with `--recursive` into the files beneath
+//! them.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Directories never descended into.
+const SKIPPED: &[&str] = &[".git", ".hg", ".svn"];
+
+/// The regular files under `root`, in name order, depth first. Version
+/// control directories are left out, and symlinks are not followed, so a
+/// link can't lead the walk out of the tree or round in a loop.
+pub fn files(root: &Path) -> io::Result<Vec<String>> {
+    let mut found = Vec::new();
+    walk(root, &mut found)?;
+    Ok(found)
+}
+
+fn walk(dir: &Path, found: &mut Vec<String>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .and_then(|entries| entries.collect::<io::Result<Vec<_>>>())
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", dir.display(), e)))?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let kind = entry.file_type()?;
+        if kind.is_dir() {
+            if !SKIPPED.iter().any(|name| entry.file_name() == *name) {
+                walk(&path, found)?;
+            }
+        } else if kind.is_file() {
+            found.push(path.to_string_lossy().into_owned());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("docs/api")).unwrap();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::write(root.join("b.md"), "").unwrap();
+        fs::write(root.join("a.md"), "").unwrap();
+        fs::write(root.join("docs/api/c.md"), "").unwrap();
+        fs::write(root.join(".git/config"), "").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(root, root.join("docs/loop")).unwrap();
+
+        let relative = |path: &String| {
+            Path::new(path)
+                .strip_prefix(root)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        };
+        let found: Vec<String> = files(root).unwrap().iter().map(relative).collect();
+        assert_eq!(found, ["a.md", "b.md", "docs/api/c.md"]);
+        assert!(files(&root.join("missing")).is_err());
+    }
+}
@@ -0,0 +1,2175 @@
+//! A WebAssembly interpreter for `--plugin`, small enough to carry instead
+//! of a runtime dependency.
+//!
+//! It runs self-contained modules: the 1.0 instruction set plus the
+//! extensions toolchains enable by default (sign extension, saturating
+//! float-to-int conversion, bulk memory, reference types and multi-value
+//! blocks). Imports, SIMD, threads and 64-bit memories are rejected when the
+//! module is parsed, so a plugin can reach nothing but its own memory.
+//!
+//! Every call runs on a budget of instructions and memory is capped, so a
+//! plugin stuck in a loop or allocating without end fails with a trap
+//! rather than hanging or exhausting the host. Modules are decoded and their
+//! indices checked but not type-checked: ill-typed code fails with an error
+//! or computes nonsense in its own memory, and never crashes nomoji.
+
+use std::sync::Arc;
+
+/// The most linear memory an instance may have, in 64 KiB pages (64 MiB).
+const MAX_PAGES: u32 = 1024;
+/// The most table entries an instance may have.
+const MAX_TABLE: u32 = 100_000;
+/// How deeply calls may nest.
+const MAX_DEPTH: usize = 1024;
+/// The most values, locals included, the operand stack may hold.
+const MAX_STACK: usize = 1 << 20;
+/// The most locals one function may declare.
+const MAX_LOCALS: usize = 50_000;
+/// The value of a null reference.
+const NULL: u64 = u64::MAX;
+const PAGE: usize = 65536;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValType {
+    I32,
+    I64,
+    F32,
+    F64,
+    FuncRef,
+    ExternRef,
+}
+
+impl ValType {
+    fn zero(self) -> u64 {
+        match self {
+            ValType::FuncRef | ValType::ExternRef => NULL,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FuncType {
+    pub params: Vec<ValType>,
+    pub results: Vec<ValType>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Limits {
+    min: u32,
+    max: Option<u32>,
+}
+
+#[derive(Debug)]
+struct Func {
+    ty: u32,
+    locals: Vec<ValType>,
+    code: Vec<Op>,
+}
+
+#[derive(Debug)]
+struct Global {
+    mutable: bool,
+    init: Vec<Op>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Export {
+    Func(u32),
+    Table(u32),
+    Memory(u32),
+    Global(u32),
+}
+
+#[derive(Debug)]
+enum Mode {
+    Passive,
+    Active { index: u32, offset: Vec<Op> },
+    Declarative,
+}
+
+#[derive(Debug)]
+struct Element {
+    mode: Mode,
+    items: Vec<Vec<Op>>,
+}
+
+#[derive(Debug)]
+struct Data {
+    mode: Mode,
+    bytes: Vec<u8>,
+}
+
+/// A decoded instruction. Structured control carries the positions of its
+/// `else` and `end` so branches jump without searching.
+#[derive(Debug)]
+enum Op {
+    Unreachable,
+    Nop,
+    Block {
+        params: u32,
+        results: u32,
+        end: u32,
+    },
+    Loop {
+        params: u32,
+        results: u32,
+    },
+    If {
+        params: u32,
+        results: u32,
+        other: u32,
+        end: u32,
+    },
+    Else {
+        end: u32,
+    },
+    End,
+    Br(u32),
+    BrIf(u32),
+    BrTable(Box<[u32]>),
+    Return,
+    Call(u32),
+    CallIndirect {
+        ty: u32,
+        table: u32,
+    },
+    Drop,
+    Select,
+    LocalGet(u32),
+    LocalSet(u32),
+    LocalTee(u32),
+    GlobalGet(u32),
+    GlobalSet(u32),
+    TableGet(u32),
+    TableSet(u32),
+    Load {
+        op: u8,
+        offset: u32,
+    },
+    Store {
+        op: u8,
+        offset: u32,
+    },
+    MemorySize,
+    MemoryGrow,
+    Const(u64),
+    Numeric(u8),
+    RefNull,
+    RefIsNull,
+    RefFunc(u32),
+    TruncSat(u8),
+    MemoryInit(u32),
+    DataDrop(u32),
+    MemoryCopy,
+    MemoryFill,
+    TableInit {
+        element: u32,
+        table: u32,
+    },
+    ElemDrop(u32),
+    TableCopy {
+        to: u32,
+        from: u32,
+    },
+    TableGrow(u32),
+    TableSize(u32),
+    TableFill(u32),
+}
+
+/// A parsed module, ready to be instantiated any number of times.
+#[derive(Debug, Default)]
+pub struct Module {
+    types: Vec<FuncType>,
+    funcs: Vec<Func>,
+    tables: Vec<(Limits, ValType)>,
+    memory: Option<Limits>,
+    globals: Vec<Global>,
+    exports: Vec<(String, Export)>,
+    start: Option<u32>,
+    elements: Vec<Element>,
+    data: Vec<Data>,
+}
+
+fn malformed(what: impl std::fmt::Display) -> String {
+    format!("malformed module: {}", what)
+}
+
+fn unsupported(what: &str) -> String {
+    format!("unsupported module: {} isn't supported", what)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self
+            .bytes
+            .get(self.pos)
+            .ok_or_else(|| malformed("unexpected end"))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| malformed("unexpected end"))?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// A LEB128 number of at most `bits` bits, sign-extended if `signed`.
+    fn leb(&mut self, bits: u32, signed: bool) -> Result<u64, String> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7F) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if signed && shift < 64 && byte & 0x40 != 0 {
+                    value |= !0 << shift;
+                }
+                return Ok(value);
+            }
+            if shift >= bits {
+                return Err(malformed("integer too long"));
+            }
+        }
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        u32::try_from(self.leb(32, false)?).map_err(|_| malformed("integer too large"))
+    }
+
+    fn len(&mut self) -> Result<usize, String> {
+        let len = self.u32()? as usize;
+        // Every item takes at least a byte, so a longer count is a lie.
+        match len <= self.bytes.len() - self.pos.min(self.bytes.len()) {
+            true => Ok(len),
+            false => Err(malformed("count exceeds the section")),
+        }
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let len = self.len()?;
+        String::from_utf8(self.bytes(len)?.to_vec()).map_err(|_| malformed("name is not UTF-8"))
+    }
+
+    fn val_type(&mut self) -> Result<ValType, String> {
+        val_type(self.byte()?)
+    }
+
+    fn ref_type(&mut self) -> Result<ValType, String> {
+        match self.val_type()? {
+            ty @ (ValType::FuncRef | ValType::ExternRef) => Ok(ty),
+            _ => Err(malformed("expected a reference type")),
+        }
+    }
+
+    fn limits(&mut self) -> Result<Limits, String> {
+        match self.byte()? {
+            0x00 => Ok(Limits {
+                min: self.u32()?,
+                max: None,
+            }),
+            0x01 => {
+                let min = self.u32()?;
+                let max = self.u32()?;
+                match min <= max {
+                    true => Ok(Limits {
+                        min,
+                        max: Some(max),
+                    }),
+                    false => Err(malformed("minimum exceeds maximum")),
+                }
+            }
+            0x02 | 0x03 => Err(unsupported("shared memory")),
+            0x04..=0x07 => Err(unsupported("64-bit memory")),
+            flags => Err(malformed(format!("limits flags {:#x}", flags))),
+        }
+    }
+}
+
+fn val_type(byte: u8) -> Result<ValType, String> {
+    match byte {
+        0x7F => Ok(ValType::I32),
+        0x7E => Ok(ValType::I64),
+        0x7D => Ok(ValType::F32),
+        0x7C => Ok(ValType::F64),
+        0x70 => Ok(ValType::FuncRef),
+        0x6F => Ok(ValType::ExternRef),
+        0x7B => Err(unsupported("SIMD")),
+        _ => Err(malformed(format!("value type {:#x}", byte))),
+    }
+}
+
+/// What a function body may refer to while it is decoded.
+struct Scope<'a> {
+    types: &'a [FuncType],
+    locals: usize,
+    memory: bool,
+}
+
+impl Module {
+    /// Parses and checks the binary module in `bytes`.
+    pub fn parse(bytes: &[u8]) -> Result<Module, String> {
+        let mut r = Reader::new(bytes);
+        if r.bytes(4).ok() != Some(b"\0asm") {
+            return Err("not a WebAssembly module".to_string());
+        }
+        if r.bytes(4)? != [1, 0, 0, 0] {
+            return Err(unsupported("this binary format version"));
+        }
+
+        let mut module = Module::default();
+        let mut declared: Vec<u32> = Vec::new();
+        while !r.is_empty() {
+            let id = r.byte()?;
+            let len = r.u32()? as usize;
+            let mut s = Reader::new(r.bytes(len)?);
+            match id {
+                0 => continue,
+                1 => {
+                    for _ in 0..s.len()? {
+                        if s.byte()? != 0x60 {
+                            return Err(malformed("expected a function type"));
+                        }
+                        let params = (0..s.len()?)
+                            .map(|_| s.val_type())
+                            .collect::<Result<_, _>>()?;
+                        let results = (0..s.len()?)
+                            .map(|_| s.val_type())
+                            .collect::<Result<_, _>>()?;
+                        module.types.push(FuncType { params, results });
+                    }
+                }
+                2 => {
+                    if s.len()? > 0 {
+                        let name = format!("{}.{}", s.name()?, s.name()?);
+                        return Err(format!(
+                            "plugins can't import anything, but the module imports {}",
+                            name
+                        ));
+                    }
+                }
+                3 => {
+                    for _ in 0..s.len()? {
+                        let ty = s.u32()?;
+                        if ty as usize >= module.types.len() {
+                            return Err(malformed("unknown type"));
+                        }
+                        declared.push(ty);
+                    }
+                }
+                4 => {
+                    for _ in 0..s.len()? {
+                        let ty = s.ref_type()?;
+                        module.tables.push((s.limits()?, ty));
+                    }
+                }
+                5 => {
+                    for _ in 0..s.len()? {
+                        if module.memory.is_some() {
+                            return Err(unsupported("more than one memory"));
+                        }
+                        module.memory = Some(s.limits()?);
+                    }
+                }
+                6 => {
+                    for _ in 0..s.len()? {
+                        s.val_type()?;
+                        let mutable = match s.byte()? {
+                            0 => false,
+                            1 => true,
+                            _ => return Err(malformed("global mutability")),
+                        };
+                        let init = const_expr(&mut s)?;
+                        module.globals.push(Global { mutable, init });
+                    }
+                }
+                7 => {
+                    for _ in 0..s.len()? {
+                        let name = s.name()?;
+                        let kind = s.byte()?;
+                        let index = s.u32()?;
+                        let export = match kind {
+                            0 => Export::Func(index),
+                            1 => Export::Table(index),
+                            2 => Export::Memory(index),
+                            3 => Export::Global(index),
+                            kind => return Err(malformed(format!("export kind {}", kind))),
+                        };
+                        module.exports.push((name, export));
+                    }
+                }
+                8 => module.start = Some(s.u32()?),
+                9 => {
+                    for _ in 0..s.len()? {
+                        module.elements.push(element(&mut s)?);
+                    }
+                }
+                10 => {
+                    let count = s.len()?;
+                    if count != declared.len() {
+                        return Err(malformed("function and code counts differ"));
+                    }
+                    for &ty in &declared {
+                        let size = s.u32()? as usize;
+                        let mut body = Reader::new(s.bytes(size)?);
+                        let func_type = &module.types[ty as usize];
+                        let mut locals = Vec::new();
+                        for _ in 0..body.len()? {
+                            let n = body.u32()? as usize;
+                            let ty = body.val_type()?;
+                            if locals.len() + n > MAX_LOCALS {
+                                return Err(unsupported("a function with that many locals"));
+                            }
+                            locals.extend(std::iter::repeat_n(ty, n));
+                        }
+                        let scope = Scope {
+                            types: &module.types,
+                            locals: func_type.params.len() + locals.len(),
+                            memory: module.memory.is_some(),
+                        };
+                        let code = expr(&mut body, &scope)?;
+                        if !body.is_empty() {
+                            return Err(malformed("code after the end of a function"));
+                        }
+                        module.funcs.push(Func { ty, locals, code });
+                    }
+                }
+                11 => {
+                    for _ in 0..s.len()? {
+                        module.data.push(data(&mut s)?);
+                    }
+                }
+                12 => {
+                    s.u32()?;
+                }
+                _ => return Err(malformed(format!("section {}", id))),
+            }
+            if !s.is_empty() {
+                return Err(malformed(format!(
+                    "section {} is longer than its contents",
+                    id
+                )));
+            }
+        }
+        if module.funcs.len() != declared.len() {
+            return Err(malformed("functions without code"));
+        }
+        module.check()?;
+        Ok(module)
+    }
+
+    /// Checks the indices the sections refer to each other by.
+    fn check(&self) -> Result<(), String> {
+        let funcs = self.funcs.len();
+        for (_, export) in &self.exports {
+            let ok = match *export {
+                Export::Func(i) => (i as usize) < funcs,
+                Export::Table(i) => (i as usize) < self.tables.len(),
+                Export::Memory(i) => i == 0 && self.memory.is_some(),
+                Export::Global(i) => (i as usize) < self.globals.len(),
+            };
+            if !ok {
+                return Err(malformed("export of an unknown item"));
+            }
+        }
+        if let Some(start) = self.start {
+            let ty = self
+                .funcs
+                .get(start as usize)
+                .map(|f| &self.types[f.ty as usize]);
+            if !ty.is_some_and(|ty| ty.params.is_empty() && ty.results.is_empty()) {
+                return Err(malformed("start function"));
+            }
+        }
+        for func in &self.funcs {
+            for op in &func.code {
+                let ok = match *op {
+                    Op::Call(i) | Op::RefFunc(i) => (i as usize) < funcs,
+                    Op::CallIndirect { ty, table } => {
+                        (ty as usize) < self.types.len() && (table as usize) < self.tables.len()
+                    }
+                    Op::GlobalGet(i) => (i as usize) < self.globals.len(),
+                    Op::GlobalSet(i) => self.globals.get(i as usize).is_some_and(|g| g.mutable),
+                    Op::TableGet(i)
+                    | Op::TableSet(i)
+                    | Op::TableGrow(i)
+                    | Op::TableSize(i)
+                    | Op::TableFill(i) => (i as usize) < self.tables.len(),
+                    Op::TableCopy { to, from } => {
+                        (to as usize) < self.tables.len() && (from as usize) < self.tables.len()
+                    }
+                    Op::TableInit { element, table } => {
+                        (element as usize) < self.elements.len()
+                            && (table as usize) < self.tables.len()
+                    }
+                    Op::ElemDrop(i) => (i as usize) < self.elements.len(),
+                    Op::MemoryInit(i) | Op::DataDrop(i) => (i as usize) < self.data.len(),
+                    _ => true,
+                };
+                if !ok {
+                    return Err(malformed("reference to an unknown or immutable item"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The type of the function exported as `name`.
+    pub fn export_type(&self, name: &str) -> Option<&FuncType> {
+        match self.export(name)? {
+            Export::Func(i) => Some(&self.types[self.funcs[i as usize].ty as usize]),
+            _ => None,
+        }
+    }
+
+    /// Whether the module exports its memory as `name`.
+    pub fn exports_memory(&self, name: &str) -> bool {
+        matches!(self.export(name), Some(Export::Memory(_)))
+    }
+
+    fn export(&self, name: &str) -> Option<Export> {
+        self.exports
+            .iter()
+            .find(|(export, _)| export == name)
+            .map(|&(_, export)| export)
+    }
+}
+
+/// A constant expression: an initializer or offset.
+fn const_expr(r: &mut Reader) -> Result<Vec<Op>, String> {
+    let scope = Scope {
+        types: &[],
+        locals: 0,
+        memory: false,
+    };
+    let code = expr(r, &scope)?;
+    let constant = code.iter().all(|op| {
+        matches!(
+            op,
+            Op::Const(_) | Op::GlobalGet(_) | Op::RefNull | Op::RefFunc(_) | Op::End
+        ) || matches!(op, Op::Numeric(0x6A..=0x6C | 0x7C..=0x7E))
+    });
+    match constant {
+        true => Ok(code),
+        false => Err(malformed("non-constant initializer")),
+    }
+}
+
+fn element(r: &mut Reader) -> Result<Element, String> {
+    let flags = r.u32()?;
+    if flags > 7 {
+        return Err(malformed(format!("element segment flags {}", flags)));
+    }
+    let mode = match flags {
+        0 | 4 => Mode::Active {
+            index: 0,
+            offset: const_expr(r)?,
+        },
+        2 | 6 => Mode::Active {
+            index: r.u32()?,
+            offset: const_expr(r)?,
+        },
+        1 | 5 => Mode::Passive,
+        _ => Mode::Declarative,
+    };
+    let exprs = flags & 4 != 0;
+    if flags & 3 != 0 {
+        // An element kind (0 for funcref) or, with expressions, a type.
+        match exprs {
+            true => {
+                r.ref_type()?;
+            }
+            false if r.byte()? != 0 => return Err(malformed("element kind")),
+            false => {}
+        }
+    }
+    let mut items = Vec::new();
+    for _ in 0..r.len()? {
+        items.push(match exprs {
+            true => const_expr(r)?,
+            false => vec![Op::RefFunc(r.u32()?), Op::End],
+        });
+    }
+    Ok(Element { mode, items })
+}
+
+fn data(r: &mut Reader) -> Result<Data, String> {
+    let mode = match r.u32()? {
+        0 => Mode::Active {
+            index: 0,
+            offset: const_expr(r)?,
+        },
+        1 => Mode::Passive,
+        2 => Mode::Active {
+            index: r.u32()?,
+            offset: const_expr(r)?,
+        },
+        flags => return Err(malformed(format!("data segment flags {}", flags))),
+    };
+    let len = r.len()?;
+    let bytes = r.bytes(len)?.to_vec();
+    Ok(Data { mode, bytes })
+}
+
+/// The parameter and result counts of a block type.
+fn block_type(r: &mut Reader, types: &[FuncType]) -> Result<(u32, u32), String> {
+    let byte = r.byte()?;
+    if byte == 0x40 {
+        return Ok((0, 0));
+    }
+    if let 0x6F | 0x70 | 0x7B..=0x7F = byte {
+        val_type(byte)?;
+        return Ok((0, 1));
+    }
+    r.pos -= 1;
+    let index = r.leb(33, true)? as i64;
+    let ty = usize::try_from(index)
+        .ok()
+        .and_then(|i| types.get(i))
+        .ok_or_else(|| malformed("unknown block type"))?;
+    Ok((ty.params.len() as u32, ty.results.len() as u32))
+}
+
+/// Decodes instructions up to the `end` closing the expression, linking
+/// each block to its `else` and `end`.
+fn expr(r: &mut Reader, scope: &Scope) -> Result<Vec<Op>, String> {
+    let mut code = Vec::new();
+    // Open blocks, by the position of the instruction opening them.
+    let mut open: Vec<usize> = Vec::new();
+    let memarg = |r: &mut Reader| -> Result<u32, String> {
+        if !scope.memory {
+            return Err(malformed("memory access without a memory"));
+        }
+        if r.u32()? & 0x40 != 0 {
+            return Err(unsupported("multiple memories"));
+        }
+        r.u32()
+    };
+    let memory = |r: &mut Reader| -> Result<(), String> {
+        match (scope.memory, r.byte()?) {
+            (true, 0) => Ok(()),
+            (false, _) => Err(malformed("memory instruction without a memory")),
+            (true, _) => Err(unsupported("multiple memories")),
+        }
+    };
+    let depth = |r: &mut Reader, open: usize| -> Result<u32, String> {
+        let depth = r.u32()?;
+        // The function body is a block too.
+        match (depth as usize) <= open {
+            true => Ok(depth),
+            false => Err(malformed("branch to an unknown label")),
+        }
+    };
+
+    loop {
+        let here = code.len();
+        let here32 = u32::try_from(here).map_err(|_| malformed("function too large"))?;
+        let op = match r.byte()? {
+            0x00 => Op::Unreachable,
+            0x01 => Op::Nop,
+            0x02 => {
+                let (params, results) = block_type(r, scope.types)?;
+                open.push(here);
+                Op::Block {
+                    params,
+                    results,
+                    end: 0,
+                }
+            }
+            0x03 => {
+                let (params, results) = block_type(r, scope.types)?;
+                open.push(here);
+                Op::Loop { params, results }
+            }
+            0x04 => {
+                let (params, results) = block_type(r, scope.types)?;
+                open.push(here);
+                Op::If {
+                    params,
+                    results,
+                    other: 0,
+                    end: 0,
+                }
+            }
+            0x05 => {
+                let start = *open.last().ok_or_else(|| malformed("else outside if"))?;
+                match &mut code[start] {
+                    Op::If { other, .. } if *other == 0 => *other = here32,
+                    _ => return Err(malformed("else outside if")),
+                }
+                Op::Else { end: 0 }
+            }
+            0x0B => {
+                let Some(start) = open.pop() else {
+                    code.push(Op::End);
+                    return Ok(code);
+                };
+                let branch = match &mut code[start] {
+                    Op::Block { end, .. } => {
+                        *end = here32;
+                        None
+                    }
+                    Op::If { other, end, .. } => {
+                        *end = here32;
+                        match *other {
+                            0 => {
+                                *other = here32;
+                                None
+                            }
+                            other => Some(other as usize),
+                        }
+                    }
+                    _ => None,
+                };
+                if let Some(other) = branch {
+                    code[other] = Op::Else { end: here32 };
+                }
+                Op::End
+            }
+            0x0C => Op::Br(depth(r, open.len())?),
+            0x0D => Op::BrIf(depth(r, open.len())?),
+            0x0E => {
+                let mut targets = Vec::new();
+                for _ in 0..=r.len()? {
+                    targets.push(depth(r, open.len())?);
+                }
+                Op::BrTable(targets.into_boxed_slice())
+            }
+            0x0F => Op::Return,
+            0x10 => Op::Call(r.u32()?),
+            0x11 => Op::CallIndirect {
+                ty: r.u32()?,
+                table: r.u32()?,
+            },
+            0x1A => Op::Drop,
+            0x1B => Op::Select,
+            0x1C => {
+                for _ in 0..r.len()? {
+                    r.val_type()?;
+                }
+                Op::Select
+            }
+            op @ 0x20..=0x22 => {
+                let index = r.u32()?;
+                if index as usize >= scope.locals {
+                    return Err(malformed("unknown local"));
+                }
+                match op {
+                    0x20 => Op::LocalGet(index),
+                    0x21 => Op::LocalSet(index),
+                    _ => Op::LocalTee(index),
+                }
+            }
+            0x23 => Op::GlobalGet(r.u32()?),
+            0x24 => Op::GlobalSet(r.u32()?),
+            0x25 => Op::TableGet(r.u32()?),
+            0x26 => Op::TableSet(r.u32()?),
+            op @ 0x28..=0x35 => Op::Load {
+                op,
+                offset: memarg(r)?,
+            },
+            op @ 0x36..=0x3E => Op::Store {
+                op,
+                offset: memarg(r)?,
+            },
+            0x3F => {
+                memory(r)?;
+                Op::MemorySize
+            }
+            0x40 => {
+                memory(r)?;
+                Op::MemoryGrow
+            }
+            0x41 => Op::Const(r.leb(32, true)? as u32 as u64),
+            0x42 => Op::Const(r.leb(64, true)?),
+            0x43 => Op::Const(u64::from(u32::from_le_bytes(
+                r.bytes(4)?.try_into().unwrap(),
+            ))),
+            0x44 => Op::Const(u64::from_le_bytes(r.bytes(8)?.try_into().unwrap())),
+            op @ 0x45..=0xC4 => Op::Numeric(op),
+            0xD0 => {
+                r.ref_type()?;
+                Op::RefNull
+            }
+            0xD1 => Op::RefIsNull,
+            0xD2 => Op::RefFunc(r.u32()?),
+            0xFC => match r.u32()? {
+                op @ 0..=7 => Op::TruncSat(op as u8),
+                8 => {
+                    let segment = r.u32()?;
+                    memory(r)?;
+                    Op::MemoryInit(segment)
+                }
+                9 => Op::DataDrop(r.u32()?),
+                10 => {
+                    memory(r)?;
+                    memory(r)?;
+                    Op::MemoryCopy
+                }
+                11 => {
+                    memory(r)?;
+                    Op::MemoryFill
+                }
+                12 => Op::TableInit {
+                    element: r.u32()?,
+                    table: r.u32()?,
+                },
+                13 => Op::ElemDrop(r.u32()?),
+                14 => Op::TableCopy {
+                    to: r.u32()?,
+                    from: r.u32()?,
+                },
+                15 => Op::TableGrow(r.u32()?),
+                16 => Op::TableSize(r.u32()?),
+                17 => Op::TableFill(r.u32()?),
+                op => return Err(malformed(format!("instruction 0xfc {}", op))),
+            },
+            0xFD => return Err(unsupported("SIMD")),
+            0xFE => return Err(unsupported("threads")),
+            op => return Err(malformed(format!("instruction {:#04x}", op))),
+        };
+        code.push(op);
+    }
+}
+
+fn trap(what: &str) -> String {
+    format!("trap: {}", what)
+}
+
+const DIVIDE_BY_ZERO: &str = "integer divide by zero";
+const OVERFLOW: &str = "integer overflow";
+const INVALID_CONVERSION: &str = "invalid conversion to integer";
+const OUT_OF_BOUNDS: &str = "out of bounds memory access";
+
+#[derive(Clone, Copy)]
+struct Label {
+    /// Stack height below the block's parameters.
+    height: usize,
+    /// Values a branch to the label carries.
+    arity: usize,
+    /// Values the block leaves when it ends.
+    results: usize,
+    /// Where a branch continues.
+    target: usize,
+    is_loop: bool,
+}
+
+#[derive(Clone, Copy)]
+struct Frame {
+    func: usize,
+    pc: usize,
+    /// Stack position of the first local.
+    base: usize,
+    /// Index of the label the function body opened.
+    label_base: usize,
+}
+
+/// A module's running state: its memory, tables and globals.
+pub struct Instance {
+    module: Arc<Module>,
+    memory: Vec<u8>,
+    max_pages: u32,
+    globals: Vec<u64>,
+    tables: Vec<Vec<u64>>,
+    table_max: Vec<u32>,
+    dropped_elements: Vec<bool>,
+    dropped_data: Vec<bool>,
+    stack: Vec<u64>,
+    fuel: u64,
+}
+
+impl Instance {
+    /// Instantiates `module`: sets up its memory, tables and globals, applies
+    /// its segments and runs its start function on a budget of `fuel`
+    /// instructions.
+    pub fn new(module: Arc<Module>, fuel: u64) -> Result<Instance, String> {
+        let (pages, max_pages) = match module.memory {
+            Some(limits) => (limits.min, limits.max.unwrap_or(MAX_PAGES).min(MAX_PAGES)),
+            None => (0, 0),
+        };
+        if pages > MAX_PAGES {
+            return Err(format!(
+                "the module needs {} pages of memory; at most {} are allowed",
+                pages, MAX_PAGES
+            ));
+        }
+        let mut tables = Vec::new();
+        let mut table_max = Vec::new();
+        for (limits, _) in &module.tables {
+            if limits.min > MAX_TABLE {
+                return Err(format!(
+                    "the module needs a table of {} entries",
+                    limits.min
+                ));
+            }
+            tables.push(vec![NULL; limits.min as usize]);
+            table_max.push(limits.max.unwrap_or(MAX_TABLE).min(MAX_TABLE));
+        }
+        let mut instance = Instance {
+            memory: vec![0; pages as usize * PAGE],
+            max_pages,
+            globals: Vec::new(),
+            tables,
+            table_max,
+            dropped_elements: vec![false; module.elements.len()],
+            dropped_data: vec![false; module.data.len()],
+            stack: Vec::new(),
+            fuel,
+            module: Arc::clone(&module),
+        };
+
+        for global in &module.globals {
+            let value = instance.eval(&global.init)?;
+            instance.globals.push(value);
+        }
+        for (i, element) in module.elements.iter().enumerate() {
+            match &element.mode {
+                Mode::Active { index, offset } => {
+                    let offset = instance.eval(offset)? as u32;
+                    let items = element
+                        .items
+                        .iter()
+                        .map(|item| instance.eval(item))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let table = instance
+                        .tables
+                        .get_mut(*index as usize)
+                        .ok_or_else(|| malformed("element segment for an unknown table"))?;
+                    let start = offset as usize;
+                    let slot = table
+                        .get_mut(start..start + items.len())
+                        .ok_or_else(|| trap("out of bounds table access"))?;
+                    slot.copy_from_slice(&items);
+                    instance.dropped_elements[i] = true;
+                }
+                Mode::Declarative => instance.dropped_elements[i] = true,
+                Mode::Passive => {}
+            }
+        }
+        for (i, data) in module.data.iter().enumerate() {
+            if let Mode::Active { index, offset } = &data.mode {
+                if *index != 0 {
+                    return Err(unsupported("multiple memories"));
+                }
+                let start = instance.eval(offset)? as u32 as usize;
+                let slot = instance
+                    .memory
+                    .get_mut(start..start + data.bytes.len())
+                    .ok_or_else(|| trap(OUT_OF_BOUNDS))?;
+                slot.copy_from_slice(&data.bytes);
+                instance.dropped_data[i] = true;
+            }
+        }
+        if let Some(start) = module.start {
+            instance.invoke(start as usize, &[])?;
+        }
+        Ok(instance)
+    }
+
+    /// Evaluates a constant expression.
+    fn eval(&self, code: &[Op]) -> Result<u64, String> {
+        let mut stack = Vec::new();
+        for op in code {
+            match *op {
+                Op::Const(value) => stack.push(value),
+                Op::GlobalGet(i) => stack.push(
+                    *self
+                        .globals
+                        .get(i as usize)
+                        .ok_or_else(|| malformed("initializer reads an unknown global"))?,
+                ),
+                Op::RefNull => stack.push(NULL),
+                Op::RefFunc(i) if (i as usize) < self.module.funcs.len() => {
+                    stack.push(u64::from(i))
+                }
+                Op::Numeric(op) => {
+                    let (Some(b), Some(a)) = (stack.pop(), stack.pop()) else {
+                        return Err(malformed("initializer"));
+                    };
+                    stack.push(match op {
+                        0x6A => (a as u32).wrapping_add(b as u32) as u64,
+                        0x6B => (a as u32).wrapping_sub(b as u32) as u64,
+                        0x6C => (a as u32).wrapping_mul(b as u32) as u64,
+                        0x7C => a.wrapping_add(b),
+                        0x7D => a.wrapping_sub(b),
+                        _ => a.wrapping_mul(b),
+                    });
+                }
+                Op::End => break,
+                _ => return Err(malformed("initializer")),
+            }
+        }
+        match stack[..] {
+            [value] => Ok(value),
+            _ => Err(malformed("initializer")),
+        }
+    }
+
+    /// Calls the function exported as `name` with `args`, on a budget of
+    /// `fuel` instructions, and returns its results.
+    pub fn call(&mut self, name: &str, args: &[u64], fuel: u64) -> Result<Vec<u64>, String> {
+        let Some(Export::Func(func)) = self.module.export(name) else {
+            return Err(format!(
+                "the module doesn't export a function named '{}'",
+                name
+            ));
+        };
+        self.fuel = fuel;
+        self.invoke(func as usize, args)
+    }
+
+    fn invoke(&mut self, func: usize, args: &[u64]) -> Result<Vec<u64>, String> {
+        let module = Arc::clone(&self.module);
+        let ty = &module.types[module.funcs[func].ty as usize];
+        if args.len() != ty.params.len() {
+            return Err(format!(
+                "expected {} arguments, got {}",
+                ty.params.len(),
+                args.len()
+            ));
+        }
+        self.stack.clear();
+        self.stack.extend_from_slice(args);
+        self.execute(&module, func)?;
+        match self.stack.len() == ty.results.len() {
+            true => Ok(std::mem::take(&mut self.stack)),
+            false => Err(malformed("function left the wrong number of results")),
+        }
+    }
+
+    /// The instance's linear memory.
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.memory
+    }
+
+    fn pop(&mut self) -> Result<u64, String> {
+        self.stack.pop().ok_or_else(|| malformed("stack underflow"))
+    }
+
+    fn pop_i32(&mut self) -> Result<i32, String> {
+        self.pop().map(|v| v as i32)
+    }
+
+    fn pop_i64(&mut self) -> Result<i64, String> {
+        self.pop().map(|v| v as i64)
+    }
+
+    fn pop_f32(&mut self) -> Result<f32, String> {
+        self.pop().map(|v| f32::from_bits(v as u32))
+    }
+
+    fn pop_f64(&mut self) -> Result<f64, String> {
+        self.pop().map(f64::from_bits)
+    }
+
+    fn push_i32(&mut self, value: i32) {
+        self.stack.push(value as u32 as u64);
+    }
+
+    fn push_i64(&mut self, value: i64) {
+        self.stack.push(value as u64);
+    }
+
+    fn push_f32(&mut self, value: f32) {
+        self.stack.push(u64::from(value.to_bits()));
+    }
+
+    fn push_f64(&mut self, value: f64) {
+        self.stack.push(value.to_bits());
+    }
+
+    fn push_bool(&mut self, value: bool) {
+        self.stack.push(u64::from(value));
+    }
+
+    /// The local at stack position `index`, which ill-typed code may have
+    /// popped.
+    fn local(&mut self, index: usize) -> Result<&mut u64, String> {
+        self.stack
+            .get_mut(index)
+            .ok_or_else(|| malformed("stack underflow"))
+    }
+
+    /// Moves the top `arity` values down to `height`, dropping the rest.
+    fn unwind(&mut self, height: usize, arity: usize) -> Result<(), String> {
+        let top = self.stack.len();
+        if top < height + arity {
+            return Err(malformed("stack underflow"));
+        }
+        self.stack.copy_within(top - arity.., height);
+        self.stack.truncate(height + arity);
+        Ok(())
+    }
+
+    /// Opens a call to `func`, whose arguments are on the stack.
+    fn enter(
+        &mut self,
+        module: &Module,
+        func: usize,
+        labels: &mut Vec<Label>,
+    ) -> Result<Frame, String> {
+        let f = &module.funcs[func];
+        let ty = &module.types[f.ty as usize];
+        let base = self
+            .stack
+            .len()
+            .checked_sub(ty.params.len())
+            .ok_or_else(|| malformed("stack underflow"))?;
+        if self.stack.len() + f.locals.len() > MAX_STACK {
+            return Err(trap("call stack exhausted"));
+        }
+        self.stack.extend(f.locals.iter().map(|ty| ty.zero()));
+        let label_base = labels.len();
+        labels.push(Label {
+            height: base,
+            arity: ty.results.len(),
+            results: ty.results.len(),
+            target: f.code.len(),
+            is_loop: false,
+        });
+        Ok(Frame {
+            func,
+            pc: 0,
+            base,
+            label_base,
+        })
+    }
+
+    /// The byte range `len` long at `address + offset`, if it is in memory.
+    fn address(&self, address: u64, offset: u32, len: usize) -> Result<usize, String> {
+        let start = address + u64::from(offset);
+        match start + len as u64 <= self.memory.len() as u64 {
+            true => Ok(start as usize),
+            false => Err(trap(OUT_OF_BOUNDS)),
+        }
+    }
+
+    fn execute(&mut self, module: &Module, entry: usize) -> Result<(), String> {
+        let mut frames: Vec<Frame> = Vec::new();
+        let mut labels: Vec<Label> = Vec::new();
+        let mut frame = self.enter(module, entry, &mut labels)?;
+
+        loop {
+            let code = &module.funcs[frame.func].code;
+            let Some(op) = code.get(frame.pc) else {
+                return Err(malformed("function without an end"));
+            };
+            frame.pc += 1;
+            self.fuel = self
+                .fuel
+                .checked_sub(1)
+                .ok_or_else(|| trap("out of fuel"))?;
+
+            // A branch to `depth`, shared by br, br_if, br_table and return.
+            let mut depth = None;
+            match op {
+                Op::Unreachable => return Err(trap("unreachable executed")),
+                Op::Nop => {}
+                &Op::Block {
+                    params,
+                    results,
+                    end,
+                } => {
+                    labels.push(self.label(params, results, end as usize + 1, false)?);
+                }
+                &Op::Loop { params, results } => {
+                    labels.push(self.label(params, results, frame.pc, true)?);
+                }
+                &Op::If {
+                    params,
+                    results,
+                    other,
+                    end,
+                } => {
+                    let condition = self.pop_i32()?;
+                    labels.push(self.label(params, results, end as usize + 1, false)?);
+                    if condition == 0 {
+                        // Into the else branch, or onto the end that closes
+                        // the block.
+                        frame.pc = match other == end {
+                            true => end as usize,
+                            false => other as usize + 1,
+                        };
+                    }
+                }
+                &Op::Else { end } => frame.pc = end as usize,
+                Op::End => {
+                    let label = labels.pop().ok_or_else(|| malformed("unbalanced end"))?;
+                    self.unwind(label.height, label.results)?;
+                    if labels.len() == frame.label_base {
+                        match frames.pop() {
+                            Some(caller) => frame = caller,
+                            None => return Ok(()),
+                        }
+                    }
+                }
+                &Op::Br(d) => depth = Some(d as usize),
+                &Op::BrIf(d) => {
+                    if self.pop_i32()? != 0 {
+                        depth = Some(d as usize);
+                    }
+                }
+                Op::BrTable(targets) => {
+                    let index = self.pop_i32()? as u32 as usize;
+                    let last = targets.len() - 1;
+                    depth = Some(targets[index.min(last)] as usize);
+                }
+                Op::Return => depth = Some(labels.len() - 1 - frame.label_base),
+                &Op::Call(func) => {
+                    if frames.len() >= MAX_DEPTH {
+                        return Err(trap("call stack exhausted"));
+                    }
+                    frames.push(frame);
+                    frame = self.enter(module, func as usize, &mut labels)?;
+                }
+                &Op::CallIndirect { ty, table } => {
+                    let index = self.pop_i32()? as u32 as usize;
+                    let func = *self.tables[table as usize]
+                        .get(index)
+                        .ok_or_else(|| trap("undefined element"))?;
+                    if func == NULL {
+                        return Err(trap("uninitialized element"));
+                    }
+                    let func = func as usize;
+                    let callee = module
+                        .funcs
+                        .get(func)
+                        .ok_or_else(|| trap("undefined element"))?;
+                    if module.types[callee.ty as usize] != module.types[ty as usize] {
+                        return Err(trap("indirect call type mismatch"));
+                    }
+                    if frames.len() >= MAX_DEPTH {
+                        return Err(trap("call stack exhausted"));
+                    }
+                    frames.push(frame);
+                    frame = self.enter(module, func, &mut labels)?;
+                }
+                Op::Drop => {
+                    self.pop()?;
+                }
+                Op::Select => {
+                    let condition = self.pop_i32()?;
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    self.stack.push(if condition != 0 { a } else { b });
+                }
+                &Op::LocalGet(i) => {
+                    let value = *self.local(frame.base + i as usize)?;
+                    self.stack.push(value);
+                }
+                &Op::LocalSet(i) => {
+                    let value = self.pop()?;
+                    *self.local(frame.base + i as usize)? = value;
+                }
+                &Op::LocalTee(i) => {
+                    let value = *self
+                        .stack
+                        .last()
+                        .ok_or_else(|| malformed("stack underflow"))?;
+                    *self.local(frame.base + i as usize)? = value;
+                }
+                &Op::GlobalGet(i) => self.stack.push(self.globals[i as usize]),
+                &Op::GlobalSet(i) => self.globals[i as usize] = self.pop()?,
+                &Op::TableGet(t) => {
+                    let index = self.pop_i32()? as u32 as usize;
+                    let value = *self.tables[t as usize]
+                        .get(index)
+                        .ok_or_else(|| trap("out of bounds table access"))?;
+                    self.stack.push(value);
+                }
+                &Op::TableSet(t) => {
+                    let value = self.pop()?;
+                    let index = self.pop_i32()? as u32 as usize;
+                    *self.tables[t as usize]
+                        .get_mut(index)
+                        .ok_or_else(|| trap("out of bounds table access"))? = value;
+                }
+                &Op::Load { op, offset } => self.load(op, offset)?,
+                &Op::Store { op, offset } => self.store(op, offset)?,
+                Op::MemorySize => self.push_i32((self.memory.len() / PAGE) as i32),
+                Op::MemoryGrow => {
+                    let delta = self.pop_i32()? as u32;
+                    let pages = (self.memory.len() / PAGE) as u32;
+                    match pages.checked_add(delta).filter(|&n| n <= self.max_pages) {
+                        Some(n) => {
+                            self.memory.resize(n as usize * PAGE, 0);
+                            self.push_i32(pages as i32);
+                        }
+                        None => self.push_i32(-1),
+                    }
+                }
+                &Op::Const(value) => self.stack.push(value),
+                &Op::Numeric(op) => self.numeric(op)?,
+                Op::RefNull => self.stack.push(NULL),
+                Op::RefIsNull => {
+                    let value = self.pop()?;
+                    self.push_bool(value == NULL);
+                }
+                &Op::RefFunc(i) => self.stack.push(u64::from(i)),
+                &Op::TruncSat(op) => self.trunc_sat(op)?,
+                &Op::MemoryInit(segment) => {
+                    let len = self.pop_i32()? as u32 as usize;
+                    let from = self.pop_i32()? as u32 as usize;
+                    let to = self.pop_i32()? as u32 as u64;
+                    let bytes = match self.dropped_data[segment as usize] {
+                        true => &[][..],
+                        false => &module.data[segment as usize].bytes[..],
+                    };
+                    let source = bytes
+                        .get(from..from.saturating_add(len))
+                        .ok_or_else(|| trap(OUT_OF_BOUNDS))?;
+                    let start = self.address(to, 0, len)?;
+                    self.memory[start..start + len].copy_from_slice(source);
+                }
+                &Op::DataDrop(segment) => self.dropped_data[segment as usize] = true,
+                Op::MemoryCopy => {
+                    let len = self.pop_i32()? as u32 as usize;
+                    let from = self.pop_i32()? as u32 as u64;
+                    let to = self.pop_i32()? as u32 as u64;
+                    let from = self.address(from, 0, len)?;
+                    let to = self.address(to, 0, len)?;
+                    self.memory.copy_within(from..from + len, to);
+                }
+                Op::MemoryFill => {
+                    let len = self.pop_i32()? as u32 as usize;
+                    let value = self.pop_i32()? as u8;
+                    let to = self.pop_i32()? as u32 as u64;
+                    let to = self.address(to, 0, len)?;
+                    self.memory[to..to + len].fill(value);
+                }
+                &Op::TableInit { element, table } => {
+                    let len = self.pop_i32()? as u32 as usize;
+                    let from = self.pop_i32()? as u32 as usize;
+                    let to = self.pop_i32()? as u32 as usize;
+                    let items = match self.dropped_elements[element as usize] {
+                        true => &[][..],
+                        false => &module.elements[element as usize].items[..],
+                    };
+                    let items = items
+                        .get(from..from.saturating_add(len))
+                        .ok_or_else(|| trap("out of bounds table access"))?
+                        .iter()
+                        .map(|item| self.eval(item))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    self.tables[table as usize]
+                        .get_mut(to..to.saturating_add(len))
+                        .ok_or_else(|| trap("out of bounds table access"))?
+                        .copy_from_slice(&items);
+                }
+                &Op::ElemDrop(element) => self.dropped_elements[element as usize] = true,
+                &Op::TableCopy { to, from } => {
+                    let len = self.pop_i32()? as u32 as usize;
+                    let source = self.pop_i32()? as u32 as usize;
+                    let target = self.pop_i32()? as u32 as usize;
+                    let items = self.tables[from as usize]
+                        .get(source..source.saturating_add(len))
+                        .ok_or_else(|| trap("out of bounds table access"))?
+                        .to_vec();
+                    self.tables[to as usize]
+                        .get_mut(target..target.saturating_add(len))
+                        .ok_or_else(|| trap("out of bounds table access"))?
+                        .copy_from_slice(&items);
+                }
+                &Op::TableGrow(t) => {
+                    let delta = self.pop_i32()? as u32;
+                    let value = self.pop()?;
+                    let table = &mut self.tables[t as usize];
+                    let size = table.len() as u32;
+                    match size
+                        .checked_add(delta)
+                        .filter(|&n| n <= self.table_max[t as usize])
+                    {
+                        Some(n) => {
+                            table.resize(n as usize, value);
+                            self.push_i32(size as i32);
+                        }
+                        None => self.push_i32(-1),
+                    }
+                }
+                &Op::TableSize(t) => self.push_i32(self.tables[t as usize].len() as i32),
+                &Op::TableFill(t) => {
+                    let len = self.pop_i32()? as u32 as usize;
+                    let value = self.pop()?;
+                    let to = self.pop_i32()? as u32 as usize;
+                    self.tables[t as usize]
+                        .get_mut(to..to.saturating_add(len))
+                        .ok_or_else(|| trap("out of bounds table access"))?
+                        .fill(value);
+                }
+            }
+
+            if let Some(depth) = depth {
+                let index = labels.len() - 1 - depth;
+                let label = labels[index];
+                self.unwind(label.height, label.arity)?;
+                if index == frame.label_base {
+                    labels.truncate(index);
+                    match frames.pop() {
+                        Some(caller) => frame = caller,
+                        None => return Ok(()),
+                    }
+                } else {
+                    labels.truncate(if label.is_loop { index + 1 } else { index });
+                    frame.pc = label.target;
+                }
+            }
+        }
+    }
+
+    /// The label a block opens over the `params` values on the stack.
+    fn label(
+        &self,
+        params: u32,
+        results: u32,
+        target: usize,
+        is_loop: bool,
+    ) -> Result<Label, String> {
+        let height = self
+            .stack
+            .len()
+            .checked_sub(params as usize)
+            .ok_or_else(|| malformed("stack underflow"))?;
+        Ok(Label {
+            height,
+            arity: (if is_loop { params } else { results }) as usize,
+            results: results as usize,
+            target,
+            is_loop,
+        })
+    }
+
+    fn load(&mut self, op: u8, offset: u32) -> Result<(), String> {
+        let (len, signed, wide) = match op {
+            0x28 | 0x2A => (4, false, false),
+            0x29 | 0x2B => (8, false, true),
+            0x2C => (1, true, false),
+            0x2D => (1, false, false),
+            0x2E => (2, true, false),
+            0x2F => (2, false, false),
+            0x30 => (1, true, true),
+            0x31 => (1, false, true),
+            0x32 => (2, true, true),
+            0x33 => (2, false, true),
+            0x34 => (4, true, true),
+            _ => (4, false, true),
+        };
+        let address = self.pop_i32()? as u32 as u64;
+        let start = self.address(address, offset, len)?;
+        let mut bytes = [0; 8];
+        bytes[..len].copy_from_slice(&self.memory[start..start + len]);
+        let mut value = u64::from_le_bytes(bytes);
+        if signed {
+            let shift = 64 - 8 * len;
+            value = (((value << shift) as i64) >> shift) as u64;
+            if !wide {
+                value &= 0xFFFF_FFFF;
+            }
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn store(&mut self, op: u8, offset: u32) -> Result<(), String> {
+        let len = match op {
+            0x36 | 0x38 | 0x3E => 4,
+            0x37 | 0x39 => 8,
+            0x3A | 0x3C => 1,
+            _ => 2,
+        };
+        let value = self.pop()?;
+        let address = self.pop_i32()? as u32 as u64;
+        let start = self.address(address, offset, len)?;
+        self.memory[start..start + len].copy_from_slice(&value.to_le_bytes()[..len]);
+        Ok(())
+    }
+
+    fn numeric(&mut self, op: u8) -> Result<(), String> {
+        match op {
+            0x45 => {
+                let a = self.pop_i32()?;
+                self.push_bool(a == 0);
+            }
+            0x46..=0x4F => {
+                let b = self.pop_i32()?;
+                let a = self.pop_i32()?;
+                let (ua, ub) = (a as u32, b as u32);
+                self.push_bool(match op {
+                    0x46 => a == b,
+                    0x47 => a != b,
+                    0x48 => a < b,
+                    0x49 => ua < ub,
+                    0x4A => a > b,
+                    0x4B => ua > ub,
+                    0x4C => a <= b,
+                    0x4D => ua <= ub,
+                    0x4E => a >= b,
+                    _ => ua >= ub,
+                });
+            }
+            0x50 => {
+                let a = self.pop_i64()?;
+                self.push_bool(a == 0);
+            }
+            0x51..=0x5A => {
+                let b = self.pop_i64()?;
+                let a = self.pop_i64()?;
+                let (ua, ub) = (a as u64, b as u64);
+                self.push_bool(match op {
+                    0x51 => a == b,
+                    0x52 => a != b,
+                    0x53 => a < b,
+                    0x54 => ua < ub,
+                    0x55 => a > b,
+                    0x56 => ua > ub,
+                    0x57 => a <= b,
+                    0x58 => ua <= ub,
+                    0x59 => a >= b,
+                    _ => ua >= ub,
+                });
+            }
+            0x5B..=0x60 => {
+                let b = self.pop_f32()?;
+                let a = self.pop_f32()?;
+                self.push_bool(compare(op - 0x5B, a.into(), b.into()));
+            }
+            0x61..=0x66 => {
+                let b = self.pop_f64()?;
+                let a = self.pop_f64()?;
+                self.push_bool(compare(op - 0x61, a, b));
+            }
+            0x67..=0x69 => {
+                let a = self.pop_i32()?;
+                self.push_i32(match op {
+                    0x67 => a.leading_zeros(),
+                    0x68 => a.trailing_zeros(),
+                    _ => a.count_ones(),
+                } as i32);
+            }
+            0x6A..=0x78 => {
+                let b = self.pop_i32()?;
+                let a = self.pop_i32()?;
+                let (ua, ub) = (a as u32, b as u32);
+                let value = match op {
+                    0x6A => a.wrapping_add(b),
+                    0x6B => a.wrapping_sub(b),
+                    0x6C => a.wrapping_mul(b),
+                    0x6D if b == 0 => return Err(trap(DIVIDE_BY_ZERO)),
+                    0x6D => a.checked_div(b).ok_or_else(|| trap(OVERFLOW))?,
+                    0x6E => ua.checked_div(ub).ok_or_else(|| trap(DIVIDE_BY_ZERO))? as i32,
+                    0x6F if b == 0 => return Err(trap(DIVIDE_BY_ZERO)),
+                    0x6F => a.wrapping_rem(b),
+                    0x70 => ua.checked_rem(ub).ok_or_else(|| trap(DIVIDE_BY_ZERO))? as i32,
+                    0x71 => a & b,
+                    0x72 => a | b,
+                    0x73 => a ^ b,
+                    0x74 => a.wrapping_shl(ub),
+                    0x75 => a.wrapping_shr(ub),
+                    0x76 => ua.wrapping_shr(ub) as i32,
+                    0x77 => ua.rotate_left(ub) as i32,
+                    _ => ua.rotate_right(ub) as i32,
+                };
+                self.push_i32(value);
+            }
+            0x79..=0x7B => {
+                let a = self.pop_i64()?;
+                self.push_i64(i64::from(match op {
+                    0x79 => a.leading_zeros(),
+                    0x7A => a.trailing_zeros(),
+                    _ => a.count_ones(),
+                }));
+            }
+            0x7C..=0x8A => {
+                let b = self.pop_i64()?;
+                let a = self.pop_i64()?;
+                let (ua, ub) = (a as u64, b as u64);
+                let shift = ub as u32;
+                let value = match op {
+                    0x7C => a.wrapping_add(b),
+                    0x7D => a.wrapping_sub(b),
+                    0x7E => a.wrapping_mul(b),
+                    0x7F if b == 0 => return Err(trap(DIVIDE_BY_ZERO)),
+                    0x7F => a.checked_div(b).ok_or_else(|| trap(OVERFLOW))?,
+                    0x80 => ua.checked_div(ub).ok_or_else(|| trap(DIVIDE_BY_ZERO))? as i64,
+                    0x81 if b == 0 => return Err(trap(DIVIDE_BY_ZERO)),
+                    0x81 => a.wrapping_rem(b),
+                    0x82 => ua.checked_rem(ub).ok_or_else(|| trap(DIVIDE_BY_ZERO))? as i64,
+                    0x83 => a & b,
+                    0x84 => a | b,
+                    0x85 => a ^ b,
+                    0x86 => a.wrapping_shl(shift),
+                    0x87 => a.wrapping_shr(shift),
+                    0x88 => ua.wrapping_shr(shift) as i64,
+                    0x89 => ua.rotate_left(shift % 64) as i64,
+                    _ => ua.rotate_right(shift % 64) as i64,
+                };
+                self.push_i64(value);
+            }
+            0x8B | 0x8C => {
+                // abs and neg only touch the sign, NaN payloads included.
+                let a = self.pop()? as u32;
+                let sign = 1 << 31;
+                self.stack.push(u64::from(match op {
+                    0x8B => a & !sign,
+                    _ => a ^ sign,
+                }));
+            }
+            0x8D..=0x91 => {
+                let a = self.pop_f32()?;
+                self.push_f32(match op {
+                    0x8D => a.ceil(),
+                    0x8E => a.floor(),
+                    0x8F => a.trunc(),
+                    0x90 => a.round_ties_even(),
+                    _ => a.sqrt(),
+                });
+            }
+            0x92..=0x98 => {
+                let b = self.pop_f32()?;
+                let a = self.pop_f32()?;
+                self.push_f32(match op {
+                    0x92 => a + b,
+                    0x93 => a - b,
+                    0x94 => a * b,
+                    0x95 => a / b,
+                    0x96 => f32_min_max(a, b, true),
+                    0x97 => f32_min_max(a, b, false),
+                    _ => a.copysign(b),
+                });
+            }
+            0x99 | 0x9A => {
+                let a = self.pop()?;
+                let sign = 1 << 63;
+                self.stack.push(match op {
+                    0x99 => a & !sign,
+                    _ => a ^ sign,
+                });
+            }
+            0x9B..=0x9F => {
+                let a = self.pop_f64()?;
+                self.push_f64(match op {
+                    0x9B => a.ceil(),
+                    0x9C => a.floor(),
+                    0x9D => a.trunc(),
+                    0x9E => a.round_ties_even(),
+                    _ => a.sqrt(),
+                });
+            }
+            0xA0..=0xA6 => {
+                let b = self.pop_f64()?;
+                let a = self.pop_f64()?;
+                self.push_f64(match op {
+                    0xA0 => a + b,
+                    0xA1 => a - b,
+                    0xA2 => a * b,
+                    0xA3 => a / b,
+                    0xA4 => f64_min_max(a, b, true),
+                    0xA5 => f64_min_max(a, b, false),
+                    _ => a.copysign(b),
+                });
+            }
+            0xA7 => {
+                let a = self.pop_i64()?;
+                self.push_i32(a as i32);
+            }
+            0xA8..=0xAB => {
+                let a = match op {
+                    0xA8 | 0xA9 => f64::from(self.pop_f32()?),
+                    _ => self.pop_f64()?,
+                };
+                let value = match op {
+                    0xA8 | 0xAA => trunc(a, -2147483648.0, 2147483648.0)? as i32,
+                    _ => trunc(a, 0.0, 4294967296.0)? as u32 as i32,
+                };
+                self.push_i32(value);
+            }
+            0xAC => {
+                let a = self.pop_i32()?;
+                self.push_i64(i64::from(a));
+            }
+            0xAD => {
+                let a = self.pop_i32()? as u32;
+                self.push_i64(i64::from(a));
+            }
+            0xAE..=0xB1 => {
+                let a = match op {
+                    0xAE | 0xAF => f64::from(self.pop_f32()?),
+                    _ => self.pop_f64()?,
+                };
+                let value = match op {
+                    0xAE | 0xB0 => trunc(a, -9223372036854775808.0, 9223372036854775808.0)? as i64,
+                    _ => trunc(a, 0.0, 18446744073709551616.0)? as u64 as i64,
+                };
+                self.push_i64(value);
+            }
+            0xB2..=0xB5 => {
+                let value = match op {
+                    0xB2 => self.pop_i32()? as f32,
+                    0xB3 => self.pop_i32()? as u32 as f32,
+                    0xB4 => self.pop_i64()? as f32,
+                    _ => self.pop_i64()? as u64 as f32,
+                };
+                self.push_f32(value);
+            }
+            0xB6 => {
+                let a = self.pop_f64()?;
+                self.push_f32(a as f32);
+            }
+            0xB7..=0xBA => {
+                let value = match op {
+                    0xB7 => f64::from(self.pop_i32()?),
+                    0xB8 => f64::from(self.pop_i32()? as u32),
+                    0xB9 => self.pop_i64()? as f64,
+                    _ => self.pop_i64()? as u64 as f64,
+                };
+                self.push_f64(value);
+            }
+            0xBB => {
+                let a = self.pop_f32()?;
+                self.push_f64(f64::from(a));
+            }
+            // The reinterpretations: the bits are already in place, but an
+            // f32 or i32 must stay in the low half.
+            0xBC | 0xBE => {
+                let a = self.pop()?;
+                self.stack.push(a & 0xFFFF_FFFF);
+            }
+            0xBD | 0xBF => {}
+            0xC0 | 0xC1 => {
+                let a = self.pop_i32()?;
+                self.push_i32(match op {
+                    0xC0 => i32::from(a as i8),
+                    _ => i32::from(a as i16),
+                });
+            }
+            _ => {
+                let a = self.pop_i64()?;
+                self.push_i64(match op {
+                    0xC2 => i64::from(a as i8),
+                    0xC3 => i64::from(a as i16),
+                    _ => i64::from(a as i32),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn trunc_sat(&mut self, op: u8) -> Result<(), String> {
+        // Rust's float-to-int casts saturate and turn NaN into zero, which
+        // is exactly what these instructions do.
+        let a = match op {
+            0 | 1 | 4 | 5 => f64::from(self.pop_f32()?),
+            _ => self.pop_f64()?,
+        };
+        match op {
+            0 | 2 => self.push_i32(a as i32),
+            1 | 3 => self.push_i32(a as u32 as i32),
+            4 | 6 => self.push_i64(a as i64),
+            _ => self.push_i64(a as u64 as i64),
+        }
+        Ok(())
+    }
+}
+
+/// eq, ne, lt, gt, le or ge, by their offset in the opcode table.
+fn compare(op: u8, a: f64, b: f64) -> bool {
+    match op {
+        0 => a == b,
+        1 => a != b,
+        2 => a < b,
+        3 => a > b,
+        4 => a <= b,
+        _ => a >= b,
+    }
+}
+
+/// `x` truncated toward zero, if that lies in `[min, max)`.
+fn trunc(x: f64, min: f64, max: f64) -> Result<f64, String> {
+    if x.is_nan() {
+        return Err(trap(INVALID_CONVERSION));
+    }
+    let t = x.trunc();
+    match t >= min && t < max {
+        true => Ok(t),
+        false => Err(trap(OVERFLOW)),
+    }
+}
+
+/// WebAssembly's min and max propagate NaN and order -0 below +0.
+fn f32_min_max(a: f32, b: f32, min: bool) -> f32 {
+    if a.is_nan() || b.is_nan() {
+        f32::NAN
+    } else if a == b {
+        match min {
+            true => f32::from_bits(a.to_bits() | b.to_bits()),
+            false => f32::from_bits(a.to_bits() & b.to_bits()),
+        }
+    } else if min {
+        a.min(b)
+    } else {
+        a.max(b)
+    }
+}
+
+fn f64_min_max(a: f64, b: f64, min: bool) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if a == b {
+        match min {
+            true => f64::from_bits(a.to_bits() | b.to_bits()),
+            false => f64::from_bits(a.to_bits() & b.to_bits()),
+        }
+    } else if min {
+        a.min(b)
+    } else {
+        a.max(b)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    pub fn uleb(mut n: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+        loop {
+            let byte = (n & 0x7F) as u8;
+            n >>= 7;
+            match n {
+                0 => return [out, vec![byte]].concat(),
+                _ => out.push(byte | 0x80),
+            }
+        }
+    }
+
+    pub fn sleb(mut n: i64) -> Vec<u8> {
+        let mut out = Vec::new();
+        loop {
+            let byte = (n & 0x7F) as u8;
+            n >>= 7;
+            if (n == 0 && byte & 0x40 == 0) || (n == -1 && byte & 0x40 != 0) {
+                out.push(byte);
+                return out;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    /// A function: parameter types, result types, local types and body.
+    pub struct Func<'a>(pub &'a [u8], pub &'a [u8], pub &'a [u8], pub Vec<u8>);
+
+    /// What [`module`] assembles. Each function gets a type of its own, and
+    /// the table and globals are listed ready encoded.
+    #[derive(Default)]
+    pub struct Spec<'a> {
+        pub funcs: Vec<Func<'a>>,
+        pub exports: Vec<(&'a str, u8, u32)>,
+        pub memory: Option<&'a [u8]>,
+        pub globals: Vec<Vec<u8>>,
+        pub table: Vec<u32>,
+        pub data: Vec<(u32, &'a [u8])>,
+    }
+
+    fn vec(items: Vec<Vec<u8>>) -> Vec<u8> {
+        [uleb(items.len() as u64), items.concat()].concat()
+    }
+
+    fn section(id: u8, contents: Vec<u8>) -> Vec<u8> {
+        [vec![id], uleb(contents.len() as u64), contents].concat()
+    }
+
+    pub fn module(spec: &Spec) -> Vec<u8> {
+        let mut out = b"\0asm\x01\0\0\0".to_vec();
+        let types = spec.funcs.iter().map(|Func(params, results, ..)| {
+            let params = [uleb(params.len() as u64), params.to_vec()].concat();
+            let results = [uleb(results.len() as u64), results.to_vec()].concat();
+            [vec![0x60], params, results].concat()
+        });
+        out.extend(section(1, vec(types.collect())));
+        let indices = (0..spec.funcs.len()).map(|i| uleb(i as u64));
+        out.extend(section(3, vec(indices.collect())));
+        if !spec.table.is_empty() {
+            let len = uleb(spec.table.len() as u64);
+            out.extend(section(4, [vec![1, 0x70, 0], len].concat()));
+        }
+        if let Some(limits) = spec.memory {
+            out.extend(section(5, [&[1][..], limits].concat()));
+        }
+        if !spec.globals.is_empty() {
+            out.extend(section(6, vec(spec.globals.clone())));
+        }
+        let exports = spec.exports.iter().map(|&(name, kind, index)| {
+            let name = [uleb(name.len() as u64), name.as_bytes().to_vec()].concat();
+            [name, vec![kind], uleb(u64::from(index))].concat()
+        });
+        out.extend(section(7, vec(exports.collect())));
+        if !spec.table.is_empty() {
+            let funcs = spec.table.iter().map(|&f| uleb(u64::from(f))).collect();
+            out.extend(section(
+                9,
+                vec(vec![[vec![0, 0x41, 0, 0x0B], vec(funcs)].concat()]),
+            ));
+        }
+        let bodies = spec.funcs.iter().map(|Func(_, _, locals, body)| {
+            let locals = locals.iter().map(|&ty| vec![1, ty]).collect();
+            let body = [vec(locals), body.clone()].concat();
+            [uleb(body.len() as u64), body].concat()
+        });
+        out.extend(section(10, vec(bodies.collect())));
+        let data = spec.data.iter().map(|&(offset, bytes)| {
+            let offset = [vec![0, 0x41], sleb(i64::from(offset)), vec![0x0B]].concat();
+            [offset, uleb(bytes.len() as u64), bytes.to_vec()].concat()
+        });
+        if !spec.data.is_empty() {
+            out.extend(section(11, vec(data.collect())));
+        }
+        out
+    }
+
+    /// A module exporting one function, `f`.
+    fn single(params: &[u8], results: &[u8], body: &[u8]) -> Arc<Module> {
+        let spec = Spec {
+            funcs: vec![Func(params, results, &[], body.to_vec())],
+            exports: vec![("f", 0, 0)],
+            memory: Some(&[0, 1]),
+            ..Spec::default()
+        };
+        Arc::new(Module::parse(&module(&spec)).unwrap())
+    }
+
+    fn run(module: &Arc<Module>, args: &[u64]) -> Result<u64, String> {
+        let mut instance = Instance::new(Arc::clone(module), 1000)?;
+        let results = instance.call("f", args, 100_000)?;
+        Ok(results.first().copied().unwrap_or_default())
+    }
+
+    #[test]
+    fn test_control_flow_and_calls() {
+        // Recursive factorial over i64.
+        let factorial = single(
+            &[0x7E],
+            &[0x7E],
+            &[
+                0x20, 0, 0x50, 0x04, 0x7E, 0x42, 1, 0x05, 0x20, 0, 0x20, 0, 0x42, 1, 0x7D, 0x10, 0,
+                0x7E, 0x0B, 0x0B,
+            ],
+        );
+        assert_eq!(run(&factorial, &[20]), Ok(2432902008176640000));
+
+        // Sums 1..=n with a loop and br_if, in a local.
+        let sum = Spec {
+            funcs: vec![Func(
+                &[0x7F],
+                &[0x7F],
+                &[0x7F],
+                vec![
+                    0x03, 0x40, 0x20, 1, 0x20, 0, 0x6A, 0x21, 1, 0x20, 0, 0x41, 1, 0x6B, 0x22, 0,
+                    0x0D, 0, 0x0B, 0x20, 1, 0x0B,
+                ],
+            )],
+            exports: vec![("f", 0, 0)],
+            ..Spec::default()
+        };
+        let sum = Arc::new(Module::parse(&module(&sum)).unwrap());
+        assert_eq!(run(&sum, &[100]), Ok(5050));
+
+        let table = single(
+            &[0x7F],
+            &[0x7F],
+            &[
+                0x02, 0x40, 0x02, 0x40, 0x02, 0x40, 0x20, 0, 0x0E, 2, 0, 1, 2, 0x0B, 0x41, 10,
+                0x0F, 0x0B, 0x41, 20, 0x0F, 0x0B, 0x41, 30, 0x0B,
+            ],
+        );
+        let picks: Vec<_> = [0, 1, 2, 9].map(|i| run(&table, &[i]).unwrap()).into();
+        assert_eq!(picks, [10, 20, 30, 30]);
+    }
+
+    #[test]
+    fn test_traps() {
+        let div = single(&[0x7F, 0x7F], &[0x7F], &[0x20, 0, 0x20, 1, 0x6D, 0x0B]);
+        assert_eq!(
+            run(&div, &[(-7i32) as u32 as u64, 2]),
+            Ok((-3i32) as u32 as u64)
+        );
+        assert!(run(&div, &[7, 0]).unwrap_err().contains("divide by zero"));
+        let min = i32::MIN as u32 as u64;
+        assert!(
+            run(&div, &[min, u64::from(u32::MAX)])
+                .unwrap_err()
+                .contains("overflow")
+        );
+
+        let spin = single(&[], &[], &[0x03, 0x40, 0x0C, 0, 0x0B, 0x0B]);
+        assert!(run(&spin, &[]).unwrap_err().contains("out of fuel"));
+        let recurse = single(&[], &[], &[0x10, 0, 0x0B]);
+        assert!(
+            run(&recurse, &[])
+                .unwrap_err()
+                .contains("call stack exhausted")
+        );
+        let unreachable = single(&[], &[], &[0x00, 0x0B]);
+        assert!(run(&unreachable, &[]).unwrap_err().contains("unreachable"));
+
+        let load = single(&[0x7F], &[0x7F], &[0x20, 0, 0x28, 2, 0, 0x0B]);
+        assert_eq!(run(&load, &[65532]), Ok(0));
+        assert!(run(&load, &[65533]).unwrap_err().contains("out of bounds"));
+    }
+
+    #[test]
+    fn test_memory() {
+        let spec = Spec {
+            funcs: vec![
+                Func(&[0x7F], &[0x7F], &[], vec![0x20, 0, 0x40, 0, 0x0B]),
+                Func(
+                    &[],
+                    &[],
+                    &[],
+                    vec![0x41, 0, 0x41, 16, 0x41, 5, 0xFC, 10, 0, 0, 0x0B],
+                ),
+            ],
+            exports: vec![("grow", 0, 0), ("copy", 0, 1)],
+            memory: Some(&[1, 1, 3]),
+            data: vec![(16, b"hello")],
+            ..Spec::default()
+        };
+        let module = Arc::new(Module::parse(&module(&spec)).unwrap());
+        let mut instance = Instance::new(module, 1000).unwrap();
+        assert_eq!(&instance.memory()[16..21], b"hello");
+        instance.call("copy", &[], 1000).unwrap();
+        assert_eq!(&instance.memory()[..5], b"hello");
+
+        let grown: Vec<_> = (0..3)
+            .map(|_| instance.call("grow", &[1], 1000).unwrap()[0])
+            .collect();
+        assert_eq!(grown, [1, 2, u64::from(u32::MAX)]);
+        assert_eq!(instance.memory().len(), 3 * PAGE);
+    }
+
+    #[test]
+    fn test_numbers() {
+        let f64_bits = |x: f64| x.to_bits();
+        let min = single(&[0x7C, 0x7C], &[0x7C], &[0x20, 0, 0x20, 1, 0xA4, 0x0B]);
+        let zero = run(&min, &[f64_bits(0.0), f64_bits(-0.0)]).unwrap();
+        assert_eq!(zero, f64_bits(-0.0));
+        assert!(f64::from_bits(run(&min, &[f64_bits(f64::NAN), f64_bits(1.0)]).unwrap()).is_nan());
+
+        let trunc = single(&[0x7C], &[0x7F], &[0x20, 0, 0xAA, 0x0B]);
+        assert_eq!(run(&trunc, &[f64_bits(-3.9)]), Ok((-3i32) as u32 as u64));
+        assert!(
+            run(&trunc, &[f64_bits(f64::NAN)])
+                .unwrap_err()
+                .contains("invalid conversion")
+        );
+        assert!(
+            run(&trunc, &[f64_bits(3e9)])
+                .unwrap_err()
+                .contains("overflow")
+        );
+        let saturate = single(&[0x7C], &[0x7F], &[0x20, 0, 0xFC, 2, 0x0B]);
+        assert_eq!(run(&saturate, &[f64_bits(3e9)]), Ok(i32::MAX as u64));
+
+        let extend = single(&[0x7F], &[0x7F], &[0x20, 0, 0xC0, 0x0B]);
+        assert_eq!(run(&extend, &[0x80]), Ok((-128i32) as u32 as u64));
+        let rotate = single(&[0x7E, 0x7E], &[0x7E], &[0x20, 0, 0x20, 1, 0x89, 0x0B]);
+        assert_eq!(run(&rotate, &[1 << 63, 66]), Ok(2));
+    }
+
+    #[test]
+    fn test_call_indirect() {
+        let spec = Spec {
+            funcs: vec![
+                Func(&[], &[0x7F], &[], vec![0x41, 1, 0x0B]),
+                Func(&[], &[0x7F], &[], vec![0x41, 2, 0x0B]),
+                Func(&[0x7F], &[0x7F], &[], vec![0x20, 0, 0x11, 0, 0, 0x0B]),
+            ],
+            exports: vec![("f", 0, 2)],
+            table: vec![0, 1, 2],
+            ..Spec::default()
+        };
+        let module = Arc::new(Module::parse(&module(&spec)).unwrap());
+        assert_eq!(run(&module, &[0]), Ok(1));
+        assert_eq!(run(&module, &[1]), Ok(2));
+        assert!(run(&module, &[2]).unwrap_err().contains("type mismatch"));
+        assert!(
+            run(&module, &[3])
+                .unwrap_err()
+                .contains("undefined element")
+        );
+    }
+
+    #[test]
+    fn test_bad_modules_are_errors() {
+        assert!(Module::parse(b"\x7fELF").is_err());
+        let imports = [
+            &b"\0asm\x01\0\0\0"[..],
+            &[2, 9, 1, 3, b'e', b'n', b'v', 1, b'f', 0, 0],
+        ]
+        .concat();
+        assert!(
+            Module::parse(&imports)
+                .unwrap_err()
+                .contains("imports env.f")
+        );
+
+        // Truncated and corrupted copies of a valid module fail cleanly,
+        // whichever stage catches them; a copy cut where its data section
+        // starts is still valid, but misses the data.
+        let spec = Spec {
+            funcs: vec![
+                Func(&[0x7F], &[0x7F], &[0x7E], vec![0x20, 0, 0x28, 2, 0, 0x0B]),
+                Func(&[], &[0x7F], &[], vec![0x41, 8, 0x10, 0, 0x0B]),
+            ],
+            exports: vec![("f", 0, 1), ("memory", 2, 0)],
+            memory: Some(&[0, 1]),
+            data: vec![(8, b"data")],
+            ..Spec::default()
+        };
+        let bytes = module(&spec);
+        let attempt = |bytes: &[u8]| {
+            let module = Arc::new(Module::parse(bytes)?);
+            let mut instance = Instance::new(module, 10_000)?;
+            instance.call("f", &[], 10_000)
+        };
+        let expected = Ok(vec![u64::from(u32::from_le_bytes(*b"data"))]);
+        assert_eq!(attempt(&bytes), expected);
+        for len in 0..bytes.len() {
+            assert_ne!(attempt(&bytes[..len]), expected);
+        }
+        let mut seed = 0x9E37_79B9_7F4A_7C15u64;
+        for _ in 0..5000 {
+            let mut corrupt = bytes.clone();
+            for _ in 0..3 {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                let at = 8 + (seed % (bytes.len() as u64 - 8)) as usize;
+                corrupt[at] = (seed >> 32) as u8;
+            }
+            let _ = attempt(&corrupt);
+        }
+    }
+}
@@ -0,0 +1,338 @@
+//! XML documents for `--xml`, such as Android `strings.xml`.
+//!
+//! Cleaning is limited to text nodes and, when a selector asks for them,
+//! attribute values. Tags, namespace declarations, comments, processing
+//! instructions and the doctype are never changed, and CDATA sections only
+//! with `--xml-cdata`. Everything is edited in place, so the rest of the
+//! document is kept byte for byte.
+
+use std::ops::Range;
+use std::str::FromStr;
+
+/// An `--xpath` selector: a path of element names, each `*` for any, and
+/// optionally an attribute at the end.
+///
+/// `/resources/string` matches from the root, `//string` or `string` at any
+/// depth, and `//string/@title` or `//@title` selects an attribute instead
+/// of text. A name without a prefix matches any namespace prefix.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Selector {
+    absolute: bool,
+    steps: Vec<String>,
+    attribute: Option<String>,
+}
+
+impl FromStr for Selector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid selector '{}': use a path like //string, /resources/string or //string/@title",
+                s
+            )
+        };
+        let (absolute, path) = match (s.strip_prefix("//"), s.strip_prefix('/')) {
+            (Some(path), _) => (false, path),
+            (None, Some(path)) => (true, path),
+            (None, None) => (false, s),
+        };
+        let mut steps: Vec<String> = path.split('/').map(str::to_string).collect();
+        let attribute = match steps.last().and_then(|step| step.strip_prefix('@')) {
+            Some(name) => {
+                let name = name.to_string();
+                steps.pop();
+                Some(name)
+            }
+            None => None,
+        };
+        let name = |step: &str| {
+            step == "*"
+                || (!step.is_empty()
+                    && !step.contains(['@', '[', ']', '(', ')', '=', ' '])
+                    && step.split(':').count() <= 2)
+        };
+        if steps.iter().any(|step| !name(step))
+            || attribute.as_deref().is_some_and(|a| !name(a))
+            || (steps.is_empty() && (absolute || attribute.is_none()))
+        {
+            return Err(invalid());
+        }
+        Ok(Selector {
+            absolute,
+            steps,
+            attribute,
+        })
+    }
+}
+
+impl Selector {
+    /// Whether the element at `path`, from the root, is the one selected.
+    fn matches(&self, path: &[&str]) -> bool {
+        if path.len() < self.steps.len() || (self.absolute && path.len() != self.steps.len()) {
+            return false;
+        }
+        path[path.len() - self.steps.len()..]
+            .iter()
+            .zip(&self.steps)
+            .all(|(name, step)| name_matches(step, name))
+    }
+}
+
+fn name_matches(pattern: &str, name: &str) -> bool {
+    pattern == "*"
+        || pattern == name
+        || (!pattern.contains(':') && name.rsplit(':').next() == Some(pattern))
+}
+
+/// Which parts of a document `--xml` may change.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Scope {
+    /// With none, every text node and no attribute.
+    pub selectors: Vec<Selector>,
+    /// Treat the content of CDATA sections as text.
+    pub cdata: bool,
+}
+
+impl Scope {
+    /// Whether text directly inside the element at `path` may change:
+    /// that element or one of its ancestors is selected.
+    fn text(&self, path: &[&str]) -> bool {
+        self.selectors.is_empty()
+            || self
+                .selectors
+                .iter()
+                .filter(|s| s.attribute.is_none())
+                .any(|s| (1..=path.len()).any(|end| s.matches(&path[..end])))
+    }
+
+    fn attribute(&self, path: &[&str], attribute: &str) -> bool {
+        self.selectors.iter().any(|s| {
+            s.attribute
+                .as_deref()
+                .is_some_and(|pattern| name_matches(pattern, attribute))
+                && s.matches(path)
+        })
+    }
+}
+
+/// Byte ranges of `document` that `scope` allows to change, in order.
+/// Fails if `document` isn't well-formed enough to tell text from markup.
+pub fn regions(document: &str, scope: &Scope) -> Result<Vec<Range<usize>>, String> {
+    let mut regions = Vec::new();
+    let mut path: Vec<&str> = Vec::new();
+    let mut i = 0;
+    let mismatch = |i: usize, what: &str| format!("Not well-formed XML: {} at byte {}", what, i);
+
+    while i < document.len() {
+        let rest = &document[i..];
+        if !rest.starts_with('<') {
+            let end = rest.find('<').map_or(document.len(), |n| i + n);
+            if !path.is_empty() && scope.text(&path) {
+                regions.push(i..end);
+            }
+            i = end;
+            continue;
+        }
+
+        let skip_to = |close: &str, what: &str| {
+            rest.find(close)
+                .map(|n| i + n + close.len())
+                .ok_or_else(|| mismatch(i, what))
+        };
+        if rest.starts_with("<!--") {
+            i = skip_to("-->", "unterminated comment")?;
+        } else if let Some(body) = rest.strip_prefix("<![CDATA[") {
+            let end = body
+                .find("]]>")
+                .ok_or_else(|| mismatch(i, "unterminated CDATA section"))?;
+            let start = i + "<![CDATA[".len();
+            if scope.cdata && scope.text(&path) {
+                regions.push(start..start + end);
+            }
+            i = start + end + "]]>".len();
+        } else if rest.starts_with("<?") {
+            i = skip_to("?>", "unterminated processing instruction")?;
+        } else if rest.starts_with("<!") {
+            i = doctype_end(document, i).ok_or_else(|| mismatch(i, "unterminated declaration"))?;
+        } else if let Some(body) = rest.strip_prefix("</") {
+            let name_len = body.find(|c: char| c == '>' || c.is_whitespace());
+            let name = &body[..name_len.unwrap_or(body.len())];
+            if path.pop() != Some(name) {
+                return Err(mismatch(i, &format!("unexpected </{}>", name)));
+            }
+            i = skip_to(">", "unterminated end tag")?;
+        } else {
+            i = start_tag(document, i, &mut path, scope, &mut regions)
+                .ok_or_else(|| mismatch(i, "malformed tag"))?;
+        }
+    }
+
+    match path.last() {
+        Some(open) => Err(format!("Not well-formed XML: <{}> is never closed", open)),
+        None => Ok(regions),
+    }
+}
+
+/// Reads the start tag at `start`, pushing its name onto `path` unless it
+/// closes itself, and adds the selected attribute values to `regions`.
+/// Returns the offset after the tag.
+fn start_tag<'a>(
+    document: &'a str,
+    start: usize,
+    path: &mut Vec<&'a str>,
+    scope: &Scope,
+    regions: &mut Vec<Range<usize>>,
+) -> Option<usize> {
+    let bytes = document.as_bytes();
+    let name_end = start + 1 + document[start + 1..].find(|c: char| is_delimiter(c))?;
+    let name = &document[start + 1..name_end];
+    if name.is_empty() {
+        return None;
+    }
+    path.push(name);
+
+    let mut i = name_end;
+    loop {
+        while bytes.get(i).is_some_and(u8::is_ascii_whitespace) {
+            i += 1;
+        }
+        match bytes.get(i)? {
+            b'>' => return Some(i + 1),
+            b'/' if bytes.get(i + 1) == Some(&b'>') => {
+                path.pop();
+                return Some(i + 2);
+            }
+            _ => {}
+        }
+        let attr_end = i + document[i..].find(|c: char| c == '=' || is_delimiter(c))?;
+        let attribute = &document[i..attr_end];
+        i = attr_end;
+        while bytes.get(i).is_some_and(u8::is_ascii_whitespace) {
+            i += 1;
+        }
+        if attribute.is_empty() || bytes.get(i) != Some(&b'=') {
+            return None;
+        }
+        i += 1;
+        while bytes.get(i).is_some_and(u8::is_ascii_whitespace) {
+            i += 1;
+        }
+        let quote = *bytes.get(i).filter(|&&q| q == b'"' || q == b'\'')?;
+        let value_start = i + 1;
+        let value_end = value_start + document[value_start..].find(quote as char)?;
+        if scope.attribute(path, attribute) {
+            regions.push(value_start..value_end);
+        }
+        i = value_end + 1;
+    }
+}
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || c == '>' || c == '/'
+}
+
+/// The offset after a `<!DOCTYPE ...>` at `start`, whose internal subset
+/// in brackets may hold `>` of its own.
+fn doctype_end(document: &str, start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, c) in document[start..].char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[') => depth += 1,
+            (None, ']') => depth -= 1,
+            (None, '>') if depth == 0 => return Some(start + i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `replacement` as it must be written in text or an attribute value.
+/// `&` is left alone, so `--to-entities` still writes character references.
+pub fn escape(replacement: &str) -> String {
+    replacement
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STRINGS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<!-- Launch 🚀 strings -->
+<resources xmlns:tools="http://schemas.android.com/tools">
+    <string name="launch 🚀" tools:title="Go 🚀">Launch 🚀</string>
+    <string name="done"><![CDATA[<b>Done ✅</b>]]></string>
+    <plurals name="items">
+        <item quantity="one">%d item 📦</item>
+    </plurals>
+    <note/>
+</resources>
+"#;
+
+    fn texts(selectors: &[&str], cdata: bool) -> Vec<&'static str> {
+        let scope = Scope {
+            selectors: selectors.iter().map(|s| s.parse().unwrap()).collect(),
+            cdata,
+        };
+        regions(STRINGS, &scope)
+            .unwrap()
+            .into_iter()
+            .map(|region| STRINGS[region].trim())
+            .filter(|text| !text.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn test_regions() {
+        assert_eq!(texts(&[], false), ["Launch 🚀", "%d item 📦"]);
+        assert_eq!(
+            texts(&[], true),
+            ["Launch 🚀", "<b>Done ✅</b>", "%d item 📦"]
+        );
+        assert_eq!(texts(&["//item"], false), ["%d item 📦"]);
+        assert_eq!(texts(&["/resources/plurals"], false), ["%d item 📦"]);
+        assert!(texts(&["/plurals"], false).is_empty());
+        assert_eq!(
+            texts(&["string/@title", "//@quantity"], false),
+            ["Go 🚀", "one"]
+        );
+        assert_eq!(
+            texts(&["/resources/*/@name"], false),
+            ["launch 🚀", "done", "items"]
+        );
+    }
+
+    #[test]
+    fn test_regions_rejects_broken_markup() {
+        let scope = Scope::default();
+        assert!(regions("<a><b></a>", &scope).unwrap_err().contains("</a>"));
+        assert!(regions("<a>", &scope).unwrap_err().contains("never closed"));
+        assert!(regions("<a x=1></a>", &scope).is_err());
+        assert!(regions("<!-- open", &scope).is_err());
+        let doctype = "<!DOCTYPE a [<!ENTITY e \"x>\">]><a>🚀</a>";
+        let found = regions(doctype, &scope).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(&doctype[found[0].clone()], "🚀");
+    }
+
+    #[test]
+    fn test_selector_parse() {
+        assert!("//string".parse::<Selector>().is_ok());
+        assert!("android:text".parse::<Selector>().is_ok());
+        for bad in ["", "/", "//", "a[1]", "a//b", "@", "a/@b/c"] {
+            assert!(bad.parse::<Selector>().is_err(), "{}", bad);
+        }
+        assert_eq!(
+            escape("<\"it's\"> &#x1F680;"),
+            "&lt;&quot;it&apos;s&quot;&gt; &#x1F680;"
+        );
+    }
+}
@@ -0,0 +1,434 @@
+//! End-to-end tests of the nomoji binary. These run on every CI platform,
+//! including Windows, where console and pipe handling differ most.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+fn nomoji(args: &[&str], stdin: &[u8]) -> Output {
+    nomoji_with_env(args, &[], stdin)
+}
+
+fn nomoji_with_env(args: &[&str], vars: &[(&str, &str)], stdin: &[u8]) -> Output {
+    let dir = tempfile::tempdir().unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_nomoji"))
+        .args(args)
+        .envs(vars.iter().copied())
+        .current_dir(dir.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn stdin_output_is_exact_utf8() {
+    let output = nomoji(&["-"], "Grüße 🚀 — naïve ✅ 日本\r\n".as_bytes());
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "Grüße  — naïve  日本\r\n".as_bytes());
+
+    let report = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        report.contains("Emojis removed from stdin: 2"),
+        "{}",
+        report
+    );
+}
+
+#[test]
+fn utf16_output_bytes_are_untouched() {
+    let output = nomoji(&["--output-encoding", "utf-16le", "-"], "é🚀\n".as_bytes());
+    assert!(output.status.success());
+    assert_eq!(output.stdout, [0xFF, 0xFE, 0xE9, 0x00, b'\n', 0x00]);
+}
+
+#[test]
+fn in_place_edit_and_report_with_non_ascii_names() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("notes-é.txt");
+    fs::write(&file, "Done ✅\n").unwrap();
+
+    let output = nomoji(&["-i", file.to_str().unwrap()], b"");
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(&file).unwrap(), "Done \n");
+
+    let report = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        report.contains("notes-é.txt: 1 emojis removed"),
+        "{}",
+        report
+    );
+}
+
+#[test]
+fn vimgrep_lines_keep_line_text() {
+    let output = nomoji(
+        &["--format", "vimgrep", "--stdin-filename", "a.md", "-"],
+        "intro\nçà 🎉 fin\n".as_bytes(),
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap().trim_end(),
+        "a.md:2:6:çà 🎉 fin"
+    );
+}
+
+#[test]
+fn report_to_file_keeps_stderr_quiet() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("a.txt");
+    let report = dir.path().join("report.json");
+    fs::write(&file, "ok 🚀\n").unwrap();
+
+    let output = nomoji(
+        &[
+            "--format",
+            "json",
+            "--report-to",
+            report.to_str().unwrap(),
+            file.to_str().unwrap(),
+        ],
+        b"",
+    );
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    // With the report elsewhere, cleaned text goes to stdout again.
+    assert_eq!(output.stdout, b"ok \n");
+    assert!(
+        fs::read_to_string(&report)
+            .unwrap()
+            .contains(r#""total_emojis":1"#)
+    );
+}
+
+#[test]
+fn multiple_files_get_headers_on_stdout() {
+    let dir = tempfile::tempdir().unwrap();
+    let a = dir.path().join("a.txt");
+    let b = dir.path().join("b.txt");
+    fs::write(&a, "one 🚀\n").unwrap();
+    fs::write(&b, "two\n").unwrap();
+    let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+
+    let output = nomoji(&[a, b], b"");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!("==> {} <==\none \n\n==> {} <==\ntwo\n", a, b)
+    );
+
+    let output = nomoji(&["--no-filename", a, b], b"");
+    assert_eq!(output.stdout, b"one \ntwo\n");
+}
+
+#[test]
+fn missing_file_fails() {
+    let output = nomoji(&["does-not-exist.txt"], b"");
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn split_documents_are_cleaned_and_reported_separately() {
+    let input = "one 🚀\0two\0three 🎉🎉\0".as_bytes();
+    let output = nomoji(&["--split-documents", "-"], input);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"one \0two\0three \0");
+
+    let output = nomoji(&["--split-documents", "--format", "json", "-"], input);
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        report.contains(r#""file":"<stdin>#3","emojis":2"#),
+        "{}",
+        report
+    );
+    assert!(!report.contains("<stdin>#4"), "{}", report);
+}
+
+#[test]
+fn escape_output_prints_a_literal() {
+    let output = nomoji(&["--escape-output", "c", "-"], "say \"hi\" 👋\n".as_bytes());
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"\"say \\\"hi\\\" \\n\"\n");
+}
+
+#[test]
+fn environment_variables_set_options_flags_override() {
+    let vars = [
+        ("NOMOJI_SKIP", "check-marks,arrows"),
+        ("NOMOJI_FORMAT", "json"),
+    ];
+    let output = nomoji_with_env(
+        &["--format", "text", "-"],
+        &vars,
+        "Ship 🚀 ✅ ➡️\n".as_bytes(),
+    );
+    assert!(output.status.success());
+    assert_eq!(output.stdout, "Ship  ✅ ➡️\n".as_bytes());
+
+    let output = nomoji_with_env(&["-"], &vars, "🚀\n".as_bytes());
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(report.starts_with('{'), "{}", report);
+
+    let output = nomoji_with_env(&["-"], &[("NOMOJI_DRY_RUN", "maybe")], b"");
+    assert_eq!(output.status.code(), Some(2));
+    let error = String::from_utf8(output.stderr).unwrap();
+    assert!(error.contains("--dry-run"), "{}", error);
+}
+
+#[test]
+fn github_summary_is_appended_when_running_in_actions() {
+    let dir = tempfile::tempdir().unwrap();
+    let summary = dir.path().join("summary.md");
+    let file = dir.path().join("notes.md");
+    fs::write(&file, "Ship 🚀 ✅\n").unwrap();
+    let summary_path = summary.to_str().unwrap();
+    let args = ["--github-summary", "--dry-run", file.to_str().unwrap()];
+
+    let output = nomoji_with_env(&args, &[("GITHUB_STEP_SUMMARY", summary_path)], b"");
+    assert!(output.status.success());
+    let table = fs::read_to_string(&summary).unwrap();
+    assert!(table.contains("| 1 | 1 | 0 | 2 |"), "{}", table);
+    assert!(table.contains("notes.md` | 2 |"), "{}", table);
+
+    // Outside Actions the flag is a no-op.
+    fs::remove_file(&summary).unwrap();
+    let output = nomoji_with_env(&args, &[("GITHUB_STEP_SUMMARY", "")], b"");
+    assert!(output.status.success());
+    assert!(!summary.exists());
+}
+
+#[test]
+fn pre_commit_fixes_files_and_fails_only_when_it_changed_some() {
+    let dir = tempfile::tempdir().unwrap();
+    let dirty = dir.path().join("dirty.md");
+    let clean = dir.path().join("clean.md");
+    fs::write(&dirty, "Ship 🚀 ✅\n").unwrap();
+    fs::write(&clean, "Ship\n").unwrap();
+    let args = [
+        "--pre-commit",
+        dirty.to_str().unwrap(),
+        clean.to_str().unwrap(),
+    ];
+
+    let output = nomoji(&args, b"");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let report = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(report.lines().count(), 1, "{}", report);
+    assert!(
+        report.ends_with("dirty.md: 2 emojis removed\n"),
+        "{}",
+        report
+    );
+    assert_eq!(fs::read_to_string(&dirty).unwrap(), "Ship  \n");
+
+    let output = nomoji(&args, b"");
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty() && output.stderr.is_empty());
+
+    let output = nomoji(&["--pre-commit"], b"");
+    assert!(output.status.success());
+}
+
+#[test]
+fn commit_msg_keeps_the_gitmoji_and_verifies_the_rest() {
+    let dir = tempfile::tempdir().unwrap();
+    let message = dir.path().join("COMMIT_EDITMSG");
+    fs::write(&message, "✨ feat: add sampling 🎉\n\nWith care ✅\n").unwrap();
+
+    let args = [
+        "--commit-msg",
+        "--keep-gitmoji",
+        "--verify",
+        message.to_str().unwrap(),
+    ];
+    let output = nomoji(&args, b"");
+    let report = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{}", report);
+    assert!(report.ends_with("2 emojis removed\n"), "{}", report);
+    assert_eq!(
+        fs::read_to_string(&message).unwrap(),
+        "✨ feat: add sampling \n\nWith care \n"
+    );
+}
+
+#[test]
+fn run_passes_on_the_exit_code_of_the_command() {
+    let exe = env!("CARGO_BIN_EXE_nomoji");
+    let output = nomoji(&["run", "--", exe, "missing.txt"], b"");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("missing.txt"), "{}", stderr);
+
+    let output = nomoji(&["run", "--", "nomoji-no-such-command"], b"");
+    assert_eq!(output.status.code(), Some(127));
+}
+
+#[test]
+fn staged_fixes_the_index_and_keeps_unstaged_work() {
+    let dir = tempfile::tempdir().unwrap();
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .current_dir(dir.path())
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?}", args);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let staged = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_nomoji"))
+            .current_dir(dir.path())
+            .arg("--staged")
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    git(&["init", "-q"]);
+    fs::write(dir.path().join("whole.md"), "Done ✅\n").unwrap();
+    fs::write(dir.path().join("partial.md"), "Ship 🚀\n").unwrap();
+    git(&["add", "."]);
+    fs::write(dir.path().join("partial.md"), "Ship 🚀\nWIP 🚧\n").unwrap();
+
+    let output = staged(&[]);
+    assert_eq!(output.status.code(), Some(1));
+    let report = String::from_utf8(output.stderr).unwrap();
+    assert!(report.contains("partial.md: 1 emojis found"), "{}", report);
+    assert_eq!(git(&["show", ":whole.md"]), "Done ✅\n");
+
+    let output = staged(&["--pre-commit"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(git(&["show", ":whole.md"]), "Done \n");
+    assert_eq!(git(&["show", ":partial.md"]), "Ship \n");
+    assert_eq!(
+        fs::read_to_string(dir.path().join("whole.md")).unwrap(),
+        "Done \n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("partial.md")).unwrap(),
+        "Ship 🚀\nWIP 🚧\n"
+    );
+
+    assert!(staged(&["--pre-commit"]).status.success());
+    assert!(staged(&[]).status.success());
+}
+
+#[test]
+fn configs_below_the_current_directory_apply_to_their_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join("sub/vendored")).unwrap();
+    fs::write(root.join(".nomoji.toml"), "keep = [\"check-marks\"]\n").unwrap();
+    fs::write(
+        root.join("sub/.nomoji.toml"),
+        "[[rules]]\nglob = \"docs/*.md\"\nkeep = [\"arrows\"]\n",
+    )
+    .unwrap();
+    fs::write(root.join("sub/vendored/.nomoji.toml"), "root = true\n").unwrap();
+    for file in [
+        "top.md",
+        "sub/docs/guide.md",
+        "sub/notes.md",
+        "sub/vendored/lib.md",
+    ] {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "✅ ➡ 🚀\n").unwrap();
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nomoji"))
+        .current_dir(root)
+        .args(["-i", "top.md", "sub/docs/guide.md", "sub/notes.md"])
+        .arg(root.join("sub/vendored/lib.md"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let read = |file: &str| fs::read_to_string(root.join(file)).unwrap();
+    assert_eq!(read("top.md"), "✅  \n");
+    assert_eq!(read("sub/docs/guide.md"), " ➡ \n");
+    assert_eq!(read("sub/notes.md"), "✅  \n");
+    assert_eq!(read("sub/vendored/lib.md"), "  \n");
+
+    fs::write(root.join("sub/.nomoji.toml"), "keep = [\"smileys\"]\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_nomoji"))
+        .current_dir(root)
+        .args(["-i", "top.md", "sub/notes.md"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("unknown category 'smileys'"), "{}", stderr);
+}
+
+#[test]
+fn sample_reads_some_files_and_estimates_the_rest() {
+    let dir = tempfile::tempdir().unwrap();
+    for i in 0..20 {
+        fs::write(dir.path().join(format!("{:02}.md", i)), "Ship 🚀\n").unwrap();
+    }
+
+    let run = || {
+        Command::new(env!("CARGO_BIN_EXE_nomoji"))
+            .current_dir(dir.path())
+            .args([
+                "-r",
+                "--sample",
+                "25%",
+                "--sample-seed",
+                "7",
+                "--format",
+                "json",
+            ])
+            .output()
+            .unwrap()
+    };
+    let output = run();
+    assert!(output.status.success(), "{:?}", output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(report.contains(r#""files_processed":5,"#), "{}", report);
+    assert!(
+        report.contains(
+            r#""estimate":{"population":20,"sampled":5,"confidence_percent":95,"emojis":{"estimate":20,"low":20,"high":20}"#
+        ),
+        "{}",
+        report
+    );
+    assert_eq!(String::from_utf8(run().stdout).unwrap(), report);
+    // Sampling only counts.
+    assert_eq!(
+        fs::read_to_string(dir.path().join("00.md")).unwrap(),
+        "Ship 🚀\n"
+    );
+}
+
+#[test]
+fn stats_exports_one_row_per_occurrence() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("docs")).unwrap();
+    fs::write(dir.path().join("docs/a.md"), "Ship 🚀 ✅\n").unwrap();
+    fs::write(dir.path().join("docs/logo.png"), b"\x89PNG\xff").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nomoji"))
+        .current_dir(dir.path())
+        .args(["stats", "-r", "--export", "csv", "-", "docs"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let csv = String::from_utf8(output.stdout).unwrap();
+    let path = Path::new("docs").join("a.md");
+    assert_eq!(
+        csv,
+        format!(
+            "file,offset,emoji,category,unicode_version,role\n\
+             {0},5,🚀,transport,6.0,pictograph\n\
+             {0},10,✅,check-marks,6.0,pictograph\n",
+            path.display()
+        )
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("logo.png: skipped, not text"), "{}", stderr);
+}